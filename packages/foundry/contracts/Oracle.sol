@@ -109,13 +109,18 @@ contract Oracle is Ownable {
     /**
      * @notice Update score with ZK proof (production mode)
      * @param protocol Protocol to update
-     * @param journal Public outputs (48 bytes: u64 + u128 + u128 + u64)
+     * @param journal Public outputs (48-byte prefix: u64 + u128 + u128 + u64)
+     * @dev Fields committed after the prefix are covered by the digest but not stored, except the
+     *      last two u32 words: the hypothetical flag and the score status (0 = Ok, 1 = Insolvent,
+     *      2 = NoData, 3 = Incomplete). What-if scores and scores that aren't Ok or Insolvent revert
      * @param seal ZK proof
      */
     function updateScoreWithProof(address protocol, bytes calldata journal, bytes calldata seal) external {
         require(isProtocolRegistered[protocol], "Protocol not registered");
         require(address(riscZeroVerifier) != address(0), "Verifier not configured");
-        require(journal.length == 48, "Invalid journal length");
+        require(journal.length >= 56, "Invalid journal length");
+        require(_readUint32LE(journal, journal.length - 8) == 0, "Hypothetical score");
+        require(_readUint32LE(journal, journal.length - 4) <= 1, "Score status not Ok or Insolvent");
 
         bytes32 imageId = protocolImageIds[protocol];
         require(imageId != bytes32(0), "Image ID not set for protocol");
@@ -190,6 +195,14 @@ contract Oracle is Ownable {
 
     /* ========== INTERNAL HELPERS ========== */
 
+    function _readUint32LE(bytes calldata data, uint256 offset) internal pure returns (uint32) {
+        uint32 value = 0;
+        for (uint256 i = 0; i < 4; i++) {
+            value |= uint32(uint8(data[offset + i])) << (i * 8);
+        }
+        return value;
+    }
+
     function _readUint64LE(bytes calldata data, uint256 offset) internal pure returns (uint64) {
         uint64 value = 0;
         for (uint256 i = 0; i < 8; i++) {
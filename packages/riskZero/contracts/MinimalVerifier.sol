@@ -16,7 +16,7 @@ contract MinimalVerifier {
     /// @notice Address of the RISC Zero verifier contract (Sepolia: 0x925d8331ddc0a1F0d96E68CF073DFE1d92b69187)
     IRiscZeroVerifier public verifier;
 
-    /// @notice The Image ID of the guest program we accept (convert AAVE_ID to bytes32)
+    /// @notice The Image ID of the guest program we accept (the hex value the host prints as "Image ID")
     bytes32 public immutable guestImageId;
 
     /// @notice Latest verified safety score (scaled by 10000, so 8000 = 80.00%)
@@ -33,7 +33,6 @@ contract MinimalVerifier {
 
     /// @notice Count of successful verifications
     uint256 public verificationCount;
-
     /// @notice Event emitted when a proof is verified successfully
     event ProofVerified(
         bytes32 indexed imageId, uint64 safetyScore, uint128 totalAssets, uint128 totalLiabilities, uint64 timestamp
@@ -50,11 +49,16 @@ contract MinimalVerifier {
     }
 
     /// @notice Verify a RISC Zero proof and store the safety score data
-    /// @param journal The public outputs from the guest program (48 bytes: u64 + u128 + u128 + u64)
+    /// @param journal The public outputs from the guest program (48-byte prefix: u64 + u128 + u128 + u64)
     /// @param seal The ZK proof bytes
     /// @dev Journal structure (little-endian): safety_score(8) + total_assets(16) + total_liabilities(16) + timestamp(8) = 48 bytes
+    /// @dev Fields committed after the prefix are covered by the digest but not stored, except the last
+    ///      two u32 words: hypothetical(4) + status(4), with status 0 = Ok, 1 = Insolvent, 2 = NoData,
+    ///      3 = Incomplete. What-if scores and scores that aren't Ok or Insolvent revert
     function verifyAndStore(bytes calldata journal, bytes calldata seal) external {
-        require(journal.length == 48, "Invalid journal length: expected 48 bytes");
+        require(journal.length >= 56, "Invalid journal length: expected at least 56 bytes");
+        require(_readUint32LE(journal, journal.length - 8) == 0, "Hypothetical score");
+        require(_readUint32LE(journal, journal.length - 4) <= 1, "Score status not Ok or Insolvent");
 
         // Compute the journal digest (SHA-256)
         bytes32 journalDigest = sha256(journal);
@@ -93,9 +97,12 @@ contract MinimalVerifier {
     /// @notice Check if a proof would be valid without storing data (view function)
     /// @param journal The public outputs from the guest program
     /// @param seal The ZK proof bytes
-    /// @return True if the proof is valid
+    /// @return True if the proof is valid and verifyAndStore would store it
     function checkProof(bytes calldata journal, bytes calldata seal) external view returns (bool) {
-        if (journal.length != 48) {
+        if (journal.length < 56) {
+            return false;
+        }
+        if (_readUint32LE(journal, journal.length - 8) != 0 || _readUint32LE(journal, journal.length - 4) > 1) {
             return false;
         }
 
@@ -142,6 +149,15 @@ contract MinimalVerifier {
     // INTERNAL HELPER FUNCTIONS
     // ============================================
 
+    /// @notice Read a uint32 in little-endian format from bytes
+    function _readUint32LE(bytes calldata data, uint256 offset) internal pure returns (uint32) {
+        uint32 value = 0;
+        for (uint256 i = 0; i < 4; i++) {
+            value |= uint32(uint8(data[offset + i])) << (i * 8);
+        }
+        return value;
+    }
+
     /// @notice Read a uint64 in little-endian format from bytes
     function _readUint64LE(bytes calldata data, uint256 offset) internal pure returns (uint64) {
         uint64 value = 0;
@@ -0,0 +1,170 @@
+// Per-reserve breakdown
+// The journal carries a breakdown of where the protocol's value sits, but it
+// goes on-chain as calldata, so its size must not grow with the reserve count.
+// Only the largest reserves are listed; everything else is summed into a
+// single "other" bucket.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{div_round, saturating_sum, RoundingMode};
+
+/// Reserves listed individually in the journal; the rest go into "other"
+pub const MAX_BREAKDOWN_RESERVES: usize = 8;
+
+/// Longest token address kept in a breakdown entry ("0x" + 40 hex digits)
+/// Longer strings are cut so a malformed address can't grow the journal
+pub const MAX_ADDRESS_LEN: usize = 42;
+
+/// Hard cap on the committed journal (bytes)
+/// The guest refuses to commit anything larger. A full breakdown is well
+/// under this, so hitting it means the output format grew unexpectedly
+pub const MAX_JOURNAL_BYTES: usize = 2048;
+
+/// Token address used for the bucket summing the omitted reserves
+pub const OTHER_BUCKET: &str = "other";
+
+/// One reserve's (or the "other" bucket's) contribution to the totals
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct ReserveValue {
+    /// The token address, or `OTHER_BUCKET`
+    pub token_address: String,
+
+    /// Asset value in USD (scaled by 1e8)
+    pub assets_usd: u128,
+
+    /// Liability value in USD (scaled by 1e8)
+    pub liabilities_usd: u128,
+}
+
+/// A breakdown bounded to `MAX_BREAKDOWN_RESERVES` entries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BoundedBreakdown {
+    /// The largest reserves by asset value, largest first
+    pub reserves: Vec<ReserveValue>,
+
+    /// Sum of every reserve not listed in `reserves`
+    pub other: ReserveValue,
+
+    /// Number of reserves summed into `other`
+    pub omitted_count: u32,
+}
+
+/// Keep the `limit` largest reserves by asset value and sum the rest
+/// Ties are broken by token address so the result doesn't depend on the
+/// order the host fetched the reserves in
+pub fn bound_breakdown(mut values: Vec<ReserveValue>, limit: usize, saturated: &mut bool) -> BoundedBreakdown {
+    values.sort_by(|a, b| {
+        b.assets_usd
+            .cmp(&a.assets_usd)
+            .then_with(|| a.token_address.cmp(&b.token_address))
+    });
+
+    let omitted = if values.len() > limit { values.split_off(limit) } else { Vec::new() };
+
+    let mut other = ReserveValue {
+        token_address: OTHER_BUCKET.to_string(),
+        ..Default::default()
+    };
+    for value in &omitted {
+        other.assets_usd = saturating_sum(other.assets_usd, value.assets_usd, saturated);
+        other.liabilities_usd = saturating_sum(other.liabilities_usd, value.liabilities_usd, saturated);
+    }
+
+    for value in &mut values {
+        if let Some((cut, _)) = value.token_address.char_indices().nth(MAX_ADDRESS_LEN) {
+            value.token_address.truncate(cut);
+        }
+    }
+
+    BoundedBreakdown {
+        reserves: values,
+        other,
+        omitted_count: omitted.len() as u32,
+    }
+}
+
+/// `part` as a share of `total`, in basis points (10_000 = all of it)
+/// 0 when the total is 0. A part larger than the total is capped at 10_000
+pub fn share_bps(part: u128, total: u128, mode: RoundingMode) -> u32 {
+    if total == 0 {
+        return 0;
+    }
+    let bps = match part.checked_mul(10_000) {
+        Some(scaled) => div_round(scaled, total, mode),
+        // Only for values near u128::MAX, where dividing the total first
+        // loses far less than a basis point
+        None => div_round(part, (total / 10_000).max(1), mode),
+    };
+    bps.min(10_000) as u32
+}
+
+/// Herfindahl-Hirschman index of how assets are spread over `values`, in bps
+/// The sum of each reserve's squared asset share: 10_000 when one reserve
+/// holds everything, 10_000 / n when n reserves hold equal amounts. Shares
+/// round up, so rounding never makes the protocol look more diversified
+pub fn concentration_bps(values: &[ReserveValue], total_assets_usd: u128) -> u32 {
+    let sum_of_squares: u128 = values
+        .iter()
+        .map(|value| share_bps(value.assets_usd, total_assets_usd, RoundingMode::Ceil) as u128)
+        .map(|share| share * share)
+        .sum();
+    div_round(sum_of_squares, 10_000, RoundingMode::Ceil).min(10_000) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(token_address: &str, assets_usd: u128, liabilities_usd: u128) -> ReserveValue {
+        ReserveValue {
+            token_address: token_address.to_string(),
+            assets_usd,
+            liabilities_usd,
+        }
+    }
+
+    #[test]
+    fn test_keeps_largest_and_sums_the_rest() {
+        let values = vec![
+            value("0xA", 100, 10),
+            value("0xB", 500, 50),
+            value("0xC", 300, 30),
+            value("0xD", 200, 20),
+        ];
+        let mut saturated = false;
+        let breakdown = bound_breakdown(values, 2, &mut saturated);
+
+        assert_eq!(breakdown.reserves, vec![value("0xB", 500, 50), value("0xC", 300, 30)]);
+        assert_eq!(breakdown.other, value(OTHER_BUCKET, 300, 30));
+        assert_eq!(breakdown.omitted_count, 2);
+        assert!(!saturated);
+    }
+
+    #[test]
+    fn test_under_limit_is_not_truncated() {
+        let mut saturated = false;
+        let breakdown = bound_breakdown(vec![value("0xA", 1, 0)], 2, &mut saturated);
+
+        assert_eq!(breakdown.reserves.len(), 1);
+        assert_eq!(breakdown.other, value(OTHER_BUCKET, 0, 0));
+        assert_eq!(breakdown.omitted_count, 0);
+    }
+
+    #[test]
+    fn test_ties_ordered_by_address() {
+        let mut saturated = false;
+        let forward = bound_breakdown(vec![value("0xA", 5, 0), value("0xB", 5, 0)], 1, &mut saturated);
+        let reverse = bound_breakdown(vec![value("0xB", 5, 0), value("0xA", 5, 0)], 1, &mut saturated);
+
+        assert_eq!(forward, reverse);
+        assert_eq!(forward.reserves[0].token_address, "0xA");
+    }
+
+    #[test]
+    fn test_long_addresses_are_cut() {
+        let mut saturated = false;
+        let breakdown = bound_breakdown(vec![value(&"f".repeat(100), 1, 0)], 1, &mut saturated);
+        assert_eq!(breakdown.reserves[0].token_address.len(), MAX_ADDRESS_LEN);
+    }
+}
@@ -0,0 +1,160 @@
+// Proof composition
+// Proving a pool with hundreds of reserves in one guest run needs a lot of
+// memory. Instead the reserves can be split into batches: the batch guest
+// values one batch and commits its PartialTotals, and the compose guest
+// verifies each batch's receipt (as a risc0 assumption), merges the totals
+// and commits the output a single run over all the reserves would. Batches
+// don't depend on each other, so they can be proven in parallel.
+//
+// Every batch commits the input it was cut from with the reserves taken out
+// (its header). The compose guest only merges batches whose header is its
+// own, so they were all scored with the same parameters.
+
+use crate::{
+    accumulate_reserves, finish_score, stamp_output, AaveInput, AaveReserveData, PartialTotals, SafetyScoreOutput,
+    ScoringStrategy,
+};
+use serde::{Deserialize, Serialize};
+
+/// What the batch guest commits
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchJournal {
+    /// The input the batch was cut from, without its reserves
+    pub header: AaveInput,
+
+    /// How many of the header's price overrides are for this batch's reserves
+    pub overrides_matched: u32,
+
+    /// The batch's reserves, valued and summed
+    pub totals: PartialTotals,
+}
+
+/// What the compose guest reads
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComposeInput {
+    /// Image ID the batches were proven with, committed in the output
+    pub batch_image_id: [u32; 8],
+
+    /// The input the batches were cut from, without its reserves
+    pub header: AaveInput,
+
+    /// Each batch's journal, in reserve order
+    pub batches: Vec<BatchJournal>,
+}
+
+/// Take an input's reserves out, leaving its header
+pub fn split_reserves(mut input: AaveInput) -> (AaveInput, Vec<AaveReserveData>) {
+    let reserves = std::mem::take(&mut input.reserves);
+    (input, reserves)
+}
+
+/// The journal the batch guest commits for a batch of an input's reserves
+/// Fails if a reserve doesn't carry the price its override records
+pub fn compute_batch(input: AaveInput) -> Result<BatchJournal, &'static str> {
+    let (header, reserves) = split_reserves(input);
+
+    let mut overrides_matched: u32 = 0;
+    for reserve in &reserves {
+        let recorded = header
+            .price_overrides
+            .iter()
+            .find(|(address, _)| address.eq_ignore_ascii_case(&reserve.token_address));
+        if let Some((_, &price)) = recorded {
+            if reserve.price_usd != price {
+                return Err("reserve price differs from its recorded override");
+            }
+            overrides_matched += 1;
+        }
+    }
+
+    let totals = accumulate_reserves(&reserves, &ScoringStrategy::for_input(&header));
+    Ok(BatchJournal { header, overrides_matched, totals })
+}
+
+/// The output the compose guest commits: the batches' totals merged and
+/// scored as one run over all their reserves would score them
+/// Checking each batch was proven is the caller's job
+pub fn compose_output(input: ComposeInput) -> Result<SafetyScoreOutput, &'static str> {
+    if input.batches.is_empty() {
+        return Err("no batches to compose");
+    }
+
+    let mut totals = PartialTotals::default();
+    let mut overrides_matched: u32 = 0;
+    for batch in input.batches {
+        if batch.header != input.header {
+            return Err("batch was cut from a different input");
+        }
+        overrides_matched = overrides_matched.saturating_add(batch.overrides_matched);
+        totals = totals.merge(batch.totals);
+    }
+
+    // Each override must be for exactly one reserve, as check_price_overrides
+    // requires of a single run
+    if overrides_matched as usize != input.header.price_overrides.len() {
+        return Err("price overrides don't match the batches' reserves");
+    }
+
+    let output = finish_score(totals, &ScoringStrategy::for_input(&input.header));
+    Ok(stamp_output(output, &input.header).with_batch_image_id(Some(input.batch_image_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_output;
+
+    const BATCH_ID: [u32; 8] = [7; 8];
+
+    /// Batches of `size` reserves, composed
+    fn composed(input: &AaveInput, size: usize) -> Result<SafetyScoreOutput, &'static str> {
+        let (header, reserves) = split_reserves(input.clone());
+        let batches = reserves
+            .chunks(size)
+            .map(|chunk| compute_batch(AaveInput { reserves: chunk.to_vec(), ..header.clone() }))
+            .collect::<Result<Vec<_>, _>>()?;
+        compose_output(ComposeInput { batch_image_id: BATCH_ID, header, batches })
+    }
+
+    #[test]
+    fn test_two_batches_compose_to_the_single_run_output() {
+        let reserves = vec![
+            AaveReserveData::builder("0xUSDC").decimals(6).total_atoken(1_000_000_000).total_variable_debt(400_000_000).build(),
+            AaveReserveData::builder("0xWETH")
+                .price_usd(2_000 * 100_000_000)
+                .total_stable_debt(100_000_000_000_000_000)
+                .volatility_bps(3_000)
+                .build(),
+            // Left out of the score for its missing price
+            AaveReserveData::builder("0xDAI").price_usd(0).build(),
+            // Dust
+            AaveReserveData::builder("0xLINK").decimals(18).total_atoken(1).build(),
+            AaveReserveData::builder("0xWBTC").decimals(8).price_usd(60_000 * 100_000_000).total_atoken(10_000_000).build(),
+        ];
+        let mut input = AaveInput::new(reserves, "Aave V3".to_string(), 1_700_000_000)
+            .with_min_reserve_usd(100_000_000)
+            .with_projection_horizon_secs(Some(86_400));
+        input.price_overrides.insert("0xwbtc".to_string(), 60_000 * 100_000_000);
+
+        let single = compute_output(&input);
+        let output = composed(&input, 3).unwrap();
+        assert_eq!(output.batch_image_id, Some(BATCH_ID));
+        assert_eq!(output, single.clone().with_batch_image_id(Some(BATCH_ID)));
+        assert!(output.hypothetical);
+        assert_eq!(output.reserves_with_missing_price, 1);
+        assert_eq!(output.skipped_reserves, 1);
+
+        // However the reserves are split
+        assert_eq!(composed(&input, 1).unwrap().safety_score, single.safety_score);
+
+        // Batches cut from another input are refused
+        let (header, reserves) = split_reserves(input.clone());
+        let mut batches = vec![compute_batch(AaveInput { reserves, ..header.clone() }).unwrap()];
+        batches[0].header.timestamp += 1;
+        assert!(compose_output(ComposeInput { batch_image_id: BATCH_ID, header, batches }).is_err());
+
+        // So is an override no batch's reserve carries
+        input.price_overrides.insert("0xaave".to_string(), 1);
+        assert!(composed(&input, 2).is_err());
+    }
+}
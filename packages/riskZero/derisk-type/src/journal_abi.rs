@@ -0,0 +1,118 @@
+// Solidity ABI encoding of the score
+// The zkVM journal uses RISC Zero's own word framing. Consumers that don't
+// want to depend on risc0 can use the canonical ABI tuple instead:
+//   abi.encode(uint64 safetyScore, uint256 totalAssets, uint256 totalLiabilities, uint64 timestamp)
+// which `abi.decode(data, (uint64, uint256, uint256, uint64))` reads back.
+// Only the four leading fields are encoded; everything after them stays
+// journal-only.
+
+use alloy_primitives::U256;
+use alloy_sol_types::SolValue;
+
+use crate::SafetyScoreOutput;
+
+/// The Solidity tuple the output is encoded as
+type AbiTuple = (u64, U256, U256, u64);
+
+/// Errors raised while decoding an ABI-encoded output
+#[derive(Debug)]
+pub enum JournalAbiError {
+    /// The bytes are not a valid `(uint64, uint256, uint256, uint64)` encoding
+    Decode(alloy_sol_types::Error),
+
+    /// A uint256 total doesn't fit the u128 the output stores it in
+    OutOfRange { field: &'static str },
+}
+
+impl std::fmt::Display for JournalAbiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JournalAbiError::Decode(e) => write!(f, "invalid ABI encoding: {}", e),
+            JournalAbiError::OutOfRange { field } => write!(f, "{} does not fit in a u128", field),
+        }
+    }
+}
+
+impl std::error::Error for JournalAbiError {}
+
+impl From<alloy_sol_types::Error> for JournalAbiError {
+    fn from(e: alloy_sol_types::Error) -> Self {
+        JournalAbiError::Decode(e)
+    }
+}
+
+/// Encode the output as `abi.encode(uint64, uint256, uint256, uint64)`
+pub fn encode_abi(output: &SafetyScoreOutput) -> Vec<u8> {
+    let tuple: AbiTuple = (
+        output.safety_score,
+        U256::from(output.total_assets_usd),
+        U256::from(output.total_liabilities_usd),
+        output.timestamp,
+    );
+    tuple.abi_encode()
+}
+
+/// Decode an `encode_abi` encoding back into an output
+/// Fields that aren't part of the ABI tuple are left at their defaults
+pub fn decode_abi(data: &[u8]) -> Result<SafetyScoreOutput, JournalAbiError> {
+    let (safety_score, total_assets_usd, total_liabilities_usd, timestamp) =
+        <AbiTuple as SolValue>::abi_decode(data, true)?;
+
+    let total_assets_usd = u128::try_from(total_assets_usd)
+        .map_err(|_| JournalAbiError::OutOfRange { field: "total_assets_usd" })?;
+    let total_liabilities_usd = u128::try_from(total_liabilities_usd)
+        .map_err(|_| JournalAbiError::OutOfRange { field: "total_liabilities_usd" })?;
+
+    Ok(SafetyScoreOutput::new(safety_score, total_assets_usd, total_liabilities_usd, timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// abi.encode(uint64(985000), uint256(1e12), uint256(9e11), uint64(1234567890))
+    const KNOWN_ENCODING: &str = concat!(
+        "00000000000000000000000000000000000000000000000000000000000f07a8",
+        "000000000000000000000000000000000000000000000000000000e8d4a51000",
+        "000000000000000000000000000000000000000000000000000000d18c2e2800",
+        "00000000000000000000000000000000000000000000000000000000499602d2",
+    );
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_matches_solidity_abi_encode() {
+        let output = SafetyScoreOutput::new(985_000, 1_000_000_000_000, 900_000_000_000, 1234567890);
+        assert_eq!(to_hex(&encode_abi(&output)), KNOWN_ENCODING);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let output = SafetyScoreOutput::new(464_285, u128::MAX, 1, u64::MAX)
+            .with_skipped_reserves(3);
+        let decoded = decode_abi(&encode_abi(&output)).unwrap();
+
+        assert_eq!(decoded.safety_score, output.safety_score);
+        assert_eq!(decoded.total_assets_usd, output.total_assets_usd);
+        assert_eq!(decoded.total_liabilities_usd, output.total_liabilities_usd);
+        assert_eq!(decoded.timestamp, output.timestamp);
+        // Not part of the tuple
+        assert_eq!(decoded.skipped_reserves, 0);
+    }
+
+    #[test]
+    fn test_rejects_invalid_encodings() {
+        // Truncated
+        assert!(matches!(decode_abi(&[0u8; 64]), Err(JournalAbiError::Decode(_))));
+
+        // A uint256 total above u128::MAX
+        let mut data = encode_abi(&SafetyScoreOutput::new(0, 0, 0, 0));
+        data[32] = 1;
+        assert!(matches!(
+            decode_abi(&data),
+            Err(JournalAbiError::OutOfRange { field: "total_assets_usd" })
+        ));
+    }
+}
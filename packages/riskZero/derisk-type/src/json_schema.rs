@@ -0,0 +1,87 @@
+// JSON Schema export
+// Integrators producing inputs from their own data feeds need the formats as
+// a contract they can check files against before prove-only mode. The
+// documents are generated from the types themselves, so they can't drift
+// from what the host actually parses
+
+use schemars::{schema_for, Schema};
+
+use crate::{AaveInput, AaveReserveData, SafetyScoreOutput, INPUT_SCHEMA_VERSION};
+
+/// JSON Schema documents for the input, a single reserve and the output,
+/// each with the name of the type it describes
+/// The input's `schema_version` is pinned to `INPUT_SCHEMA_VERSION`, since
+/// prove-only mode refuses any other version
+pub fn json_schemas() -> Vec<(&'static str, Schema)> {
+    let mut input = schema_for!(AaveInput);
+    if let Some(version) = input.pointer_mut("/properties/schema_version").and_then(|v| v.as_object_mut()) {
+        version.insert("const".to_string(), INPUT_SCHEMA_VERSION.into());
+    }
+
+    vec![
+        ("AaveInput", input),
+        ("AaveReserveData", schema_for!(AaveReserveData)),
+        ("SafetyScoreOutput", schema_for!(SafetyScoreOutput)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_output;
+
+    fn validator(name: &str) -> jsonschema::Validator {
+        let (_, schema) = json_schemas().into_iter().find(|(n, _)| *n == name).unwrap();
+        jsonschema::validator_for(schema.as_value()).unwrap()
+    }
+
+    /// An input every amount of which fits a JSON number without rounding
+    fn known_good() -> AaveInput {
+        AaveInput::new(
+            vec![
+                AaveReserveData::builder("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48")
+                    .decimals(6)
+                    .total_atoken(1_000_000_000)
+                    .total_variable_debt(400_000_000)
+                    .liquidation_threshold(7_800)
+                    .build(),
+                AaveReserveData::builder("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2").volatility_bps(3_000).build(),
+            ],
+            "Aave V3".to_string(),
+            1_700_000_000,
+        )
+        .with_block_number(Some(18_500_000))
+    }
+
+    #[test]
+    fn test_known_good_files_validate() {
+        let input = known_good();
+        let json = serde_json::to_value(&input).unwrap();
+        assert!(validator("AaveInput").is_valid(&json));
+        assert!(validator("AaveReserveData").is_valid(&json["reserves"][0]));
+
+        let output = serde_json::to_value(compute_output(&input)).unwrap();
+        assert!(validator("SafetyScoreOutput").is_valid(&output));
+    }
+
+    #[test]
+    fn test_malformed_input_fails() {
+        let validator = validator("AaveInput");
+        let good = serde_json::to_value(known_good()).unwrap();
+
+        // An amount as a string, a missing required field, a threshold too
+        // large for its u16, and an input from an older schema
+        let mut quoted = good.clone();
+        quoted["reserves"][0]["total_atoken"] = "1000000000".into();
+        let mut missing = good.clone();
+        missing.as_object_mut().unwrap().remove("reserves");
+        let mut too_large = good.clone();
+        too_large["reserves"][0]["liquidation_threshold"] = 70_000.into();
+        let mut old = good;
+        old["schema_version"] = (INPUT_SCHEMA_VERSION - 1).into();
+
+        for malformed in [quoted, missing, too_large, old] {
+            assert!(!validator.is_valid(&malformed), "{}", malformed);
+        }
+    }
+}
@@ -1,11 +1,48 @@
 // Core types shared between guest (zkVM) and host programs
 // These types MUST be identical on both sides for serialization to work
+// (their zkVM layout is pinned by the golden vectors in host/tests/golden)
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub mod breakdown;
+pub mod compose;
+#[cfg(feature = "abi")]
+pub mod journal_abi;
+#[cfg(feature = "json-schema")]
+pub mod json_schema;
+pub mod schema;
+pub mod score;
+pub mod usd;
+
+pub use breakdown::{
+    bound_breakdown, concentration_bps, share_bps, BoundedBreakdown, ReserveValue, MAX_BREAKDOWN_RESERVES,
+    MAX_JOURNAL_BYTES,
+};
+pub use schema::INPUT_SCHEMA_VERSION;
+#[cfg(feature = "json-schema")]
+pub use json_schema::json_schemas;
+#[cfg(feature = "host")]
+pub use schema::{migrate_input, SchemaError};
+pub use score::{
+    accumulate_reserves, check_output_invariants, check_price_overrides, check_score_bounds, check_weights,
+    collateral_enabled, compute_output, compute_safety_score, finish_score, near_cap, scaled, stamp_output, PartialTotals,
+    ScoringStrategy, NEAR_CAP_BPS, RAY, SCORE_SCALE, SECONDS_PER_YEAR,
+};
+pub use usd::{isolation_debt_to_usd, USD_SCALE};
+#[cfg(feature = "host")]
+pub use usd::UsdAmount;
+
+/// Version of the scoring code, committed in every output
+/// The guest's logic all lives in this crate, so its version says how a
+/// score was computed where the image ID only says which binary proved it.
+/// Bump it whenever scoring changes
+pub const GUEST_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Represents a single reserve (asset) in the Aave protocol
 /// Contains all data needed to calculate that asset's contribution to the safety score
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct AaveReserveData {
     /// The token address (e.g., USDC, WETH, DAI)
     pub token_address: String,
@@ -20,18 +57,405 @@ pub struct AaveReserveData {
     /// Total amount borrowed at variable interest rate (in token's native decimals)
     pub total_variable_debt: u128,
     
-    /// Price of the asset in USD, scaled by 1e8
+    /// Oracle price of the asset in the input's base currency
+    /// USD scaled by 1e8 on mainnet (see `AaveInput::base_currency`)
     /// Example: If 1 WETH = $2000, this would be 200000000000 (2000 * 1e8)
     pub price_usd: u128,
     
     /// Number of decimals for this token (e.g., 6 for USDC, 18 for WETH)
     pub decimals: u8,
+
+    /// Liquidation threshold in basis points (e.g., 8250 = 82.5%)
+    /// Decoded from the reserve configuration bitmap; None when unavailable
+    #[serde(default)]
+    pub liquidation_threshold: Option<u16>,
+
+    /// Recent price volatility in basis points (e.g., 6000 = 60%)
+    /// Discounts the reserve's contribution to the score; None leaves it undiscounted
+    #[serde(default)]
+    pub volatility_bps: Option<u16>,
+
+    /// Debt borrowed against this reserve as isolated collateral
+    /// Aave's `isolationModeTotalDebt`: whole USD with 2 decimals (1e2), NOT the
+    /// token's decimals - convert with `usd::isolation_debt_to_usd` before use
+    #[serde(default)]
+    pub isolation_mode_debt: u128,
+
+    /// Aave eMode category this reserve belongs to (0 = none)
+    /// Decoded from bits 168-175 of the reserve configuration bitmap
+    #[serde(default)]
+    pub emode_category: u8,
+
+    /// Supply cap in whole tokens (not the token's decimals); 0 means uncapped
+    /// Decoded from bits 116-151 of the reserve configuration bitmap
+    #[serde(default)]
+    pub supply_cap: u64,
+
+    /// Borrow cap in whole tokens (not the token's decimals); 0 means uncapped
+    /// Decoded from bits 80-115 of the reserve configuration bitmap
+    #[serde(default)]
+    pub borrow_cap: u64,
+
+    /// Unix time the reserve's indices were last updated on-chain
+    /// (`ReserveData.lastUpdateTimestamp`); 0 when unknown
+    #[serde(default)]
+    pub last_update_timestamp: u64,
+
+    /// Whether the reserve has siloed borrowing: its debt can only be taken
+    /// on its own, a restriction Aave puts on riskier long-tail assets
+    /// Decoded from bit 62 of the reserve configuration bitmap
+    #[serde(default)]
+    pub siloed_borrowing: bool,
+
+    /// Current supply rate, annual, in ray (1e27) as Aave reports it
+    /// (`ReserveData.currentLiquidityRate`); 0 when unknown
+    #[serde(default)]
+    pub liquidity_rate: u128,
+
+    /// Current variable borrow rate, annual, in ray (1e27)
+    /// (`ReserveData.currentVariableBorrowRate`); 0 when unknown
+    #[serde(default)]
+    pub variable_borrow_rate: u128,
+
+    /// Whether an on-chain amount (supply or debt) was too large for u128
+    /// and was clamped to u128::MAX when fetched; its value is then a lower
+    /// bound, like the output's totals when `saturated` is set there
+    #[serde(default)]
+    pub saturated: bool,
+}
+
+impl AaveReserveData {
+    /// Start building a reserve; see `AaveReserveDataBuilder` for the defaults
+    pub fn builder(token_address: impl Into<String>) -> AaveReserveDataBuilder {
+        AaveReserveDataBuilder::new(token_address)
+    }
+
+    /// A reserve from its core fields, rejected if they can't be valued
+    /// The optional fields start unset (no threshold, volatility, isolation
+    /// debt, eMode, caps, update time, siloed borrowing, rates or saturation).
+    /// Host code building reserves from external data
+    /// should prefer this to a struct literal, so bad data fails here rather
+    /// than producing a meaningless score inside the guest
+    pub fn try_new(
+        token_address: impl Into<String>,
+        total_atoken: u128,
+        total_stable_debt: u128,
+        total_variable_debt: u128,
+        price_usd: u128,
+        decimals: u8,
+    ) -> Result<Self, ReserveDataError> {
+        let reserve = AaveReserveData {
+            token_address: token_address.into(),
+            total_atoken,
+            total_stable_debt,
+            total_variable_debt,
+            price_usd,
+            decimals,
+            liquidation_threshold: None,
+            volatility_bps: None,
+            isolation_mode_debt: 0,
+            emode_category: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            last_update_timestamp: 0,
+            siloed_borrowing: false,
+            liquidity_rate: 0,
+            variable_borrow_rate: 0,
+            saturated: false,
+        };
+        reserve.validate()?;
+        Ok(reserve)
+    }
+
+    /// Check the invariants `try_new` enforces
+    pub fn validate(&self) -> Result<(), ReserveDataError> {
+        if self.decimals > MAX_DECIMALS {
+            return Err(ReserveDataError::DecimalsOutOfRange { decimals: self.decimals });
+        }
+        if self.total_stable_debt.checked_add(self.total_variable_debt).is_none() {
+            return Err(ReserveDataError::DebtOverflow);
+        }
+        if self.price_usd == 0 {
+            return Err(ReserveDataError::ZeroPrice);
+        }
+        Ok(())
+    }
+}
+
+/// Most decimals a reserve may have: 10^38 is the largest power of ten a u128 holds
+pub const MAX_DECIMALS: u8 = 38;
+
+/// Why `AaveReserveData::try_new` rejected a reserve
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReserveDataError {
+    /// More than `MAX_DECIMALS` decimals
+    DecimalsOutOfRange { decimals: u8 },
+
+    /// Stable plus variable debt doesn't fit in a u128
+    DebtOverflow,
+
+    /// The oracle returned no price
+    ZeroPrice,
+}
+
+impl std::fmt::Display for ReserveDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReserveDataError::DecimalsOutOfRange { decimals } => {
+                write!(f, "{} decimals is more than the supported {}", decimals, MAX_DECIMALS)
+            }
+            ReserveDataError::DebtOverflow => write!(f, "stable plus variable debt overflows a u128"),
+            ReserveDataError::ZeroPrice => write!(f, "price is zero"),
+        }
+    }
+}
+
+impl std::error::Error for ReserveDataError {}
+
+/// Builds an `AaveReserveData` from only the fields that matter to the caller
+/// Defaults: one whole token supplied, no debt, a $1.00 price, 18 decimals,
+/// and no threshold, volatility, isolation debt, eMode, caps, update time,
+/// siloed borrowing, rates or saturation.
+/// Fields added to the reserve later get a default here, so callers don't
+/// have to change
+#[derive(Debug, Clone)]
+pub struct AaveReserveDataBuilder {
+    reserve: AaveReserveData,
+    /// None until set: one whole token at whatever decimals `build` sees
+    total_atoken: Option<u128>,
+}
+
+impl AaveReserveDataBuilder {
+    pub fn new(token_address: impl Into<String>) -> Self {
+        Self {
+            reserve: AaveReserveData {
+                token_address: token_address.into(),
+                total_atoken: 0,
+                total_stable_debt: 0,
+                total_variable_debt: 0,
+                price_usd: 100_000_000,
+                decimals: 18,
+                liquidation_threshold: None,
+                volatility_bps: None,
+                isolation_mode_debt: 0,
+                emode_category: 0,
+                supply_cap: 0,
+                borrow_cap: 0,
+                last_update_timestamp: 0,
+                siloed_borrowing: false,
+                liquidity_rate: 0,
+                variable_borrow_rate: 0,
+                saturated: false,
+            },
+            total_atoken: None,
+        }
+    }
+
+    /// Supply in the token's decimals
+    pub fn total_atoken(mut self, total_atoken: u128) -> Self {
+        self.total_atoken = Some(total_atoken);
+        self
+    }
+
+    /// Stable-rate debt in the token's decimals
+    pub fn total_stable_debt(mut self, total_stable_debt: u128) -> Self {
+        self.reserve.total_stable_debt = total_stable_debt;
+        self
+    }
+
+    /// Variable-rate debt in the token's decimals
+    pub fn total_variable_debt(mut self, total_variable_debt: u128) -> Self {
+        self.reserve.total_variable_debt = total_variable_debt;
+        self
+    }
+
+    /// Price in the base currency, scaled by 1e8 for USD
+    pub fn price_usd(mut self, price_usd: u128) -> Self {
+        self.reserve.price_usd = price_usd;
+        self
+    }
+
+    pub fn decimals(mut self, decimals: u8) -> Self {
+        self.reserve.decimals = decimals;
+        self
+    }
+
+    /// Liquidation threshold in basis points
+    pub fn liquidation_threshold(mut self, bps: u16) -> Self {
+        self.reserve.liquidation_threshold = Some(bps);
+        self
+    }
+
+    /// Price volatility in basis points
+    pub fn volatility_bps(mut self, bps: u16) -> Self {
+        self.reserve.volatility_bps = Some(bps);
+        self
+    }
+
+    /// Isolation-mode debt in Aave's 1e2 USD scale
+    pub fn isolation_mode_debt(mut self, isolation_mode_debt: u128) -> Self {
+        self.reserve.isolation_mode_debt = isolation_mode_debt;
+        self
+    }
+
+    pub fn emode_category(mut self, emode_category: u8) -> Self {
+        self.reserve.emode_category = emode_category;
+        self
+    }
+
+    /// Supply cap in whole tokens
+    pub fn supply_cap(mut self, supply_cap: u64) -> Self {
+        self.reserve.supply_cap = supply_cap;
+        self
+    }
+
+    /// Borrow cap in whole tokens
+    pub fn borrow_cap(mut self, borrow_cap: u64) -> Self {
+        self.reserve.borrow_cap = borrow_cap;
+        self
+    }
+
+    /// Unix time the reserve was last updated on-chain
+    pub fn last_update_timestamp(mut self, last_update_timestamp: u64) -> Self {
+        self.reserve.last_update_timestamp = last_update_timestamp;
+        self
+    }
+
+    /// Mark the reserve as siloed-borrowing
+    pub fn siloed_borrowing(mut self, siloed_borrowing: bool) -> Self {
+        self.reserve.siloed_borrowing = siloed_borrowing;
+        self
+    }
+
+    /// Annual supply rate in ray (1e27)
+    pub fn liquidity_rate(mut self, liquidity_rate: u128) -> Self {
+        self.reserve.liquidity_rate = liquidity_rate;
+        self
+    }
+
+    /// Annual variable borrow rate in ray (1e27)
+    pub fn variable_borrow_rate(mut self, variable_borrow_rate: u128) -> Self {
+        self.reserve.variable_borrow_rate = variable_borrow_rate;
+        self
+    }
+
+    /// Mark an amount as clamped to u128::MAX when fetched
+    pub fn saturated(mut self, saturated: bool) -> Self {
+        self.reserve.saturated = saturated;
+        self
+    }
+
+    pub fn build(self) -> AaveReserveData {
+        let one_token = 10u128.saturating_pow(self.reserve.decimals as u32);
+        AaveReserveData {
+            total_atoken: self.total_atoken.unwrap_or(one_token),
+            ..self.reserve
+        }
+    }
+}
+
+/// An Aave efficiency-mode category (e.g. correlated stablecoins)
+/// Positions in eMode use the category's risk parameters instead of the reserve's
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct EModeCategory {
+    /// Category id, as referenced by `AaveReserveData::emode_category`
+    pub id: u8,
+
+    /// Liquidation threshold in basis points for assets in this category
+    pub liquidation_threshold: u16,
+
+    /// Human-readable label (e.g., "Stablecoins")
+    pub label: String,
+}
+
+/// Address Aave's oracle reports as `BASE_CURRENCY` when prices are in USD
+pub const USD_BASE_CURRENCY: &str = "0x0000000000000000000000000000000000000000";
+
+/// The currency the price oracle quotes in
+/// USD with a 1e8 unit on mainnet, but some deployments quote in ETH (1e18).
+/// Values are rescaled to 1e8 of the base currency, and converted to USD when
+/// the base currency's own USD price is known
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct BaseCurrency {
+    /// The oracle's `BASE_CURRENCY`; the zero address means USD
+    pub address: String,
+
+    /// The oracle's `BASE_CURRENCY_UNIT`: the price of one whole base currency
+    /// unit (1e8 for USD, 1e18 for ETH)
+    pub unit: u128,
+
+    /// USD price of one base currency unit, scaled by 1e8
+    /// Only used for non-USD bases; without it the output stays denominated
+    /// in the base currency
+    pub price_usd: Option<u128>,
+}
+
+impl Default for BaseCurrency {
+    /// USD with Aave's usual 1e8 unit
+    fn default() -> Self {
+        Self {
+            address: USD_BASE_CURRENCY.to_string(),
+            unit: USD_SCALE,
+            price_usd: None,
+        }
+    }
+}
+
+impl BaseCurrency {
+    /// Whether the oracle quotes in USD
+    pub fn is_usd(&self) -> bool {
+        self.address.trim_start_matches("0x").bytes().all(|b| b == b'0')
+    }
+
+    /// The output's denomination: None for USD, otherwise the base currency
+    /// address when no USD price was supplied to convert it
+    pub fn denomination(&self) -> Option<String> {
+        if self.is_usd() || self.price_usd.is_some() {
+            None
+        } else {
+            Some(self.address.clone())
+        }
+    }
+
+    /// Rescale a value priced in base currency units to the output's 1e8 scale
+    /// A zero unit can't value anything and rescales to 0
+    /// Saturates at u128::MAX (setting `saturated`) rather than overflowing
+    pub fn rescale(&self, value: u128, mode: RoundingMode, saturated: &mut bool) -> u128 {
+        let factor = match self.price_usd {
+            Some(price_usd) if !self.is_usd() => price_usd,
+            _ => USD_SCALE,
+        };
+        if self.unit == 0 {
+            return 0;
+        }
+        if factor == self.unit {
+            return value;
+        }
+
+        // Split the multiplication so large totals don't overflow it
+        let whole = (value / self.unit).checked_mul(factor);
+        let part = div_round((value % self.unit).saturating_mul(factor), self.unit, mode);
+        match whole.and_then(|whole| whole.checked_add(part)) {
+            Some(rescaled) => rescaled,
+            None => {
+                *saturated = true;
+                u128::MAX
+            }
+        }
+    }
 }
 
 /// Input structure sent from host to guest
 /// This is what gets serialized and passed into the zkVM
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct AaveInput {
+    /// Schema version this input was written with (see `INPUT_SCHEMA_VERSION`)
+    /// Checked before a saved file is replayed through prove-only mode
+    pub schema_version: u32,
+
     /// Vector of all reserves to analyze
     pub reserves: Vec<AaveReserveData>,
     
@@ -40,11 +464,226 @@ pub struct AaveInput {
     
     /// Timestamp of data snapshot (for auditing)
     pub timestamp: u64,
+
+    /// Minimum asset value (USD, scaled by 1e8) for a reserve to be scored
+    /// Dust reserves below this are skipped inside the guest; 0 disables the filter
+    pub min_reserve_usd: u128,
+
+    /// Addresses of reserves deliberately left out by the host's allow/deny lists
+    /// Recorded so the proof states what wasn't counted; the guest checks none
+    /// of them appear in `reserves`
+    pub excluded: Vec<String>,
+
+    /// eMode categories referenced by the reserves
+    /// When a reserve's category is listed here, its threshold replaces the
+    /// reserve's own in the weighted health factor
+    pub emode_categories: Vec<EModeCategory>,
+
+    /// The currency reserve prices are quoted in
+    pub base_currency: BaseCurrency,
+
+    /// Count only collateral-enabled reserves' supply as assets, for a
+    /// conservative view of the buffer; debt on every reserve still counts
+    pub collateral_only: bool,
+
+    /// Number of reserves the host failed to fetch, which are missing from
+    /// `reserves`; a non-zero count means the snapshot is incomplete
+    pub failed_reserves: u32,
+
+    /// Governance-approved lowest score to report (scaled by 1e4)
+    /// A lower computed score is raised to this and the output marked `clamped`
+    pub score_floor: Option<u64>,
+
+    /// Governance-approved highest score to report (scaled by 1e4)
+    /// A higher computed score is lowered to this and the output marked `clamped`
+    pub score_ceiling: Option<u64>,
+
+    /// Funds outside the reserves that backstop a shortfall, e.g. Aave's
+    /// Safety Module, valued like the totals (USD, scaled by 1e8)
+    /// Counted on the asset side of the score; 0 means no backstop
+    pub backstop_usd: u128,
+
+    /// Custom risk weights per token address (lowercase), in basis points
+    /// A listed reserve's assets count at this share in the score (5_000 =
+    /// 50%); unlisted reserves count in full. At most 10_000
+    pub weights: BTreeMap<String, u16>,
+
+    /// Seconds since a reserve's last on-chain update, relative to
+    /// `timestamp`, after which it counts as stale; None disables the check
+    /// Stale reserves are still scored, only counted in the output
+    pub stale_after_secs: Option<u64>,
+
+    /// Block the reserve data was read at, when known: the pinned RPC block,
+    /// or the block a subgraph had indexed (which can lag the chain head)
+    pub block_number: Option<u64>,
+
+    /// Committed unchanged in the output, so proofs of identical state still
+    /// produce distinct journals a consumer can order and dedupe: the block
+    /// number or a host-supplied counter. None commits no nonce
+    pub nonce: Option<u64>,
+
+    /// Haircut (bps) on the assets of siloed-borrowing reserves in the score,
+    /// like a risk weight of 10_000 minus it: 2_000 counts them at 80%
+    /// 0 applies none; anything above 10_000 counts them at nothing
+    pub siloed_haircut_bps: u16,
+
+    /// Seconds of interest to accrue at the reserves' current rates for a
+    /// projected score alongside the spot one; None projects nothing
+    pub projection_horizon_secs: Option<u64>,
+
+    /// Reserve prices replaced for a what-if scenario, by lowercase token
+    /// address, at the price the reserve carries in this input. Empty when
+    /// every price is the oracle's; otherwise the output is hypothetical
+    pub price_overrides: BTreeMap<String, u128>,
+}
+
+impl AaveInput {
+    /// Helper to create a new input tagged with the current schema version
+    pub fn new(reserves: Vec<AaveReserveData>, protocol_name: String, timestamp: u64) -> Self {
+        Self {
+            schema_version: INPUT_SCHEMA_VERSION,
+            reserves,
+            protocol_name,
+            timestamp,
+            min_reserve_usd: 0,
+            excluded: Vec::new(),
+            emode_categories: Vec::new(),
+            base_currency: BaseCurrency::default(),
+            collateral_only: false,
+            failed_reserves: 0,
+            score_floor: None,
+            score_ceiling: None,
+            backstop_usd: 0,
+            weights: BTreeMap::new(),
+            stale_after_secs: None,
+            block_number: None,
+            nonce: None,
+            siloed_haircut_bps: 0,
+            projection_horizon_secs: None,
+            price_overrides: BTreeMap::new(),
+        }
+    }
+
+    /// Skip reserves whose asset value is below `min_reserve_usd` (scaled by 1e8)
+    pub fn with_min_reserve_usd(mut self, min_reserve_usd: u128) -> Self {
+        self.min_reserve_usd = min_reserve_usd;
+        self
+    }
+
+    /// Record the reserves that were filtered out before scoring
+    pub fn with_excluded(mut self, excluded: Vec<String>) -> Self {
+        self.excluded = excluded;
+        self
+    }
+
+    /// Attach the eMode categories the reserves belong to
+    pub fn with_emode_categories(mut self, emode_categories: Vec<EModeCategory>) -> Self {
+        self.emode_categories = emode_categories;
+        self
+    }
+
+    /// Record the currency the reserve prices are quoted in
+    pub fn with_base_currency(mut self, base_currency: BaseCurrency) -> Self {
+        self.base_currency = base_currency;
+        self
+    }
+
+    /// Count only collateral-enabled reserves as assets
+    pub fn with_collateral_only(mut self, collateral_only: bool) -> Self {
+        self.collateral_only = collateral_only;
+        self
+    }
+
+    /// Record how many reserves failed to fetch
+    pub fn with_failed_reserves(mut self, failed_reserves: u32) -> Self {
+        self.failed_reserves = failed_reserves;
+        self
+    }
+
+    /// Clamp the reported score into [floor, ceiling]; None leaves that side open
+    pub fn with_score_bounds(mut self, score_floor: Option<u64>, score_ceiling: Option<u64>) -> Self {
+        self.score_floor = score_floor;
+        self.score_ceiling = score_ceiling;
+        self
+    }
+
+    /// Count `backstop_usd` (scaled by 1e8) of off-protocol funds as assets
+    pub fn with_backstop_usd(mut self, backstop_usd: u128) -> Self {
+        self.backstop_usd = backstop_usd;
+        self
+    }
+
+    /// Weight reserves' assets in the score by these bps (keys lowercase)
+    pub fn with_weights(mut self, weights: BTreeMap<String, u16>) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Count reserves not updated within `stale_after_secs` of the snapshot as stale
+    pub fn with_stale_after_secs(mut self, stale_after_secs: Option<u64>) -> Self {
+        self.stale_after_secs = stale_after_secs;
+        self
+    }
+
+    /// Record the block the data was read at
+    pub fn with_block_number(mut self, block_number: Option<u64>) -> Self {
+        self.block_number = block_number;
+        self
+    }
+
+    /// Commit `nonce` in the output to tell this update apart from others
+    pub fn with_nonce(mut self, nonce: Option<u64>) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    /// Count siloed-borrowing reserves' assets `siloed_haircut_bps` lower in the score
+    pub fn with_siloed_haircut_bps(mut self, siloed_haircut_bps: u16) -> Self {
+        self.siloed_haircut_bps = siloed_haircut_bps;
+        self
+    }
+
+    /// Also project the score `projection_horizon_secs` ahead at current rates
+    pub fn with_projection_horizon_secs(mut self, projection_horizon_secs: Option<u64>) -> Self {
+        self.projection_horizon_secs = projection_horizon_secs;
+        self
+    }
+
+    /// Record the reserve prices a scenario replaced
+    pub fn with_price_overrides(mut self, price_overrides: BTreeMap<String, u128>) -> Self {
+        self.price_overrides = price_overrides;
+        self
+    }
+}
+
+/// Why a score came out as it did, committed so a consumer can tell an
+/// insolvent protocol from one that couldn't be scored: both score 0
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum ScoreStatus {
+    /// Scored over every reserve it should cover. Also what saved JSON
+    /// outputs from before the status was committed load as; the journal's
+    /// encoding is positional, so older journals don't decode at all
+    #[default]
+    Ok,
+
+    /// Liabilities cover all of the (weighted) assets, so the computed score
+    /// is 0; a score floor may still have raised `safety_score`
+    Insolvent,
+
+    /// No scored reserve had any assets or liabilities: there was nothing to
+    /// score, and the 0 says nothing about solvency
+    NoData,
+
+    /// Some reserves had no price or failed to fetch, so the score covers
+    /// only part of the protocol (see `data_completeness_bps`)
+    Incomplete,
 }
 
 /// Output structure committed to the zkVM journal
 /// This is the PUBLIC output that goes on-chain
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct SafetyScoreOutput {
     /// The calculated safety score (scaled by 1e4)
     /// Example: 98.5% = 985000 (98.5 * 1e4)
@@ -52,13 +691,178 @@ pub struct SafetyScoreOutput {
     pub safety_score: u64,
     
     /// Total assets in USD (scaled by 1e8)
+    /// In the base currency instead when `denomination` is set
     pub total_assets_usd: u128,
     
     /// Total liabilities in USD (scaled by 1e8)
+    /// In the base currency instead when `denomination` is set
     pub total_liabilities_usd: u128,
     
     /// Timestamp when this was calculated
     pub timestamp: u64,
+
+    /// Protocol-wide weighted average health factor (scaled by 1e4)
+    /// sum(collateral_usd * liq_threshold) / sum(debt_usd), as used by Aave risk dashboards
+    /// Example: 1.5 = 15000; u64::MAX when there is no debt
+    /// None when any reserve is missing its liquidation threshold
+    pub weighted_health_factor: Option<u64>,
+
+    /// Number of reserves excluded for being below the input's `min_reserve_usd`
+    #[serde(default)]
+    pub skipped_reserves: u32,
+
+    /// Whether any sum overflowed u128 and was clamped at u128::MAX, or a
+    /// scored reserve's amounts were clamped when fetched
+    /// When set, the totals (and the score derived from them) are lower bounds
+    #[serde(default)]
+    pub saturated: bool,
+
+    /// Whether any reserve's asset value was discounted by its volatility
+    /// When set, the score is volatility-weighted; the committed totals never are
+    #[serde(default)]
+    pub volatility_adjusted: bool,
+
+    /// Total isolation-mode debt across scored reserves (USD, scaled by 1e8)
+    /// Also a floor on total_liabilities_usd, which already includes this debt
+    /// through the borrowed reserves' debt tokens
+    #[serde(default)]
+    pub isolation_mode_debt_usd: u128,
+
+    /// Number of reserves the host excluded via its allow/deny lists
+    #[serde(default)]
+    pub excluded_reserves: u32,
+
+    /// Number of reserves left out of the score because their price was 0
+    /// (e.g. an oracle feed outage). When non-zero the score is incomplete
+    #[serde(default)]
+    pub reserves_with_missing_price: u32,
+
+    /// The largest scored reserves by asset value, largest first
+    /// At most `MAX_BREAKDOWN_RESERVES` entries so the journal size stays bounded
+    #[serde(default)]
+    pub reserve_breakdown: Vec<ReserveValue>,
+
+    /// Sum of the scored reserves not listed in `reserve_breakdown`
+    #[serde(default)]
+    pub other_reserves: ReserveValue,
+
+    /// Number of reserves summed into `other_reserves`
+    #[serde(default)]
+    pub omitted_count: u32,
+
+    /// Whether the breakdown was cut to `MAX_BREAKDOWN_RESERVES`
+    #[serde(default)]
+    pub truncated: bool,
+
+    /// Currency the values are in when it isn't USD: the oracle's base
+    /// currency address, for inputs priced in a non-USD base with no USD
+    /// price supplied to convert it. None means USD
+    #[serde(default)]
+    pub denomination: Option<String>,
+
+    /// Number of scored reserves whose supply or debt is at or near its cap
+    /// (see `score::NEAR_CAP_BPS`), a sign of concentration with little headroom
+    #[serde(default)]
+    pub capped_reserves: u32,
+
+    /// Whether only collateral-enabled reserves counted as assets
+    /// When set, total_assets_usd leaves out supply that can't back loans
+    #[serde(default)]
+    pub collateral_only: bool,
+
+    /// Stable-rate part of total_liabilities_usd (USD, scaled by 1e8)
+    #[serde(default)]
+    pub total_stable_liabilities_usd: u128,
+
+    /// Variable-rate part of total_liabilities_usd (USD, scaled by 1e8)
+    /// Stable + variable equals the total unless the isolation-mode floor
+    /// raised it (see `isolation_mode_debt_usd`)
+    #[serde(default)]
+    pub total_variable_liabilities_usd: u128,
+
+    /// Whether the computed score fell outside the input's score_floor or
+    /// score_ceiling and `safety_score` is the bound instead
+    #[serde(default)]
+    pub clamped: bool,
+
+    /// Backstop funds the score counted on top of total_assets_usd (USD,
+    /// scaled by 1e8); the totals themselves cover the reserves only
+    #[serde(default)]
+    pub backstop_usd: u128,
+
+    /// Whether any scored reserve's assets were weighted by the input's
+    /// custom `weights` or `siloed_haircut_bps`; like volatility, this only
+    /// ever lowers the score
+    #[serde(default)]
+    pub risk_weighted: bool,
+
+    /// Number of scored reserves last updated on-chain more than the input's
+    /// `stale_after_secs` before the snapshot; their indices may be out of date
+    #[serde(default)]
+    pub stale_reserve_count: u32,
+
+    /// The input's `nonce`, passed through so two updates over the same
+    /// state don't share a journal; on-chain, a nonce no higher than the last
+    /// accepted one marks a replayed or stale update
+    #[serde(default)]
+    pub nonce: Option<u64>,
+
+    /// Herfindahl-Hirschman index of the scored reserves' asset shares, in
+    /// bps: 10_000 when a single reserve holds all assets, lower the more
+    /// evenly they're spread. 0 when there are no assets
+    #[serde(default)]
+    pub asset_concentration_bps: u32,
+
+    /// Number of scored reserves with siloed borrowing; their assets were
+    /// haircut in the score when the input set `siloed_haircut_bps`
+    #[serde(default)]
+    pub siloed_reserve_count: u32,
+
+    /// Number of reserves whose data made it into the score
+    #[serde(default)]
+    pub reserves_included: u32,
+
+    /// Number of reserves the score should have covered: those included plus
+    /// any that failed to fetch or had no price. Reserves left out on purpose
+    /// (filtered or dust) count in neither
+    #[serde(default)]
+    pub reserves_total: u32,
+
+    /// reserves_included out of reserves_total, in bps (rounded down)
+    /// A consumer can refuse scores computed over too little of the protocol
+    #[serde(default)]
+    pub data_completeness_bps: u32,
+
+    /// Score once the input's `projection_horizon_secs` of interest has
+    /// accrued at current rates: variable debt grows at its borrow rate and
+    /// supplied assets at theirs. None when no horizon was set
+    #[serde(default)]
+    pub projected_score: Option<u64>,
+
+    /// `GUEST_VERSION` of the code that computed the score, so consumers can
+    /// gate on it across image ID changes. Empty in journals from before it
+    /// was committed
+    #[serde(default)]
+    pub guest_version: String,
+
+    /// For an output composed from batch proofs (see compose.rs), the image
+    /// ID the batches were proven with. A consumer must check it is the batch
+    /// guest's, since the composing guest accepts any. None when the output
+    /// was computed in a single run
+    #[serde(default)]
+    pub batch_image_id: Option<[u32; 8]>,
+
+    // These two must stay the last fields: the on-chain verifiers read them
+    // as the journal's last two words, whatever comes before
+
+    /// Whether the input overrode any reserve price for a what-if scenario:
+    /// the score is for those prices, not the market's
+    #[serde(default)]
+    pub hypothetical: bool,
+
+    /// How the score was arrived at; see `ScoreStatus`
+    #[serde(default)]
+    pub status: ScoreStatus,
 }
 
 impl SafetyScoreOutput {
@@ -74,42 +878,406 @@ impl SafetyScoreOutput {
             total_assets_usd,
             total_liabilities_usd,
             timestamp,
+            weighted_health_factor: None,
+            skipped_reserves: 0,
+            saturated: false,
+            volatility_adjusted: false,
+            isolation_mode_debt_usd: 0,
+            excluded_reserves: 0,
+            reserves_with_missing_price: 0,
+            reserve_breakdown: Vec::new(),
+            other_reserves: ReserveValue::default(),
+            omitted_count: 0,
+            truncated: false,
+            capped_reserves: 0,
+            denomination: None,
+            collateral_only: false,
+            total_stable_liabilities_usd: 0,
+            total_variable_liabilities_usd: 0,
+            clamped: false,
+            backstop_usd: 0,
+            risk_weighted: false,
+            stale_reserve_count: 0,
+            nonce: None,
+            asset_concentration_bps: 0,
+            siloed_reserve_count: 0,
+            reserves_included: 0,
+            reserves_total: 0,
+            data_completeness_bps: 0,
+            projected_score: None,
+            guest_version: String::new(),
+            batch_image_id: None,
+            hypothetical: false,
+            status: ScoreStatus::Ok,
         }
     }
+
+    /// Attach the weighted health factor to the output
+    pub fn with_weighted_health_factor(mut self, weighted_health_factor: Option<u64>) -> Self {
+        self.weighted_health_factor = weighted_health_factor;
+        self
+    }
+
+    /// Record how many dust reserves were excluded from the score
+    pub fn with_skipped_reserves(mut self, skipped_reserves: u32) -> Self {
+        self.skipped_reserves = skipped_reserves;
+        self
+    }
+
+    /// Record whether any total was clamped on overflow
+    pub fn with_saturated(mut self, saturated: bool) -> Self {
+        self.saturated = saturated;
+        self
+    }
+
+    /// Record whether the score was weighted by reserve volatility
+    pub fn with_volatility_adjusted(mut self, volatility_adjusted: bool) -> Self {
+        self.volatility_adjusted = volatility_adjusted;
+        self
+    }
+
+    /// Record the isolation-mode debt total (USD, scaled by 1e8)
+    pub fn with_isolation_mode_debt_usd(mut self, isolation_mode_debt_usd: u128) -> Self {
+        self.isolation_mode_debt_usd = isolation_mode_debt_usd;
+        self
+    }
+
+    /// Record how many reserves were excluded by the host's filters
+    pub fn with_excluded_reserves(mut self, excluded_reserves: u32) -> Self {
+        self.excluded_reserves = excluded_reserves;
+        self
+    }
+
+    /// Record how many reserves were left out for having no price
+    pub fn with_reserves_with_missing_price(mut self, reserves_with_missing_price: u32) -> Self {
+        self.reserves_with_missing_price = reserves_with_missing_price;
+        self
+    }
+
+    /// Attach a bounded per-reserve breakdown
+    pub fn with_breakdown(mut self, breakdown: BoundedBreakdown) -> Self {
+        self.reserve_breakdown = breakdown.reserves;
+        self.other_reserves = breakdown.other;
+        self.omitted_count = breakdown.omitted_count;
+        self.truncated = breakdown.omitted_count > 0;
+        self
+    }
+
+    /// Record how many reserves are at or near their supply or borrow cap
+    pub fn with_capped_reserves(mut self, capped_reserves: u32) -> Self {
+        self.capped_reserves = capped_reserves;
+        self
+    }
+
+    /// Record the currency the values are in (None for USD)
+    pub fn with_denomination(mut self, denomination: Option<String>) -> Self {
+        self.denomination = denomination;
+        self
+    }
+
+    /// Record whether only collateral-enabled reserves counted as assets
+    pub fn with_collateral_only(mut self, collateral_only: bool) -> Self {
+        self.collateral_only = collateral_only;
+        self
+    }
+
+    /// Record how the liabilities split between stable and variable rate debt
+    pub fn with_debt_mix(mut self, stable_usd: u128, variable_usd: u128) -> Self {
+        self.total_stable_liabilities_usd = stable_usd;
+        self.total_variable_liabilities_usd = variable_usd;
+        self
+    }
+
+    /// Record that the score was clamped into the input's bounds
+    pub fn with_clamped(mut self, clamped: bool) -> Self {
+        self.clamped = clamped;
+        self
+    }
+
+    /// Record the backstop counted as assets in the score
+    pub fn with_backstop_usd(mut self, backstop_usd: u128) -> Self {
+        self.backstop_usd = backstop_usd;
+        self
+    }
+
+    /// Record whether custom risk weights were applied to the score
+    pub fn with_risk_weighted(mut self, risk_weighted: bool) -> Self {
+        self.risk_weighted = risk_weighted;
+        self
+    }
+
+    /// Record how many scored reserves had stale on-chain data
+    pub fn with_stale_reserve_count(mut self, stale_reserve_count: u32) -> Self {
+        self.stale_reserve_count = stale_reserve_count;
+        self
+    }
+
+    /// Record the input's nonce
+    pub fn with_nonce(mut self, nonce: Option<u64>) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    /// Record how concentrated the assets are in few reserves
+    pub fn with_asset_concentration_bps(mut self, asset_concentration_bps: u32) -> Self {
+        self.asset_concentration_bps = asset_concentration_bps;
+        self
+    }
+
+    /// Record how many scored reserves had siloed borrowing
+    pub fn with_siloed_reserve_count(mut self, siloed_reserve_count: u32) -> Self {
+        self.siloed_reserve_count = siloed_reserve_count;
+        self
+    }
+
+    /// Record how many of the reserves the score should have covered it did
+    /// With nothing to cover, the data is complete
+    pub fn with_completeness(mut self, reserves_included: u32, reserves_total: u32) -> Self {
+        self.reserves_included = reserves_included;
+        self.reserves_total = reserves_total;
+        self.data_completeness_bps = if reserves_total == 0 {
+            10_000
+        } else {
+            share_bps(reserves_included as u128, reserves_total as u128, RoundingMode::Floor)
+        };
+        self
+    }
+
+    /// Attach the score projected over the input's horizon
+    pub fn with_projected_score(mut self, projected_score: Option<u64>) -> Self {
+        self.projected_score = projected_score;
+        self
+    }
+
+    /// Mark the output as computed over overridden prices
+    pub fn with_hypothetical(mut self, hypothetical: bool) -> Self {
+        self.hypothetical = hypothetical;
+        self
+    }
+
+    /// Record the version of the code that computed the output
+    pub fn with_guest_version(mut self, guest_version: impl Into<String>) -> Self {
+        self.guest_version = guest_version.into();
+        self
+    }
+
+    /// Record how the score was arrived at
+    pub fn with_status(mut self, status: ScoreStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Record the image ID of the batch proofs the output was composed from
+    pub fn with_batch_image_id(mut self, batch_image_id: Option<[u32; 8]>) -> Self {
+        self.batch_image_id = batch_image_id;
+        self
+    }
     
     /// Convert safety score to human-readable percentage
     /// Example: 985000 -> 98.50%
+    /// Host-only: the guest keeps floating point out of its execution
+    /// For display only: f64 can land just below a boundary (0.29% * 100 is
+    /// 28.999...), so compare or threshold with `to_basis_points` instead
+    #[cfg(feature = "host")]
     pub fn to_percentage(&self) -> f64 {
         self.safety_score as f64 / 10000.0
     }
+
+    /// The score in whole basis points (1 bp = 0.01%), computed exactly
+    /// Sub-basis-point precision is truncated: 985_099 -> 9_850
+    pub fn to_basis_points(&self) -> u32 {
+        let bps = self.safety_score / (SCORE_SCALE / 10_000);
+        u32::try_from(bps).unwrap_or(u32::MAX)
+    }
+
+    /// The score as a percentage of `scale`, the value a 100% score was computed as
+    /// Fails for a zero scale, or a score above it (which no valid output has)
+    #[cfg(feature = "host")]
+    pub fn to_percentage_with_scale(&self, scale: u64) -> Result<f64, ScoreScaleError> {
+        if scale == 0 {
+            return Err(ScoreScaleError::ZeroScale);
+        }
+        if self.safety_score > scale {
+            return Err(ScoreScaleError::ScoreAboveScale { score: self.safety_score, scale });
+        }
+        // Whole percent exactly, then only the remainder goes through f64
+        let hundredths = self.safety_score as u128 * 100;
+        let whole = hundredths / scale as u128;
+        let remainder = hundredths % scale as u128;
+        Ok(whole as f64 + remainder as f64 / scale as f64)
+    }
+
+    /// Inverse of `to_percentage`: the scaled score for a percentage
+    /// Example: 98.5 -> 985000, rounded and clamped like `scaled`
+    #[cfg(feature = "host")]
+    pub fn from_percentage(pct: f64) -> u64 {
+        scaled(pct)
+    }
+}
+
+/// Why `SafetyScoreOutput::to_percentage_with_scale` couldn't render a score
+#[cfg(feature = "host")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScoreScaleError {
+    ZeroScale,
+
+    /// The score is larger than 100% at this scale
+    ScoreAboveScale { score: u64, scale: u64 },
+}
+
+#[cfg(feature = "host")]
+impl std::fmt::Display for ScoreScaleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScoreScaleError::ZeroScale => write!(f, "score scale must not be zero"),
+            ScoreScaleError::ScoreAboveScale { score, scale } => {
+                write!(f, "score {} is above its scale {}; was it computed at another scale?", score, scale)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "host")]
+impl std::error::Error for ScoreScaleError {}
+
+/// How integer division rounds when converting amounts to USD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// Round toward zero (truncate) - understates the value
+    Floor,
+    /// Round away from zero - overstates the value
+    Ceil,
+    /// Round to the nearest value, halves rounding up
+    Nearest,
+}
+
+/// Divide with the given rounding mode
+fn div_round(numerator: u128, denominator: u128, mode: RoundingMode) -> u128 {
+    round_quotient(numerator / denominator, numerator % denominator, denominator, mode)
+        .expect("a u128 quotient with a remainder is below u128::MAX")
+}
+
+/// Apply `mode` to a truncated quotient; None if rounding up overflows
+fn round_quotient(quotient: u128, remainder: u128, denominator: u128, mode: RoundingMode) -> Option<u128> {
+    match mode {
+        RoundingMode::Floor => Some(quotient),
+        RoundingMode::Ceil if remainder > 0 => quotient.checked_add(1),
+        RoundingMode::Ceil => Some(quotient),
+        RoundingMode::Nearest if remainder >= denominator - remainder => quotient.checked_add(1),
+        RoundingMode::Nearest => Some(quotient),
+    }
+}
+
+/// `a * b / denominator` rounded once with `mode`, without overflowing the product
+/// None if the result doesn't fit in a u128
+fn mul_div(a: u128, b: u128, denominator: u128, mode: RoundingMode) -> Option<u128> {
+    if let Some(product) = a.checked_mul(b) {
+        return Some(div_round(product, denominator, mode));
+    }
+
+    // The full 256-bit product as (high, low) halves, from 64-bit limbs
+    const LOW: u128 = u64::MAX as u128;
+    let (a_hi, a_lo, b_hi, b_lo) = (a >> 64, a & LOW, b >> 64, b & LOW);
+    let cross = a_lo * b_hi;
+    let (lo_lo, hi_lo, hi_hi) = (a_lo * b_lo, a_hi * b_lo, a_hi * b_hi);
+    let (middle, middle_carry) = hi_lo.overflowing_add(cross);
+    let (low, low_carry) = lo_lo.overflowing_add(middle << 64);
+    let high = hi_hi + (middle >> 64) + ((middle_carry as u128) << 64) + low_carry as u128;
+
+    // A high half at or above the denominator means a quotient beyond u128
+    if high >= denominator {
+        return None;
+    }
+
+    // Long division, one bit of the low half at a time
+    let (mut quotient, mut remainder) = (0u128, high);
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+    round_quotient(quotient, remainder, denominator, mode)
 }
 
 /// Helper function to normalize token amounts to USD
 /// Handles different token decimals properly
+/// Truncates (`RoundingMode::Floor`); see `normalize_amount_rounded`
 pub fn normalize_amount(amount: u128, decimals: u8, price_usd: u128) -> u128 {
-    // amount is in token's native decimals
-    // price_usd is scaled by 1e8
-    // We want to return USD value scaled by 1e8
-    
-    // Convert amount to 18 decimals first for precision
-    let amount_normalized = if decimals < 18 {
-        amount * 10u128.pow((18 - decimals) as u32)
-    } else if decimals > 18 {
-        amount / 10u128.pow((decimals - 18) as u32)
+    normalize_amount_rounded(amount, decimals, price_usd, RoundingMode::Floor)
+}
+
+/// Same as `normalize_amount`, rounding with `mode`
+/// The guest rounds assets down and liabilities up for a conservative score
+///
+/// The result is `amount * price_usd / 10^decimals` (USD scaled by 1e8,
+/// since price_usd is) rounded once, so it is always within one unit (1e-8
+/// USD) of the exact value: never above it for Floor, never below it for
+/// Ceil, and at most half a unit off for Nearest. The product is computed in
+/// 256 bits, so large amounts don't overflow; a value beyond u128 saturates
+/// at u128::MAX. Beyond 38 decimals (more than a u128 scale holds) the
+/// division is split in two and rounds twice.
+pub fn normalize_amount_rounded(amount: u128, decimals: u8, price_usd: u128, mode: RoundingMode) -> u128 {
+    match 10u128.checked_pow(decimals as u32) {
+        Some(scale) => mul_div(amount, price_usd, scale, mode).unwrap_or(u128::MAX),
+        None => {
+            let value = normalize_amount_rounded(amount, 38, price_usd, mode);
+            div_round(value, 10u128.saturating_pow(decimals as u32 - 38), mode)
+        }
+    }
+}
+
+/// Helper function to add to a running total without panicking on overflow
+/// Clamps at u128::MAX and sets `saturated`, so extreme inputs still produce
+/// a well-defined (flagged) result instead of aborting the proof
+pub fn saturating_sum(total: u128, value: u128, saturated: &mut bool) -> u128 {
+    match total.checked_add(value) {
+        Some(sum) => sum,
+        None => {
+            *saturated = true;
+            u128::MAX
+        }
+    }
+}
+
+/// Helper function to discount an asset value by its volatility
+/// value * (1 - volatility), with volatility in basis points and capped at 100%
+/// A missing volatility leaves the value unchanged
+pub fn volatility_discount(value_usd: u128, volatility_bps: Option<u16>) -> u128 {
+    match volatility_bps {
+        None => value_usd,
+        Some(bps) => {
+            let kept = 10_000 - (bps as u128).min(10_000);
+            // Divide first so the multiplication can't overflow for huge values
+            (value_usd / 10_000) * kept + (value_usd % 10_000) * kept / 10_000
+        }
+    }
+}
+
+/// Helper function to compute a health factor scaled by 1e4
+/// `weighted_collateral_usd` is sum(collateral_usd * liq_threshold_bps), so the
+/// basis-point scale of the thresholds directly provides the 1e4 output scale
+/// Returns u64::MAX when there is no debt (mirrors Aave's infinite health factor)
+pub fn health_factor(weighted_collateral_usd: u128, total_debt_usd: u128) -> u64 {
+    if total_debt_usd == 0 {
+        return u64::MAX;
+    }
+    let hf = weighted_collateral_usd / total_debt_usd;
+    if hf > u64::MAX as u128 {
+        u64::MAX
     } else {
-        amount
-    };
-    
-    // Multiply by price (which is already in 1e8 scale)
-    // Result should be in 1e8 scale
-    // amount_normalized is 1e18, price is 1e8, so result is 1e26
-    // We need to divide by 1e18 to get back to 1e8 scale
-    (amount_normalized * price_usd) / 10u128.pow(18)
+        hf as u64
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use primitive_types::U512;
+    use proptest::prelude::*;
 
     #[test]
     fn test_normalize_amount() {
@@ -130,6 +1298,162 @@ mod tests {
         assert_eq!(result, 200_000_000_000u128); // $2000 in 1e8 scale
     }
 
+    #[test]
+    fn test_rounding_modes() {
+        let price = 100_000_000u128; // $1
+
+        // 0.5e-8 USD: exactly half a unit of the 1e8 scale
+        let half = 5_000_000_000u128;
+        assert_eq!(normalize_amount_rounded(half, 18, price, RoundingMode::Floor), 0);
+        assert_eq!(normalize_amount_rounded(half, 18, price, RoundingMode::Ceil), 1);
+        assert_eq!(normalize_amount_rounded(half, 18, price, RoundingMode::Nearest), 1);
+
+        // 2.4e-8 USD
+        let below_half = 24_000_000_000u128;
+        assert_eq!(normalize_amount_rounded(below_half, 18, price, RoundingMode::Floor), 2);
+        assert_eq!(normalize_amount_rounded(below_half, 18, price, RoundingMode::Ceil), 3);
+        assert_eq!(normalize_amount_rounded(below_half, 18, price, RoundingMode::Nearest), 2);
+
+        // 2.6e-8 USD
+        let above_half = 26_000_000_000u128;
+        assert_eq!(normalize_amount_rounded(above_half, 18, price, RoundingMode::Floor), 2);
+        assert_eq!(normalize_amount_rounded(above_half, 18, price, RoundingMode::Ceil), 3);
+        assert_eq!(normalize_amount_rounded(above_half, 18, price, RoundingMode::Nearest), 3);
+
+        // Evenly divisible values are the same in every mode
+        for mode in [RoundingMode::Floor, RoundingMode::Ceil, RoundingMode::Nearest] {
+            assert_eq!(normalize_amount_rounded(1_000_000_000, 6, price, mode), 100_000_000_000);
+        }
+
+        // normalize_amount keeps its truncating behavior
+        assert_eq!(normalize_amount(above_half, 18, price), 2);
+    }
+
+    #[test]
+    fn test_normalize_large_amounts_and_decimals() {
+        // 33,999,999 base units of a 24-decimal token at $3e8 are worth about
+        // 1.02e-8 USD; dividing down to 18 decimals first used to floor this to 0
+        let expensive = 3 * 10u128.pow(16); // $3e8
+        assert_eq!(normalize_amount(33_999_999, 24, expensive), 1);
+        assert_eq!(normalize_amount_rounded(33_999_999, 24, expensive, RoundingMode::Ceil), 2);
+
+        // 1e30 units of a 0-decimal token: scaling to 18 decimals overflowed u128
+        let price = 100_000_000u128; // $1
+        let amount = 10u128.pow(30);
+        assert_eq!(normalize_amount(amount, 0, price), amount * price);
+
+        // amount * price overflows, the value doesn't: 3e20 tokens at $1e8 each
+        assert_eq!(normalize_amount(3 * 10u128.pow(38), 18, 10u128.pow(16)), 3 * 10u128.pow(36));
+
+        // A value beyond u128 saturates instead of wrapping
+        assert_eq!(normalize_amount(u128::MAX, 0, 2), u128::MAX);
+        assert_eq!(normalize_amount_rounded(u128::MAX, 0, 1, RoundingMode::Ceil), u128::MAX);
+    }
+
+    #[test]
+    fn test_try_new_accepts_valid_reserve() {
+        let reserve = AaveReserveData::try_new("0xA0b8", 1_000_000, 200_000, 300_000, 100_000_000, 6).unwrap();
+        assert_eq!(reserve.total_stable_debt + reserve.total_variable_debt, 500_000);
+        assert_eq!(reserve.liquidation_threshold, None);
+
+        // The limits themselves are allowed
+        assert!(AaveReserveData::try_new("0x1", 0, u128::MAX, 0, 1, MAX_DECIMALS).is_ok());
+    }
+
+    #[test]
+    fn test_try_new_rejects_too_many_decimals() {
+        assert_eq!(
+            AaveReserveData::try_new("0x1", 1, 0, 0, 100_000_000, 39).unwrap_err(),
+            ReserveDataError::DecimalsOutOfRange { decimals: 39 }
+        );
+    }
+
+    #[test]
+    fn test_try_new_rejects_debt_overflow() {
+        assert_eq!(
+            AaveReserveData::try_new("0x1", 1, u128::MAX, 1, 100_000_000, 18).unwrap_err(),
+            ReserveDataError::DebtOverflow
+        );
+    }
+
+    #[test]
+    fn test_try_new_rejects_zero_price() {
+        assert_eq!(
+            AaveReserveData::try_new("0x1", 1, 0, 0, 0, 18).unwrap_err(),
+            ReserveDataError::ZeroPrice
+        );
+        // The builder still allows it, for testing the guest's missing-price handling
+        assert!(AaveReserveData::builder("0x1").price_usd(0).build().validate().is_err());
+    }
+
+    /// Strategies for the fuzz targets: mostly realistic values (up to 1e30
+    /// base units, up to $1e8 a token), sometimes anything a u128 holds
+    fn arb_amount() -> impl Strategy<Value = u128> {
+        prop_oneof![3 => 0u128..=10u128.pow(30), 1 => any::<u128>()]
+    }
+
+    fn arb_price() -> impl Strategy<Value = u128> {
+        prop_oneof![3 => 0u128..=10u128.pow(16), 1 => any::<u128>()]
+    }
+
+    /// The exact value `amount * price_usd / 10^decimals` as quotient and
+    /// remainder, in 512 bits so nothing can overflow
+    fn exact_value(amount: u128, decimals: u8, price_usd: u128) -> (U512, U512, U512) {
+        let scale = U512::exp10(decimals as usize);
+        let product = U512::from(amount) * U512::from(price_usd);
+        (product / scale, product % scale, scale)
+    }
+
+    proptest! {
+        /// Within the documented bound: exactly the reference value rounded once
+        #[test]
+        fn prop_normalize_matches_exact_value(
+            amount in arb_amount(),
+            decimals in 0u8..=30,
+            price_usd in arb_price(),
+        ) {
+            let (quotient, remainder, scale) = exact_value(amount, decimals, price_usd);
+            let round_up = |up: bool| if up { quotient + 1 } else { quotient };
+            let expected = [
+                (RoundingMode::Floor, quotient),
+                (RoundingMode::Ceil, round_up(!remainder.is_zero())),
+                (RoundingMode::Nearest, round_up(remainder * 2 >= scale)),
+            ];
+
+            for (mode, exact) in expected {
+                let result = normalize_amount_rounded(amount, decimals, price_usd, mode);
+                if exact > U512::from(u128::MAX) {
+                    prop_assert_eq!(result, u128::MAX, "{:?} should saturate", mode);
+                } else {
+                    prop_assert_eq!(U512::from(result), exact, "{:?}", mode);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_reserve_builder_defaults_are_scoreable() {
+        let reserve = AaveReserveData::builder("0xTOKEN").build();
+        assert_eq!(reserve.total_atoken, 10u128.pow(18));
+        assert_eq!(reserve.total_stable_debt + reserve.total_variable_debt, 0);
+        assert_eq!((reserve.price_usd, reserve.decimals), (100_000_000, 18));
+
+        // One token at $1.00 with no debt: $1 of assets and a full score
+        let output = compute_safety_score(&[reserve], &ScoringStrategy::default());
+        assert_eq!(output.total_assets_usd, USD_SCALE);
+        assert_eq!(output.total_liabilities_usd, 0);
+        assert_eq!(output.safety_score, SCORE_SCALE);
+        assert_eq!(output.skipped_reserves, 0);
+
+        // Unset supply follows the decimals; set values are kept as given
+        let usdc = AaveReserveData::builder("0xUSDC").decimals(6).total_variable_debt(400_000).build();
+        assert_eq!(usdc.total_atoken, 1_000_000);
+        assert_eq!(usdc.total_variable_debt, 400_000);
+        let set = AaveReserveData::builder("0xUSDC").total_atoken(5).decimals(6).build();
+        assert_eq!(set.total_atoken, 5);
+    }
+
+    #[cfg(feature = "host")]
     #[test]
     fn test_safety_score_percentage() {
         let output = SafetyScoreOutput::new(
@@ -141,4 +1465,153 @@ mod tests {
         
         assert_eq!(output.to_percentage(), 98.5);
     }
+
+    #[cfg(feature = "host")]
+    #[test]
+    fn test_score_from_percentage() {
+        assert_eq!(SafetyScoreOutput::from_percentage(0.0), 0);
+        assert_eq!(SafetyScoreOutput::from_percentage(100.0), SCORE_SCALE);
+        assert_eq!(SafetyScoreOutput::from_percentage(98.5), 985_000);
+
+        // 99.995 isn't exact in f64; rounding still lands on the intended unit
+        assert_eq!(SafetyScoreOutput::from_percentage(99.995), 999_950);
+        assert_eq!(SafetyScoreOutput::from_percentage(99.99994), 999_999);
+        assert_eq!(SafetyScoreOutput::from_percentage(99.99995), SCORE_SCALE);
+
+        // Out of range clamps
+        assert_eq!(SafetyScoreOutput::from_percentage(150.0), SCORE_SCALE);
+        assert_eq!(SafetyScoreOutput::from_percentage(-0.1), 0);
+        assert_eq!(SafetyScoreOutput::from_percentage(f64::NAN), 0);
+        assert_eq!(SafetyScoreOutput::from_percentage(f64::INFINITY), SCORE_SCALE);
+
+        // Every score survives the round trip
+        for score in [0, 1, 411_764, 999_950, 999_999, SCORE_SCALE] {
+            let output = SafetyScoreOutput::new(score, 0, 0, 0);
+            assert_eq!(SafetyScoreOutput::from_percentage(output.to_percentage()), score);
+        }
+    }
+
+    #[test]
+    fn test_basis_points_are_exact() {
+        let bps = |score| SafetyScoreOutput::new(score, 0, 0, 0).to_basis_points();
+        assert_eq!(bps(0), 0);
+        assert_eq!(bps(99), 0);
+        assert_eq!(bps(2_900), 29);
+        assert_eq!(bps(985_099), 9_850);
+        assert_eq!(bps(SCORE_SCALE), 10_000);
+        assert_eq!(bps(u64::MAX), u32::MAX);
+    }
+
+    #[cfg(feature = "host")]
+    #[test]
+    fn test_basis_points_against_f64_percentage() {
+        for score in [0, 100, 5_000, 411_764, 985_000, 985_099, 999_999, SCORE_SCALE] {
+            let output = SafetyScoreOutput::new(score, 0, 0, 0);
+            assert_eq!(output.to_basis_points(), (output.to_percentage() * 100.0) as u32, "{}", score);
+        }
+
+        // Where f64 falls just short of a boundary, only the integer path is right
+        for (score, exact) in [(2_900, 29), (5_700, 57)] {
+            let output = SafetyScoreOutput::new(score, 0, 0, 0);
+            assert_eq!(output.to_basis_points(), exact);
+            assert_eq!((output.to_percentage() * 100.0) as u32, exact - 1);
+        }
+    }
+
+    #[cfg(feature = "host")]
+    #[test]
+    fn test_percentage_with_scale() {
+        let output = SafetyScoreOutput::new(985_000, 0, 0, 0);
+        assert_eq!(output.to_percentage_with_scale(SCORE_SCALE), Ok(98.5));
+        assert_eq!(output.to_percentage_with_scale(SCORE_SCALE), Ok(output.to_percentage()));
+        // The same score computed at a 1e9 scale
+        assert_eq!(output.to_percentage_with_scale(1_000_000_000), Ok(0.0985));
+
+        assert_eq!(output.to_percentage_with_scale(0), Err(ScoreScaleError::ZeroScale));
+        assert_eq!(
+            output.to_percentage_with_scale(10_000),
+            Err(ScoreScaleError::ScoreAboveScale { score: 985_000, scale: 10_000 })
+        );
+
+        // score * 100 would overflow a u64
+        let full = SafetyScoreOutput::new(u64::MAX, 0, 0, 0);
+        assert_eq!(full.to_percentage_with_scale(u64::MAX), Ok(100.0));
+    }
+
+    #[test]
+    fn test_scaled_is_const() {
+        const THRESHOLD: u64 = scaled(60.0);
+        assert_eq!(THRESHOLD, 600_000);
+        assert_eq!(scaled(0.0001), 1);
+    }
+
+    #[test]
+    fn test_base_currency_rescale() {
+        let mut saturated = false;
+
+        // USD with the usual 1e8 unit is left as is
+        let usd = BaseCurrency::default();
+        assert!(usd.is_usd());
+        assert_eq!(usd.rescale(123_456_789, RoundingMode::Floor, &mut saturated), 123_456_789);
+
+        // 1e18 unit: 1.5 units rescale to 1.5e8, remainders rounded per mode
+        let eth = BaseCurrency { address: "0x01".to_string(), unit: 10u128.pow(18), price_usd: None };
+        assert!(!eth.is_usd());
+        assert_eq!(eth.rescale(15 * 10u128.pow(17), RoundingMode::Floor, &mut saturated), 150_000_000);
+        assert_eq!(eth.rescale(1, RoundingMode::Floor, &mut saturated), 0);
+        assert_eq!(eth.rescale(1, RoundingMode::Ceil, &mut saturated), 1);
+        assert!(!saturated);
+
+        // A USD price large enough to overflow clamps instead
+        let expensive = BaseCurrency { price_usd: Some(u128::MAX), ..eth.clone() };
+        assert_eq!(expensive.rescale(u128::MAX, RoundingMode::Floor, &mut saturated), u128::MAX);
+        assert!(saturated);
+
+        // A zero unit values nothing
+        let broken = BaseCurrency { unit: 0, ..eth };
+        assert_eq!(broken.rescale(1_000, RoundingMode::Floor, &mut saturated), 0);
+    }
+
+    #[test]
+    fn test_saturating_sum() {
+        let mut saturated = false;
+        assert_eq!(saturating_sum(1, 2, &mut saturated), 3);
+        assert_eq!(saturating_sum(u128::MAX - 1, 1, &mut saturated), u128::MAX);
+        assert!(!saturated);
+
+        // One past the boundary clamps and raises the flag
+        assert_eq!(saturating_sum(u128::MAX, 1, &mut saturated), u128::MAX);
+        assert!(saturated);
+
+        // The flag stays set once raised
+        assert_eq!(saturating_sum(0, 5, &mut saturated), 5);
+        assert!(saturated);
+    }
+
+    #[test]
+    fn test_volatility_discount() {
+        let value = 1_000 * 100_000_000u128; // $1000
+
+        assert_eq!(volatility_discount(value, None), value);
+        assert_eq!(volatility_discount(value, Some(0)), value);
+        assert_eq!(volatility_discount(value, Some(2_500)), 750 * 100_000_000);
+
+        // Volatility above 100% can't make the value negative
+        assert_eq!(volatility_discount(value, Some(20_000)), 0);
+
+        // No overflow at the u128 boundary
+        assert_eq!(volatility_discount(u128::MAX, Some(0)), u128::MAX);
+        assert!(volatility_discount(u128::MAX, Some(5_000)) < u128::MAX / 2 + 1);
+    }
+
+    #[test]
+    fn test_health_factor() {
+        // $1000 collateral at 80% threshold against $500 debt -> HF 1.6
+        let weighted = 1_000 * 100_000_000u128 * 8_000;
+        let debt = 500 * 100_000_000u128;
+        assert_eq!(health_factor(weighted, debt), 16_000);
+
+        // No debt -> infinite health factor
+        assert_eq!(health_factor(weighted, 0), u64::MAX);
+    }
 }
@@ -0,0 +1,836 @@
+// Input schema versioning
+// Saved aave_input.json files are replayed through prove-only mode, so the
+// host has to know which shape a file was written with before trusting it
+
+// Only the version constant is needed by the guest; loading and migrating
+// files is host-only, which keeps serde_json out of guest builds
+
+#[cfg(feature = "host")]
+use serde::Deserialize;
+
+#[cfg(feature = "host")]
+use std::collections::BTreeMap;
+#[cfg(feature = "host")]
+use std::io::Read;
+
+#[cfg(feature = "host")]
+use crate::{AaveInput, AaveReserveData, BaseCurrency, EModeCategory};
+
+/// Current version of the `AaveInput` schema
+/// Bump this whenever a field is added, removed or changes meaning
+pub const INPUT_SCHEMA_VERSION: u32 = 21;
+
+// Schema history:
+// v0 - unversioned files written before schema tagging existed
+// v1 - adds schema_version
+// v2 - adds per-reserve liquidation_threshold
+// v3 - adds min_reserve_usd
+// v4 - adds per-reserve volatility_bps
+// v5 - adds per-reserve isolation_mode_debt
+// v6 - adds excluded (reserves filtered out by --include/--exclude-reserve)
+// v7 - adds per-reserve emode_category and emode_categories
+// v8 - adds base_currency (the oracle's quote currency and unit)
+// v9 - adds per-reserve supply_cap and borrow_cap
+// v10 - adds collateral_only
+// v11 - adds failed_reserves
+// v12 - adds score_floor and score_ceiling
+// v13 - adds backstop_usd
+// v14 - adds weights (custom per-reserve risk weights)
+// v15 - adds per-reserve last_update_timestamp and stale_after_secs
+// v16 - adds block_number
+// v17 - adds nonce
+// v18 - adds per-reserve siloed_borrowing and siloed_haircut_bps
+// v19 - adds per-reserve liquidity_rate and variable_borrow_rate, and
+//       projection_horizon_secs
+// v20 - adds price_overrides (what-if prices from --price-override/--stress)
+// v21 - adds per-reserve saturated (amounts clamped to u128::MAX when fetched)
+
+/// Errors raised while loading or migrating a saved input file
+#[cfg(feature = "host")]
+#[derive(Debug)]
+pub enum SchemaError {
+    /// The file could not be parsed as JSON of the expected shape
+    Parse(serde_json::Error),
+
+    /// The file was written with a schema this build does not understand
+    Unsupported { found: u32, expected: u32 },
+}
+
+#[cfg(feature = "host")]
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaError::Parse(e) => write!(f, "failed to parse input file: {}", e),
+            SchemaError::Unsupported { found, expected } => write!(
+                f,
+                "input schema v{} is not supported (expected v{})",
+                found, expected
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "host")]
+impl std::error::Error for SchemaError {}
+
+#[cfg(feature = "host")]
+impl From<serde_json::Error> for SchemaError {
+    fn from(e: serde_json::Error) -> Self {
+        SchemaError::Parse(e)
+    }
+}
+
+/// Only reads the version tag, ignoring every other field
+/// Files written before versioning existed have no tag and report v0
+#[cfg(feature = "host")]
+#[derive(Deserialize)]
+struct VersionProbe {
+    #[serde(default)]
+    schema_version: u32,
+}
+
+/// Shape shared by v0-v20 inputs (the version tag is ignored here)
+/// Reserves from v0/v1 files have no liquidation threshold and decode with None,
+/// no pre-v4 reserve carries a volatility, and pre-v5 reserves decode with no
+/// isolation-mode debt
+/// Inputs older than v3 get no dust filter (min_reserve_usd = 0), and since
+/// filtering didn't exist before v6 nothing is recorded as excluded
+/// Pre-v7 reserves decode outside any eMode category, and every pre-v8 input
+/// was priced in USD with a 1e8 unit. Pre-v9 reserves decode as uncapped, and
+/// every pre-v10 input counted all supplied assets. Fetch failures weren't
+/// recorded before v11 and migrate as none, no pre-v12 input had score
+/// bounds. No pre-v13 input counted a backstop, pre-v14 inputs weight every
+/// reserve in full, and pre-v15 reserves have no known update time. No
+/// pre-v16 input recorded the block it was read at, none before v17
+/// committed a nonce, and pre-v18 reserves decode as not siloed (with no
+/// haircut to apply). Pre-v19 reserves have no known rates, so nothing is
+/// projected, and every pre-v20 input was scored at on-chain prices. No
+/// pre-v21 reserve was clamped: its fetch failed instead
+#[cfg(feature = "host")]
+#[derive(Deserialize)]
+struct LegacyAaveInput {
+    reserves: Vec<AaveReserveData>,
+    protocol_name: String,
+    timestamp: u64,
+    #[serde(default)]
+    min_reserve_usd: u128,
+    #[serde(default)]
+    excluded: Vec<String>,
+    #[serde(default)]
+    emode_categories: Vec<EModeCategory>,
+    #[serde(default)]
+    base_currency: BaseCurrency,
+    #[serde(default)]
+    collateral_only: bool,
+    #[serde(default)]
+    failed_reserves: u32,
+    #[serde(default)]
+    score_floor: Option<u64>,
+    #[serde(default)]
+    score_ceiling: Option<u64>,
+    #[serde(default)]
+    backstop_usd: u128,
+    #[serde(default)]
+    weights: BTreeMap<String, u16>,
+    #[serde(default)]
+    stale_after_secs: Option<u64>,
+    #[serde(default)]
+    block_number: Option<u64>,
+    #[serde(default)]
+    nonce: Option<u64>,
+    #[serde(default)]
+    siloed_haircut_bps: u16,
+    #[serde(default)]
+    projection_horizon_secs: Option<u64>,
+    #[serde(default)]
+    price_overrides: BTreeMap<String, u128>,
+}
+
+/// Read the schema version of a saved input file without fully decoding it
+#[cfg(feature = "host")]
+pub fn input_schema_version(json: &str) -> Result<u32, SchemaError> {
+    let probe: VersionProbe = serde_json::from_str(json)?;
+    Ok(probe.schema_version)
+}
+
+/// Like `input_schema_version`, streaming from `reader`
+/// Every other field is skipped as it is read, so nothing is kept in memory
+#[cfg(feature = "host")]
+pub fn input_schema_version_from_reader<R: Read>(reader: R) -> Result<u32, SchemaError> {
+    let probe: VersionProbe = serde_json::from_reader(reader)?;
+    Ok(probe.schema_version)
+}
+
+/// Load a saved input file, refusing anything not written with the current schema
+#[cfg(feature = "host")]
+pub fn load_input(json: &str) -> Result<AaveInput, SchemaError> {
+    check_version(input_schema_version(json)?)?;
+    Ok(serde_json::from_str(json)?)
+}
+
+/// Like `load_input`, decoding straight from `reader` so a large file is
+/// never held in memory as text alongside the decoded input
+/// The version is checked once decoded; a file from another schema usually
+/// fails to decode first and is reported as `Parse`, so callers that can
+/// reread the file should probe it with `input_schema_version_from_reader`
+#[cfg(feature = "host")]
+pub fn load_input_from_reader<R: Read>(reader: R) -> Result<AaveInput, SchemaError> {
+    let input: AaveInput = serde_json::from_reader(reader)?;
+    check_version(input.schema_version)?;
+    Ok(input)
+}
+
+#[cfg(feature = "host")]
+fn check_version(version: u32) -> Result<(), SchemaError> {
+    if version != INPUT_SCHEMA_VERSION {
+        return Err(SchemaError::Unsupported {
+            found: version,
+            expected: INPUT_SCHEMA_VERSION,
+        });
+    }
+    Ok(())
+}
+
+/// Upgrade a saved input file from any known older schema to the current one
+/// Files already on the current schema are returned unchanged
+#[cfg(feature = "host")]
+pub fn migrate_input(old_json: &str) -> Result<AaveInput, SchemaError> {
+    match input_schema_version(old_json)? {
+        0..=20 => {
+            // NOTE: typed decoding on purpose - going through serde_json::Value
+            // would round u128 amounts through f64 and corrupt them
+            let old: LegacyAaveInput = serde_json::from_str(old_json)?;
+            Ok(AaveInput::new(old.reserves, old.protocol_name, old.timestamp)
+                .with_min_reserve_usd(old.min_reserve_usd)
+                .with_excluded(old.excluded)
+                .with_emode_categories(old.emode_categories)
+                .with_base_currency(old.base_currency)
+                .with_collateral_only(old.collateral_only)
+                .with_failed_reserves(old.failed_reserves)
+                .with_score_bounds(old.score_floor, old.score_ceiling)
+                .with_backstop_usd(old.backstop_usd)
+                .with_weights(old.weights)
+                .with_stale_after_secs(old.stale_after_secs)
+                .with_block_number(old.block_number)
+                .with_nonce(old.nonce)
+                .with_siloed_haircut_bps(old.siloed_haircut_bps)
+                .with_projection_horizon_secs(old.projection_horizon_secs)
+                .with_price_overrides(old.price_overrides))
+        }
+        INPUT_SCHEMA_VERSION => load_input(old_json),
+        found => Err(SchemaError::Unsupported {
+            found,
+            expected: INPUT_SCHEMA_VERSION,
+        }),
+    }
+}
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use super::*;
+
+    fn sample_input() -> AaveInput {
+        AaveInput::new(
+            vec![AaveReserveData {
+                token_address: "0xUSDC".to_string(),
+                total_atoken: 2_883_313_057_534_146_509_735_272_106,
+                total_stable_debt: 0,
+                total_variable_debt: 1_000_000,
+                price_usd: 100_000_000,
+                decimals: 6,
+                liquidation_threshold: Some(7_800),
+                volatility_bps: Some(4_500),
+                isolation_mode_debt: 12_345,
+                emode_category: 1,
+                supply_cap: 0,
+                borrow_cap: 0,
+                last_update_timestamp: 1234560000,
+                siloed_borrowing: false,
+                liquidity_rate: 0,
+                variable_borrow_rate: 0,
+                saturated: false,
+            }],
+            "Aave V3".to_string(),
+            1234567890,
+        )
+        .with_excluded(vec!["0xDEAD".to_string()])
+        .with_emode_categories(vec![EModeCategory {
+            id: 1,
+            liquidation_threshold: 9_500,
+            label: "Stablecoins".to_string(),
+        }])
+    }
+
+    #[test]
+    fn test_round_trip_current_schema() {
+        let input = sample_input();
+        let json = serde_json::to_string(&input).unwrap();
+
+        assert_eq!(input_schema_version(&json).unwrap(), INPUT_SCHEMA_VERSION);
+
+        let loaded = load_input(&json).unwrap();
+        assert_eq!(loaded.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(loaded.reserves[0].total_atoken, input.reserves[0].total_atoken);
+        assert_eq!(loaded.reserves[0].liquidation_threshold, Some(7_800));
+        assert_eq!(loaded.reserves[0].volatility_bps, Some(4_500));
+        assert_eq!(loaded.reserves[0].isolation_mode_debt, 12_345);
+        assert_eq!(loaded.excluded, vec!["0xDEAD".to_string()]);
+        assert_eq!(loaded.reserves[0].emode_category, 1);
+        assert_eq!(loaded.emode_categories, input.emode_categories);
+        assert_eq!(loaded.timestamp, input.timestamp);
+    }
+
+    #[test]
+    fn test_migrate_v11_keeps_failed_reserves_and_has_no_bounds() {
+        let json = r#"{
+            "schema_version": 11,
+            "reserves": [],
+            "protocol_name": "Aave V3",
+            "timestamp": 1,
+            "min_reserve_usd": 0,
+            "excluded": [],
+            "emode_categories": [],
+            "base_currency": {"address": "0x0000000000000000000000000000000000000000", "unit": 100000000, "price_usd": null},
+            "collateral_only": true,
+            "failed_reserves": 3
+        }"#;
+
+        assert!(matches!(load_input(json), Err(SchemaError::Unsupported { found: 11, .. })));
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.failed_reserves, 3);
+        assert!(migrated.collateral_only);
+        assert_eq!((migrated.score_floor, migrated.score_ceiling), (None, None));
+    }
+
+    #[test]
+    fn test_migrate_v12_keeps_bounds_and_has_no_backstop() {
+        let json = r#"{
+            "schema_version": 12,
+            "reserves": [],
+            "protocol_name": "Aave V3",
+            "timestamp": 1,
+            "min_reserve_usd": 0,
+            "excluded": [],
+            "emode_categories": [],
+            "base_currency": {"address": "0x0000000000000000000000000000000000000000", "unit": 100000000, "price_usd": null},
+            "collateral_only": false,
+            "failed_reserves": 0,
+            "score_floor": 250000,
+            "score_ceiling": null
+        }"#;
+
+        assert!(matches!(load_input(json), Err(SchemaError::Unsupported { found: 12, .. })));
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!((migrated.score_floor, migrated.score_ceiling), (Some(250_000), None));
+        assert_eq!(migrated.backstop_usd, 0);
+    }
+
+    #[test]
+    fn test_migrate_v13_keeps_backstop_and_weights_nothing() {
+        let json = r#"{
+            "schema_version": 13,
+            "reserves": [],
+            "protocol_name": "Aave V3",
+            "timestamp": 1,
+            "min_reserve_usd": 0,
+            "excluded": [],
+            "emode_categories": [],
+            "base_currency": {"address": "0x0000000000000000000000000000000000000000", "unit": 100000000, "price_usd": null},
+            "collateral_only": false,
+            "failed_reserves": 0,
+            "score_floor": null,
+            "score_ceiling": null,
+            "backstop_usd": 340282366920938463463374607431768211455
+        }"#;
+
+        assert!(matches!(load_input(json), Err(SchemaError::Unsupported { found: 13, .. })));
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.backstop_usd, u128::MAX);
+        assert!(migrated.weights.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_v14_keeps_weights_and_has_no_update_times() {
+        let json = r#"{
+            "schema_version": 14,
+            "reserves": [{
+                "token_address": "0xUSDC",
+                "total_atoken": 1000,
+                "total_stable_debt": 0,
+                "total_variable_debt": 0,
+                "price_usd": 100000000,
+                "decimals": 6
+            }],
+            "protocol_name": "Aave V3",
+            "timestamp": 1,
+            "min_reserve_usd": 0,
+            "excluded": [],
+            "emode_categories": [],
+            "base_currency": {"address": "0x0000000000000000000000000000000000000000", "unit": 100000000, "price_usd": null},
+            "collateral_only": false,
+            "failed_reserves": 0,
+            "score_floor": null,
+            "score_ceiling": null,
+            "backstop_usd": 0,
+            "weights": {"0xusdc": 5000}
+        }"#;
+
+        assert!(matches!(load_input(json), Err(SchemaError::Unsupported { found: 14, .. })));
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.weights.get("0xusdc"), Some(&5_000));
+        assert_eq!(migrated.reserves[0].last_update_timestamp, 0);
+        assert_eq!(migrated.stale_after_secs, None);
+    }
+
+    #[test]
+    fn test_migrate_v15_keeps_staleness_and_has_no_block() {
+        let json = r#"{
+            "schema_version": 15,
+            "reserves": [{
+                "token_address": "0xUSDC",
+                "total_atoken": 1000,
+                "total_stable_debt": 0,
+                "total_variable_debt": 0,
+                "price_usd": 100000000,
+                "decimals": 6,
+                "last_update_timestamp": 1699990000
+            }],
+            "protocol_name": "Aave V3",
+            "timestamp": 1700000000,
+            "min_reserve_usd": 0,
+            "excluded": [],
+            "emode_categories": [],
+            "base_currency": {"address": "0x0000000000000000000000000000000000000000", "unit": 100000000, "price_usd": null},
+            "collateral_only": false,
+            "failed_reserves": 0,
+            "score_floor": null,
+            "score_ceiling": null,
+            "backstop_usd": 0,
+            "weights": {},
+            "stale_after_secs": 86400
+        }"#;
+
+        assert!(matches!(load_input(json), Err(SchemaError::Unsupported { found: 15, .. })));
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.reserves[0].last_update_timestamp, 1_699_990_000);
+        assert_eq!(migrated.stale_after_secs, Some(86_400));
+        assert_eq!(migrated.block_number, None);
+    }
+
+    #[test]
+    fn test_migrate_v16_keeps_block_and_has_no_nonce() {
+        let json = r#"{
+            "schema_version": 16,
+            "reserves": [{
+                "token_address": "0xUSDC",
+                "total_atoken": 1000,
+                "total_stable_debt": 0,
+                "total_variable_debt": 0,
+                "price_usd": 100000000,
+                "decimals": 6
+            }],
+            "protocol_name": "Aave V3",
+            "timestamp": 1700000000,
+            "min_reserve_usd": 0,
+            "excluded": [],
+            "emode_categories": [],
+            "base_currency": {"address": "0x0000000000000000000000000000000000000000", "unit": 100000000, "price_usd": null},
+            "collateral_only": false,
+            "failed_reserves": 0,
+            "score_floor": null,
+            "score_ceiling": null,
+            "backstop_usd": 0,
+            "weights": {},
+            "stale_after_secs": null,
+            "block_number": 18500000
+        }"#;
+
+        assert!(matches!(load_input(json), Err(SchemaError::Unsupported { found: 16, .. })));
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.block_number, Some(18_500_000));
+        assert_eq!(migrated.nonce, None);
+    }
+
+    #[test]
+    fn test_migrate_v17_keeps_nonce_and_has_no_siloed_reserves() {
+        let json = r#"{
+            "schema_version": 17,
+            "reserves": [{
+                "token_address": "0xUSDC",
+                "total_atoken": 1000,
+                "total_stable_debt": 0,
+                "total_variable_debt": 0,
+                "price_usd": 100000000,
+                "decimals": 6
+            }],
+            "protocol_name": "Aave V3",
+            "timestamp": 1700000000,
+            "min_reserve_usd": 0,
+            "excluded": [],
+            "emode_categories": [],
+            "base_currency": {"address": "0x0000000000000000000000000000000000000000", "unit": 100000000, "price_usd": null},
+            "collateral_only": false,
+            "failed_reserves": 0,
+            "score_floor": null,
+            "score_ceiling": null,
+            "backstop_usd": 0,
+            "weights": {},
+            "stale_after_secs": null,
+            "block_number": 18500000,
+            "nonce": 7
+        }"#;
+
+        assert!(matches!(load_input(json), Err(SchemaError::Unsupported { found: 17, .. })));
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.nonce, Some(7));
+        assert!(!migrated.reserves[0].siloed_borrowing);
+        assert_eq!(migrated.siloed_haircut_bps, 0);
+    }
+
+    #[test]
+    fn test_migrate_v18_keeps_siloed_haircut_and_has_no_rates() {
+        let json = r#"{
+            "schema_version": 18,
+            "reserves": [{
+                "token_address": "0xUSDC",
+                "total_atoken": 1000,
+                "total_stable_debt": 0,
+                "total_variable_debt": 0,
+                "price_usd": 100000000,
+                "decimals": 6,
+                "siloed_borrowing": true
+            }],
+            "protocol_name": "Aave V3",
+            "timestamp": 1700000000,
+            "min_reserve_usd": 0,
+            "excluded": [],
+            "emode_categories": [],
+            "base_currency": {"address": "0x0000000000000000000000000000000000000000", "unit": 100000000, "price_usd": null},
+            "collateral_only": false,
+            "failed_reserves": 0,
+            "score_floor": null,
+            "score_ceiling": null,
+            "backstop_usd": 0,
+            "weights": {},
+            "stale_after_secs": null,
+            "block_number": 18500000,
+            "nonce": 7,
+            "siloed_haircut_bps": 2000
+        }"#;
+
+        assert!(matches!(load_input(json), Err(SchemaError::Unsupported { found: 18, .. })));
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert!(migrated.reserves[0].siloed_borrowing);
+        assert_eq!(migrated.siloed_haircut_bps, 2_000);
+        assert_eq!(migrated.reserves[0].variable_borrow_rate, 0);
+        assert_eq!(migrated.projection_horizon_secs, None);
+    }
+
+    #[test]
+    fn test_migrate_v19_keeps_horizon_and_has_no_overrides() {
+        let json = r#"{
+            "schema_version": 19,
+            "reserves": [{
+                "token_address": "0xUSDC",
+                "total_atoken": 1000,
+                "total_stable_debt": 0,
+                "total_variable_debt": 0,
+                "price_usd": 100000000,
+                "decimals": 6,
+                "liquidity_rate": 41200000000000000000000000,
+                "variable_borrow_rate": 58300000000000000000000000
+            }],
+            "protocol_name": "Aave V3",
+            "timestamp": 1700000000,
+            "min_reserve_usd": 0,
+            "excluded": [],
+            "emode_categories": [],
+            "base_currency": {"address": "0x0000000000000000000000000000000000000000", "unit": 100000000, "price_usd": null},
+            "collateral_only": false,
+            "failed_reserves": 0,
+            "score_floor": null,
+            "score_ceiling": null,
+            "backstop_usd": 0,
+            "weights": {},
+            "stale_after_secs": null,
+            "block_number": 18500000,
+            "nonce": 7,
+            "siloed_haircut_bps": 0,
+            "projection_horizon_secs": 86400
+        }"#;
+
+        assert!(matches!(load_input(json), Err(SchemaError::Unsupported { found: 19, .. })));
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.projection_horizon_secs, Some(86_400));
+        assert_eq!(migrated.reserves[0].variable_borrow_rate, 58_300_000_000_000_000_000_000_000);
+        assert!(migrated.price_overrides.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_v20_keeps_overrides_and_has_no_saturated_reserves() {
+        let json = r#"{
+            "schema_version": 20,
+            "reserves": [{
+                "token_address": "0xUSDC",
+                "total_atoken": 1000,
+                "total_stable_debt": 0,
+                "total_variable_debt": 0,
+                "price_usd": 90000000,
+                "decimals": 6
+            }],
+            "protocol_name": "Aave V3",
+            "timestamp": 1700000000,
+            "min_reserve_usd": 0,
+            "excluded": [],
+            "emode_categories": [],
+            "base_currency": {"address": "0x0000000000000000000000000000000000000000", "unit": 100000000, "price_usd": null},
+            "collateral_only": false,
+            "failed_reserves": 0,
+            "score_floor": null,
+            "score_ceiling": null,
+            "backstop_usd": 0,
+            "weights": {},
+            "stale_after_secs": null,
+            "block_number": 18500000,
+            "nonce": null,
+            "siloed_haircut_bps": 0,
+            "projection_horizon_secs": null,
+            "price_overrides": {"0xusdc": 90000000}
+        }"#;
+
+        assert!(matches!(load_input(json), Err(SchemaError::Unsupported { found: 20, .. })));
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.price_overrides.get("0xusdc"), Some(&90_000_000));
+        assert!(!migrated.reserves[0].saturated);
+    }
+
+    #[test]
+    fn test_streamed_large_input_matches_string_path() {
+        let reserve = sample_input().reserves.remove(0);
+        let reserves = (0..5_000)
+            .map(|i| AaveReserveData {
+                token_address: format!("0x{:040x}", i),
+                total_variable_debt: i as u128 * 1_000_003,
+                ..reserve.clone()
+            })
+            .collect();
+        let input = AaveInput::new(reserves, "Aave V3".to_string(), 1234567890);
+        let json = serde_json::to_string_pretty(&input).unwrap();
+        assert!(json.len() > 1_000_000, "fixture should be multi-megabyte, got {} bytes", json.len());
+
+        let streamed = load_input_from_reader(json.as_bytes()).unwrap();
+        let loaded = load_input(&json).unwrap();
+        assert_eq!(streamed.reserves.len(), 5_000);
+        assert_eq!(serde_json::to_string(&streamed).unwrap(), serde_json::to_string(&loaded).unwrap());
+        assert_eq!(input_schema_version_from_reader(json.as_bytes()).unwrap(), INPUT_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_streamed_input_checks_version() {
+        let mut input = sample_input();
+        input.schema_version = INPUT_SCHEMA_VERSION + 1;
+        let json = serde_json::to_string(&input).unwrap();
+        assert!(matches!(
+            load_input_from_reader(json.as_bytes()),
+            Err(SchemaError::Unsupported { found, .. }) if found == INPUT_SCHEMA_VERSION + 1
+        ));
+
+        // An older file fails to decode, and the probe tells why
+        let v1 = r#"{"schema_version":1,"reserves":[],"protocol_name":"Aave V3","timestamp":1}"#;
+        assert!(matches!(load_input_from_reader(v1.as_bytes()), Err(SchemaError::Parse(_))));
+        assert_eq!(input_schema_version_from_reader(v1.as_bytes()).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_rejects_unversioned_file() {
+        let json = r#"{"reserves":[],"protocol_name":"Aave V3","timestamp":1}"#;
+
+        match load_input(json) {
+            Err(SchemaError::Unsupported { found, expected }) => {
+                assert_eq!(found, 0);
+                assert_eq!(expected, INPUT_SCHEMA_VERSION);
+            }
+            other => panic!("expected unsupported schema, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rejects_newer_schema() {
+        let json = r#"{"schema_version":999,"reserves":[],"protocol_name":"Aave V3","timestamp":1}"#;
+
+        assert!(matches!(
+            load_input(json),
+            Err(SchemaError::Unsupported { found: 999, .. })
+        ));
+        assert!(matches!(
+            migrate_input(json),
+            Err(SchemaError::Unsupported { found: 999, .. })
+        ));
+    }
+
+    #[test]
+    fn test_migrate_v1_adds_missing_thresholds() {
+        let json = r#"{
+            "schema_version": 1,
+            "reserves": [{
+                "token_address": "0xUSDC",
+                "total_atoken": 1000,
+                "total_stable_debt": 0,
+                "total_variable_debt": 10,
+                "price_usd": 100000000,
+                "decimals": 6
+            }],
+            "protocol_name": "Aave V3",
+            "timestamp": 1
+        }"#;
+
+        assert!(matches!(
+            load_input(json),
+            Err(SchemaError::Unsupported { found: 1, .. })
+        ));
+
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.reserves[0].liquidation_threshold, None);
+    }
+
+    #[test]
+    fn test_migrate_v2_disables_dust_filter() {
+        let json = r#"{
+            "schema_version": 2,
+            "reserves": [{
+                "token_address": "0xUSDC",
+                "total_atoken": 1000,
+                "total_stable_debt": 0,
+                "total_variable_debt": 10,
+                "price_usd": 100000000,
+                "decimals": 6,
+                "liquidation_threshold": 7800
+            }],
+            "protocol_name": "Aave V3",
+            "timestamp": 1
+        }"#;
+
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.min_reserve_usd, 0);
+        assert_eq!(migrated.reserves[0].liquidation_threshold, Some(7_800));
+    }
+
+    #[test]
+    fn test_migrate_v3_keeps_dust_filter() {
+        let json = r#"{
+            "schema_version": 3,
+            "reserves": [{
+                "token_address": "0xUSDC",
+                "total_atoken": 1000,
+                "total_stable_debt": 0,
+                "total_variable_debt": 10,
+                "price_usd": 100000000,
+                "decimals": 6,
+                "liquidation_threshold": 7800
+            }],
+            "protocol_name": "Aave V3",
+            "timestamp": 1,
+            "min_reserve_usd": 1000000000
+        }"#;
+
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.min_reserve_usd, 1_000_000_000);
+        assert_eq!(migrated.reserves[0].volatility_bps, None);
+        assert_eq!(migrated.reserves[0].isolation_mode_debt, 0);
+        assert_eq!(migrated.reserves[0].emode_category, 0);
+        assert!(migrated.emode_categories.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_v0_preserves_large_amounts() {
+        let json = r#"{
+            "reserves": [{
+                "token_address": "0xff34b3d4aee8ddcd6f9afffb6fe49bd371b8a357",
+                "total_atoken": 2883313057534146509735272106,
+                "total_stable_debt": 324607599980172812522864,
+                "total_variable_debt": 3011156542036519396643415022,
+                "price_usd": 100000000,
+                "decimals": 18
+            }],
+            "protocol_name": "Aave V3",
+            "timestamp": 1762077720
+        }"#;
+
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.reserves[0].total_atoken, 2883313057534146509735272106);
+        assert_eq!(migrated.reserves[0].total_variable_debt, 3011156542036519396643415022);
+        assert_eq!(migrated.timestamp, 1762077720);
+        assert_eq!(migrated.reserves[0].liquidation_threshold, None);
+        assert!(migrated.excluded.is_empty());
+
+        // A migrated file must load cleanly afterwards
+        let rewritten = serde_json::to_string(&migrated).unwrap();
+        assert!(load_input(&rewritten).is_ok());
+    }
+
+    #[test]
+    fn test_migrate_v7_keeps_categories_and_prices_in_usd() {
+        let json = r#"{
+            "schema_version": 7,
+            "reserves": [{
+                "token_address": "0xUSDC",
+                "total_atoken": 1000000,
+                "total_stable_debt": 0,
+                "total_variable_debt": 0,
+                "price_usd": 100000000,
+                "decimals": 6,
+                "emode_category": 1
+            }],
+            "protocol_name": "Aave V3",
+            "timestamp": 1,
+            "min_reserve_usd": 0,
+            "excluded": ["0xDEAD"],
+            "emode_categories": [{"id": 1, "liquidation_threshold": 9500, "label": "Stablecoins"}]
+        }"#;
+
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.excluded, vec!["0xDEAD".to_string()]);
+        assert_eq!(migrated.emode_categories, sample_input().emode_categories);
+        assert_eq!(migrated.base_currency, BaseCurrency::default());
+        assert!(migrated.base_currency.is_usd());
+    }
+
+    #[test]
+    fn test_migrate_v9_keeps_base_currency_and_counts_all_assets() {
+        let json = r#"{
+            "schema_version": 9,
+            "reserves": [],
+            "protocol_name": "Aave V3",
+            "timestamp": 1,
+            "min_reserve_usd": 0,
+            "excluded": [],
+            "emode_categories": [],
+            "base_currency": {
+                "address": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                "unit": 1000000000000000000,
+                "price_usd": null
+            }
+        }"#;
+
+        let migrated = migrate_input(json).unwrap();
+        assert_eq!(migrated.schema_version, INPUT_SCHEMA_VERSION);
+        assert_eq!(migrated.base_currency.unit, 1_000_000_000_000_000_000);
+        assert!(!migrated.base_currency.is_usd());
+        assert!(!migrated.collateral_only);
+    }
+}
@@ -0,0 +1,1253 @@
+// Safety score computation
+// The scoring logic the guest proves, kept here as plain Rust so host tests
+// can call exactly the same code without running the zkVM
+
+use crate::{
+    bound_breakdown, concentration_bps, div_round, health_factor, isolation_debt_to_usd, normalize_amount_rounded, saturating_sum,
+    volatility_discount, AaveInput, AaveReserveData, BaseCurrency, EModeCategory, ReserveValue, RoundingMode,
+    SafetyScoreOutput, ScoreStatus, GUEST_VERSION, MAX_BREAKDOWN_RESERVES,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Scale of the safety score: 1_000_000 = 100%
+pub const SCORE_SCALE: u64 = 1_000_000;
+
+/// Scaled score for a percentage, e.g. `scaled(98.5) == 985_000`
+/// Rounds to the nearest unit and clamps to [0, SCORE_SCALE]; NaN gives 0.
+/// `const` so expected scores and thresholds can be written as percentages
+pub const fn scaled(pct: f64) -> u64 {
+    // The float to int cast saturates, which takes care of negatives and NaN
+    let score = (pct * (SCORE_SCALE / 100) as f64 + 0.5) as u64;
+    if score > SCORE_SCALE {
+        SCORE_SCALE
+    } else {
+        score
+    }
+}
+
+/// Share of a supply or borrow cap (bps) at which a reserve counts as capped
+pub const NEAR_CAP_BPS: u128 = 9_500;
+
+/// Aave's fixed-point unit for rates: 1e27 = 100% a year
+pub const RAY: u128 = 1_000_000_000_000_000_000_000_000_000;
+
+/// Year length Aave's rates are annualized over
+pub const SECONDS_PER_YEAR: u128 = 365 * 86_400;
+
+/// Precision of the growth factor interest is accrued with: 1e12 keeps a
+/// single second at 1% a year (about 3e-10) from rounding to nothing
+const GROWTH_PRECISION: u128 = 1_000_000_000_000;
+
+/// Parameters that change how reserves are scored
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScoringStrategy {
+    /// Reserves with an asset value (USD, scaled by 1e8) below this are skipped
+    pub min_reserve_usd: u128,
+
+    /// eMode categories whose thresholds replace a member reserve's own
+    /// threshold in the weighted health factor
+    pub emode_categories: Vec<EModeCategory>,
+
+    /// Currency reserve prices are quoted in, and how to convert it to USD
+    pub base_currency: BaseCurrency,
+
+    /// Count only collateral-enabled reserves' supply as assets
+    pub collateral_only: bool,
+
+    /// Governance bounds the reported score is clamped into (scaled by 1e4)
+    pub score_floor: Option<u64>,
+    pub score_ceiling: Option<u64>,
+
+    /// Off-protocol funds (USD, scaled by 1e8) added to the assets the score
+    /// is computed from; the committed totals don't include them
+    pub backstop_usd: u128,
+
+    /// Custom risk weights (bps) per lowercase token address
+    pub weights: BTreeMap<String, u16>,
+
+    /// Time of the snapshot, and how long before it a reserve's last
+    /// on-chain update may be before the reserve counts as stale
+    pub snapshot_timestamp: u64,
+    pub stale_after_secs: Option<u64>,
+
+    /// Haircut (bps) on siloed-borrowing reserves' assets in the score
+    pub siloed_haircut_bps: u16,
+
+    /// Seconds of interest to accrue for the projected score; None skips it
+    pub projection_horizon_secs: Option<u64>,
+}
+
+impl ScoringStrategy {
+    /// The strategy an input asks for
+    pub fn for_input(input: &AaveInput) -> Self {
+        Self {
+            min_reserve_usd: input.min_reserve_usd,
+            emode_categories: input.emode_categories.clone(),
+            base_currency: input.base_currency.clone(),
+            collateral_only: input.collateral_only,
+            score_floor: input.score_floor,
+            score_ceiling: input.score_ceiling,
+            backstop_usd: input.backstop_usd,
+            weights: input.weights.clone(),
+            snapshot_timestamp: input.timestamp,
+            stale_after_secs: input.stale_after_secs,
+            siloed_haircut_bps: input.siloed_haircut_bps,
+            projection_horizon_secs: input.projection_horizon_secs,
+        }
+    }
+
+    /// Whether a reserve was last updated more than `stale_after_secs` before
+    /// the snapshot. A reserve with no known update time is never stale
+    pub fn is_stale(&self, reserve: &AaveReserveData) -> bool {
+        match self.stale_after_secs {
+            Some(max_age) if reserve.last_update_timestamp != 0 => {
+                self.snapshot_timestamp.saturating_sub(reserve.last_update_timestamp) > max_age
+            }
+            _ => false,
+        }
+    }
+
+    /// Custom risk weight (bps) for a reserve, None when it isn't listed
+    pub fn asset_weight(&self, reserve: &AaveReserveData) -> Option<u16> {
+        if self.weights.is_empty() {
+            return None;
+        }
+        self.weights.get(&reserve.token_address.to_ascii_lowercase()).copied()
+    }
+
+    /// The score to report for a computed score, and whether it was clamped
+    /// The ceiling wins if the bounds cross; `check_score_bounds` rejects that
+    pub fn clamp_score(&self, score: u64) -> (u64, bool) {
+        let floored = self.score_floor.map_or(score, |floor| score.max(floor));
+        let bounded = self.score_ceiling.map_or(floored, |ceiling| floored.min(ceiling));
+        (bounded, bounded != score)
+    }
+
+    /// Liquidation threshold (bps) to weight a reserve's collateral by
+    /// Uses the reserve's eMode category threshold when the category is known,
+    /// otherwise the reserve's base threshold
+    pub fn liquidation_threshold(&self, reserve: &AaveReserveData) -> Option<u16> {
+        if reserve.emode_category != 0 {
+            if let Some(category) = self.emode_categories.iter().find(|c| c.id == reserve.emode_category) {
+                return Some(category.liquidation_threshold);
+            }
+        }
+        reserve.liquidation_threshold
+    }
+}
+
+/// Whether a reserve's supply or debt has reached `NEAR_CAP_BPS` of its cap
+/// Caps are in whole tokens, so they're scaled by the token's decimals first
+pub fn near_cap(reserve: &AaveReserveData) -> bool {
+    let one_token = 10u128.saturating_pow(reserve.decimals as u32);
+    let near = |amount: u128, cap: u64| {
+        // amount >= cap * NEAR_CAP_BPS / 10_000, without dividing
+        cap != 0
+            && amount.saturating_mul(10_000)
+                >= (cap as u128).saturating_mul(one_token).saturating_mul(NEAR_CAP_BPS)
+    };
+    let total_debt = reserve.total_stable_debt.saturating_add(reserve.total_variable_debt);
+
+    near(reserve.total_atoken, reserve.supply_cap) || near(total_debt, reserve.borrow_cap)
+}
+
+/// Whether a reserve's supply can back loans
+/// Aave V3 has no separate flag for this: a reserve is collateral exactly
+/// when its liquidation threshold is non-zero (as the data provider's
+/// `usageAsCollateralEnabled` reports). An unknown threshold counts as collateral
+pub fn collateral_enabled(reserve: &AaveReserveData) -> bool {
+    reserve.liquidation_threshold != Some(0)
+}
+
+/// Sums over a set of reserves, before they're turned into a score
+/// Scoring reserves in batches and merging the batches' totals gives the same
+/// output as scoring them all at once, which is what lets batches be proven
+/// separately and composed (see compose.rs)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialTotals {
+    pub total_assets_usd: u128,
+    pub total_liabilities_usd: u128,
+
+    /// The liabilities split by rate mode; variable-rate debt reprices with
+    /// utilization, so the mix is a risk signal the combined total hides
+    pub stable_liabilities_usd: u128,
+    pub variable_liabilities_usd: u128,
+
+    /// Collateral weighted by liquidation threshold (bps) for the health
+    /// factor, only meaningful when no reserve was missing its threshold
+    pub weighted_collateral_usd: u128,
+    pub missing_thresholds: bool,
+
+    /// Dust reserves below the strategy's threshold are excluded from the score
+    pub skipped_reserves: u32,
+
+    /// Sums clamp at u128::MAX instead of panicking; the flag is committed so
+    /// a verifier can tell the totals are lower bounds. A scored reserve
+    /// whose amounts were clamped when fetched sets it too
+    pub saturated: bool,
+
+    /// Assets discounted by each reserve's volatility, weight and siloed
+    /// haircut; only the score uses this, the committed totals stay at market
+    /// value. Equal to total_assets_usd when no discount applies
+    pub score_assets_usd: u128,
+    pub volatility_adjusted: bool,
+
+    /// Whether any scored reserve had a custom risk weight or the siloed
+    /// haircut applied on top
+    pub risk_weighted: bool,
+
+    /// Debt borrowed against isolated collateral. Aave reports it in USD with
+    /// 2 decimals, so it is converted with isolation_debt_to_usd rather than
+    /// normalize_amount (no token decimals or oracle price involved)
+    pub isolation_mode_debt_usd: u128,
+
+    /// Reserves priced at 0 can't be valued at all. Counting them as worth $0
+    /// would drop their debt too and could make the protocol look safer, so
+    /// they're left out and counted instead
+    pub reserves_with_missing_price: u32,
+
+    /// Scored reserves with little headroom left under their supply or borrow cap
+    pub capped_reserves: u32,
+
+    /// Scored reserves whose indices haven't been updated on-chain for a while
+    pub stale_reserve_count: u32,
+
+    /// Scored reserves Aave restricts to siloed borrowing
+    pub siloed_reserve_count: u32,
+
+    /// Interest the scored assets and the variable debt accrue over the
+    /// projection horizon at current rates. Stable debt is left as it is: its
+    /// rate was fixed when it was borrowed and Aave no longer reports it here
+    pub asset_interest_usd: u128,
+    pub liability_interest_usd: u128,
+
+    /// Each scored reserve's values, in input order
+    pub values: Vec<ReserveValue>,
+}
+
+impl PartialTotals {
+    /// Totals over both batches, as if their reserves had been scored
+    /// together (`self`'s first)
+    pub fn merge(self, other: PartialTotals) -> PartialTotals {
+        let mut saturated = self.saturated || other.saturated;
+        let mut sum = |a: u128, b: u128| saturating_sum(a, b, &mut saturated);
+        let mut values = self.values;
+        values.extend(other.values);
+        PartialTotals {
+            total_assets_usd: sum(self.total_assets_usd, other.total_assets_usd),
+            total_liabilities_usd: sum(self.total_liabilities_usd, other.total_liabilities_usd),
+            stable_liabilities_usd: sum(self.stable_liabilities_usd, other.stable_liabilities_usd),
+            variable_liabilities_usd: sum(self.variable_liabilities_usd, other.variable_liabilities_usd),
+            weighted_collateral_usd: sum(self.weighted_collateral_usd, other.weighted_collateral_usd),
+            missing_thresholds: self.missing_thresholds || other.missing_thresholds,
+            skipped_reserves: self.skipped_reserves.saturating_add(other.skipped_reserves),
+            score_assets_usd: sum(self.score_assets_usd, other.score_assets_usd),
+            volatility_adjusted: self.volatility_adjusted || other.volatility_adjusted,
+            risk_weighted: self.risk_weighted || other.risk_weighted,
+            isolation_mode_debt_usd: sum(self.isolation_mode_debt_usd, other.isolation_mode_debt_usd),
+            reserves_with_missing_price: self.reserves_with_missing_price.saturating_add(other.reserves_with_missing_price),
+            capped_reserves: self.capped_reserves.saturating_add(other.capped_reserves),
+            stale_reserve_count: self.stale_reserve_count.saturating_add(other.stale_reserve_count),
+            siloed_reserve_count: self.siloed_reserve_count.saturating_add(other.siloed_reserve_count),
+            asset_interest_usd: sum(self.asset_interest_usd, other.asset_interest_usd),
+            liability_interest_usd: sum(self.liability_interest_usd, other.liability_interest_usd),
+            values,
+            saturated,
+        }
+    }
+}
+
+/// Compute the safety score and totals for a set of reserves
+/// The timestamp and excluded count aren't known here and are left at 0 for
+/// the caller to fill in
+pub fn compute_safety_score(reserves: &[AaveReserveData], strategy: &ScoringStrategy) -> SafetyScoreOutput {
+    finish_score(accumulate_reserves(reserves, strategy), strategy)
+}
+
+/// Value each reserve and sum the values, without scoring them yet
+pub fn accumulate_reserves(reserves: &[AaveReserveData], strategy: &ScoringStrategy) -> PartialTotals {
+    let mut totals = PartialTotals { values: Vec::with_capacity(reserves.len()), ..PartialTotals::default() };
+    let saturated = &mut totals.saturated;
+
+    for reserve in reserves {
+        if reserve.price_usd == 0 {
+            totals.reserves_with_missing_price += 1;
+            continue;
+        }
+
+        // Assets = aToken balance (what users have deposited)
+        // Rounded down so rounding never makes the protocol look safer
+        // Values come out in the oracle's base unit and are rescaled to 1e8
+        let asset_value_usd = strategy.base_currency.rescale(
+            normalize_amount_rounded(reserve.total_atoken, reserve.decimals, reserve.price_usd, RoundingMode::Floor),
+            RoundingMode::Floor,
+            saturated,
+        );
+
+        // Skip dust reserves - they barely move the score but still cost cycles
+        if asset_value_usd < strategy.min_reserve_usd {
+            totals.skipped_reserves += 1;
+            continue;
+        }
+
+        if near_cap(reserve) {
+            totals.capped_reserves += 1;
+        }
+        if strategy.is_stale(reserve) {
+            totals.stale_reserve_count += 1;
+        }
+        if reserve.saturated {
+            *saturated = true;
+        }
+
+        // Supply that can't back loans still counts towards the dust filter
+        // above, but in collateral-only mode not as an asset
+        let asset_value_usd = if strategy.collateral_only && !collateral_enabled(reserve) {
+            0
+        } else {
+            asset_value_usd
+        };
+
+        // Liabilities = stable debt + variable debt, rounded up for the same reason
+        let total_debt = saturating_sum(reserve.total_stable_debt, reserve.total_variable_debt, saturated);
+        let liability_value_usd = strategy.base_currency.rescale(
+            normalize_amount_rounded(total_debt, reserve.decimals, reserve.price_usd, RoundingMode::Ceil),
+            RoundingMode::Ceil,
+            saturated,
+        );
+
+        // The stable part is valued on its own and the variable part is the
+        // rest, so the two always add up to the reserve's rounded liability
+        let stable_value_usd = strategy.base_currency.rescale(
+            normalize_amount_rounded(reserve.total_stable_debt, reserve.decimals, reserve.price_usd, RoundingMode::Ceil),
+            RoundingMode::Ceil,
+            saturated,
+        ).min(liability_value_usd);
+        let variable_value_usd = liability_value_usd - stable_value_usd;
+
+        match strategy.liquidation_threshold(reserve) {
+            Some(threshold) => {
+                let weighted = asset_value_usd.checked_mul(threshold as u128).unwrap_or_else(|| {
+                    *saturated = true;
+                    u128::MAX
+                });
+                totals.weighted_collateral_usd = saturating_sum(totals.weighted_collateral_usd, weighted, saturated);
+            }
+            None => totals.missing_thresholds = true,
+        }
+
+        if reserve.volatility_bps.is_some() {
+            totals.volatility_adjusted = true;
+        }
+        let mut discounted_value_usd = volatility_discount(asset_value_usd, reserve.volatility_bps);
+
+        // A weight of w bps keeps w / 10_000 of the value: a discount of 10_000 - w
+        if let Some(weight) = strategy.asset_weight(reserve) {
+            totals.risk_weighted = true;
+            discounted_value_usd = volatility_discount(discounted_value_usd, Some(10_000 - weight.min(10_000)));
+        }
+
+        // Like a weight, the siloed haircut lowers the score but not the totals
+        if reserve.siloed_borrowing {
+            totals.siloed_reserve_count += 1;
+            if strategy.siloed_haircut_bps > 0 {
+                totals.risk_weighted = true;
+                discounted_value_usd = volatility_discount(discounted_value_usd, Some(strategy.siloed_haircut_bps));
+            }
+        }
+
+        // Assets accrue rounded down and debt rounded up, like the values themselves
+        if let Some(horizon_secs) = strategy.projection_horizon_secs {
+            let earned = accrued_interest(discounted_value_usd, reserve.liquidity_rate, horizon_secs, RoundingMode::Floor);
+            let owed = accrued_interest(variable_value_usd, reserve.variable_borrow_rate, horizon_secs, RoundingMode::Ceil);
+            totals.asset_interest_usd = saturating_sum(totals.asset_interest_usd, earned, saturated);
+            totals.liability_interest_usd = saturating_sum(totals.liability_interest_usd, owed, saturated);
+        }
+
+        if reserve.isolation_mode_debt > 0 {
+            let debt_usd = isolation_debt_to_usd(reserve.isolation_mode_debt);
+            totals.isolation_mode_debt_usd = saturating_sum(totals.isolation_mode_debt_usd, debt_usd, saturated);
+        }
+
+        totals.values.push(ReserveValue {
+            token_address: reserve.token_address.clone(),
+            assets_usd: asset_value_usd,
+            liabilities_usd: liability_value_usd,
+        });
+
+        totals.total_assets_usd = saturating_sum(totals.total_assets_usd, asset_value_usd, saturated);
+        totals.score_assets_usd = saturating_sum(totals.score_assets_usd, discounted_value_usd, saturated);
+        totals.total_liabilities_usd = saturating_sum(totals.total_liabilities_usd, liability_value_usd, saturated);
+        totals.stable_liabilities_usd = saturating_sum(totals.stable_liabilities_usd, stable_value_usd, saturated);
+        totals.variable_liabilities_usd = saturating_sum(totals.variable_liabilities_usd, variable_value_usd, saturated);
+    }
+    totals
+}
+
+/// Turn summed reserve values into the score and committed totals
+pub fn finish_score(totals: PartialTotals, strategy: &ScoringStrategy) -> SafetyScoreOutput {
+    let PartialTotals {
+        total_assets_usd,
+        mut total_liabilities_usd,
+        stable_liabilities_usd,
+        variable_liabilities_usd,
+        weighted_collateral_usd,
+        missing_thresholds,
+        skipped_reserves,
+        mut saturated,
+        score_assets_usd,
+        volatility_adjusted,
+        risk_weighted,
+        isolation_mode_debt_usd,
+        reserves_with_missing_price,
+        capped_reserves,
+        stale_reserve_count,
+        siloed_reserve_count,
+        asset_interest_usd,
+        liability_interest_usd,
+        values,
+    } = totals;
+
+    // Isolation-mode debt is also minted as debt tokens on the borrowed
+    // reserves, so it's already in total_liabilities_usd - adding it again would
+    // double count. It is used as a floor instead: liabilities can never be
+    // less than the isolated debt, e.g. when a borrowed reserve failed to fetch
+    // Isolation debt is always USD, so the floor only applies to USD totals
+    let denomination = strategy.base_currency.denomination();
+    if denomination.is_none() && isolation_mode_debt_usd > total_liabilities_usd {
+        total_liabilities_usd = isolation_mode_debt_usd;
+    }
+
+    // HF = sum(collateral_usd * liq_threshold) / sum(debt_usd), scaled by 1e4
+    // Skipped entirely if any reserve is missing its threshold, since a partial
+    // sum would overstate the protocol's risk
+    let weighted_health_factor = if missing_thresholds {
+        None
+    } else {
+        Some(health_factor(weighted_collateral_usd, total_liabilities_usd))
+    };
+
+    // One value per reserve that made it through the price and dust checks
+    let included = values.len() as u32;
+
+    // Measured over every scored reserve, before the breakdown folds the
+    // smallest into "other"
+    let asset_concentration_bps = concentration_bps(&values, total_assets_usd);
+    let breakdown = bound_breakdown(values, MAX_BREAKDOWN_RESERVES, &mut saturated);
+
+    // A backstop covers shortfalls, so it counts towards the buffer but isn't
+    // collateral: it stays out of the totals and the health factor
+    let score_assets_usd = saturating_sum(score_assets_usd, strategy.backstop_usd, &mut saturated);
+
+    // Governance bounds apply to the finished score only; the committed
+    // totals stay as computed, so a clamped score is visible against them
+    let (safety_score, clamped) = strategy.clamp_score(buffer_score(score_assets_usd, total_liabilities_usd));
+
+    // The same score once the horizon's interest has accrued; the committed
+    // totals stay at the snapshot
+    let projected_score = strategy.projection_horizon_secs.map(|_| {
+        let assets_usd = saturating_sum(score_assets_usd, asset_interest_usd, &mut saturated);
+        let liabilities_usd = saturating_sum(total_liabilities_usd, liability_interest_usd, &mut saturated);
+        strategy.clamp_score(buffer_score(assets_usd, liabilities_usd)).0
+    });
+
+    // Several very different states all score 0; the status tells them
+    // apart. Missing data outranks insolvency, since the unpriced reserves'
+    // debt is left out along with their assets
+    let status = if total_assets_usd == 0 && total_liabilities_usd == 0 {
+        ScoreStatus::NoData
+    } else if reserves_with_missing_price > 0 {
+        ScoreStatus::Incomplete
+    } else if total_liabilities_usd > 0 && total_liabilities_usd >= score_assets_usd {
+        ScoreStatus::Insolvent
+    } else {
+        ScoreStatus::Ok
+    };
+
+    SafetyScoreOutput::new(
+        safety_score,
+        total_assets_usd,
+        total_liabilities_usd,
+        0,
+    )
+    .with_weighted_health_factor(weighted_health_factor)
+    .with_skipped_reserves(skipped_reserves)
+    .with_saturated(saturated)
+    .with_volatility_adjusted(volatility_adjusted)
+    .with_isolation_mode_debt_usd(isolation_mode_debt_usd)
+    .with_reserves_with_missing_price(reserves_with_missing_price)
+    .with_breakdown(breakdown)
+    .with_denomination(denomination)
+    .with_capped_reserves(capped_reserves)
+    .with_collateral_only(strategy.collateral_only)
+    .with_debt_mix(stable_liabilities_usd, variable_liabilities_usd)
+    .with_clamped(clamped)
+    .with_backstop_usd(strategy.backstop_usd)
+    .with_risk_weighted(risk_weighted)
+    .with_stale_reserve_count(stale_reserve_count)
+    .with_asset_concentration_bps(asset_concentration_bps)
+    .with_siloed_reserve_count(siloed_reserve_count)
+    .with_completeness(included, included.saturating_add(reserves_with_missing_price))
+    .with_projected_score(projected_score)
+    .with_guest_version(GUEST_VERSION)
+    .with_status(status)
+}
+
+/// Simple interest on `value_usd` over `horizon_secs` at an annual rate in ray
+/// Aave compounds variable debt per second, but over the short horizons a
+/// projection is for the difference is far below the score's precision
+fn accrued_interest(value_usd: u128, rate_ray: u128, horizon_secs: u64, mode: RoundingMode) -> u128 {
+    if value_usd == 0 || rate_ray == 0 || horizon_secs == 0 {
+        return 0;
+    }
+    let growth = div_round(
+        rate_ray.saturating_mul(horizon_secs as u128),
+        SECONDS_PER_YEAR * (RAY / GROWTH_PRECISION),
+        mode,
+    );
+    // Saturated totals can overflow the scaling; divide first for those
+    match value_usd.checked_mul(growth) {
+        Some(scaled) => div_round(scaled, GROWTH_PRECISION, mode),
+        None => (value_usd / GROWTH_PRECISION).saturating_mul(growth),
+    }
+}
+
+/// Check an input's score bounds are within SCORE_SCALE and don't cross
+pub fn check_score_bounds(score_floor: Option<u64>, score_ceiling: Option<u64>) -> Result<(), &'static str> {
+    if score_floor.is_some_and(|floor| floor > SCORE_SCALE) || score_ceiling.is_some_and(|ceiling| ceiling > SCORE_SCALE) {
+        return Err("score bounds exceed SCORE_SCALE");
+    }
+    if let (Some(floor), Some(ceiling)) = (score_floor, score_ceiling) {
+        if floor > ceiling {
+            return Err("score floor is above the score ceiling");
+        }
+    }
+    Ok(())
+}
+
+/// The output the guest commits for an input: the score and totals, stamped
+/// with the input's timestamp and exclusion count. Guest-side checks on the
+/// input (bounds, weights, exclusions) are the caller's job
+pub fn compute_output(input: &AaveInput) -> SafetyScoreOutput {
+    stamp_output(compute_safety_score(&input.reserves, &ScoringStrategy::for_input(input)), input)
+}
+
+/// Stamp a score computed over `input`'s reserves with what the input says
+/// about them: its timestamp, exclusions, failed fetches, nonce and overrides
+pub fn stamp_output(output: SafetyScoreOutput, input: &AaveInput) -> SafetyScoreOutput {
+    let output = SafetyScoreOutput { timestamp: input.timestamp, ..output };
+    // Reserves that failed to fetch never made it into the input, but the
+    // score should have covered them
+    let (included, total) = (output.reserves_included, output.reserves_total.saturating_add(input.failed_reserves));
+    let status = match output.status {
+        ScoreStatus::Ok | ScoreStatus::Insolvent if included < total => ScoreStatus::Incomplete,
+        status => status,
+    };
+    output
+        .with_completeness(included, total)
+        .with_excluded_reserves(input.excluded.len() as u32)
+        .with_nonce(input.nonce)
+        .with_hypothetical(!input.price_overrides.is_empty())
+        .with_status(status)
+}
+
+/// Check every recorded price override is the price its reserve carries
+/// Otherwise an output could be marked hypothetical, or not, for prices other
+/// than the ones it scored
+pub fn check_price_overrides(input: &AaveInput) -> Result<(), &'static str> {
+    for (address, &price) in &input.price_overrides {
+        match input.reserves.iter().find(|reserve| reserve.token_address.eq_ignore_ascii_case(address)) {
+            Some(reserve) if reserve.price_usd == price => {}
+            Some(_) => return Err("reserve price differs from its recorded override"),
+            None => return Err("price override for a reserve not in the input"),
+        }
+    }
+    Ok(())
+}
+
+/// Check custom risk weights are at most 10_000 bps (100%) and keyed by
+/// lowercase address. Heavier weights would count more than a reserve is
+/// worth, and a mixed-case key would silently never match
+pub fn check_weights(weights: &BTreeMap<String, u16>) -> Result<(), &'static str> {
+    if weights.values().any(|&weight| weight > 10_000) {
+        return Err("risk weight exceeds 10_000 bps");
+    }
+    if weights.keys().any(|address| address.bytes().any(|b| b.is_ascii_uppercase())) {
+        return Err("risk weight address is not lowercase");
+    }
+    Ok(())
+}
+
+/// Check an output's committed values agree with each other
+/// The guest runs this before committing, so a scoring bug fails the proof
+/// instead of putting an absurd score on-chain. Only integer comparisons and
+/// one division, to stay cheap in the zkVM
+pub fn check_output_invariants(output: &SafetyScoreOutput) -> Result<(), &'static str> {
+    if output.safety_score > SCORE_SCALE {
+        return Err("safety score exceeds SCORE_SCALE");
+    }
+
+    // Equal unless the isolation-mode floor raised the total
+    let debt_mix = output.total_stable_liabilities_usd.saturating_add(output.total_variable_liabilities_usd);
+    if debt_mix > output.total_liabilities_usd {
+        return Err("stable and variable liabilities exceed the total");
+    }
+
+    // No data means nothing was valued, and only then
+    let no_totals = output.total_assets_usd == 0 && output.total_liabilities_usd == 0;
+    if (output.status == ScoreStatus::NoData) != no_totals {
+        return Err("status disagrees with the committed totals");
+    }
+
+    // A clamped score is a governance bound, which needn't follow from the totals
+    if output.clamped {
+        return Ok(());
+    }
+    // The score counts the backstop as assets on top of the totals
+    let assets_usd = output.total_assets_usd.saturating_add(output.backstop_usd);
+    if output.safety_score > 0 && output.total_liabilities_usd >= assets_usd {
+        return Err("non-zero score with liabilities covering all assets");
+    }
+
+    // The score is computed from volatility-discounted and risk-weighted
+    // assets, which are never more than the committed totals, so it can only
+    // come out lower
+    let recomputed = buffer_score(assets_usd, output.total_liabilities_usd);
+    if output.volatility_adjusted || output.risk_weighted {
+        if output.safety_score > recomputed {
+            return Err("volatility-weighted score exceeds the score of the committed totals");
+        }
+    } else if output.safety_score != recomputed {
+        return Err("safety score doesn't match the committed totals");
+    }
+    Ok(())
+}
+
+/// Safety Score = (Buffer / Total Assets) * 100, scaled by 1e4
+/// Where Buffer = Total Assets - Total Liabilities
+///
+/// This represents what percentage of assets are "safe" (not owed to borrowers)
+/// Examples:
+/// - Score = 100% → No debt, fully safe
+/// - Score = 95% → Protocol has 5% buffer
+/// - Score = 0% → Protocol is insolvent (liabilities >= assets), or has no assets
+fn buffer_score(assets_usd: u128, liabilities_usd: u128) -> u64 {
+    if assets_usd == 0 || liabilities_usd >= assets_usd {
+        return 0;
+    }
+
+    let buffer = assets_usd - liabilities_usd;
+
+    // Saturated totals can be large enough for the scaling to overflow;
+    // fall back to scaling the divisor down instead (buffer <= assets,
+    // so assets >= 1e6 whenever the multiplication overflows)
+    let score = match buffer.checked_mul(SCORE_SCALE as u128) {
+        Some(scaled) => scaled / assets_usd,
+        None => buffer / (assets_usd / SCORE_SCALE as u128),
+    };
+
+    // Cap at 100%
+    score.min(SCORE_SCALE as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn reserve(total_atoken: u128, total_debt: u128, price_usd: u128, decimals: u8) -> AaveReserveData {
+        AaveReserveData::builder("0xTEST")
+            .total_atoken(total_atoken)
+            .total_variable_debt(total_debt)
+            .price_usd(price_usd)
+            .decimals(decimals)
+            .build()
+    }
+
+    #[test]
+    fn test_known_score() {
+        // $1,000 supplied, $400 borrowed -> 60% buffer
+        let reserves = [reserve(1_000_000_000, 400_000_000, 100_000_000, 6)];
+        let output = compute_safety_score(&reserves, &ScoringStrategy::default());
+
+        assert_eq!(output.safety_score, 600_000);
+        assert_eq!(output.total_assets_usd, 1_000 * 100_000_000);
+        assert_eq!(output.total_liabilities_usd, 400 * 100_000_000);
+        assert_eq!(output.weighted_health_factor, None);
+    }
+
+    #[test]
+    fn test_empty_reserves_score_zero() {
+        let output = compute_safety_score(&[], &ScoringStrategy::default());
+        assert_eq!(output.safety_score, 0);
+        assert_eq!(output.status, ScoreStatus::NoData);
+    }
+
+    #[test]
+    fn test_status_tells_zero_scores_apart() {
+        let strategy = ScoringStrategy::default();
+        let solvent = reserve(1_000_000_000, 400_000_000, 100_000_000, 6);
+        assert_eq!(compute_safety_score(std::slice::from_ref(&solvent), &strategy).status, ScoreStatus::Ok);
+
+        // $1,000 supplied, $1,200 borrowed
+        let underwater = [reserve(1_000_000_000, 1_200_000_000, 100_000_000, 6)];
+        let output = compute_safety_score(&underwater, &strategy);
+        assert_eq!(output.safety_score, 0);
+        assert_eq!(output.status, ScoreStatus::Insolvent);
+
+        // Still insolvent when a floor raises the score
+        let floored = ScoringStrategy { score_floor: Some(scaled(10.0)), ..Default::default() };
+        let output = compute_safety_score(&underwater, &floored);
+        assert_eq!((output.safety_score, output.status), (scaled(10.0), ScoreStatus::Insolvent));
+
+        // Assets weighted down to nothing, with nothing borrowed, aren't insolvent
+        let mut long_tail = reserve(1_000_000_000, 0, 100_000_000, 6);
+        long_tail.token_address = "0xLongTail".to_string();
+        let zero_weight = ScoringStrategy { weights: BTreeMap::from([("0xlongtail".to_string(), 0)]), ..Default::default() };
+        let output = compute_safety_score(&[long_tail], &zero_weight);
+        assert_eq!((output.safety_score, output.status), (0, ScoreStatus::Ok));
+        assert_eq!(check_output_invariants(&output), Ok(()));
+
+        // Only unpriced or worthless reserves: nothing to score
+        let unpriced = reserve(1_000_000_000, 400_000_000, 0, 6);
+        assert_eq!(compute_safety_score(std::slice::from_ref(&unpriced), &strategy).status, ScoreStatus::NoData);
+        assert_eq!(compute_safety_score(&[reserve(0, 0, 100_000_000, 6)], &strategy).status, ScoreStatus::NoData);
+
+        // An unpriced reserve next to a scored one
+        let output = compute_safety_score(&[solvent.clone(), unpriced], &strategy);
+        assert_eq!(output.status, ScoreStatus::Incomplete);
+
+        // So does a reserve that failed to fetch, even under an insolvent score
+        let input = AaveInput::new(underwater.to_vec(), "Aave V3".to_string(), 1);
+        assert_eq!(compute_output(&input).status, ScoreStatus::Insolvent);
+        let partial = AaveInput { failed_reserves: 1, ..input };
+        assert_eq!(compute_output(&partial).status, ScoreStatus::Incomplete);
+
+        // With nothing fetched at all there's still no data
+        let nothing = AaveInput { reserves: Vec::new(), ..partial };
+        assert_eq!(compute_output(&nothing).status, ScoreStatus::NoData);
+    }
+
+    #[test]
+    fn test_dust_reserves_skipped() {
+        let reserves = [
+            reserve(1_000_000_000, 400_000_000, 100_000_000, 6),
+            reserve(5_000_000, 5_000_000, 100_000_000, 6), // $5 of pure debt
+        ];
+        let strategy = ScoringStrategy { min_reserve_usd: 10 * 100_000_000, ..Default::default() };
+        let output = compute_safety_score(&reserves, &strategy);
+
+        assert_eq!(output.skipped_reserves, 1);
+        assert_eq!(output.safety_score, 600_000);
+    }
+
+    #[test]
+    fn test_eth_base_currency_rescales() {
+        // ETH-based oracle (unit 1e18): 1 USDC = 1/2000 ETH
+        let eth = BaseCurrency {
+            address: "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".to_string(),
+            unit: 1_000_000_000_000_000_000,
+            price_usd: None,
+        };
+        let reserves = [reserve(1_000_000_000, 400_000_000, 500_000_000_000_000, 6)];
+
+        // Without a USD price the totals stay in ETH, scaled by 1e8
+        let strategy = ScoringStrategy { base_currency: eth.clone(), ..Default::default() };
+        let output = compute_safety_score(&reserves, &strategy);
+        assert_eq!(output.total_assets_usd, 50_000_000); // 0.5 ETH
+        assert_eq!(output.total_liabilities_usd, 20_000_000); // 0.2 ETH
+        assert_eq!(output.safety_score, 600_000);
+        assert_eq!(output.denomination, Some(eth.address.clone()));
+
+        // With ETH at $2,000 they match the same reserve priced in USD
+        let strategy = ScoringStrategy {
+            base_currency: BaseCurrency { price_usd: Some(2_000 * 100_000_000), ..eth },
+            ..Default::default()
+        };
+        let output = compute_safety_score(&reserves, &strategy);
+        assert_eq!(output.total_assets_usd, 1_000 * 100_000_000);
+        assert_eq!(output.total_liabilities_usd, 400 * 100_000_000);
+        assert_eq!(output.denomination, None);
+    }
+
+    #[test]
+    fn test_reserves_near_caps_are_counted() {
+        // 1,000 USDC supplied, 400 borrowed
+        let uncapped = reserve(1_000_000_000, 400_000_000, 100_000_000, 6);
+        // 95.2% of a 1,050-token supply cap
+        let near_supply_cap = AaveReserveData { supply_cap: 1_050, ..uncapped.clone() };
+        // 95.2% of a 420-token borrow cap
+        let near_borrow_cap = AaveReserveData { borrow_cap: 420, ..uncapped.clone() };
+        let headroom = AaveReserveData { supply_cap: 2_000, borrow_cap: 2_000, ..uncapped.clone() };
+
+        assert!(near_cap(&near_supply_cap));
+        assert!(near_cap(&near_borrow_cap));
+        assert!(!near_cap(&headroom));
+        assert!(!near_cap(&uncapped));
+
+        let output = compute_safety_score(&[near_supply_cap, near_borrow_cap, headroom, uncapped], &ScoringStrategy::default());
+        assert_eq!(output.capped_reserves, 2);
+    }
+
+    #[test]
+    fn test_output_invariants() {
+        let reserves = [reserve(1_000_000_000, 400_000_000, 100_000_000, 6)];
+        let output = compute_safety_score(&reserves, &ScoringStrategy::default());
+        assert_eq!(check_output_invariants(&output), Ok(()));
+
+        // Each of these could only come from a scoring bug
+        let over_scale = SafetyScoreOutput { safety_score: SCORE_SCALE + 1, ..output.clone() };
+        assert!(check_output_invariants(&over_scale).is_err());
+        let insolvent = SafetyScoreOutput { total_liabilities_usd: output.total_assets_usd, ..output.clone() };
+        assert!(check_output_invariants(&insolvent).is_err());
+        let mismatched = SafetyScoreOutput { safety_score: output.safety_score + 1, ..output.clone() };
+        assert!(check_output_invariants(&mismatched).is_err());
+        let no_data = SafetyScoreOutput { status: ScoreStatus::NoData, ..output.clone() };
+        assert!(check_output_invariants(&no_data).is_err());
+        let empty = compute_safety_score(&[], &ScoringStrategy::default());
+        assert_eq!(check_output_invariants(&empty), Ok(()));
+        assert!(check_output_invariants(&SafetyScoreOutput { status: ScoreStatus::Ok, ..empty }).is_err());
+
+        // Volatility only ever lowers the score below the totals' score
+        let discounted = AaveReserveData { volatility_bps: Some(5_000), ..reserves[0].clone() };
+        let weighted = compute_safety_score(&[discounted], &ScoringStrategy::default());
+        assert!(weighted.safety_score < output.safety_score);
+        assert_eq!(check_output_invariants(&weighted), Ok(()));
+        let inflated = SafetyScoreOutput { safety_score: output.safety_score + 1, ..weighted };
+        assert!(check_output_invariants(&inflated).is_err());
+    }
+
+    #[test]
+    fn test_debt_mix_sums_to_liabilities() {
+        // About $300 stable and $100 variable, at a price that makes each part round
+        let mixed = AaveReserveData {
+            total_stable_debt: 300_000_001,
+            ..reserve(1_000_000_000, 100_000_001, 100_000_001, 6)
+        };
+        let output = compute_safety_score(&[mixed.clone(), mixed], &ScoringStrategy::default());
+
+        assert_eq!(
+            output.total_stable_liabilities_usd + output.total_variable_liabilities_usd,
+            output.total_liabilities_usd
+        );
+        // Rounding each part up on its own would give 10_000_000_201 variable
+        // and overshoot the total by a unit per reserve
+        assert_eq!(output.total_stable_liabilities_usd, 2 * 30_000_000_401);
+        assert_eq!(output.total_variable_liabilities_usd, 2 * 10_000_000_200);
+        assert_eq!(check_output_invariants(&output), Ok(()));
+
+        // A split larger than the total is refused
+        let overstated = SafetyScoreOutput {
+            total_variable_liabilities_usd: output.total_variable_liabilities_usd + 1,
+            ..output
+        };
+        assert!(check_output_invariants(&overstated).is_err());
+    }
+
+    #[test]
+    fn test_collateral_only_lowers_score() {
+        let collateral = AaveReserveData { liquidation_threshold: Some(7_800), ..reserve(1_000_000_000, 400_000_000, 100_000_000, 6) };
+        let reserves = [collateral.clone(), collateral.clone()];
+        let strategy = ScoringStrategy { collateral_only: true, ..Default::default() };
+
+        // Both reserves are collateral: nothing changes
+        let all_collateral = compute_safety_score(&reserves, &strategy);
+        assert_eq!(all_collateral.safety_score, 600_000);
+        assert!(all_collateral.collateral_only);
+
+        // Disabling collateral on one drops its supply but keeps its debt
+        let disabled = AaveReserveData { liquidation_threshold: Some(0), ..collateral.clone() };
+        assert!(!collateral_enabled(&disabled));
+        let output = compute_safety_score(&[collateral.clone(), disabled.clone()], &strategy);
+        assert_eq!(output.total_assets_usd, 1_000 * 100_000_000);
+        assert_eq!(output.total_liabilities_usd, 800 * 100_000_000);
+        assert_eq!(output.safety_score, 200_000);
+        assert!(output.safety_score < all_collateral.safety_score);
+
+        // Without the mode every supplied asset counts
+        let output = compute_safety_score(&[collateral, disabled], &ScoringStrategy::default());
+        assert_eq!(output.safety_score, 600_000);
+        assert!(!output.collateral_only);
+    }
+
+    #[test]
+    fn test_low_score_is_raised_to_floor() {
+        // $1,000 supplied, $900 borrowed -> 10%, under a 25% floor
+        let reserves = [reserve(1_000_000_000, 900_000_000, 100_000_000, 6)];
+        let strategy = ScoringStrategy { score_floor: Some(scaled(25.0)), ..Default::default() };
+        let output = compute_safety_score(&reserves, &strategy);
+
+        assert_eq!(output.safety_score, scaled(25.0));
+        assert!(output.clamped);
+        // The totals still show the computed 10%
+        assert_eq!(output.total_liabilities_usd, 900 * 100_000_000);
+        assert_eq!(check_output_invariants(&output), Ok(()));
+        let unmarked = SafetyScoreOutput { clamped: false, ..output };
+        assert!(check_output_invariants(&unmarked).is_err());
+
+        // A floor even lifts an insolvent protocol off zero
+        let insolvent = [reserve(1_000_000_000, 1_200_000_000, 100_000_000, 6)];
+        assert_eq!(compute_safety_score(&insolvent, &strategy).safety_score, scaled(25.0));
+    }
+
+    #[test]
+    fn test_high_score_is_capped_at_ceiling() {
+        // $1,000 supplied, $10 borrowed -> 99%, over a 95% ceiling
+        let reserves = [reserve(1_000_000_000, 10_000_000, 100_000_000, 6)];
+        let strategy = ScoringStrategy {
+            score_floor: Some(scaled(25.0)),
+            score_ceiling: Some(scaled(95.0)),
+            ..Default::default()
+        };
+        let output = compute_safety_score(&reserves, &strategy);
+        assert_eq!(output.safety_score, scaled(95.0));
+        assert!(output.clamped);
+        assert_eq!(check_output_invariants(&output), Ok(()));
+
+        // Inside the band nothing changes
+        let inside = [reserve(1_000_000_000, 400_000_000, 100_000_000, 6)];
+        let output = compute_safety_score(&inside, &strategy);
+        assert_eq!(output.safety_score, 600_000);
+        assert!(!output.clamped);
+    }
+
+    #[test]
+    fn test_backstop_raises_score() {
+        // $1,000 supplied, $400 borrowed, $500 backstop -> $1,100 buffer on $1,500
+        let reserves = [reserve(1_000_000_000, 400_000_000, 100_000_000, 6)];
+        let strategy = ScoringStrategy { backstop_usd: 500 * 100_000_000, ..Default::default() };
+        let output = compute_safety_score(&reserves, &strategy);
+
+        assert_eq!(output.safety_score, 733_333);
+        assert!(output.safety_score > compute_safety_score(&reserves, &ScoringStrategy::default()).safety_score);
+        // The totals still cover the reserves only
+        assert_eq!(output.total_assets_usd, 1_000 * 100_000_000);
+        assert_eq!(output.backstop_usd, 500 * 100_000_000);
+        assert_eq!(check_output_invariants(&output), Ok(()));
+        let unbacked = SafetyScoreOutput { backstop_usd: 0, ..output };
+        assert!(check_output_invariants(&unbacked).is_err());
+
+        // $1,000 supplied against $1,200 borrowed is insolvent until the
+        // backstop covers the $200 shortfall: $300 buffer on $1,500
+        let insolvent = [reserve(1_000_000_000, 1_200_000_000, 100_000_000, 6)];
+        assert_eq!(compute_safety_score(&insolvent, &ScoringStrategy::default()).safety_score, 0);
+        let output = compute_safety_score(&insolvent, &strategy);
+        assert_eq!(output.safety_score, scaled(20.0));
+        assert_eq!(check_output_invariants(&output), Ok(()));
+    }
+
+    #[test]
+    fn test_half_weight_lowers_score() {
+        // Two $1,000 reserves, $400 borrowed on the first -> $1,600 of $2,000, 80%
+        let mut risky = reserve(1_000_000_000, 0, 100_000_000, 6);
+        risky.token_address = "0xLongTail".to_string();
+        let reserves = [reserve(1_000_000_000, 400_000_000, 100_000_000, 6), risky];
+        let unweighted = compute_safety_score(&reserves, &ScoringStrategy::default());
+        assert_eq!(unweighted.safety_score, scaled(80.0));
+        assert!(!unweighted.risk_weighted);
+
+        // 50% on the long-tail asset: $1,100 of $1,500, 73.33%
+        let strategy = ScoringStrategy {
+            weights: BTreeMap::from([("0xlongtail".to_string(), 5_000)]),
+            ..Default::default()
+        };
+        let weighted = compute_safety_score(&reserves, &strategy);
+        assert_eq!(weighted.safety_score, 733_333);
+        assert_eq!(unweighted.safety_score - weighted.safety_score, 66_667);
+        assert!(weighted.risk_weighted);
+        // The totals stay at market value
+        assert_eq!(weighted.total_assets_usd, unweighted.total_assets_usd);
+        assert_eq!(check_output_invariants(&weighted), Ok(()));
+
+        // A full weight changes nothing, and heavier weights are refused
+        let full = ScoringStrategy { weights: BTreeMap::from([("0xlongtail".to_string(), 10_000)]), ..Default::default() };
+        assert_eq!(compute_safety_score(&reserves, &full).safety_score, scaled(80.0));
+        assert!(check_weights(&full.weights).is_ok());
+        assert!(check_weights(&BTreeMap::from([("0xlongtail".to_string(), 10_001)])).is_err());
+        assert!(check_weights(&BTreeMap::from([("0xLongTail".to_string(), 5_000)])).is_err());
+    }
+
+    #[test]
+    fn test_siloed_haircut_lowers_score() {
+        // Same as above, with the second reserve siloed: 80% without a haircut
+        let siloed = AaveReserveData { siloed_borrowing: true, ..reserve(1_000_000_000, 0, 100_000_000, 6) };
+        let reserves = [reserve(1_000_000_000, 400_000_000, 100_000_000, 6), siloed];
+        let plain = compute_safety_score(&reserves, &ScoringStrategy::default());
+        assert_eq!(plain.safety_score, scaled(80.0));
+        assert_eq!(plain.siloed_reserve_count, 1);
+        assert!(!plain.risk_weighted);
+
+        // A 50% haircut counts it as $500: $1,100 of $1,500, 73.33%
+        let strategy = ScoringStrategy { siloed_haircut_bps: 5_000, ..Default::default() };
+        let haircut = compute_safety_score(&reserves, &strategy);
+        assert_eq!(haircut.safety_score, 733_333);
+        assert_eq!(haircut.siloed_reserve_count, 1);
+        assert!(haircut.risk_weighted);
+        assert_eq!(haircut.total_assets_usd, plain.total_assets_usd);
+        assert_eq!(check_output_invariants(&haircut), Ok(()));
+    }
+
+    #[test]
+    fn test_old_update_marks_reserve_stale() {
+        let now = 1_700_000_000;
+        let fresh = AaveReserveData { last_update_timestamp: now - 60, ..reserve(1_000_000_000, 400_000_000, 100_000_000, 6) };
+        let old = AaveReserveData { last_update_timestamp: now - 3 * 86_400, ..fresh.clone() };
+        let unknown = AaveReserveData { last_update_timestamp: 0, ..fresh.clone() };
+        let reserves = [fresh, old, unknown];
+
+        let strategy = ScoringStrategy { snapshot_timestamp: now, stale_after_secs: Some(86_400), ..Default::default() };
+        let output = compute_safety_score(&reserves, &strategy);
+        assert_eq!(output.stale_reserve_count, 1);
+        // Stale reserves are flagged, not dropped
+        assert_eq!(output.total_assets_usd, 3 * 1_000 * 100_000_000);
+
+        // Without a threshold nothing is stale
+        let output = compute_safety_score(&reserves, &ScoringStrategy { snapshot_timestamp: now, ..Default::default() });
+        assert_eq!(output.stale_reserve_count, 0);
+    }
+
+    #[test]
+    fn test_nonce_tells_identical_states_apart() {
+        let input = AaveInput::new(vec![reserve(1_000_000_000, 400_000_000, 100_000_000, 6)], "Aave V3".to_string(), 1);
+        let first = compute_output(&input.clone().with_nonce(Some(18_500_000)));
+        let second = compute_output(&input.clone().with_nonce(Some(18_500_001)));
+
+        assert_eq!(first.nonce, Some(18_500_000));
+        assert_ne!(first, second);
+        // Only the nonce differs
+        assert_eq!(SafetyScoreOutput { nonce: None, ..first }, compute_output(&input));
+    }
+
+    #[test]
+    fn test_high_borrow_rate_lowers_projected_score() {
+        // $1,000 supplied, $400 borrowed at 50% a year
+        let reserves = [AaveReserveData { variable_borrow_rate: RAY / 2, ..reserve(1_000_000_000, 400_000_000, 100_000_000, 6) }];
+        let spot = compute_safety_score(&reserves, &ScoringStrategy::default());
+        assert_eq!(spot.safety_score, scaled(60.0));
+        assert_eq!(spot.projected_score, None);
+
+        // A year of accrual owes $200 more: $400 buffer on $1,000
+        let year = ScoringStrategy { projection_horizon_secs: Some(SECONDS_PER_YEAR as u64), ..Default::default() };
+        let projected = compute_safety_score(&reserves, &year);
+        assert_eq!(projected.projected_score, Some(scaled(40.0)));
+        // Only the projection moves; the spot score and totals don't
+        assert_eq!(SafetyScoreOutput { projected_score: None, ..projected }, spot);
+
+        // A day of it already shows, and supply earning the same rate offsets it
+        let day = ScoringStrategy { projection_horizon_secs: Some(86_400), ..Default::default() };
+        let projected_day = compute_safety_score(&reserves, &day).projected_score.unwrap();
+        assert!(projected_day < spot.safety_score);
+        let earning = [AaveReserveData { liquidity_rate: RAY / 2, ..reserves[0].clone() }];
+        assert!(compute_safety_score(&earning, &day).projected_score.unwrap() > projected_day);
+    }
+
+    #[test]
+    fn test_score_bounds_are_checked() {
+        assert_eq!(check_score_bounds(None, None), Ok(()));
+        assert_eq!(check_score_bounds(Some(scaled(25.0)), Some(scaled(95.0))), Ok(()));
+        assert_eq!(check_score_bounds(Some(SCORE_SCALE), Some(SCORE_SCALE)), Ok(()));
+        assert!(check_score_bounds(Some(scaled(95.0)), Some(scaled(25.0))).is_err());
+        assert!(check_score_bounds(None, Some(SCORE_SCALE + 1)).is_err());
+    }
+
+    #[test]
+    fn test_zero_priced_reserve_is_counted_not_scored() {
+        // The dust filter would otherwise catch a $0 reserve; it must be
+        // reported as unpriced, not as dust
+        let strategy = ScoringStrategy { min_reserve_usd: 1, ..Default::default() };
+        let reserves = [
+            reserve(1_000_000_000, 400_000_000, 100_000_000, 6),
+            reserve(1_000_000_000, 900_000_000, 0, 6),
+        ];
+        let output = compute_safety_score(&reserves, &strategy);
+
+        assert_eq!(output.reserves_with_missing_price, 1);
+        assert_eq!(output.skipped_reserves, 0);
+        assert_eq!(output.safety_score, 600_000);
+        assert!(output.reserve_breakdown.iter().all(|r| r.assets_usd > 0));
+    }
+
+    #[test]
+    fn test_reserve_clamped_when_fetched_marks_output_saturated() {
+        let healthy = reserve(1_000_000_000, 400_000_000, 100_000_000, 6);
+        let clamped = AaveReserveData { saturated: true, ..healthy.clone() };
+        assert!(!compute_safety_score(std::slice::from_ref(&healthy), &ScoringStrategy::default()).saturated);
+        assert!(compute_safety_score(&[healthy.clone(), clamped.clone()], &ScoringStrategy::default()).saturated);
+
+        // Unless it isn't scored
+        let unpriced = AaveReserveData { price_usd: 0, ..clamped };
+        assert!(!compute_safety_score(&[healthy, unpriced], &ScoringStrategy::default()).saturated);
+    }
+
+    #[test]
+    fn test_skipped_reserves_lower_completeness() {
+        let healthy = || reserve(1_000_000_000, 400_000_000, 100_000_000, 6);
+        let input = AaveInput::new(vec![healthy(), healthy(), healthy()], "Aave V3".to_string(), 1)
+            .with_min_reserve_usd(1);
+        let complete = compute_output(&input);
+        assert_eq!((complete.reserves_included, complete.reserves_total), (3, 3));
+        assert_eq!(complete.data_completeness_bps, 10_000);
+
+        // One reserve unpriced and one that failed to fetch: 3 of 5. Dust is
+        // left out on purpose, so it doesn't count against completeness
+        let mut reserves = input.reserves.clone();
+        reserves.push(reserve(1_000_000_000, 0, 0, 6));
+        reserves.push(reserve(1, 0, 1, 6));
+        let partial = compute_output(&AaveInput { reserves, failed_reserves: 1, ..input });
+        assert_eq!(partial.skipped_reserves, 1);
+        assert_eq!((partial.reserves_included, partial.reserves_total), (3, 5));
+        assert_eq!(partial.data_completeness_bps, 6_000);
+
+        // Nothing to score is nothing missing
+        let empty = compute_output(&AaveInput::new(Vec::new(), "Aave V3".to_string(), 1));
+        assert_eq!(empty.data_completeness_bps, 10_000);
+    }
+
+    #[test]
+    fn test_dominant_asset_gives_high_concentration() {
+        // $9,000 of one asset and $1,000 of another: 90%² + 10%² = 82%
+        let reserves = [
+            reserve(9_000_000_000, 0, 100_000_000, 6),
+            reserve(1_000_000_000, 500_000_000, 100_000_000, 6),
+        ];
+        let output = compute_safety_score(&reserves, &ScoringStrategy::default());
+        assert_eq!(output.asset_concentration_bps, 8_200);
+        assert_eq!(crate::share_bps(1_000 * 100_000_000, output.total_assets_usd, RoundingMode::Floor), 1_000);
+
+        // Spread evenly over ten reserves, the index falls to 1/10
+        let even: Vec<_> = (0..10).map(|_| reserve(1_000_000_000, 0, 100_000_000, 6)).collect();
+        assert_eq!(compute_safety_score(&even, &ScoringStrategy::default()).asset_concentration_bps, 1_000);
+
+        // A single reserve holds everything
+        let single = compute_safety_score(&reserves[..1], &ScoringStrategy::default());
+        assert_eq!(single.asset_concentration_bps, 10_000);
+        assert_eq!(compute_safety_score(&[], &ScoringStrategy::default()).asset_concentration_bps, 0);
+    }
+
+    #[test]
+    fn test_breakdown_other_bucket_sums_omitted_reserves() {
+        // Reserves worth $1..$12, each with $1 of debt
+        let reserves: Vec<_> = (1..=12u128)
+            .map(|dollars| reserve(dollars * 1_000_000, 1_000_000, 100_000_000, 6))
+            .collect();
+        let output = compute_safety_score(&reserves, &ScoringStrategy::default());
+
+        assert!(output.truncated);
+        assert_eq!(output.reserve_breakdown.len(), MAX_BREAKDOWN_RESERVES);
+        assert_eq!(output.omitted_count as usize, 12 - MAX_BREAKDOWN_RESERVES);
+        assert_eq!(output.reserve_breakdown[0].assets_usd, 12 * 100_000_000);
+
+        // $1 + $2 + $3 + $4 fall into "other"
+        assert_eq!(output.other_reserves.assets_usd, 10 * 100_000_000);
+        assert_eq!(output.other_reserves.liabilities_usd, 4 * 100_000_000);
+
+        // Listed reserves plus "other" account for the full totals
+        let listed_assets: u128 = output.reserve_breakdown.iter().map(|r| r.assets_usd).sum();
+        let listed_liabilities: u128 = output.reserve_breakdown.iter().map(|r| r.liabilities_usd).sum();
+        assert_eq!(listed_assets + output.other_reserves.assets_usd, output.total_assets_usd);
+        assert_eq!(listed_liabilities + output.other_reserves.liabilities_usd, output.total_liabilities_usd);
+    }
+
+    #[test]
+    fn test_emode_thresholds_raise_health_factor() {
+        // $1,000 USDC + $1,000 DAI supplied, $1,500 borrowed, both at a 77% base threshold
+        let mut usdc = reserve(1_000_000_000, 1_500_000_000, 100_000_000, 6);
+        usdc.liquidation_threshold = Some(7_700);
+        usdc.emode_category = 1;
+        let mut dai = reserve(1_000_000_000_000_000_000_000, 0, 100_000_000, 18);
+        dai.liquidation_threshold = Some(7_700);
+        dai.emode_category = 1;
+        let reserves = [usdc, dai];
+
+        let base = compute_safety_score(&reserves, &ScoringStrategy::default());
+        let stablecoins = ScoringStrategy {
+            emode_categories: vec![EModeCategory {
+                id: 1,
+                liquidation_threshold: 9_500,
+                label: "Stablecoins".to_string(),
+            }],
+            ..Default::default()
+        };
+        let emode = compute_safety_score(&reserves, &stablecoins);
+
+        // 2000 * 77% / 1500 = 1.0266 vs 2000 * 95% / 1500 = 1.2666
+        assert_eq!(base.weighted_health_factor, Some(10_266));
+        assert_eq!(emode.weighted_health_factor, Some(12_666));
+        assert!(emode.weighted_health_factor > base.weighted_health_factor);
+
+        // The buffer score doesn't depend on thresholds
+        assert_eq!(emode.safety_score, base.safety_score);
+    }
+
+    #[test]
+    fn test_unknown_emode_category_uses_base_threshold() {
+        let mut usdc = reserve(1_000_000_000, 0, 100_000_000, 6);
+        usdc.liquidation_threshold = Some(7_700);
+        usdc.emode_category = 3;
+
+        assert_eq!(ScoringStrategy::default().liquidation_threshold(&usdc), Some(7_700));
+    }
+
+    /// Reserves small enough that normalization can't overflow
+    fn arb_reserve() -> impl Strategy<Value = AaveReserveData> {
+        (0u128..=10u128.pow(16), 0u128..=10u128.pow(16), 1u128..=10u128.pow(10), 6u8..=18)
+            .prop_map(|(atoken, debt, price, decimals)| reserve(atoken, debt, price, decimals))
+    }
+
+    proptest! {
+        #[test]
+        fn prop_score_within_scale(reserves in prop::collection::vec(arb_reserve(), 0..8)) {
+            let output = compute_safety_score(&reserves, &ScoringStrategy::default());
+            prop_assert!(output.safety_score <= SCORE_SCALE);
+        }
+
+        #[test]
+        fn prop_outputs_satisfy_invariants(reserves in prop::collection::vec(arb_reserve(), 0..8)) {
+            let output = compute_safety_score(&reserves, &ScoringStrategy::default());
+            prop_assert_eq!(check_output_invariants(&output), Ok(()));
+        }
+
+        #[test]
+        fn prop_insolvent_scores_zero(reserves in prop::collection::vec(arb_reserve(), 0..8)) {
+            let output = compute_safety_score(&reserves, &ScoringStrategy::default());
+            if output.total_liabilities_usd >= output.total_assets_usd {
+                prop_assert_eq!(output.safety_score, 0);
+            }
+        }
+
+        #[test]
+        fn prop_adding_assets_never_lowers_score(
+            reserves in prop::collection::vec(arb_reserve(), 1..8),
+            extra in arb_reserve(),
+            min_reserve_usd in 0u128..=10u128.pow(12),
+        ) {
+            let strategy = ScoringStrategy { min_reserve_usd, ..Default::default() };
+            let before = compute_safety_score(&reserves, &strategy);
+
+            // A reserve with supply but no debt only adds assets
+            let mut with_extra = reserves.clone();
+            with_extra.push(AaveReserveData { total_variable_debt: 0, ..extra });
+            let after = compute_safety_score(&with_extra, &strategy);
+
+            prop_assert!(after.safety_score >= before.safety_score);
+        }
+
+        #[test]
+        fn prop_more_supply_never_lowers_score(
+            reserves in prop::collection::vec(arb_reserve(), 1..8),
+            index in any::<prop::sample::Index>(),
+            extra_atoken in 0u128..=10u128.pow(16),
+        ) {
+            let strategy = ScoringStrategy::default();
+            let before = compute_safety_score(&reserves, &strategy);
+
+            let mut grown = reserves.clone();
+            let i = index.index(grown.len());
+            grown[i].total_atoken += extra_atoken;
+            let after = compute_safety_score(&grown, &strategy);
+
+            prop_assert!(after.safety_score >= before.safety_score);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+// USD amount formatting
+// Values are 1e8-scaled integers; formatting them through f64 loses cents once
+// a protocol's TVL passes 2^53, so everything here is integer arithmetic
+// The scale conversions are used by scoring in the guest; UsdAmount is
+// display-only and built with the host feature
+
+#[cfg(feature = "host")]
+use serde::{Deserialize, Serialize};
+
+/// Scale used for all USD values in this crate (1e8)
+pub const USD_SCALE: u128 = 100_000_000;
+
+/// Decimals Aave uses for `isolationModeTotalDebt` and debt ceilings
+/// Those values are whole USD with 2 decimals (cents), independent of any
+/// token's decimals or the oracle's 1e8 price scale
+pub const ISOLATION_DEBT_DECIMALS: u32 = 2;
+
+/// Convert Aave's isolation-mode debt (USD, 1e2) to this crate's 1e8 scale
+/// Example: 12_345 (= $123.45) becomes 12_345_000_000
+/// Saturates at u128::MAX rather than overflowing
+pub fn isolation_debt_to_usd(isolation_mode_debt: u128) -> u128 {
+    isolation_mode_debt.saturating_mul(USD_SCALE / 10u128.pow(ISOLATION_DEBT_DECIMALS))
+}
+
+/// A USD value scaled by 1e8, displayed exactly as a dollar string
+/// Example: UsdAmount(123_456_789_000) displays as "$1,234.57"
+#[cfg(feature = "host")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct UsdAmount(pub u128);
+
+#[cfg(feature = "host")]
+impl UsdAmount {
+    /// The raw 1e8-scaled value
+    pub fn raw(&self) -> u128 {
+        self.0
+    }
+
+    /// Value rounded half-up to whole cents
+    pub fn cents(&self) -> u128 {
+        let per_cent = USD_SCALE / 100;
+        let cents = self.0 / per_cent;
+        if self.0 % per_cent >= per_cent / 2 {
+            cents + 1
+        } else {
+            cents
+        }
+    }
+
+    /// Format like `Display`, but with another currency symbol in place of "$"
+    /// For a 1e8-scaled amount already converted out of USD
+    pub fn with_symbol(&self, symbol: &str) -> String {
+        let cents = self.cents();
+        let dollars = (cents / 100).to_string();
+
+        // Insert thousands separators from the right
+        let lead = dollars.len() % 3;
+        let mut grouped = String::with_capacity(dollars.len() + dollars.len() / 3);
+        for (i, digit) in dollars.chars().enumerate() {
+            if i > 0 && i % 3 == lead {
+                grouped.push(',');
+            }
+            grouped.push(digit);
+        }
+
+        format!("{}{}.{:02}", symbol, grouped, cents % 100)
+    }
+}
+
+#[cfg(feature = "host")]
+impl From<u128> for UsdAmount {
+    fn from(value: u128) -> Self {
+        UsdAmount(value)
+    }
+}
+
+#[cfg(feature = "host")]
+impl std::fmt::Display for UsdAmount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.with_symbol("$"))
+    }
+}
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isolation_debt_scaling() {
+        // $123.45 in Aave's 2-decimal units
+        assert_eq!(isolation_debt_to_usd(12_345), 12_345_000_000);
+        assert_eq!(UsdAmount(isolation_debt_to_usd(12_345)).to_string(), "$123.45");
+
+        // A $1M debt ceiling worth of debt
+        assert_eq!(isolation_debt_to_usd(100_000_000), 1_000_000 * USD_SCALE);
+
+        assert_eq!(isolation_debt_to_usd(0), 0);
+        assert_eq!(isolation_debt_to_usd(u128::MAX), u128::MAX);
+    }
+
+    #[test]
+    fn test_small_amounts() {
+        assert_eq!(UsdAmount(0).to_string(), "$0.00");
+        assert_eq!(UsdAmount(100_000_000).to_string(), "$1.00");
+        assert_eq!(UsdAmount(123_456_789_000).to_string(), "$1,234.57");
+        assert_eq!(UsdAmount(99_999_999_999).to_string(), "$1,000.00");
+    }
+
+    #[test]
+    fn test_rounds_half_up_to_cents() {
+        assert_eq!(UsdAmount(1_499_999).to_string(), "$0.01");
+        assert_eq!(UsdAmount(1_500_000).to_string(), "$0.02");
+        assert_eq!(UsdAmount(499_999).to_string(), "$0.00");
+    }
+
+    #[test]
+    fn test_exact_above_f64_integer_range() {
+        // 2^53 + 1 dollars and 1 cent - not representable exactly as f64
+        let dollars = (1u128 << 53) + 1;
+        let amount = UsdAmount(dollars * USD_SCALE + 1_000_000);
+        assert_eq!(amount.to_string(), "$9,007,199,254,740,993.01");
+
+        // Sepolia output total assets from a real run
+        let amount = UsdAmount(69_877_005_961_417_054_618);
+        assert_eq!(amount.to_string(), "$698,770,059,614.17");
+    }
+
+    #[test]
+    fn test_other_symbol() {
+        assert_eq!(UsdAmount(123_456_789_000).with_symbol("€"), "€1,234.57");
+        assert_eq!(UsdAmount(0).with_symbol("CHF "), "CHF 0.00");
+    }
+
+    #[test]
+    fn test_max_value_does_not_overflow() {
+        let amount = UsdAmount(u128::MAX);
+        assert_eq!(amount.to_string(), "$3,402,823,669,209,384,634,633,746,074,317.68");
+    }
+}
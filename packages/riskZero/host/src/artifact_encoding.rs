@@ -0,0 +1,162 @@
+// Artifact Encoding
+// The journal and seal are saved as raw bytes, which block explorers, web
+// verifiers and Remix can't take directly. --artifact-encoding hex or base64
+// writes a text copy of each next to the .bin file (proof_seal.hex,
+// proof_journal.b64, ...); the .bin files are always written because
+// --resume-submission reads them. Any of the three can be read back, e.g.
+// by --mode verify-raw.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use eyre::{Result, eyre};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Largest artifact printed to the terminal; the seal always fits, a journal
+/// with a long reserve breakdown may not
+pub const PRINT_LIMIT_BYTES: usize = 1024;
+
+/// How the journal and seal are written out, from --artifact-encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactEncoding {
+    Binary,
+    Hex,
+    Base64,
+}
+
+impl FromStr for ArtifactEncoding {
+    type Err = eyre::Report;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "binary" => Ok(Self::Binary),
+            "hex" => Ok(Self::Hex),
+            "base64" => Ok(Self::Base64),
+            _ => Err(eyre!("Unknown artifact encoding '{}' (expected binary, hex or base64)", name)),
+        }
+    }
+}
+
+impl std::fmt::Display for ArtifactEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Binary => "binary",
+            Self::Hex => "hex",
+            Self::Base64 => "base64",
+        };
+        f.write_str(name)
+    }
+}
+
+impl ArtifactEncoding {
+    /// File extension for artifacts in this encoding
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Binary => "bin",
+            Self::Hex => "hex",
+            Self::Base64 => "b64",
+        }
+    }
+
+    /// Encode bytes as written to file; hex gets a 0x prefix, as Remix expects for `bytes`
+    pub fn encode(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Binary => bytes.to_vec(),
+            Self::Hex => format!("0x{}", hex::encode(bytes)).into_bytes(),
+            Self::Base64 => STANDARD.encode(bytes).into_bytes(),
+        }
+    }
+}
+
+/// Read an artifact in whichever encoding its extension names (.hex or
+/// .b64); anything else is read as raw bytes
+pub fn read_artifact(path: &str) -> Result<Vec<u8>> {
+    let data = std::fs::read(path).map_err(|e| eyre!("Failed to read {}: {}", path, e))?;
+    let text = || std::str::from_utf8(&data).map(str::trim).map_err(|_| eyre!("{} is not text", path));
+    match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+        Some("hex") => {
+            let text = text()?;
+            hex::decode(text.strip_prefix("0x").unwrap_or(text)).map_err(|e| eyre!("Invalid hex in {}: {}", path, e))
+        }
+        Some("b64") => STANDARD.decode(text()?).map_err(|e| eyre!("Invalid base64 in {}: {}", path, e)),
+        _ => Ok(data),
+    }
+}
+
+/// Write a text copy of the artifact at `bin_path`, swapping its extension
+/// for the encoding's; returns the new path
+pub fn write_encoded(bin_path: &str, bytes: &[u8], encoding: ArtifactEncoding) -> Result<String> {
+    let path = Path::new(bin_path).with_extension(encoding.extension());
+    std::fs::write(&path, encoding.encode(bytes))?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENCODINGS: [ArtifactEncoding; 3] = [ArtifactEncoding::Binary, ArtifactEncoding::Hex, ArtifactEncoding::Base64];
+
+    /// What a verifier or a pasted Remix argument reads back
+    fn decode(encoding: ArtifactEncoding, data: &[u8]) -> Vec<u8> {
+        let text = || std::str::from_utf8(data).unwrap();
+        match encoding {
+            ArtifactEncoding::Binary => data.to_vec(),
+            ArtifactEncoding::Hex => hex::decode(text().strip_prefix("0x").unwrap()).unwrap(),
+            ArtifactEncoding::Base64 => STANDARD.decode(text()).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_each_encoding() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for encoding in ENCODINGS {
+            assert_eq!(decode(encoding, &encoding.encode(&bytes)), bytes, "{}", encoding);
+            assert_eq!(decode(encoding, &encoding.encode(&[])), Vec::<u8>::new(), "{}", encoding);
+            assert_eq!(encoding.to_string().parse::<ArtifactEncoding>().unwrap(), encoding);
+        }
+    }
+
+    #[test]
+    fn test_text_encodings() {
+        assert_eq!(ArtifactEncoding::Hex.encode(&[0xde, 0xad]), b"0xdead");
+        assert_eq!(ArtifactEncoding::Base64.encode(&[0xde, 0xad]), b"3q0=");
+        assert_eq!("HEX".parse::<ArtifactEncoding>().unwrap(), ArtifactEncoding::Hex);
+        assert!("base32".parse::<ArtifactEncoding>().is_err());
+    }
+
+    #[test]
+    fn test_write_encoded_next_to_binary() {
+        let dir = std::env::temp_dir().join("derisk_artifact_encoding");
+        std::fs::create_dir_all(&dir).unwrap();
+        let bin_path = dir.join("proof_seal.bin");
+
+        let path = write_encoded(bin_path.to_str().unwrap(), &[1, 2, 3], ArtifactEncoding::Hex).unwrap();
+        assert!(path.ends_with("proof_seal.hex"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "0x010203");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_artifact_in_each_encoding() {
+        let dir = std::env::temp_dir().join("derisk_artifact_read");
+        std::fs::create_dir_all(&dir).unwrap();
+        let bin_path = dir.join("proof_journal.bin");
+        let bin_path = bin_path.to_str().unwrap();
+        let bytes: Vec<u8> = (0..=255).collect();
+
+        std::fs::write(bin_path, &bytes).unwrap();
+        for encoding in ENCODINGS {
+            let path = write_encoded(bin_path, &bytes, encoding).unwrap();
+            assert_eq!(read_artifact(&path).unwrap(), bytes, "{}", encoding);
+        }
+
+        // Pasted hex often loses its prefix or gains a trailing newline
+        let hex_path = dir.join("pasted.hex");
+        std::fs::write(&hex_path, "dead\n").unwrap();
+        assert_eq!(read_artifact(hex_path.to_str().unwrap()).unwrap(), vec![0xde, 0xad]);
+        std::fs::write(&hex_path, "0xnothex").unwrap();
+        assert!(read_artifact(hex_path.to_str().unwrap()).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
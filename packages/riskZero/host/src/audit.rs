@@ -0,0 +1,138 @@
+// Input Audit
+// `--mode audit` checks a published output really follows from a published
+// input, without a proof: it reruns the reference scoring (the same
+// derisk_type code the guest runs) on --input-file and compares every
+// committed value with --journal. It says nothing about who computed the
+// journal; verifying the receipt is what proves that.
+
+use derisk_type::{check_output_invariants, check_score_bounds, check_weights, compute_output, AaveInput, SafetyScoreOutput};
+use eyre::Result;
+
+/// One committed value that doesn't match the recomputed one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discrepancy {
+    pub field: String,
+    pub committed: String,
+    pub recomputed: String,
+}
+
+/// Result of auditing one input/journal pair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditReport {
+    /// Reasons the guest would have refused the input outright
+    pub rejected: Vec<String>,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl AuditReport {
+    pub fn passed(&self) -> bool {
+        self.rejected.is_empty() && self.discrepancies.is_empty()
+    }
+
+    pub fn print(&self) {
+        if self.passed() {
+            status!("✅ PASS: the journal matches the output recomputed from the input");
+            return;
+        }
+        status!("❌ FAIL: the journal does not follow from the input");
+        for reason in &self.rejected {
+            status!("  - the guest would reject this input: {}", reason);
+        }
+        for discrepancy in &self.discrepancies {
+            status!("  - {}: committed {}, recomputed {}",
+                discrepancy.field, discrepancy.committed, discrepancy.recomputed);
+        }
+    }
+}
+
+/// Recompute the output for `input` and compare it with `committed`
+pub fn audit(input: &AaveInput, committed: &SafetyScoreOutput) -> Result<AuditReport> {
+    let mut rejected = Vec::new();
+    if let Err(problem) = check_score_bounds(input.score_floor, input.score_ceiling) {
+        rejected.push(problem.to_string());
+    }
+    if let Err(problem) = check_weights(&input.weights) {
+        rejected.push(problem.to_string());
+    }
+    if input.reserves.iter().any(|reserve| {
+        input.excluded.iter().any(|excluded| excluded.eq_ignore_ascii_case(&reserve.token_address))
+    }) {
+        rejected.push("an excluded reserve is present".to_string());
+    }
+
+    let recomputed = compute_output(input);
+    if let Err(violation) = check_output_invariants(&recomputed) {
+        rejected.push(violation.to_string());
+    }
+
+    Ok(AuditReport { rejected, discrepancies: compare(committed, &recomputed)? })
+}
+
+/// Every top-level field whose value differs, compared through JSON so
+/// fields added to the output later are covered without changes here
+fn compare(committed: &SafetyScoreOutput, recomputed: &SafetyScoreOutput) -> Result<Vec<Discrepancy>> {
+    let serde_json::Value::Object(committed) = serde_json::to_value(committed)? else {
+        unreachable!("SafetyScoreOutput serializes as an object");
+    };
+    let recomputed = serde_json::to_value(recomputed)?;
+    Ok(committed
+        .into_iter()
+        .filter(|(field, value)| recomputed.get(field) != Some(value))
+        .map(|(field, value)| Discrepancy {
+            recomputed: recomputed.get(&field).map(|v| v.to_string()).unwrap_or_default(),
+            committed: value.to_string(),
+            field,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derisk_type::{AaveReserveData, USD_SCALE};
+
+    fn input() -> AaveInput {
+        AaveInput::new(
+            vec![
+                AaveReserveData::builder("0xUSDC").decimals(6).total_atoken(1_000_000_000).total_variable_debt(400_000_000).build(),
+                AaveReserveData::builder("0xWETH").liquidation_threshold(8_250).build(),
+            ],
+            "Audit Test".to_string(),
+            1_700_000_000,
+        )
+    }
+
+    #[test]
+    fn test_matching_pair_passes() {
+        let input = input();
+        let report = audit(&input, &compute_output(&input)).unwrap();
+        assert!(report.passed(), "{:?}", report);
+    }
+
+    #[test]
+    fn test_mismatched_pair_lists_discrepancies() {
+        let input = input();
+        let honest = compute_output(&input);
+        let tampered = SafetyScoreOutput {
+            safety_score: honest.safety_score + 1,
+            total_liabilities_usd: honest.total_liabilities_usd - USD_SCALE,
+            ..honest.clone()
+        };
+
+        let report = audit(&input, &tampered).unwrap();
+        assert!(!report.passed());
+        let fields: Vec<&str> = report.discrepancies.iter().map(|d| d.field.as_str()).collect();
+        assert_eq!(fields, vec!["safety_score", "total_liabilities_usd"]);
+        assert_eq!(report.discrepancies[0].recomputed, honest.safety_score.to_string());
+
+        // A journal from another input fails too, even with every value self-consistent
+        let other = input.clone().with_min_reserve_usd(10 * USD_SCALE);
+        assert!(!audit(&other, &honest).unwrap().passed());
+
+        // And an input the guest would refuse never passes
+        let crossed = input.with_score_bounds(Some(900_000), Some(100_000));
+        let report = audit(&crossed, &compute_output(&crossed)).unwrap();
+        assert_eq!(report.rejected.len(), 1);
+        assert!(!report.passed());
+    }
+}
@@ -0,0 +1,195 @@
+// Reserve Count Benchmark
+// `--mode bench` measures how guest cost grows with the number of reserves,
+// to tell whether per-reserve normalization dominates. It scores synthetic
+// inputs of each --bench-reserves size (default 1,5,10,25,50), executing
+// only by default so it runs in CI in seconds; --bench-prove also times a
+// STARK proof of each. Results print as a table and are written to
+// reserve_bench.csv in --output-dir.
+
+use derisk_type::{AaveInput, AaveReserveData};
+use eyre::{Result, eyre};
+use risc0_zkvm::Prover;
+use std::time::{Duration, Instant};
+
+use crate::guest;
+use methods::AAVE_ELF;
+
+/// Reserve counts benchmarked without --bench-reserves
+pub const DEFAULT_RESERVE_COUNTS: &str = "1,5,10,25,50";
+
+/// File in the output directory the results are written to
+pub const BENCH_FILE: &str = "reserve_bench.csv";
+
+/// CSV columns, in BenchRow field order; prove_ms is empty when not proving
+const CSV_HEADER: &str = "reserves,cycles,segments,execute_ms,prove_ms";
+
+/// Unix time synthetic inputs are stamped with, so every run executes the same input
+const SYNTHETIC_TIMESTAMP: u64 = 1_700_000_000;
+
+/// Decimals the synthetic reserves cycle through, as USDC, WBTC and WETH
+const SYNTHETIC_DECIMALS: [u8; 3] = [6, 8, 18];
+
+/// Cost of scoring one input size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchRow {
+    pub reserves: usize,
+    pub cycles: u64,
+    pub segments: usize,
+    pub execute: Duration,
+    /// None unless proving was requested
+    pub prove: Option<Duration>,
+}
+
+impl BenchRow {
+    /// Cycles per reserve, which stays flat when per-reserve work dominates
+    pub fn cycles_per_reserve(&self) -> u64 {
+        self.cycles / self.reserves.max(1) as u64
+    }
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            self.reserves,
+            self.cycles,
+            self.segments,
+            self.execute.as_millis(),
+            self.prove.map(|prove| prove.as_millis().to_string()).unwrap_or_default()
+        )
+    }
+}
+
+/// Parse a comma-separated list of reserve counts, e.g. "1,5,10"
+pub fn parse_counts(spec: &str) -> Result<Vec<usize>> {
+    spec.split(',')
+        .map(|count| match count.trim().parse::<usize>() {
+            Ok(0) | Err(_) => Err(eyre!("--bench-reserves takes positive counts like 1,5,10, got '{}'", count)),
+            Ok(count) => Ok(count),
+        })
+        .collect()
+}
+
+/// A deterministic input with `reserves` reserves of mixed decimals and prices
+/// Every reserve has supply and debt, so each one takes the full scoring path
+pub fn synthetic_input(reserves: usize) -> AaveInput {
+    let reserves = (0..reserves)
+        .map(|i| {
+            let decimals = SYNTHETIC_DECIMALS[i % SYNTHETIC_DECIMALS.len()];
+            let supply = 1_000_000 * 10u128.pow(u32::from(decimals));
+            let mut reserve = AaveReserveData::builder(format!("0x{:040x}", i + 1))
+                .decimals(decimals)
+                .total_atoken(supply)
+                .total_variable_debt(supply * 2 / 5)
+                .price_usd(100_000_000 * (1 + i as u128 % 5))
+                .liquidation_threshold(8_000);
+            if i % 2 == 1 {
+                reserve = reserve.total_stable_debt(supply / 20);
+            }
+            reserve.build()
+        })
+        .collect();
+    AaveInput::new(reserves, "Synthetic Benchmark".to_string(), SYNTHETIC_TIMESTAMP)
+}
+
+/// Execute, and prove when `prover` is given, a synthetic input of each size
+pub fn run_bench(counts: &[usize], segment_limit_po2: Option<u32>, prover: Option<&dyn Prover>) -> Result<Vec<BenchRow>> {
+    counts
+        .iter()
+        .map(|&reserves| {
+            let input = synthetic_input(reserves);
+
+            let started = Instant::now();
+            let execution = guest::execute_guest(&input, segment_limit_po2)?;
+            let execute = started.elapsed();
+
+            let prove = match prover {
+                Some(prover) => {
+                    let env = guest::build_env(&input, segment_limit_po2)?;
+                    let started = Instant::now();
+                    prover
+                        .prove(env, AAVE_ELF)
+                        .map_err(|e| eyre!("Failed to prove {} reserves: {}", reserves, e))?;
+                    Some(started.elapsed())
+                }
+                None => None,
+            };
+
+            status!("✓ {} reserves: {} cycles", reserves, execution.cycles);
+            Ok(BenchRow { reserves, cycles: execution.cycles, segments: execution.segments, execute, prove })
+        })
+        .collect()
+}
+
+/// Results as CSV, one row per input size
+pub fn to_csv(rows: &[BenchRow]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    for row in rows {
+        csv.push('\n');
+        csv.push_str(&row.csv_row());
+    }
+    csv.push('\n');
+    csv
+}
+
+/// Print results in the host's summary style
+pub fn print_table(rows: &[BenchRow]) {
+    status!("\n⏱  Reserve Count Benchmark:");
+    status!("  {:>8} {:>12} {:>14} {:>8} {:>10} {:>10}", "reserves", "cycles", "cycles/reserve", "segments", "execute", "prove");
+    for row in rows {
+        status!(
+            "  {:>8} {:>12} {:>14} {:>8} {:>10} {:>10}",
+            row.reserves,
+            row.cycles,
+            row.cycles_per_reserve(),
+            row.segments,
+            format!("{:.2}s", row.execute.as_secs_f64()),
+            row.prove.map(|prove| format!("{:.1}s", prove.as_secs_f64())).unwrap_or_else(|| "-".to_string())
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_input_sizes() {
+        let input = synthetic_input(25);
+        assert_eq!(input.reserves.len(), 25);
+        assert_eq!(
+            serde_json::to_string(&synthetic_input(25)).unwrap(),
+            serde_json::to_string(&input).unwrap(),
+            "synthetic inputs must be deterministic"
+        );
+
+        // Distinct tokens, all decimals represented, and every reserve valuable
+        let mut tokens: Vec<_> = input.reserves.iter().map(|r| &r.token_address).collect();
+        tokens.dedup();
+        assert_eq!(tokens.len(), 25);
+        for decimals in SYNTHETIC_DECIMALS {
+            assert!(input.reserves.iter().any(|r| r.decimals == decimals));
+        }
+        assert!(input.reserves.iter().all(|r| r.validate().is_ok() && r.total_variable_debt > 0));
+    }
+
+    #[test]
+    fn test_parse_counts() {
+        assert_eq!(parse_counts(DEFAULT_RESERVE_COUNTS).unwrap(), vec![1, 5, 10, 25, 50]);
+        assert_eq!(parse_counts(" 2, 3").unwrap(), vec![2, 3]);
+        assert!(parse_counts("1,0").is_err());
+        assert!(parse_counts("1,,5").is_err());
+    }
+
+    #[test]
+    fn test_bench_executes_each_size() {
+        let rows = run_bench(&[1, 5], None, None).unwrap();
+        assert_eq!(rows.iter().map(|row| row.reserves).collect::<Vec<_>>(), vec![1, 5]);
+        assert!(rows[1].cycles > rows[0].cycles);
+        assert!(rows.iter().all(|row| row.prove.is_none()));
+
+        let csv = to_csv(&rows);
+        assert!(csv.starts_with(CSV_HEADER));
+        assert_eq!(csv.lines().count(), 3);
+        // No proof, no prove_ms
+        assert!(csv.lines().nth(1).unwrap().ends_with(','));
+    }
+}
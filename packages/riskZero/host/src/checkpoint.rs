@@ -0,0 +1,213 @@
+// Fetch Checkpoint
+// Fetching a large deployment reserve by reserve can fail near the end on a
+// flaky RPC. Every fetched reserve is appended to a checkpoint file as it
+// arrives, so a rerun with --resume-fetch only fetches what is missing.
+//
+// The file is JSON lines: a header naming the pool and block it was written
+// for, then one AaveReserveData per line. A line cut short by a crash is
+// ignored on resume and that reserve is fetched again.
+
+use derisk_type::AaveReserveData;
+use eyre::{Result, eyre};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+
+/// Checkpoint file name in the output directory
+pub const CHECKPOINT_FILE: &str = "fetch_checkpoint.jsonl";
+
+/// What a checkpoint was written for; resuming against anything else would
+/// mix reserves from two different snapshots
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointHeader {
+    pub pool: String,
+    /// None when reading the latest block
+    pub block: Option<u64>,
+}
+
+/// Reserves fetched so far, mirrored to disk as they arrive
+#[derive(Debug)]
+pub struct FetchCheckpoint {
+    path: String,
+    file: File,
+    reserves: Vec<AaveReserveData>,
+}
+
+impl FetchCheckpoint {
+    /// Start a new checkpoint, discarding any previous one at `path`
+    pub fn create(path: &str, header: &CheckpointHeader) -> Result<Self> {
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", serde_json::to_string(header)?)?;
+        file.sync_data()?;
+
+        Ok(Self {
+            path: path.to_string(),
+            file,
+            reserves: Vec::new(),
+        })
+    }
+
+    /// Continue the checkpoint at `path`, or start one if there is none
+    /// Fails when the checkpoint was written for a different pool or block
+    pub fn resume(path: &str, header: &CheckpointHeader) -> Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::create(path, header),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut lines = contents.lines();
+        let found: CheckpointHeader = match lines.next() {
+            Some(line) => serde_json::from_str(line)
+                .map_err(|e| eyre!("Checkpoint {} has an invalid header: {}", path, e))?,
+            None => return Self::create(path, header),
+        };
+        if &found != header {
+            return Err(eyre!(
+                "Checkpoint {} was written for pool {} at block {:?}, not pool {} at block {:?}; \
+                 rerun without --resume-fetch to start over",
+                path, found.pool, found.block, header.pool, header.block
+            ));
+        }
+
+        // Only a torn final line is expected; anything else is still skipped
+        // rather than trusted, and simply fetched again
+        let reserves: Vec<AaveReserveData> = lines
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+
+        // Rewrite without any torn line so new records start on a fresh line
+        let mut checkpoint = Self::create(path, header)?;
+        for reserve in reserves {
+            checkpoint.record(reserve)?;
+        }
+        Ok(checkpoint)
+    }
+
+    /// A previously fetched reserve, by token address
+    pub fn get(&self, token_address: &str) -> Option<&AaveReserveData> {
+        self.reserves
+            .iter()
+            .find(|reserve| reserve.token_address.eq_ignore_ascii_case(token_address))
+    }
+
+    /// Number of reserves in the checkpoint
+    pub fn count(&self) -> usize {
+        self.reserves.len()
+    }
+
+    /// Append a fetched reserve, syncing it to disk before returning
+    pub fn record(&mut self, reserve: AaveReserveData) -> Result<()> {
+        writeln!(self.file, "{}", serde_json::to_string(&reserve)?)?;
+        self.file.sync_data()?;
+        self.reserves.push(reserve);
+        Ok(())
+    }
+
+    /// Delete the checkpoint once the fetch no longer needs it
+    pub fn remove(self) -> Result<()> {
+        drop(self.file);
+        std::fs::remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+
+    /// Append bytes without a trailing newline, like a write cut off by a crash
+    fn append_raw(path: &str, data: &str) {
+        let mut file = OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(data.as_bytes()).unwrap();
+    }
+
+    fn reserve(token_address: &str, total_atoken: u128) -> AaveReserveData {
+        AaveReserveData {
+            token_address: token_address.to_string(),
+            total_atoken,
+            total_stable_debt: 0,
+            total_variable_debt: 0,
+            price_usd: 100_000_000,
+            decimals: 6,
+            liquidation_threshold: None,
+            volatility_bps: None,
+            isolation_mode_debt: 0,
+            emode_category: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            last_update_timestamp: 0,
+            siloed_borrowing: false,
+            liquidity_rate: 0,
+            variable_borrow_rate: 0,
+            saturated: false,
+        }
+    }
+
+    fn header(block: Option<u64>) -> CheckpointHeader {
+        CheckpointHeader {
+            pool: "0xPOOL".to_string(),
+            block,
+        }
+    }
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(name);
+        std::fs::remove_file(&path).ok();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_resume_after_mid_fetch_failure() {
+        let path = temp_path("derisk_checkpoint_resume.jsonl");
+        let all = ["0xA", "0xB", "0xC", "0xD"];
+
+        // First run: two reserves land, the process dies writing the third
+        {
+            let mut checkpoint = FetchCheckpoint::create(&path, &header(Some(100))).unwrap();
+            checkpoint.record(reserve("0xA", u128::MAX)).unwrap();
+            checkpoint.record(reserve("0xB", 2)).unwrap();
+        }
+        append_raw(&path, "{\"token_address\":\"0xC\",\"total_at");
+
+        // Second run: only the missing reserves are fetched
+        let mut checkpoint = FetchCheckpoint::resume(&path, &header(Some(100))).unwrap();
+        assert_eq!(checkpoint.count(), 2);
+        assert_eq!(checkpoint.get("0xa").unwrap().total_atoken, u128::MAX);
+
+        let mut fetched = Vec::new();
+        for address in all {
+            if checkpoint.get(address).is_none() {
+                checkpoint.record(reserve(address, 3)).unwrap();
+                fetched.push(address);
+            }
+        }
+        assert_eq!(fetched, vec!["0xC", "0xD"]);
+
+        // The rewritten file holds every reserve exactly once
+        let reloaded = FetchCheckpoint::resume(&path, &header(Some(100))).unwrap();
+        assert_eq!(reloaded.count(), 4);
+        reloaded.remove().unwrap();
+        assert!(std::fs::metadata(&path).is_err());
+    }
+
+    #[test]
+    fn test_resume_rejects_other_snapshot() {
+        let path = temp_path("derisk_checkpoint_mismatch.jsonl");
+        let mut checkpoint = FetchCheckpoint::create(&path, &header(Some(100))).unwrap();
+        checkpoint.record(reserve("0xA", 1)).unwrap();
+
+        assert!(FetchCheckpoint::resume(&path, &header(Some(101))).is_err());
+        checkpoint.remove().unwrap();
+    }
+
+    #[test]
+    fn test_resume_without_checkpoint_starts_fresh() {
+        let path = temp_path("derisk_checkpoint_missing.jsonl");
+        let checkpoint = FetchCheckpoint::resume(&path, &header(None)).unwrap();
+
+        assert_eq!(checkpoint.count(), 0);
+        checkpoint.remove().unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+// Composed Proving
+// --mode compose proves the input's reserves in batches of
+// --compose-batch-size, each with the batch guest, then proves the compose
+// guest over the batch receipts (see derisk_type::compose). A proof's memory
+// grows with the reserves it covers, so batching bounds it for large pools,
+// and the batches don't depend on each other.
+//
+// The composed receipt is for AAVE_COMPOSE_ID rather than AAVE_ID, so the
+// on-chain verifier, which is pinned to AAVE_ID, doesn't accept it yet.
+
+use derisk_type::compose::{split_reserves, BatchJournal, ComposeInput};
+use derisk_type::{AaveInput, SafetyScoreOutput};
+use eyre::{Result, eyre};
+use methods::{AAVE_BATCH_ELF, AAVE_BATCH_ID, AAVE_COMPOSE_ELF, AAVE_COMPOSE_ID};
+use risc0_zkvm::{ExecutorEnv, ProverOpts, Receipt};
+
+use crate::prover::{self, ProverKind};
+
+/// A receipt for the compose guest, standing for every batch it merged
+pub struct ComposedProof {
+    pub receipt: Receipt,
+    pub batches: usize,
+    /// Cycles across the batches and the composition
+    pub total_cycles: u64,
+}
+
+/// The input cut into inputs of at most `batch_size` reserves each, in order
+pub fn split_batches(input: &AaveInput, batch_size: usize) -> Result<Vec<AaveInput>> {
+    if batch_size == 0 {
+        return Err(eyre!("--compose-batch-size must be at least 1"));
+    }
+    let (header, reserves) = split_reserves(input.clone());
+    Ok(reserves
+        .chunks(batch_size)
+        .map(|chunk| AaveInput { reserves: chunk.to_vec(), ..header.clone() })
+        .collect())
+}
+
+/// Prove each batch of `input`, then compose the batch receipts into one
+pub fn prove_composed(input: &AaveInput, batch_size: usize, kind: Option<ProverKind>) -> Result<ComposedProof> {
+    let prover = prover::select_prover(kind)?;
+    let batches = split_batches(input, batch_size)?;
+
+    let mut total_cycles = 0;
+    let mut receipts = Vec::with_capacity(batches.len());
+    let mut journals: Vec<BatchJournal> = Vec::with_capacity(batches.len());
+    for (i, batch) in batches.iter().enumerate() {
+        let mut builder = ExecutorEnv::builder();
+        builder
+            .write(batch)
+            .map_err(|e| eyre!("Failed to write batch {}: {}", i + 1, e))?;
+        let env = builder.build().map_err(|e| eyre!("Failed to build env: {}", e))?;
+        let prove_info = prover
+            .prove(env, AAVE_BATCH_ELF)
+            .map_err(|e| eyre!("Failed to prove batch {}: {}", i + 1, e))?;
+        status!("  - Batch {}/{}: {} reserves, {} cycles",
+            i + 1, batches.len(), batch.reserves.len(), prove_info.stats.total_cycles);
+
+        total_cycles += prove_info.stats.total_cycles;
+        journals.push(prove_info.receipt.journal.decode()?);
+        receipts.push(prove_info.receipt);
+    }
+
+    // Each batch receipt is an assumption the compose guest verifies
+    let (header, _) = split_reserves(input.clone());
+    let compose_input = ComposeInput { batch_image_id: AAVE_BATCH_ID, header, batches: journals };
+    let mut builder = ExecutorEnv::builder();
+    for receipt in receipts {
+        builder.add_assumption(receipt);
+    }
+    builder
+        .write(&compose_input)
+        .map_err(|e| eyre!("Failed to write composition input: {}", e))?;
+    let env = builder.build().map_err(|e| eyre!("Failed to build env: {}", e))?;
+
+    // A succinct receipt resolves the assumptions, so it verifies on its own
+    let prove_info = prover
+        .prove_with_opts(env, AAVE_COMPOSE_ELF, &ProverOpts::succinct())
+        .map_err(|e| eyre!("Failed to prove composition: {}", e))?;
+    total_cycles += prove_info.stats.total_cycles;
+
+    Ok(ComposedProof { receipt: prove_info.receipt, batches: batches.len(), total_cycles })
+}
+
+/// Verify a composed receipt and decode its output
+/// Fails unless its batches were proven by this build's batch guest
+pub fn verify_composed(receipt: &Receipt) -> Result<SafetyScoreOutput> {
+    receipt
+        .verify(AAVE_COMPOSE_ID)
+        .map_err(|e| eyre!("Composed proof failed verification: {}", e))?;
+    let output: SafetyScoreOutput = receipt.journal.decode()?;
+    if output.batch_image_id != Some(AAVE_BATCH_ID) {
+        return Err(eyre!("Composed proof merges batches from another guest: {:?}", output.batch_image_id));
+    }
+    Ok(output)
+}
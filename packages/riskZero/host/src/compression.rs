@@ -0,0 +1,121 @@
+// Artifact Compression
+// A serialized receipt is hundreds of KB, which adds up over many saved runs.
+// With --compress-artifacts the receipts are written gzipped as
+// `<name>.bin.gz`; readers ask for `<name>.bin` and get the bytes back
+// whichever form is on disk. The seal and journal are a few hundred bytes
+// and always stay uncompressed.
+
+use eyre::{Result, eyre};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use risc0_zkvm::Receipt;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::receipt_format;
+
+/// Suffix appended to a compressed artifact's file name
+pub const GZIP_SUFFIX: &str = ".gz";
+
+/// Write `bytes` to `path`, or gzipped to `path.gz` when `compress` is set
+/// The other form is removed, so a reader never picks up a stale copy from
+/// an earlier run. Returns the path written
+pub fn write_artifact(path: &str, bytes: &[u8], compress: bool) -> Result<String> {
+    let compressed_path = format!("{}{}", path, GZIP_SUFFIX);
+    let (written, stale) = if compress {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes)?;
+        std::fs::write(&compressed_path, encoder.finish()?)?;
+        (compressed_path, path.to_string())
+    } else {
+        std::fs::write(path, bytes)?;
+        (path.to_string(), compressed_path)
+    };
+
+    if Path::new(&stale).exists() {
+        std::fs::remove_file(&stale)?;
+    }
+    Ok(written)
+}
+
+/// Read the artifact written for `path`, decompressing `path.gz` if that is
+/// what's on disk. A path that already ends in .gz is decompressed too
+pub fn read_artifact(path: &str) -> Result<Vec<u8>> {
+    let compressed_path = if path.ends_with(GZIP_SUFFIX) {
+        path.to_string()
+    } else if Path::new(path).exists() {
+        return std::fs::read(path).map_err(|e| eyre!("Failed to read {}: {}", path, e));
+    } else {
+        format!("{}{}", path, GZIP_SUFFIX)
+    };
+
+    let compressed = std::fs::read(&compressed_path)
+        .map_err(|e| eyre!("Failed to read {} (or {}): {}", path, compressed_path, e))?;
+    let mut bytes = Vec::new();
+    GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut bytes)
+        .map_err(|e| eyre!("Failed to decompress {}: {}", compressed_path, e))?;
+    Ok(bytes)
+}
+
+/// Load a receipt saved with `write_artifact`, compressed or not, in either
+/// of the formats `receipt_format` writes
+pub fn load_receipt(path: &str) -> Result<Receipt> {
+    receipt_format::deserialize_receipt(&read_artifact(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use risc0_zkvm::{sha::Digest, Groth16Receipt, InnerReceipt, ReceiptClaim};
+
+    fn receipt() -> Receipt {
+        let claim = ReceiptClaim::ok(Digest::ZERO, vec![7u8; 64]);
+        let inner = Groth16Receipt::new(vec![3u8; 256], claim.into(), Digest::ZERO);
+        Receipt::new(InnerReceipt::Groth16(inner), vec![7u8; 64])
+    }
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(name);
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(format!("{}{}", path.display(), GZIP_SUFFIX)).ok();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_receipt_round_trips_through_compression() {
+        let path = temp_path("derisk_compressed_receipt.bin");
+        let bytes = bincode::serialize(&receipt()).unwrap();
+
+        let written = write_artifact(&path, &bytes, true).unwrap();
+        assert_eq!(written, format!("{}.gz", path));
+        assert!(!Path::new(&path).exists());
+
+        // Asked for by its plain name, read back byte for byte
+        let loaded = load_receipt(&path).unwrap();
+        assert_eq!(bincode::serialize(&loaded).unwrap(), bytes);
+        assert_eq!(read_artifact(&written).unwrap(), bytes);
+        std::fs::remove_file(written).unwrap();
+    }
+
+    #[test]
+    fn test_rewrite_replaces_the_other_form() {
+        let path = temp_path("derisk_rewritten_receipt.bin");
+
+        write_artifact(&path, b"old", true).unwrap();
+        write_artifact(&path, b"new", false).unwrap();
+        assert!(!Path::new(&format!("{}.gz", path)).exists());
+        assert_eq!(read_artifact(&path).unwrap(), b"new");
+
+        write_artifact(&path, b"newer", true).unwrap();
+        assert!(!Path::new(&path).exists());
+        assert_eq!(read_artifact(&path).unwrap(), b"newer");
+        std::fs::remove_file(format!("{}.gz", path)).unwrap();
+    }
+
+    #[test]
+    fn test_missing_artifact_names_both_paths() {
+        let path = temp_path("derisk_missing_receipt.bin");
+        let err = read_artifact(&path).unwrap_err().to_string();
+        assert!(err.contains(&path) && err.contains(".gz"), "{}", err);
+    }
+}
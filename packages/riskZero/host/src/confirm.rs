@@ -0,0 +1,68 @@
+// Confirmation Prompts
+// Groth16 proving takes minutes and a submission spends gas on a real
+// chain, so both ask before they start. --assume-yes answers for automation.
+// Without a terminal there's no one to ask: rather than block on stdin (and
+// hang a CI job), the run fails and says to pass --assume-yes.
+
+use eyre::{Result, eyre};
+use std::io::{BufRead, IsTerminal, Write};
+
+/// Ask to go ahead with `action`, e.g. "Start Groth16 proving"
+/// Passes straight through with `assume_yes`; fails without a terminal
+pub fn confirm(action: &str, assume_yes: bool) -> Result<()> {
+    let stdin = std::io::stdin();
+    let interactive = stdin.is_terminal();
+    confirm_with(action, assume_yes, interactive, stdin.lock())
+}
+
+fn confirm_with(action: &str, assume_yes: bool, interactive: bool, mut answers: impl BufRead) -> Result<()> {
+    if assume_yes {
+        return Ok(());
+    }
+    if !interactive {
+        return Err(eyre!("{} needs confirmation, but stdin is not a terminal; pass --assume-yes to run unattended", action));
+    }
+
+    // The prompt goes to stderr so it never lands in --output-stdout's JSON
+    eprint!("❓ {}? [y/N] ", action);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    answers.read_line(&mut answer)?;
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(eyre!("{} cancelled", action)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_interactive_without_flag_errors_instead_of_blocking() {
+        // A reader that fails if touched: the check must not wait for input
+        struct NoInput;
+        impl std::io::Read for NoInput {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                panic!("prompted for input without a terminal");
+            }
+        }
+        let no_input = std::io::BufReader::new(NoInput);
+
+        let err = confirm_with("Start Groth16 proving", false, false, no_input).unwrap_err();
+        assert!(err.to_string().contains("--assume-yes"), "{}", err);
+
+        let no_input = std::io::BufReader::new(NoInput);
+        assert!(confirm_with("Start Groth16 proving", true, false, no_input).is_ok());
+    }
+
+    #[test]
+    fn test_interactive_answers() {
+        assert!(confirm_with("Submit", false, true, "y\n".as_bytes()).is_ok());
+        assert!(confirm_with("Submit", false, true, " YES \n".as_bytes()).is_ok());
+        assert!(confirm_with("Submit", false, true, "\n".as_bytes()).is_err());
+        assert!(confirm_with("Submit", false, true, "no\n".as_bytes()).is_err());
+        // Closed stdin counts as no
+        assert!(confirm_with("Submit", false, true, "".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,30 @@
+// Console Output
+// Banners and progress normally go to stdout. With --output-stdout, stdout
+// carries only the JSON result so the host can sit in a pipeline; everything
+// printed through `status!` moves to stderr instead.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static PIPE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Route all `status!` output to stderr from now on
+pub fn enable_pipe_mode() {
+    PIPE_MODE.store(true, Ordering::Relaxed);
+}
+
+/// Whether stdout is reserved for the JSON result
+pub fn pipe_mode() -> bool {
+    PIPE_MODE.load(Ordering::Relaxed)
+}
+
+/// `println!` for human-readable output; goes to stderr in pipe mode
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if $crate::console::pipe_mode() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
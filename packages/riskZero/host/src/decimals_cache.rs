@@ -0,0 +1,103 @@
+// Token Decimals Cache
+// A token's decimals never change, yet every fetch asked each reserve's
+// underlying token for them again. Known decimals are kept in a small JSON
+// file in the output directory, keyed by chain id and token address, and the
+// fetcher looks there before calling decimals(). --refresh-decimals ignores
+// what was stored and asks every token again.
+
+use alloy::primitives::Address;
+use eyre::Result;
+use std::collections::BTreeMap;
+use std::future::Future;
+
+/// Cache file name in the output directory
+pub const DECIMALS_CACHE_FILE: &str = "decimals_cache.json";
+
+/// Decimals of the tokens seen so far, per chain
+/// The default cache is in-memory only and never written
+#[derive(Debug, Default)]
+pub struct DecimalsCache {
+    path: Option<String>,
+    chain_id: u64,
+    /// chain id → token address (lowercase) → decimals
+    chains: BTreeMap<u64, BTreeMap<String, u8>>,
+    changed: bool,
+}
+
+impl DecimalsCache {
+    /// Load the cache at `path` for tokens on `chain_id`
+    /// A missing or unreadable file starts an empty cache, since anything
+    /// not in it is simply fetched. With `refresh`, this chain's entries are
+    /// dropped so every token is asked again (other chains are kept)
+    pub fn load(path: &str, chain_id: u64, refresh: bool) -> Self {
+        let mut chains: BTreeMap<u64, BTreeMap<String, u8>> = match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                status!("⚠ Ignoring unreadable decimals cache {}: {}", path, e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        if refresh {
+            chains.remove(&chain_id);
+        }
+        Self { path: Some(path.to_string()), chain_id, chains, changed: refresh }
+    }
+
+    /// Decimals of `token`, from the cache or else from `fetch`
+    /// Fetched decimals are remembered for the next run once `save`d
+    pub async fn decimals<F, Fut>(&mut self, token: Address, fetch: F) -> Result<u8>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<u8>>,
+    {
+        let key = format!("{:?}", token);
+        if let Some(&decimals) = self.chains.get(&self.chain_id).and_then(|tokens| tokens.get(&key)) {
+            return Ok(decimals);
+        }
+        let decimals = fetch().await?;
+        self.chains.entry(self.chain_id).or_default().insert(key, decimals);
+        self.changed = true;
+        Ok(decimals)
+    }
+
+    /// Write the cache back to its file if anything was added
+    pub fn save(&self) -> Result<()> {
+        if let (Some(path), true) = (&self.path, self.changed) {
+            if let Some(dir) = std::path::Path::new(path).parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, serde_json::to_string_pretty(&self.chains)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::eyre;
+
+    #[tokio::test]
+    async fn test_cached_decimals_skip_the_call() {
+        let path = std::env::temp_dir().join("derisk_decimals_cache_test.json");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let usdc: Address = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".parse().unwrap();
+
+        // Cold run: asks the token and stores the answer
+        let mut cold = DecimalsCache::load(path, 1, false);
+        assert_eq!(cold.decimals(usdc, || async { Ok(6) }).await.unwrap(), 6);
+        cold.save().unwrap();
+
+        // Warm run: answered from the file; the provider errors if it's called
+        let provider = || async { Err(eyre!("decimals() called for a cached token")) };
+        let mut warm = DecimalsCache::load(path, 1, false);
+        assert_eq!(warm.decimals(usdc, provider).await.unwrap(), 6);
+
+        // Another chain, or --refresh-decimals, asks again
+        assert!(DecimalsCache::load(path, 11_155_111, false).decimals(usdc, provider).await.is_err());
+        assert!(DecimalsCache::load(path, 1, true).decimals(usdc, provider).await.is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
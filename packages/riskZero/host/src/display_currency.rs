@@ -0,0 +1,194 @@
+// Display Currency
+// Scores are computed, proven and committed in USD, and the proof knows
+// nothing about other currencies. `--display-currency EUR --fx-rate 0.92`
+// converts the totals the host prints, and adds a `display` block to the
+// --output-stdout JSON, at the supplied rate. The journal, seal and saved
+// files are unchanged.
+
+use derisk_type::{SafetyScoreOutput, UsdAmount, USD_SCALE};
+use eyre::{Result, eyre};
+use serde::Serialize;
+
+/// Scale of `DisplayCurrency::rate` (1e8, as for USD values)
+pub const RATE_SCALE: u128 = 100_000_000;
+
+/// Most fractional digits a rate keeps at `RATE_SCALE`
+const RATE_DECIMALS: usize = 8;
+
+/// Currency printed totals are shown in, with its rate to USD
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayCurrency {
+    /// ISO 4217 code, e.g. "EUR"
+    pub code: String,
+    /// Units of the currency per US dollar, scaled by 1e8
+    pub rate: u128,
+}
+
+impl Default for DisplayCurrency {
+    fn default() -> Self {
+        Self { code: "USD".to_string(), rate: RATE_SCALE }
+    }
+}
+
+impl DisplayCurrency {
+    /// A currency from its code and a decimal rate such as "0.92"
+    /// The rate is parsed exactly; more than 8 decimal places is an error
+    pub fn new(code: &str, rate: &str) -> Result<Self> {
+        let code = code.trim().to_ascii_uppercase();
+        if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(eyre!("--display-currency takes a 3-letter code like EUR, got '{}'", code));
+        }
+        let rate = parse_rate(rate)?;
+        Ok(Self { code, rate })
+    }
+
+    pub fn is_usd(&self) -> bool {
+        self.code == "USD"
+    }
+
+    /// Convert a 1e8-scaled USD value, rounding half up and saturating
+    pub fn convert(&self, usd: u128) -> u128 {
+        let whole = (usd / USD_SCALE).saturating_mul(self.rate);
+        let fraction = (usd % USD_SCALE).saturating_mul(self.rate).saturating_add(USD_SCALE / 2) / USD_SCALE;
+        whole.saturating_add(fraction)
+    }
+
+    /// A USD value as printed: the converted amount followed by the USD amount,
+    /// or just the USD amount when displaying USD
+    pub fn format(&self, usd: u128) -> String {
+        if self.is_usd() {
+            return UsdAmount(usd).to_string();
+        }
+        format!("{} ({})", UsdAmount(self.convert(usd)).with_symbol(&self.symbol()), UsdAmount(usd))
+    }
+
+    fn symbol(&self) -> String {
+        match self.code.as_str() {
+            "EUR" => "€".to_string(),
+            "GBP" => "£".to_string(),
+            "JPY" => "¥".to_string(),
+            code => format!("{} ", code),
+        }
+    }
+
+    /// The output's totals in this currency, for the JSON output
+    /// None when displaying USD, so the JSON is unchanged by default, and for
+    /// an output in a non-USD base currency, which the rate doesn't apply to
+    pub fn totals(&self, output: &SafetyScoreOutput) -> Option<DisplayTotals> {
+        if self.is_usd() || output.denomination.is_some() {
+            return None;
+        }
+        Some(DisplayTotals {
+            currency: self.code.clone(),
+            fx_rate: self.rate,
+            total_assets: self.convert(output.total_assets_usd),
+            total_liabilities: self.convert(output.total_liabilities_usd),
+            buffer: self.convert(output.total_assets_usd.saturating_sub(output.total_liabilities_usd)),
+        })
+    }
+}
+
+/// Totals converted for display; all values scaled by 1e8
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DisplayTotals {
+    pub currency: String,
+    /// Units of `currency` per US dollar
+    pub fx_rate: u128,
+    pub total_assets: u128,
+    pub total_liabilities: u128,
+    pub buffer: u128,
+}
+
+/// An output as printed by --output-stdout, with display totals alongside
+#[derive(Debug, Serialize)]
+pub struct DisplayedOutput<'a> {
+    #[serde(flatten)]
+    pub output: &'a SafetyScoreOutput,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayTotals>,
+}
+
+/// Parse a positive decimal rate into its 1e8-scaled form
+fn parse_rate(rate: &str) -> Result<u128> {
+    let invalid = || eyre!("--fx-rate must be a positive decimal like 0.92, got '{}'", rate);
+    let (whole, fraction) = rate.trim().split_once('.').unwrap_or((rate.trim(), ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(invalid());
+    }
+    if fraction.len() > RATE_DECIMALS {
+        return Err(eyre!("--fx-rate keeps at most {} decimal places, got '{}'", RATE_DECIMALS, rate));
+    }
+    let digits = |part: &str| -> Result<u128> {
+        if part.is_empty() {
+            return Ok(0);
+        }
+        if !part.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        part.parse().map_err(|_| invalid())
+    };
+
+    let fraction = digits(fraction)? * 10u128.pow((RATE_DECIMALS - fraction.len()) as u32);
+    let scaled = digits(whole)?
+        .checked_mul(RATE_SCALE)
+        .and_then(|whole| whole.checked_add(fraction))
+        .ok_or_else(invalid)?;
+    if scaled == 0 {
+        return Err(invalid());
+    }
+    Ok(scaled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eur_rate_converts_display_value() {
+        let eur = DisplayCurrency::new("eur", "0.92").unwrap();
+        assert_eq!(eur.rate, 92_000_000);
+
+        // $1,000.00 at 0.92 EUR per USD
+        let usd = 1_000 * USD_SCALE;
+        assert_eq!(eur.convert(usd), 920 * USD_SCALE);
+        assert_eq!(eur.format(usd), "€920.00 ($1,000.00)");
+
+        let output = SafetyScoreOutput::new(600_000, usd, 400 * USD_SCALE, 1_700_000_000);
+        let totals = eur.totals(&output).unwrap();
+        assert_eq!(totals.total_liabilities, 368 * USD_SCALE);
+        assert_eq!(totals.buffer, 552 * USD_SCALE);
+    }
+
+    #[test]
+    fn test_usd_display_is_unchanged() {
+        let usd = DisplayCurrency::default();
+        assert_eq!(usd.format(123_456_789_000), "$1,234.57");
+
+        let output = SafetyScoreOutput::new(600_000, 1_000, 400, 1_700_000_000);
+        assert_eq!(usd.totals(&output), None);
+        let displayed = DisplayedOutput { output: &output, display: usd.totals(&output) };
+        assert_eq!(serde_json::to_value(&displayed).unwrap(), serde_json::to_value(&output).unwrap());
+    }
+
+    #[test]
+    fn test_conversion_rounds_and_saturates() {
+        let gbp = DisplayCurrency::new("GBP", "0.79123456").unwrap();
+        // 1e-8 USD is 0.79e-8 GBP, which rounds to 1
+        assert_eq!(gbp.convert(1), 1);
+        assert_eq!(gbp.format(100 * USD_SCALE), "£79.12 ($100.00)");
+
+        let jpy = DisplayCurrency::new("JPY", "150").unwrap();
+        assert_eq!(jpy.convert(u128::MAX), u128::MAX);
+    }
+
+    #[test]
+    fn test_rejects_bad_rates_and_codes() {
+        assert!(DisplayCurrency::new("EUR", "0").is_err());
+        assert!(DisplayCurrency::new("EUR", "-0.92").is_err());
+        assert!(DisplayCurrency::new("EUR", "0.123456789").is_err());
+        assert!(DisplayCurrency::new("EUR", "1e3").is_err());
+        assert!(DisplayCurrency::new("EUR", ".").is_err());
+        assert!(DisplayCurrency::new("EURO", "0.92").is_err());
+        assert_eq!(DisplayCurrency::new("CHF", ".9").unwrap().rate, 90_000_000);
+    }
+}
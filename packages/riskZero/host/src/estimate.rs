@@ -0,0 +1,121 @@
+// Proving Cost Estimate
+// Cycle counts are only known after execution, so the host executes first
+// and uses the count to estimate how long the full proof will take
+
+use std::time::Duration;
+
+/// Default STARK proving throughput, in cycles per second (CPU prover)
+pub const DEFAULT_CYCLES_PER_SECOND: u64 = 100_000;
+
+/// Approximate fixed time to wrap a STARK receipt into Groth16
+/// Independent of the guest's cycle count
+pub const GROTH16_WRAP_TIME: Duration = Duration::from_secs(120);
+
+/// Estimated proving time for an execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProvingEstimate {
+    pub cycles: u64,
+    /// Time for the STARK proof (scales with cycles)
+    pub stark: Duration,
+    /// Time for the Groth16 wrap (fixed)
+    pub groth16: Duration,
+}
+
+impl ProvingEstimate {
+    /// Estimate proving time from a cycle count and prover throughput
+    pub fn new(cycles: u64, cycles_per_second: u64) -> Self {
+        let cycles_per_second = cycles_per_second.max(1);
+        // Round up so tiny jobs never estimate to zero seconds
+        let stark_secs = cycles.div_ceil(cycles_per_second);
+        Self {
+            cycles,
+            stark: Duration::from_secs(stark_secs),
+            groth16: GROTH16_WRAP_TIME,
+        }
+    }
+
+    /// Total estimated wall-clock time
+    pub fn total(&self) -> Duration {
+        self.stark + self.groth16
+    }
+
+    /// Estimated cost in prover-hours (multiply by your hourly machine price)
+    pub fn prover_hours(&self) -> f64 {
+        self.total().as_secs_f64() / 3600.0
+    }
+
+    /// Print the estimate in the host's summary style
+    pub fn print(&self) {
+        status!("📐 Proving Estimate:");
+        status!("  - Cycles: {}", self.cycles);
+        status!("  - STARK: ~{}", format_duration(self.stark));
+        status!("  - Groth16: ~{}", format_duration(self.groth16));
+        status!("  - Total: ~{} ({:.2} prover-hours)", format_duration(self.total()), self.prover_hours());
+    }
+}
+
+/// Refuse to prove when the execution exceeds the cycle budget
+pub fn check_cycle_budget(cycles: u64, max_cycles: Option<u64>) -> eyre::Result<()> {
+    match max_cycles {
+        Some(max) if cycles > max => Err(eyre::eyre!(
+            "Execution used {} cycles, exceeding the --max-cycles budget of {}",
+            cycles, max
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Format a duration as "1h 02m 03s" / "2m 03s" / "3s"
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if h > 0 {
+        format!("{}h {:02}m {:02}s", h, m, s)
+    } else if m > 0 {
+        format!("{}m {:02}s", m, s)
+    } else {
+        format!("{}s", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_math() {
+        // 3M cycles at 100k cycles/s = 30s STARK + fixed Groth16 wrap
+        let estimate = ProvingEstimate::new(3_000_000, 100_000);
+        assert_eq!(estimate.stark, Duration::from_secs(30));
+        assert_eq!(estimate.total(), Duration::from_secs(30) + GROTH16_WRAP_TIME);
+
+        // Partial seconds round up
+        let estimate = ProvingEstimate::new(100_001, 100_000);
+        assert_eq!(estimate.stark, Duration::from_secs(2));
+
+        // A zero throughput doesn't divide by zero
+        let estimate = ProvingEstimate::new(10, 0);
+        assert_eq!(estimate.stark, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_prover_hours() {
+        let estimate = ProvingEstimate::new(3_600 * 100_000 - 120 * 100_000, 100_000);
+        assert_eq!(estimate.total(), Duration::from_secs(3_600));
+        assert!((estimate.prover_hours() - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_cycle_budget() {
+        assert!(check_cycle_budget(1_000, None).is_ok());
+        assert!(check_cycle_budget(1_000, Some(1_000)).is_ok());
+        assert!(check_cycle_budget(1_001, Some(1_000)).is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(3)), "3s");
+        assert_eq!(format_duration(Duration::from_secs(123)), "2m 03s");
+        assert_eq!(format_duration(Duration::from_secs(3_723)), "1h 02m 03s");
+    }
+}
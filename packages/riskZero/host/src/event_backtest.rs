@@ -0,0 +1,383 @@
+// Event Backtest
+// Scoring a long history with --blocks costs a full fetch per block. With
+// --from-events the full state is fetched once, at the first block, and the
+// pool's events are replayed from there: supplies, withdrawals, borrows,
+// repayments and liquidations move each reserve's totals, and
+// ReserveDataUpdated carries the indices interest accrues by. Logs are read
+// with eth_getLogs in batches of --log-batch-blocks, and the state is scored
+// at every --blocks point into score_backtest.csv.
+//
+// The replayed state approximates the real one: prices stay at the first
+// block's (the pool doesn't log oracle updates), interest before a reserve's
+// first event in the range isn't counted, stable debt doesn't accrue, and
+// treasury mints and bridged (unbacked) supply are ignored. Points are scored
+// with compute_output, the code the guest runs, without executing the zkVM.
+
+use alloy::{
+    primitives::{Address, Log, B256, U256},
+    providers::{Provider, RootProvider},
+    rpc::types::Filter,
+    sol,
+    sol_types::{SolEvent, SolEventInterface},
+    transports::BoxTransport,
+};
+use derisk_type::{compute_output, AaveInput};
+use eyre::{Result, eyre};
+use std::collections::BTreeMap;
+
+use crate::series::{ScoreSeries, SeriesPoint};
+use crate::shutdown::ShutdownSignal;
+
+/// Default number of blocks per eth_getLogs call; most providers cap a
+/// single call somewhere between 2_000 and 10_000 blocks
+pub const DEFAULT_LOG_BATCH_BLOCKS: u64 = 2_000;
+
+/// File the backtest series is written to in the output directory
+pub const BACKTEST_CSV_FILE: &str = "score_backtest.csv";
+
+// Events an Aave V3 pool emits when a reserve's totals or indices change
+sol! {
+    interface IPool {
+        event ReserveDataUpdated(
+            address indexed reserve,
+            uint256 liquidityRate,
+            uint256 stableBorrowRate,
+            uint256 variableBorrowRate,
+            uint256 liquidityIndex,
+            uint256 variableBorrowIndex
+        );
+        event Supply(
+            address indexed reserve,
+            address user,
+            address indexed onBehalfOf,
+            uint256 amount,
+            uint16 indexed referralCode
+        );
+        event Withdraw(address indexed reserve, address indexed user, address indexed to, uint256 amount);
+        event Borrow(
+            address indexed reserve,
+            address user,
+            address indexed onBehalfOf,
+            uint256 amount,
+            uint8 interestRateMode,
+            uint256 borrowRate,
+            uint16 indexed referralCode
+        );
+        event Repay(
+            address indexed reserve,
+            address indexed user,
+            address indexed repayer,
+            uint256 amount,
+            bool useATokens
+        );
+        event LiquidationCall(
+            address indexed collateralAsset,
+            address indexed debtAsset,
+            address indexed user,
+            uint256 debtToCover,
+            uint256 liquidatedCollateralAmount,
+            address liquidator,
+            bool receiveAToken
+        );
+    }
+}
+
+/// Aave's interestRateMode for stable-rate debt (variable is 2)
+const STABLE_RATE_MODE: u8 = 1;
+
+/// How one event changes a reserve, in the reserve token's units
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReserveChange {
+    /// Liquidity and variable borrow indices (ray) once interest accrued
+    Indices { liquidity_index: u128, variable_borrow_index: u128 },
+    Supplied(u128),
+    Withdrawn(u128),
+    Borrowed { amount: u128, stable: bool },
+    /// Debt repaid; with aTokens, the supply is burned to pay it
+    Repaid { amount: u128, with_atokens: bool },
+    /// Debt a liquidator repaid
+    LiquidatedDebt(u128),
+    /// Collateral seized; it stays supplied when the liquidator takes aTokens
+    LiquidatedCollateral { amount: u128, as_atokens: bool },
+}
+
+/// A decoded pool event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolEvent {
+    pub block: u64,
+    /// Block time, when the node reports it with the log
+    pub timestamp: Option<u64>,
+    /// Reserve token address, lowercase as reserves key it
+    pub reserve: String,
+    pub change: ReserveChange,
+}
+
+/// topic0 of every event the replay reads
+pub fn event_signatures() -> Vec<B256> {
+    vec![
+        IPool::ReserveDataUpdated::SIGNATURE_HASH,
+        IPool::Supply::SIGNATURE_HASH,
+        IPool::Withdraw::SIGNATURE_HASH,
+        IPool::Borrow::SIGNATURE_HASH,
+        IPool::Repay::SIGNATURE_HASH,
+        IPool::LiquidationCall::SIGNATURE_HASH,
+    ]
+}
+
+/// Decode one log into the reserve changes it records
+/// A liquidation changes two reserves; logs of other events give none
+pub fn decode_log(block: u64, timestamp: Option<u64>, log: &Log) -> Result<Vec<PoolEvent>> {
+    match log.topics().first() {
+        Some(topic) if event_signatures().contains(topic) => {}
+        _ => return Ok(Vec::new()),
+    }
+    let event = IPool::IPoolEvents::decode_raw_log(log.topics(), &log.data.data, true)
+        .map_err(|e| eyre!("Malformed pool event in block {}: {}", block, e))?;
+
+    let at = |reserve: Address, change| PoolEvent { block, timestamp, reserve: format!("{:?}", reserve), change };
+    Ok(match event {
+        IPool::IPoolEvents::ReserveDataUpdated(e) => vec![at(
+            e.reserve,
+            ReserveChange::Indices {
+                liquidity_index: amount(e.liquidityIndex)?,
+                variable_borrow_index: amount(e.variableBorrowIndex)?,
+            },
+        )],
+        IPool::IPoolEvents::Supply(e) => vec![at(e.reserve, ReserveChange::Supplied(amount(e.amount)?))],
+        IPool::IPoolEvents::Withdraw(e) => vec![at(e.reserve, ReserveChange::Withdrawn(amount(e.amount)?))],
+        IPool::IPoolEvents::Borrow(e) => vec![at(
+            e.reserve,
+            ReserveChange::Borrowed { amount: amount(e.amount)?, stable: e.interestRateMode == STABLE_RATE_MODE },
+        )],
+        IPool::IPoolEvents::Repay(e) => vec![at(
+            e.reserve,
+            ReserveChange::Repaid { amount: amount(e.amount)?, with_atokens: e.useATokens },
+        )],
+        IPool::IPoolEvents::LiquidationCall(e) => vec![
+            at(e.debtAsset, ReserveChange::LiquidatedDebt(amount(e.debtToCover)?)),
+            at(
+                e.collateralAsset,
+                ReserveChange::LiquidatedCollateral {
+                    amount: amount(e.liquidatedCollateralAmount)?,
+                    as_atokens: e.receiveAToken,
+                },
+            ),
+        ],
+    })
+}
+
+fn amount(value: U256) -> Result<u128> {
+    value.try_into().map_err(|_| eyre!("Event amount {} too large for u128", value))
+}
+
+/// Read the pool's events over blocks `from..=to`, `batch_blocks` at a time
+/// Providers cap how much one eth_getLogs call may cover, so a long range is
+/// split; events come back in block order. An interrupt stops between batches
+pub async fn fetch_events(
+    provider: &RootProvider<BoxTransport>,
+    pool: Address,
+    from: u64,
+    to: u64,
+    batch_blocks: u64,
+    shutdown: &ShutdownSignal,
+) -> Result<Vec<PoolEvent>> {
+    if batch_blocks == 0 {
+        return Err(eyre!("--log-batch-blocks must be at least 1"));
+    }
+    let mut events = Vec::new();
+    let mut start = from;
+    while start <= to {
+        if shutdown.is_requested() {
+            return Err(eyre!("Interrupted reading logs at block {}", start));
+        }
+        let end = start.saturating_add(batch_blocks - 1).min(to);
+        let filter = Filter::new()
+            .address(pool)
+            .from_block(start)
+            .to_block(end)
+            .event_signature(event_signatures());
+        let logs = provider
+            .get_logs(&filter)
+            .await
+            .map_err(|e| eyre!("eth_getLogs for blocks {}..={} failed: {}", start, end, e))?;
+        for log in &logs {
+            let block = log.block_number.ok_or_else(|| eyre!("Log without a block number in {}..={}", start, end))?;
+            events.extend(decode_log(block, log.block_timestamp, &log.inner)?);
+        }
+        status!("  - Blocks {}..={}: {} events", start, end, logs.len());
+
+        match end.checked_add(1) {
+            Some(next) => start = next,
+            None => break,
+        }
+    }
+    Ok(events)
+}
+
+/// A snapshot moved forward event by event
+#[derive(Debug, Clone)]
+pub struct Replay {
+    input: AaveInput,
+    /// Last seen (liquidity, variable borrow) index per reserve
+    indices: BTreeMap<String, (u128, u128)>,
+}
+
+impl Replay {
+    pub fn new(snapshot: AaveInput) -> Self {
+        Self { input: snapshot, indices: BTreeMap::new() }
+    }
+
+    /// The state as replayed so far
+    pub fn input(&self) -> &AaveInput {
+        &self.input
+    }
+
+    /// Apply one event; events for reserves not in the snapshot (filtered
+    /// out, or listed later in the range) are ignored
+    pub fn apply(&mut self, event: &PoolEvent) {
+        let reserves = &mut self.input.reserves;
+        let Some(reserve) = reserves.iter_mut().find(|r| r.token_address.eq_ignore_ascii_case(&event.reserve)) else {
+            return;
+        };
+        if let Some(timestamp) = event.timestamp {
+            self.input.timestamp = timestamp;
+        }
+
+        // Debt repaid or liquidated is taken off variable debt first
+        let reduce_debt = |reserve: &mut derisk_type::AaveReserveData, amount: u128| {
+            let from_variable = amount.min(reserve.total_variable_debt);
+            reserve.total_variable_debt -= from_variable;
+            reserve.total_stable_debt = reserve.total_stable_debt.saturating_sub(amount - from_variable);
+        };
+
+        match event.change {
+            ReserveChange::Indices { liquidity_index, variable_borrow_index } => {
+                // Balances grow with the index; the first one seen is only a baseline
+                if let Some((last_liquidity, last_variable)) =
+                    self.indices.insert(event.reserve.clone(), (liquidity_index, variable_borrow_index))
+                {
+                    reserve.total_atoken = grow(reserve.total_atoken, liquidity_index, last_liquidity);
+                    reserve.total_variable_debt =
+                        grow(reserve.total_variable_debt, variable_borrow_index, last_variable);
+                }
+            }
+            ReserveChange::Supplied(amount) => reserve.total_atoken = reserve.total_atoken.saturating_add(amount),
+            ReserveChange::Withdrawn(amount) => reserve.total_atoken = reserve.total_atoken.saturating_sub(amount),
+            ReserveChange::Borrowed { amount, stable: true } => {
+                reserve.total_stable_debt = reserve.total_stable_debt.saturating_add(amount)
+            }
+            ReserveChange::Borrowed { amount, stable: false } => {
+                reserve.total_variable_debt = reserve.total_variable_debt.saturating_add(amount)
+            }
+            ReserveChange::Repaid { amount, with_atokens } => {
+                reduce_debt(reserve, amount);
+                if with_atokens {
+                    reserve.total_atoken = reserve.total_atoken.saturating_sub(amount);
+                }
+            }
+            ReserveChange::LiquidatedDebt(amount) => reduce_debt(reserve, amount),
+            ReserveChange::LiquidatedCollateral { amount, as_atokens } => {
+                if !as_atokens {
+                    reserve.total_atoken = reserve.total_atoken.saturating_sub(amount);
+                }
+            }
+        }
+    }
+}
+
+/// `balance` scaled by `index / last_index`, as a balance accrues interest
+fn grow(balance: u128, index: u128, last_index: u128) -> u128 {
+    if last_index == 0 {
+        return balance;
+    }
+    (U256::from(balance) * U256::from(index) / U256::from(last_index)).saturating_to()
+}
+
+/// Replay `events` (in block order) over `snapshot`, scoring the state as of
+/// the end of each of `blocks` (ascending)
+pub fn replay_series(snapshot: AaveInput, events: &[PoolEvent], blocks: &[u64]) -> ScoreSeries {
+    let mut replay = Replay::new(snapshot);
+    let mut pending = events.iter().peekable();
+    let mut series = ScoreSeries::default();
+    for &block in blocks {
+        while let Some(event) = pending.next_if(|event| event.block <= block) {
+            replay.apply(event);
+        }
+        series.points.push(SeriesPoint::new(block, &compute_output(replay.input())));
+    }
+    series
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::rpc::types::Log as RpcLog;
+    use derisk_type::AaveReserveData;
+
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    /// eth_getLogs results recorded from an Aave V3 pool, one of each event
+    fn recorded_events() -> Vec<PoolEvent> {
+        let path = format!("{}/tests/fixtures/pool_event_logs.json", env!("CARGO_MANIFEST_DIR"));
+        let json = std::fs::read_to_string(path).unwrap();
+        let logs: Vec<RpcLog> = serde_json::from_str(&json).unwrap();
+        logs.iter()
+            .flat_map(|log| decode_log(log.block_number.unwrap(), log.block_timestamp, &log.inner).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_recorded_logs_decode_into_deltas() {
+        let events = recorded_events();
+        let changes: Vec<(u64, &str, ReserveChange)> =
+            events.iter().map(|e| (e.block, e.reserve.as_str(), e.change.clone())).collect();
+        assert_eq!(changes, vec![
+            (18_500_001, USDC, ReserveChange::Indices {
+                liquidity_index: 1_000_000_000_000_000_000_000_000_000,
+                variable_borrow_index: 1_000_000_000_000_000_000_000_000_000,
+            }),
+            (18_500_001, USDC, ReserveChange::Supplied(500_000_000)),
+            (18_500_002, USDC, ReserveChange::Borrowed { amount: 200_000_000, stable: false }),
+            (18_500_003, USDC, ReserveChange::Indices {
+                liquidity_index: 1_010_000_000_000_000_000_000_000_000,
+                variable_borrow_index: 1_020_000_000_000_000_000_000_000_000,
+            }),
+            (18_500_003, USDC, ReserveChange::Repaid { amount: 100_000_000, with_atokens: false }),
+            (18_500_004, WETH, ReserveChange::Withdrawn(250_000_000_000_000_000)),
+            (18_500_005, USDC, ReserveChange::LiquidatedDebt(50_000_000)),
+            (18_500_005, WETH, ReserveChange::LiquidatedCollateral {
+                amount: 30_000_000_000_000_000,
+                as_atokens: false,
+            }),
+        ]);
+        assert_eq!(events[0].timestamp, Some(1_700_000_012));
+
+        // Replayed over $1,000 of USDC ($400 borrowed) and 1 WETH
+        let snapshot = AaveInput::new(
+            vec![
+                AaveReserveData::builder(USDC)
+                    .decimals(6)
+                    .total_atoken(1_000_000_000)
+                    .total_variable_debt(400_000_000)
+                    .build(),
+                AaveReserveData::builder(WETH).price_usd(2_000 * 100_000_000).build(),
+            ],
+            "Aave V3".to_string(),
+            1_700_000_000,
+        );
+        let mut replay = Replay::new(snapshot.clone());
+        events.iter().for_each(|event| replay.apply(event));
+        let usdc = &replay.input().reserves[0];
+        // 1,500 supplied grows 1% to 1,515; 600 borrowed grows 2% to 612, less
+        // 100 repaid and 50 liquidated
+        assert_eq!(usdc.total_atoken, 1_515_000_000);
+        assert_eq!(usdc.total_variable_debt, 462_000_000);
+        assert_eq!(replay.input().reserves[1].total_atoken, 720_000_000_000_000_000);
+
+        // Points before the first event score the snapshot as fetched
+        let series = replay_series(snapshot.clone(), &events, &[18_500_000, 18_500_005]);
+        assert_eq!(series.points[0].safety_score, compute_output(&snapshot).safety_score);
+        assert_eq!(series.points[1].safety_score, compute_output(replay.input()).safety_score);
+    }
+}
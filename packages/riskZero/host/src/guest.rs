@@ -0,0 +1,107 @@
+// Guest Execution
+// Runs the guest program without proving, which is fast enough to get the
+// safety score and cycle counts before committing to a full proof
+//
+// Execution is split into segments of at most 2^segment_limit_po2 cycles
+// (risc0's default is 2^20), and each segment is proven on its own. A smaller
+// limit bounds the memory a single segment needs while proving, at the cost
+// of more segments: each one adds continuation overhead (paging in and out
+// of memory) and its own proof to aggregate, so total proving time grows.
+
+use derisk_type::{AaveInput, SafetyScoreOutput};
+use eyre::{Result, eyre};
+use methods::AAVE_ELF;
+use risc0_zkvm::{default_executor, ExecutorEnv};
+
+/// Result of executing the guest without proving
+#[derive(Debug, Clone)]
+pub struct Execution {
+    /// The decoded journal
+    pub output: SafetyScoreOutput,
+    /// Raw journal bytes exactly as a proof would commit them
+    pub journal: Vec<u8>,
+    /// Total cycles used, including paging overhead
+    pub cycles: u64,
+    /// Number of segments the execution was split into
+    pub segments: usize,
+}
+
+/// The guest's environment: the input, and the segment size when one is given
+pub fn build_env(input: &AaveInput, segment_limit_po2: Option<u32>) -> Result<ExecutorEnv<'static>> {
+    let mut builder = ExecutorEnv::builder();
+    builder
+        .write(input)
+        .map_err(|e| eyre!("Failed to write input: {}", e))?;
+    if let Some(po2) = segment_limit_po2 {
+        builder.segment_limit_po2(po2);
+    }
+    builder.build().map_err(|e| eyre!("Failed to build env: {}", e))
+}
+
+/// Execute the guest on `input` and decode its journal
+/// `segment_limit_po2` as for `build_env`; None keeps risc0's default
+pub fn execute_guest(input: &AaveInput, segment_limit_po2: Option<u32>) -> Result<Execution> {
+    let env = build_env(input, segment_limit_po2)?;
+
+    let session = default_executor()
+        .execute(env, AAVE_ELF)
+        .map_err(|e| eyre!("Failed to execute guest: {}", e))?;
+
+    let output: SafetyScoreOutput = session.journal.decode()
+        .map_err(|e| eyre!("Failed to decode journal: {}", e))?;
+
+    Ok(Execution {
+        output,
+        journal: session.journal.bytes.clone(),
+        cycles: session.cycles(),
+        segments: session.segments.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derisk_type::AaveReserveData;
+
+    /// Far below the default of 2^20, so even a one-reserve input spans several segments
+    const SMALL_SEGMENT_PO2: u32 = 15;
+
+    #[test]
+    fn test_small_segment_limit_still_completes() {
+        let input = AaveInput::new(
+            vec![AaveReserveData::builder("0xUSDC").decimals(6).total_variable_debt(400_000).build()],
+            "Segment Test".to_string(),
+            1_700_000_000,
+        );
+
+        let default = execute_guest(&input, None).unwrap();
+        let limited = execute_guest(&input, Some(SMALL_SEGMENT_PO2)).unwrap();
+
+        // Same result, split at least as finely
+        assert_eq!(limited.journal, default.journal);
+        assert_eq!(limited.output.safety_score, 600_000);
+        assert!(limited.segments >= default.segments);
+        assert!(limited.segments > 1, "2^{} cycles should not fit the whole guest", SMALL_SEGMENT_PO2);
+    }
+
+    #[test]
+    fn test_execution_commits_guest_version() {
+        let input = AaveInput::new(
+            vec![AaveReserveData::builder("0xUSDC").decimals(6).build()],
+            "Version Test".to_string(),
+            1_700_000_000,
+        );
+
+        // The guest commits the version of the scoring crate it was built from
+        let manifest = include_str!("../../derisk-type/Cargo.toml");
+        let version = manifest
+            .lines()
+            .find_map(|line| line.strip_prefix("version = "))
+            .map(|version| version.trim_matches('"'))
+            .unwrap();
+
+        let execution = execute_guest(&input, None).unwrap();
+        assert_eq!(execution.output.guest_version, version);
+        assert_eq!(execution.output.guest_version, derisk_type::GUEST_VERSION);
+    }
+}
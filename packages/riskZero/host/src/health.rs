@@ -0,0 +1,240 @@
+// Health Status
+// `--mode status` gives operators running the oracle as a service a one-glance
+// health check without fetching or proving anything. It reads what the last
+// runs left in the output directory (safety_score_output.json, the proof
+// manifest, last_submission.json) and asks the oracle for the score it holds
+// and when that score was last updated.
+
+use alloy::primitives::TxHash;
+use derisk_type::SafetyScoreOutput;
+use eyre::Result;
+use std::path::Path;
+
+use crate::journal_diff::load_output;
+use crate::submission::{SubmissionRecord, TxStatus};
+use crate::telemetry::ProofManifest;
+
+/// File in the output directory holding the last computed output
+pub const OUTPUT_FILE: &str = "safety_score_output.json";
+
+/// The oracle reads a status check needs
+/// Implemented by `OracleReader`; tests use an in-memory mock
+#[allow(async_fn_in_trait)]
+pub trait OracleView {
+    /// Score the oracle currently holds for the protocol
+    async fn current_score(&self) -> Result<u64>;
+    /// Unix time of the oracle's last update for the protocol, 0 if never updated
+    async fn last_update_time(&self) -> Result<u64>;
+    /// Look up a transaction by hash
+    async fn tx_status(&self, tx_hash: TxHash) -> Result<TxStatus>;
+}
+
+/// Local and on-chain state at one point in time
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    /// Last computed output, from `OUTPUT_FILE`
+    pub last_output: Option<SafetyScoreOutput>,
+    /// Unix time the last proof finished, from the proof manifest
+    pub last_proof_at: Option<u64>,
+    pub submission: Option<SubmissionRecord>,
+    /// Current status of `submission`'s tx
+    pub submission_status: Option<TxStatus>,
+    /// As stored by the oracle
+    pub onchain_score: u64,
+    /// Unix time of the oracle's last update, 0 if never updated
+    pub onchain_updated_at: u64,
+    /// Unix time the report was taken
+    pub checked_at: u64,
+}
+
+impl HealthReport {
+    /// Seconds since the oracle was last updated; None if it never was
+    pub fn staleness(&self) -> Option<u64> {
+        (self.onchain_updated_at != 0).then(|| self.checked_at.saturating_sub(self.onchain_updated_at))
+    }
+
+    pub fn print(&self) {
+        status!("🩺 Oracle Health:");
+        match &self.last_output {
+            Some(output) => status!(
+                "  - Last computed score: {:.4}% (input time {})",
+                output.to_percentage(),
+                output.timestamp
+            ),
+            None => status!("  - Last computed score: none ({} not found)", OUTPUT_FILE),
+        }
+        match self.last_proof_at {
+            Some(at) => status!("  - Last proof: {} ({} ago)", at, format_age(self.checked_at.saturating_sub(at))),
+            None => status!("  - Last proof: none"),
+        }
+        match (&self.submission, self.submission_status) {
+            (Some(record), Some(tx_status)) => {
+                status!("  - Last submission: {} ({})", record.tx_hash, describe_tx(tx_status))
+            }
+            _ => status!("  - Last submission: none"),
+        }
+        status!("  - On-chain score: {}", self.onchain_score);
+        match self.staleness() {
+            Some(age) => status!(
+                "  - On-chain updated: {} ({} ago)",
+                self.onchain_updated_at,
+                format_age(age)
+            ),
+            None => status!("  - On-chain updated: never"),
+        }
+    }
+}
+
+fn describe_tx(tx_status: TxStatus) -> String {
+    match tx_status {
+        TxStatus::Confirmed { block } => format!("confirmed in block {}", block),
+        TxStatus::Pending => "pending".to_string(),
+        TxStatus::Unknown => "dropped".to_string(),
+    }
+}
+
+/// An age in its two largest units, e.g. "3h 12m"
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3_599 => format!("{}m {}s", secs / 60, secs % 60),
+        3_600..=86_399 => format!("{}h {}m", secs / 3_600, secs % 3_600 / 60),
+        _ => format!("{}d {}h", secs / 86_400, secs % 86_400 / 3_600),
+    }
+}
+
+/// Gather the saved state in `output_dir` and the oracle's view of it
+/// Missing local files are reported as absent rather than failing the check
+pub async fn check_health<O: OracleView>(oracle: &O, output_dir: &str, checked_at: u64) -> Result<HealthReport> {
+    let output_path = format!("{}/{}", output_dir, OUTPUT_FILE);
+    let last_output = if Path::new(&output_path).exists() {
+        Some(load_output(&output_path)?)
+    } else {
+        None
+    };
+    let last_proof_at = ProofManifest::load(output_dir)?.map(|manifest| manifest.telemetry.recorded_at);
+
+    let submission = SubmissionRecord::load(output_dir)?;
+    let submission_status = match &submission {
+        Some(record) => Some(oracle.tx_status(record.tx_hash).await?),
+        None => None,
+    };
+
+    Ok(HealthReport {
+        last_output,
+        last_proof_at,
+        submission,
+        submission_status,
+        onchain_score: oracle.current_score().await?,
+        onchain_updated_at: oracle.last_update_time().await?,
+        checked_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receipt_format::ReceiptFormat;
+    use crate::telemetry::ProofTelemetry;
+
+    /// An oracle last updated at a fixed time, with one mined tx
+    struct MockOracle {
+        score: u64,
+        updated_at: u64,
+        mined: TxHash,
+    }
+
+    impl OracleView for MockOracle {
+        async fn current_score(&self) -> Result<u64> {
+            Ok(self.score)
+        }
+
+        async fn last_update_time(&self) -> Result<u64> {
+            Ok(self.updated_at)
+        }
+
+        async fn tx_status(&self, tx_hash: TxHash) -> Result<TxStatus> {
+            if tx_hash == self.mined {
+                Ok(TxStatus::Confirmed { block: 21_000_000 })
+            } else {
+                Ok(TxStatus::Unknown)
+            }
+        }
+    }
+
+    fn temp_output_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("derisk_health_{}", name));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.to_str().unwrap().to_string()
+    }
+
+    fn manifest(recorded_at: u64) -> ProofManifest {
+        ProofManifest {
+            artifacts: vec!["proof_journal.bin".to_string(), "proof_seal.bin".to_string()],
+            receipt_format: ReceiptFormat::Bincode,
+            telemetry: ProofTelemetry {
+                recorded_at,
+                version: "0.1.0".to_string(),
+                image_id: "0xabc".to_string(),
+                reserve_count: 12,
+                total_cycles: 1_048_576,
+                segments: 1,
+                stark_ms: 60_000,
+                groth16_ms: 30_000,
+                total_ms: 90_000,
+                seal_bytes: 260,
+                journal_bytes: 120,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report_from_saved_run_and_oracle() {
+        let output_dir = temp_output_dir("fixture");
+        let output = SafetyScoreOutput::new(985_000, 1_000, 15, 1_700_000_000);
+        std::fs::write(format!("{}/{}", output_dir, OUTPUT_FILE), serde_json::to_string(&output).unwrap()).unwrap();
+        manifest(1_700_000_600).save(&output_dir).unwrap();
+        let record = SubmissionRecord {
+            tx_hash: TxHash::with_last_byte(7),
+            journal_digest: "00".repeat(32),
+            confirmed_block: Some(21_000_000),
+        };
+        record.save(&output_dir).unwrap();
+
+        let oracle = MockOracle { score: 98, updated_at: 1_700_000_000, mined: record.tx_hash };
+        let report = check_health(&oracle, &output_dir, 1_700_003_600).await.unwrap();
+
+        assert_eq!(report.last_output.as_ref().map(|o| o.safety_score), Some(985_000));
+        assert_eq!(report.last_proof_at, Some(1_700_000_600));
+        assert_eq!(report.submission, Some(record));
+        assert_eq!(report.submission_status, Some(TxStatus::Confirmed { block: 21_000_000 }));
+        assert_eq!(report.onchain_score, 98);
+        assert_eq!(report.staleness(), Some(3_600));
+        report.print();
+
+        std::fs::remove_dir_all(&output_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_report_with_nothing_saved() {
+        let output_dir = temp_output_dir("empty");
+        let oracle = MockOracle { score: 0, updated_at: 0, mined: TxHash::ZERO };
+
+        let report = check_health(&oracle, &output_dir, 1_700_000_000).await.unwrap();
+        assert!(report.last_output.is_none());
+        assert_eq!(report.last_proof_at, None);
+        assert_eq!(report.submission_status, None);
+        // An oracle that was never updated isn't "stale by 54 years"
+        assert_eq!(report.staleness(), None);
+
+        std::fs::remove_dir_all(&output_dir).ok();
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(42), "42s");
+        assert_eq!(format_age(3_725), "1h 2m");
+        assert_eq!(format_age(90_000), "1d 1h");
+    }
+}
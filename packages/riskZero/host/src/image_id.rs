@@ -0,0 +1,120 @@
+// Image ID Encoding
+// The guest's image ID is compiled in as [u32; 8], but verifier contracts take
+// it as a bytes32. This renders it in the same byte order risc0's `Digest`
+// uses (and that `guestImageId` must be deployed with), so the two can be
+// compared by eye or with --expected-image-id.
+//
+// --verify-image-id-file checks it against an ID committed to the repo
+// instead, so a guest edit or a build that doesn't reproduce is caught before
+// anything is proven: the file only changes in a commit that means to.
+
+use eyre::{Result, eyre};
+
+/// Render an image ID as a 0x-prefixed 32-byte hex string
+/// Each u32 word is written little-endian, matching `Digest::from(AAVE_ID)`
+pub fn image_id_hex(id: &[u32; 8]) -> String {
+    let bytes: Vec<u8> = id.iter().flat_map(|word| word.to_le_bytes()).collect();
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Parse a hex image ID (with or without 0x) back into its [u32; 8] form
+pub fn parse_image_id(hex_id: &str) -> Result<[u32; 8]> {
+    let hex_id = hex_id.trim();
+    let hex_id = hex_id.strip_prefix("0x").unwrap_or(hex_id);
+    let bytes = hex::decode(hex_id).map_err(|e| eyre!("Invalid image ID hex: {}", e))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| eyre!("Image ID must be 32 bytes, got {}", bytes.len()))?;
+    Ok(image_id_from_bytes(bytes))
+}
+
+/// Unpack a bytes32 image ID, as a verifier contract stores it, into its [u32; 8] form
+pub fn image_id_from_bytes(bytes: [u8; 32]) -> [u32; 8] {
+    let mut id = [0u32; 8];
+    for (word, chunk) in id.iter_mut().zip(bytes.chunks_exact(4)) {
+        *word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    id
+}
+
+/// Abort unless the compiled guest is the one the caller expects
+/// Guards against proving (and submitting) with a different guest version
+pub fn check_expected_image_id(expected: &str, actual: &[u32; 8]) -> Result<()> {
+    if parse_image_id(expected)? != *actual {
+        return Err(eyre!(
+            "Image ID mismatch: expected {}, but this build's guest is {}",
+            expected.trim(), image_id_hex(actual)
+        ));
+    }
+    Ok(())
+}
+
+/// Abort unless the compiled guest is the one committed in the file at `path`
+/// The file holds the hex ID on a line of its own; blank lines and lines
+/// starting with # are ignored
+pub fn check_image_id_file(path: &str, actual: &[u32; 8]) -> Result<()> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| eyre!("Failed to read image ID file {}: {}", path, e))?;
+    let expected = contents
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .ok_or_else(|| eyre!("Image ID file {} holds no image ID", path))?;
+    let expected_id = parse_image_id(expected).map_err(|e| eyre!("Image ID file {}: {}", path, e))?;
+    if expected_id != *actual {
+        return Err(eyre!(
+            "This build's guest is {}, but {} expects {}\nThe guest source (or its build) changed; if that's intended, update {} with the new image ID and commit it",
+            image_id_hex(actual), path, expected, path
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_round_trip() {
+        let id = [0x03020100, 0x07060504, 0, 0, 0, 0, 0, 0xdeadbeef];
+        let hex_id = image_id_hex(&id);
+
+        // Words are packed little-endian, one after another
+        assert_eq!(
+            hex_id,
+            "0x00010203040506070000000000000000000000000000000000000000efbeadde"
+        );
+        assert_eq!(parse_image_id(&hex_id).unwrap(), id);
+        assert_eq!(parse_image_id(hex_id.trim_start_matches("0x")).unwrap(), id);
+    }
+
+    #[test]
+    fn test_expected_image_id_guard() {
+        let id = [1, 2, 3, 4, 5, 6, 7, 8];
+        assert!(check_expected_image_id(&image_id_hex(&id), &id).is_ok());
+        assert!(check_expected_image_id(&image_id_hex(&[0; 8]), &id).is_err());
+        assert!(check_expected_image_id("0x1234", &id).is_err());
+    }
+
+    #[test]
+    fn test_image_id_file_guard() {
+        let id = [1, 2, 3, 4, 5, 6, 7, 8];
+        let dir = std::env::temp_dir().join("derisk_image_id_file");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("expected_image_id.txt");
+        let path = path.to_str().unwrap();
+
+        std::fs::write(path, format!("# Aave guest\n{}\n", image_id_hex(&id))).unwrap();
+        assert!(check_image_id_file(path, &id).is_ok());
+
+        std::fs::write(path, image_id_hex(&[0; 8])).unwrap();
+        let err = check_image_id_file(path, &id).unwrap_err().to_string();
+        assert!(err.contains("guest source (or its build) changed"), "{}", err);
+        assert!(err.contains(&image_id_hex(&id)), "{}", err);
+
+        std::fs::write(path, "# nothing yet\n").unwrap();
+        assert!(check_image_id_file(path, &id).is_err());
+        std::fs::remove_file(path).unwrap();
+        assert!(check_image_id_file(path, &id).is_err());
+    }
+}
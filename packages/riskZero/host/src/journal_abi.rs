@@ -0,0 +1,217 @@
+// Journal ABI
+// The on-chain verifier hashes the raw journal with sha256 and reads the
+// leading fields as fixed-width little-endian integers (see
+// contracts/MinimalVerifier.sol). It also reads the last two words, the
+// hypothetical flag and the score status, and rejects what-if and
+// NoData/Incomplete scores. This module mirrors that decoding so encoding
+// mismatches are caught before a transaction is sent.
+
+use derisk_type::{SafetyScoreOutput, ScoreStatus};
+use eyre::{Result, eyre};
+use sha2::{Digest, Sha256};
+
+/// Length of the fixed-width prefix read by the Solidity verifier
+/// safety_score(8) + total_assets(16) + total_liabilities(16) + timestamp(8)
+pub const SOLIDITY_JOURNAL_LEN: usize = 48;
+
+/// Length of the trailer read by the Solidity verifier
+/// hypothetical(4) + status(4), the output's last two fields as zkVM words
+pub const SOLIDITY_TRAILER_LEN: usize = 8;
+
+/// Encode the fields the Solidity verifier reads, in its fixed-width layout
+/// This is byte-for-byte what the zkVM serializer commits for these fields
+pub fn encode_for_verifier(output: &SafetyScoreOutput) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(SOLIDITY_JOURNAL_LEN);
+    bytes.extend_from_slice(&output.safety_score.to_le_bytes());
+    bytes.extend_from_slice(&output.total_assets_usd.to_le_bytes());
+    bytes.extend_from_slice(&output.total_liabilities_usd.to_le_bytes());
+    bytes.extend_from_slice(&output.timestamp.to_le_bytes());
+    bytes
+}
+
+/// The journal digest passed to `IRiscZeroVerifier.verify` (sha256 of the raw journal)
+pub fn journal_digest(journal: &[u8]) -> [u8; 32] {
+    Sha256::digest(journal).into()
+}
+
+/// Decode a journal the way Solidity does: fixed-width little-endian fields
+/// at fixed offsets, plus the hypothetical flag and status from its last two
+/// words. Fields in between are not read on-chain and are left at their
+/// defaults.
+pub fn decode_fixed_width(journal: &[u8]) -> Result<SafetyScoreOutput> {
+    let min_len = SOLIDITY_JOURNAL_LEN + SOLIDITY_TRAILER_LEN;
+    if journal.len() < min_len {
+        return Err(eyre!(
+            "Journal too short for the Solidity verifier: {} bytes, need {}",
+            journal.len(), min_len
+        ));
+    }
+
+    let trailer = journal.len() - SOLIDITY_TRAILER_LEN;
+    let status = match read_u32_le(journal, trailer + 4) {
+        0 => ScoreStatus::Ok,
+        1 => ScoreStatus::Insolvent,
+        2 => ScoreStatus::NoData,
+        3 => ScoreStatus::Incomplete,
+        other => return Err(eyre!("Journal ends with an unknown score status {}", other)),
+    };
+    Ok(SafetyScoreOutput::new(
+        read_u64_le(journal, 0),
+        read_u128_le(journal, 8),
+        read_u128_le(journal, 24),
+        read_u64_le(journal, 40),
+    )
+    .with_hypothetical(read_u32_le(journal, trailer) != 0)
+    .with_status(status))
+}
+
+/// Check that the journal decodes on-chain to the same values the host sees
+pub fn check_verifier_compat(journal: &[u8], output: &SafetyScoreOutput) -> Result<()> {
+    let onchain = decode_fixed_width(journal)?;
+    if journal[..SOLIDITY_JOURNAL_LEN] != encode_for_verifier(output)[..] {
+        return Err(eyre!(
+            "Journal prefix does not match the Solidity layout (on-chain would read score {}, host decoded {})",
+            onchain.safety_score, output.safety_score
+        ));
+    }
+    if (onchain.hypothetical, onchain.status) != (output.hypothetical, output.status) {
+        return Err(eyre!(
+            "Journal trailer does not match the Solidity layout (on-chain would read {:?}, hypothetical {}; host decoded {:?}, hypothetical {})",
+            onchain.status, onchain.hypothetical, output.status, output.hypothetical
+        ));
+    }
+    Ok(())
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> u32 {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_le_bytes(buf)
+}
+
+fn read_u64_le(data: &[u8], offset: usize) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&data[offset..offset + 8]);
+    u64::from_le_bytes(buf)
+}
+
+fn read_u128_le(data: &[u8], offset: usize) -> u128 {
+    let mut buf = [0u8; 16];
+    buf.copy_from_slice(&data[offset..offset + 16]);
+    u128::from_le_bytes(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derisk_type::{bound_breakdown, ReserveValue, MAX_BREAKDOWN_RESERVES, MAX_JOURNAL_BYTES};
+
+    /// Journal saved in sepolia_output/journal_hex.txt, from before the
+    /// trailer was committed
+    const SEPOLIA_JOURNAL_HEX: &str = "e88f0c00000000009ad91d9ab2febcc9030000000000000005f0599ad40961ab0000000000000000182c076900000000";
+
+    /// The Sepolia journal with an Ok, non-hypothetical trailer appended
+    fn sepolia_journal() -> Vec<u8> {
+        [hex::decode(SEPOLIA_JOURNAL_HEX).unwrap(), vec![0u8; SOLIDITY_TRAILER_LEN]].concat()
+    }
+
+    #[test]
+    fn test_decode_matches_solidity_vector() {
+        let output = decode_fixed_width(&sepolia_journal()).unwrap();
+
+        // Same run as sepolia_output/safety_score_output.json
+        assert_eq!(output.safety_score, 823_272);
+        assert_eq!(output.total_assets_usd, 69_877_005_961_417_054_618);
+        assert_eq!(output.total_liabilities_usd, 12_349_162_461_953_912_837);
+        assert_eq!(output.timestamp, 1_762_077_720);
+        assert!(!output.hypothetical);
+        assert_eq!(output.status, ScoreStatus::Ok);
+
+        // The journal as committed then has no trailer, so it's refused
+        assert!(decode_fixed_width(&hex::decode(SEPOLIA_JOURNAL_HEX).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_encode_round_trips_vector() {
+        let output = SafetyScoreOutput::new(
+            823_272,
+            69_877_005_961_417_054_618,
+            12_349_162_461_953_912_837,
+            1_762_077_720,
+        );
+        assert_eq!(hex::encode(encode_for_verifier(&output)), SEPOLIA_JOURNAL_HEX);
+        assert!(check_verifier_compat(&sepolia_journal(), &output).is_ok());
+    }
+
+    #[test]
+    fn test_journal_digest() {
+        let journal = hex::decode(SEPOLIA_JOURNAL_HEX).unwrap();
+        assert_eq!(
+            hex::encode(journal_digest(&journal)),
+            "c4c12aebda7b61376bae636b281c1216c2dbe8f5d15acb4279cd0aa6de96d2ba"
+        );
+    }
+
+    #[test]
+    fn test_zkvm_serde_prefix_matches_solidity_layout() {
+        // Newer outputs append fields after the timestamp; the prefix must not move
+        let output = SafetyScoreOutput::new(985_000, 1_000_000_000_000, 900_000_000_000, 1234567890)
+            .with_weighted_health_factor(Some(15_000))
+            .with_skipped_reserves(2);
+        let words = risc0_zkvm::serde::to_vec(&output).unwrap();
+        let journal: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+
+        assert!(journal.len() > SOLIDITY_JOURNAL_LEN);
+        assert!(check_verifier_compat(&journal, &output).is_ok());
+    }
+
+    #[test]
+    fn test_zkvm_serde_trailer_matches_solidity_layout() {
+        // The verifier reads the hypothetical flag and status as the last two words
+        let output = SafetyScoreOutput::new(0, 0, 0, 1234567890)
+            .with_guest_version("test")
+            .with_batch_image_id(Some([7; 8]))
+            .with_hypothetical(true)
+            .with_status(ScoreStatus::Incomplete);
+        let words = risc0_zkvm::serde::to_vec(&output).unwrap();
+        let journal: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+
+        assert_eq!(journal[journal.len() - SOLIDITY_TRAILER_LEN..], [1, 0, 0, 0, 3, 0, 0, 0]);
+        let onchain = decode_fixed_width(&journal).unwrap();
+        assert!(onchain.hypothetical);
+        assert_eq!(onchain.status, ScoreStatus::Incomplete);
+        assert!(check_verifier_compat(&journal, &output).is_ok());
+        assert!(check_verifier_compat(&journal, &output.clone().with_status(ScoreStatus::Ok)).is_err());
+    }
+
+    #[test]
+    fn test_full_breakdown_fits_journal_cap() {
+        // Worst case: a full breakdown of max-length addresses and max values
+        let reserves = (0..MAX_BREAKDOWN_RESERVES + 20)
+            .map(|i| ReserveValue {
+                token_address: format!("0x{:0>40}", i),
+                assets_usd: u128::MAX / 64,
+                liabilities_usd: u128::MAX / 64,
+            })
+            .collect();
+        let mut saturated = false;
+        let output = SafetyScoreOutput::new(u64::MAX, u128::MAX, u128::MAX, u64::MAX)
+            .with_weighted_health_factor(Some(u64::MAX))
+            .with_breakdown(bound_breakdown(reserves, MAX_BREAKDOWN_RESERVES, &mut saturated))
+            .with_denomination(Some(format!("0x{:0>40}", 1)));
+        let words = risc0_zkvm::serde::to_vec(&output).unwrap();
+
+        assert!(output.truncated);
+        assert!(words.len() * 4 <= MAX_JOURNAL_BYTES);
+    }
+
+    #[test]
+    fn test_rejects_short_journal() {
+        assert!(decode_fixed_width(&[0u8; 40]).is_err());
+        assert!(decode_fixed_width(&[0u8; SOLIDITY_JOURNAL_LEN + SOLIDITY_TRAILER_LEN]).is_ok());
+
+        let mut unknown_status = sepolia_journal();
+        *unknown_status.last_mut().unwrap() = 4;
+        assert!(decode_fixed_width(&unknown_status).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+// Journal Diff
+// Compares two proof outputs so keepers can see what changed between runs
+// and decide whether an on-chain update is warranted
+
+use derisk_type::{SafetyScoreOutput, UsdAmount};
+use eyre::{Result, eyre};
+use risc0_zkvm::Journal;
+
+/// Direction of change in protocol safety between two runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Safer,
+    Riskier,
+    Unchanged,
+}
+
+/// Differences between an older and a newer `SafetyScoreOutput`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputDiff {
+    /// Change in safety score (scaled by 1e4)
+    pub score_delta: i64,
+    /// Change in total assets (USD, scaled by 1e8)
+    pub assets_delta: i128,
+    /// Change in total liabilities (USD, scaled by 1e8)
+    pub liabilities_delta: i128,
+    /// Seconds between the two snapshots
+    pub timestamp_delta: i64,
+}
+
+impl OutputDiff {
+    /// Compute the deltas going from `old` to `new`
+    pub fn between(old: &SafetyScoreOutput, new: &SafetyScoreOutput) -> Self {
+        Self {
+            score_delta: new.safety_score as i64 - old.safety_score as i64,
+            assets_delta: signed_delta(old.total_assets_usd, new.total_assets_usd),
+            liabilities_delta: signed_delta(old.total_liabilities_usd, new.total_liabilities_usd),
+            timestamp_delta: new.timestamp as i64 - old.timestamp as i64,
+        }
+    }
+
+    /// Whether the protocol got safer or riskier, judged by the safety score
+    pub fn trend(&self) -> Trend {
+        match self.score_delta {
+            d if d > 0 => Trend::Safer,
+            d if d < 0 => Trend::Riskier,
+            _ => Trend::Unchanged,
+        }
+    }
+
+    /// Print a human-readable summary of the deltas
+    pub fn print(&self, old: &SafetyScoreOutput, new: &SafetyScoreOutput) {
+        status!("📊 Journal Diff:");
+        status!("  - Safety Score: {:.4}% → {:.4}% ({:+.4}%)",
+            old.to_percentage(), new.to_percentage(), self.score_delta as f64 / 1e4);
+        status!("  - Total Assets: {} → {} ({})",
+            UsdAmount(old.total_assets_usd), UsdAmount(new.total_assets_usd),
+            format_usd_delta(self.assets_delta));
+        status!("  - Total Liabilities: {} → {} ({})",
+            UsdAmount(old.total_liabilities_usd), UsdAmount(new.total_liabilities_usd),
+            format_usd_delta(self.liabilities_delta));
+        status!("  - Timestamp: {} → {} ({:+}s)",
+            old.timestamp, new.timestamp, self.timestamp_delta);
+
+        match self.trend() {
+            Trend::Safer => status!("\n✅ Protocol got SAFER"),
+            Trend::Riskier => status!("\n⚠️  Protocol got RISKIER"),
+            Trend::Unchanged => status!("\n➖ Safety score unchanged"),
+        }
+    }
+}
+
+/// Load a `SafetyScoreOutput` from a raw journal (.bin) or a saved output (.json)
+pub fn load_output(path: &str) -> Result<SafetyScoreOutput> {
+    let bytes = std::fs::read(path)?;
+    if path.ends_with(".json") {
+        Ok(serde_json::from_slice(&bytes)?)
+    } else {
+        Journal::new(bytes)
+            .decode()
+            .map_err(|e| eyre!("Failed to decode journal {}: {}", path, e))
+    }
+}
+
+/// Difference of two u128 values, saturating at the i128 range
+fn signed_delta(old: u128, new: u128) -> i128 {
+    if new >= old {
+        i128::try_from(new - old).unwrap_or(i128::MAX)
+    } else {
+        i128::try_from(old - new).map(|d| -d).unwrap_or(i128::MIN)
+    }
+}
+
+/// Format a signed 1e8-scaled USD delta as "+$1,234.56" / "-$1,234.56"
+fn format_usd_delta(delta: i128) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+    format!("{}{}", sign, UsdAmount(delta.unsigned_abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../sepolia_output/safety_score_output.json");
+    const NEW_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../output/safety_score_output.json");
+
+    #[test]
+    fn test_diff_fixture_outputs() {
+        let old = load_output(OLD_FIXTURE).unwrap();
+        let new = load_output(NEW_FIXTURE).unwrap();
+
+        let diff = OutputDiff::between(&old, &new);
+        assert_eq!(diff.score_delta, -17);
+        assert_eq!(diff.assets_delta, 900_761_080_228_723);
+        assert_eq!(diff.liabilities_delta, 1_366_128_621_659_057);
+        assert_eq!(diff.timestamp_delta, 11_336);
+        assert_eq!(diff.trend(), Trend::Riskier);
+
+        // Reversing the order flips every delta
+        let reversed = OutputDiff::between(&new, &old);
+        assert_eq!(reversed.score_delta, 17);
+        assert_eq!(reversed.assets_delta, -900_761_080_228_723);
+        assert_eq!(reversed.trend(), Trend::Safer);
+    }
+
+    #[test]
+    fn test_diff_from_journal_bytes() {
+        let old = SafetyScoreOutput::new(900_000, 1_000, 100, 1);
+        let new = SafetyScoreOutput::new(900_000, 2_000, 200, 61);
+
+        let dir = std::env::temp_dir().join("derisk_journal_diff_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("proof_journal.bin");
+        let bytes = risc0_zkvm::serde::to_vec(&new).unwrap();
+        std::fs::write(&path, words_to_bytes(&bytes)).unwrap();
+
+        let decoded = load_output(path.to_str().unwrap()).unwrap();
+        let diff = OutputDiff::between(&old, &decoded);
+        assert_eq!(diff.trend(), Trend::Unchanged);
+        assert_eq!(diff.assets_delta, 1_000);
+        assert_eq!(diff.timestamp_delta, 60);
+    }
+
+    #[test]
+    fn test_format_usd_delta() {
+        assert_eq!(format_usd_delta(150_000_000), "+$1.50");
+        assert_eq!(format_usd_delta(-150_000_000), "-$1.50");
+        assert_eq!(format_usd_delta(0), "+$0.00");
+    }
+
+    /// Flatten zkVM serde words into the little-endian bytes stored in a journal
+    fn words_to_bytes(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+}
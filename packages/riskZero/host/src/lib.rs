@@ -0,0 +1,53 @@
+// DeRisk Oracle - Host Library
+// Everything the CLI does, for other Rust programs to drive: fetching
+// reserves, proving (see session.rs for the pipeline as a whole) and
+// submitting to the oracle. main.rs is a thin command line over this.
+
+#[macro_use]
+pub mod console;
+pub mod aave_fetcher;
+pub mod artifact_encoding;
+pub mod audit;
+pub mod bench;
+pub mod checkpoint;
+pub mod composition;
+pub mod compression;
+pub mod confirm;
+pub mod decimals_cache;
+pub mod display_currency;
+pub mod estimate;
+pub mod event_backtest;
+pub mod guest;
+pub mod health;
+pub mod image_id;
+pub mod journal_abi;
+pub mod journal_diff;
+pub mod network_config;
+pub mod oracle_submitter;
+pub mod output_dir;
+pub mod portfolio;
+pub mod price_fallback;
+pub mod prover;
+pub mod receipt_compare;
+pub mod receipt_format;
+pub mod redact;
+pub mod report;
+pub mod reserve_source;
+pub mod risk_weights;
+pub mod rpc;
+pub mod saved_json;
+pub mod scenario;
+pub mod seal;
+pub mod series;
+pub mod session;
+pub mod shutdown;
+pub mod signer;
+pub mod subgraph_fetcher;
+pub mod submission;
+pub mod synthetic;
+pub mod telemetry;
+pub mod volatility;
+pub mod watch;
+
+#[cfg(test)]
+mod mock_rpc;
@@ -4,40 +4,265 @@
 // 2. Execute guest program in zkVM to compute safety score
 // 3. Extract proof and journal
 // 4. Submit to on-chain oracle (future)
+// The steps themselves live in the host library (see session.rs); this is
+// the command line over them
 
-mod aave_fetcher;
-mod oracle_submitter;
-
-use aave_fetcher::{AaveFetcher, AaveAddresses};
-use oracle_submitter::OracleSubmitter;
-use methods::{AAVE_ELF, AAVE_ID};
-use risc0_zkvm::{default_prover, ExecutorEnv};
-use risc0_groth16::{Prover as Groth16Prover, ProverOpts};
-use derisk_type::SafetyScoreOutput;
+use host::status;
+use host::{
+    artifact_encoding, audit, bench, checkpoint, composition, compression, confirm, console, decimals_cache,
+    estimate, event_backtest, guest, health, image_id, journal_abi, journal_diff, network_config,
+    output_dir, portfolio, price_fallback, prover, receipt_compare, receipt_format, redact, report,
+    risk_weights, rpc, saved_json, seal, series, signer, submission, synthetic, telemetry, volatility, watch,
+};
+use host::aave_fetcher::{AaveFetcher, AaveAddresses, AaveVersion, ReserveFilter};
+use host::artifact_encoding::ArtifactEncoding;
+use host::display_currency::DisplayCurrency;
+use host::estimate::ProvingEstimate;
+use host::journal_diff::OutputDiff;
+use host::oracle_submitter::{OracleReader, OracleSubmitter};
+use host::price_fallback::PriceTable;
+use host::prover::ProverKind;
+use host::report::RunMeta;
+use host::scenario::PriceScenario;
+use host::reserve_source::{ReserveSource, Source, SourceKind};
+use host::subgraph_fetcher::SubgraphFetcher;
+use host::rpc::RpcOptions;
+use host::shutdown::{Phase, ShutdownSignal};
+use host::telemetry::{ProofManifest, ProofTelemetry};
+use host::watch::WatchConfig;
+use host::session::{Groth16Proof, ProvingSession};
+use methods::AAVE_ID;
+use derisk_type::{schema, usd::USD_SCALE, AaveInput, SafetyScoreOutput, SchemaError, UsdAmount};
 use clap::Parser;
 use eyre::Result;
+use std::time::Duration;
 
 /// DeRisk Oracle CLI
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Network to use (mainnet, sepolia)
+    /// Network to use (mainnet, sepolia, or custom with --network-config)
     #[arg(short, long, default_value = "mainnet")]
     network: String,
 
+    /// TOML file with the chain config for --network custom
+    #[arg(long)]
+    network_config: Option<String>,
+
+    /// Aave version of the pool: v3, or v2 for older markets and forks
+    /// Built-in V2 addresses exist for mainnet only; use --network custom elsewhere
+    #[arg(long, default_value = "v3", value_name = "v2|v3")]
+    aave_version: AaveVersion,
+
     /// RPC endpoint URL
     #[arg(short, long, default_value = "https://eth.llamarpc.com")]
     rpc_url: String,
 
-    /// Mode: fetch-only, prove-only, or full
+    /// Mode: fetch-only, prove-only, execute, full, migrate, diff, portfolio, compare-receipts, status,
+    /// bench, audit, schema, verify-raw, compose or generate
+    /// execute runs the guest without proving and prints the result
+    /// compose proves the reserves in batches and composes them into one receipt
+    /// generate writes a synthetic input of --reserves reserves from --seed, for prove-only
+    /// compare-receipts checks a STARK and a Groth16 receipt commit the same output
+    /// status reports the last run in --output-dir against the oracle's current score
+    /// bench measures guest cycles and time on synthetic inputs of --bench-reserves sizes
+    /// audit recomputes --input-file's output and checks --journal commits exactly that
+    /// schema writes JSON Schema documents for the input and output formats to --output-dir
+    /// verify-raw checks a Groth16 --seal proves --journal, without a receipt file
     #[arg(short, long, default_value = "full")]
     mode: String,
 
-    /// Input file (for prove-only mode)
+    /// Input file (for prove-only, execute, migrate and audit modes); - reads stdin
     #[arg(short, long)]
     input_file: Option<String>,
 
+    /// Write the SafetyScoreOutput JSON to stdout, moving all other output to stderr
+    /// For pipelines, e.g. `my-source | host --mode execute --input-file - --output-stdout`
+    #[arg(long, default_value = "false")]
+    output_stdout: bool,
+
+    /// Protocol to include in portfolio mode, as <network>[=<rpc_url>]; repeatable
+    /// Without an RPC URL the protocol is fetched through --rpc-url
+    #[arg(long = "protocol", value_name = "NETWORK[=RPC_URL]")]
+    protocols: Vec<String>,
+
+    /// Two outputs to compare in diff mode (proof_journal.bin or safety_score_output.json)
+    #[arg(long, num_args = 2, value_names = ["OLD", "NEW"])]
+    diff_files: Vec<String>,
+
+    /// Published output to check in audit mode (proof_journal.bin or safety_score_output.json),
+    /// or the journal to verify in verify-raw mode (.bin, .hex or .b64)
+    #[arg(long, value_name = "FILE")]
+    journal: Option<String>,
+
+    /// Groth16 seal to verify in verify-raw mode: the on-chain seal or the bare
+    /// 256-byte proof (.bin, .hex or .b64)
+    #[arg(long, value_name = "FILE")]
+    seal: Option<String>,
+
+    /// Image ID the proof is checked against in verify-raw mode, as 0x-prefixed hex
+    /// (defaults to this build's guest)
+    #[arg(long, value_name = "HEX")]
+    image_id: Option<String>,
+
+    /// Receipts to check in compare-receipts mode (defaults to the output dir's
+    /// stark_receipt.bin and proof_receipt.bin)
+    #[arg(long, num_args = 2, value_names = ["STARK", "GROTH16"])]
+    receipt_files: Vec<String>,
+
+    /// Minimum reserve asset value in whole USD; smaller reserves are skipped by the guest
+    /// Overrides the value stored in a prove-only input file when given
+    #[arg(long)]
+    min_reserve_usd: Option<u128>,
+
+    /// USD price of the oracle's base currency (scaled by 1e8), for deployments
+    /// not quoting in USD; without it the output stays in the base currency
+    #[arg(long, value_name = "PRICE_1E8")]
+    base_price_usd: Option<u128>,
+
+    /// Count only collateral-enabled reserves as assets (debt on every reserve still counts)
+    /// For a conservative view of the buffer; the mode is committed in the output
+    #[arg(long, default_value = "false")]
+    collateral_only: bool,
+
+    /// Lowest score to report, scaled by 1e4 (985000 = 98.5%); a lower computed
+    /// score is raised to it and the output marked clamped
+    #[arg(long, value_name = "SCORE_1E4")]
+    score_floor: Option<u64>,
+
+    /// Highest score to report, scaled by 1e4; a higher computed score is
+    /// lowered to it and the output marked clamped
+    #[arg(long, value_name = "SCORE_1E4")]
+    score_ceiling: Option<u64>,
+
+    /// Off-protocol funds backstopping a shortfall (e.g. the Safety Module), in whole USD
+    /// Counted as assets in the score; overrides a fetched or saved backstop
+    #[arg(long, value_name = "USD")]
+    backstop_usd: Option<u128>,
+
+    /// Staked token of a safety module (e.g. stkAAVE) to fetch the backstop from:
+    /// its total supply valued at the staked asset's oracle price
+    #[arg(long, value_name = "ADDRESS")]
+    safety_module: Option<String>,
+
+    /// Count a reserve as stale when its last on-chain update is more than this many
+    /// seconds before the snapshot; the count is committed in the output
+    #[arg(long, value_name = "SECS")]
+    stale_after: Option<u64>,
+
+    /// Nonce committed in the journal so proofs of identical state are distinguishable
+    /// on-chain, e.g. an update counter; defaults to the block the input was read at
+    #[arg(long)]
+    nonce: Option<u64>,
+
+    /// Only score this reserve (underlying asset address); repeatable
+    #[arg(long = "include-reserve", value_name = "ADDRESS")]
+    include_reserves: Vec<String>,
+
+    /// Never score this reserve (underlying asset address); repeatable, wins over --include-reserve
+    #[arg(long = "exclude-reserve", value_name = "ADDRESS")]
+    exclude_reserves: Vec<String>,
+
+    /// JSON file mapping token address to volatility in bps; weights the score by volatility
+    /// Overrides the values stored in a prove-only input file when given
+    #[arg(long)]
+    volatility_file: Option<String>,
+
+    /// JSON file mapping token address to a custom risk weight in bps (5000 = 50%);
+    /// listed reserves' assets count at that share in the score, the rest in full
+    /// Overrides the weights stored in a prove-only input file when given
+    #[arg(long, value_name = "FILE")]
+    weights_file: Option<String>,
+
+    /// Haircut in bps (2000 = 20%) on the assets of reserves with siloed borrowing,
+    /// in the score only; the number of siloed reserves is committed either way
+    #[arg(long, value_name = "BPS")]
+    siloed_haircut: Option<u16>,
+
+    /// Also commit a projected score: the score after this many seconds of interest at
+    /// the reserves' current rates (86400 = one day), next to the spot score
+    #[arg(long, value_name = "SECS")]
+    projection_horizon: Option<u64>,
+
+    /// What-if price for a reserve, in the oracle's base unit (1e8 = $1 on USD markets);
+    /// repeatable. The output is committed as hypothetical
+    #[arg(long = "price-override", value_name = "ADDRESS=PRICE")]
+    price_overrides: Vec<String>,
+
+    /// Scale reserve prices by a percentage for a what-if score, e.g. -40: ADDRESS=PCT for
+    /// one reserve, a bare PCT for all the others; repeatable. --price-override wins
+    #[arg(long, value_name = "[ADDRESS=]PCT")]
+    stress: Vec<String>,
+
+    /// Oracle to price an asset from when the network's price oracle reverts for it
+    /// Must quote in the same base currency; overrides the network config's fallback_oracle
+    #[arg(long, value_name = "ADDRESS")]
+    fallback_oracle: Option<String>,
+
+    /// JSON file mapping token address to a price in the oracle's base currency units,
+    /// used for assets no oracle can price instead of skipping the reserve
+    #[arg(long, value_name = "FILE")]
+    fallback_prices: Option<String>,
+
+    /// Fetch every value at this block number instead of the latest block
+    #[arg(long)]
+    block: Option<u64>,
+
+    /// Unix timestamp to stamp the input with, for reproducible proofs
+    /// Defaults to the --block timestamp when a block is pinned, otherwise the current time
+    /// Overrides the value stored in a prove-only input file when given
+    #[arg(long)]
+    timestamp: Option<u64>,
+
+    /// Where reserve data comes from: rpc reads the contracts; subgraph queries --subgraph-url,
+    /// which is faster but can lag the chain by a few blocks (Aave V3 only)
+    #[arg(long, default_value = "rpc", value_name = "rpc|subgraph")]
+    source: SourceKind,
+
+    /// Aave V3 subgraph endpoint for --source subgraph
+    #[arg(long, value_name = "URL")]
+    subgraph_url: Option<String>,
+
+    /// Read reserve supply and debt from Aave's protocol data provider instead of each token contract
+    #[arg(long, default_value = "false")]
+    use_data_provider: bool,
+
+    /// Continue an interrupted or partly failed fetch from its checkpoint in the output dir
+    #[arg(long, default_value = "false")]
+    resume_fetch: bool,
+
+    /// Ask every token for its decimals again instead of reading the cache in the output dir
+    #[arg(long, default_value = "false")]
+    refresh_decimals: bool,
+
+    /// Seconds allowed for a single RPC request before it fails
+    #[arg(long, default_value = "30")]
+    rpc_timeout: u64,
+
+    /// Seconds allowed to connect to the RPC endpoint before a request fails
+    #[arg(long, default_value = "10")]
+    connect_timeout: u64,
+
+    /// Seconds allowed to fetch a single reserve before it is skipped
+    #[arg(long, default_value = "30")]
+    per_reserve_timeout: u64,
+
+    /// Refuse to fetch a pool listing more reserves than this
+    #[arg(long, default_value = "200")]
+    max_reserves: usize,
+
+    /// Abort the run if any reserve can't be fetched, instead of skipping it
+    /// and scoring the rest. On by default with --submit
+    #[arg(long, default_value = "false")]
+    strict: bool,
+
+    /// With --submit, skip reserves that can't be fetched rather than abort
+    #[arg(long, default_value = "false", conflicts_with = "strict")]
+    allow_partial: bool,
+
     /// Output directory for proof artifacts
+    /// May be a template using {network}, {block}, {timestamp} and {protocol}
+    /// (e.g. ./runs/{network}/{timestamp}), which gives each run its own directory
     #[arg(short, long, default_value = "./output")]
     output_dir: String,
 
@@ -45,11 +270,173 @@ struct Args {
     #[arg(long, default_value = "false")]
     submit: bool,
 
+    /// With --submit, send a score whose status is NoData or Incomplete too
+    /// Without it only Ok and Insolvent scores are submitted; the bundled
+    /// Oracle and MinimalVerifier contracts revert on any other status
+    #[arg(long, default_value = "false")]
+    submit_any_status: bool,
+
+    /// Prover to use: cpu, cuda (needs `--features cuda`) or bonsai (needs
+    /// `--features bonsai`, BONSAI_API_KEY and BONSAI_API_URL)
+    /// Without it, risc0's default selection (RISC0_PROVER) applies
+    #[arg(long, value_name = "cpu|cuda|bonsai")]
+    prover: Option<ProverKind>,
+
+    /// Also write the journal and seal as hex or base64 text (proof_seal.hex,
+    /// proof_journal.b64, ...) for pasting into explorers or Remix
+    #[arg(long, default_value = "binary", value_name = "binary|hex|base64")]
+    artifact_encoding: ArtifactEncoding,
+
+    /// Also show printed totals in this currency (e.g. EUR), converted at --fx-rate
+    /// Display only: the proof, journal and saved files stay in USD
+    #[arg(long, value_name = "CODE")]
+    display_currency: Option<String>,
+
+    /// Units of --display-currency per US dollar, e.g. 0.92 for EUR
+    #[arg(long, value_name = "RATE")]
+    fx_rate: Option<String>,
+
+    /// Refuse to prove if executing the guest takes more than this many cycles
+    #[arg(long)]
+    max_cycles: Option<u64>,
+
+    /// Split guest execution into segments of at most 2^N cycles (risc0's default is 20)
+    /// Lower it if proving runs out of memory: each segment needs less, but
+    /// there are more of them, each adding continuation overhead and proving time
+    #[arg(long, value_name = "N")]
+    segment_limit_po2: Option<u32>,
+
+    /// Reserves per batch proof in compose mode
+    #[arg(long, default_value = "32")]
+    compose_batch_size: usize,
+
+    /// Prover throughput used for the proving-time estimate
+    #[arg(long, default_value_t = estimate::DEFAULT_CYCLES_PER_SECOND)]
+    cycles_per_second: u64,
+
+    /// Gzip the saved receipts (proof_receipt.bin.gz, stark_receipt.bin.gz);
+    /// compare-receipts reads either form
+    #[arg(long, default_value = "false")]
+    compress_artifacts: bool,
+
+    /// Write aave_input.json and safety_score_output.json on one line instead of pretty-printed
+    #[arg(long, default_value = "false")]
+    json_compact: bool,
+
+    /// Append proving cost (cycles, phase timings, seal size) to this CSV, one row per proof
+    #[arg(long, value_name = "CSV")]
+    bench_output: Option<String>,
+
+    /// Reserve counts of the synthetic inputs bench mode scores
+    #[arg(long, default_value = bench::DEFAULT_RESERVE_COUNTS, value_name = "n1,n2,...")]
+    bench_reserves: String,
+
+    /// Also time a STARK proof of each input in bench mode (slow; execute only by default)
+    #[arg(long, default_value = "false")]
+    bench_prove: bool,
+
+    /// Number of reserves generate mode makes up
+    #[arg(long, default_value_t = synthetic::DEFAULT_RESERVES, value_name = "N")]
+    reserves: usize,
+
+    /// Seed for generate mode; the same seed always generates the same input
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
+    /// Percentage of generated reserves with more debt than supply (0-100)
+    #[arg(long, default_value = "10", value_name = "PERCENT")]
+    insolvent_share: u8,
+
+    /// Run continuously, re-scoring every --interval seconds
+    #[arg(long, default_value = "false")]
+    watch: bool,
+
+    /// Don't ask before Groth16 proving or submitting on-chain
+    /// Required when stdin isn't a terminal (CI, cron, keepers)
+    #[arg(short = 'y', long, default_value = "false")]
+    assume_yes: bool,
+
+    /// Log RPC URLs as given instead of masking their API keys
+    /// For debugging an endpoint; don't use it where logs are kept
+    #[arg(long, default_value = "false")]
+    no_redact: bool,
+
+    /// Log every RPC call (method, target, calldata) and its raw result or
+    /// error to stderr, for debugging reserves that fail to decode. Very noisy
+    #[arg(long, default_value = "false")]
+    verbose_rpc: bool,
+
+    /// Score each of these blocks without proving, for a trend series: a list
+    /// (b1,b2,...) or an inclusive range (start:end:step)
+    /// Writes score_series.csv and score_series.json to --output-dir
+    #[arg(long, value_name = "b1,b2,...|start:end:step")]
+    blocks: Option<String>,
+
+    /// With --blocks, fetch the first block only and reconstruct the rest
+    /// from the pool's events, instead of a full fetch per block
+    /// Much cheaper over long ranges, but approximate; see event_backtest.rs
+    /// Writes score_backtest.csv to --output-dir (RPC source only)
+    #[arg(long, default_value = "false")]
+    from_events: bool,
+
+    /// Blocks covered by each eth_getLogs call with --from-events
+    #[arg(long, default_value_t = event_backtest::DEFAULT_LOG_BATCH_BLOCKS)]
+    log_batch_blocks: u64,
+
+    /// Seconds between cycles in watch mode
+    #[arg(long, default_value = "300")]
+    interval: u64,
+
+    /// In watch mode, write each cycle's result to stdout as one line of JSON
+    /// ({timestamp, block, score, assets, liabilities, submitted, tx_hash?, error?}),
+    /// moving all other output to stderr
+    #[arg(long, default_value = "false", requires = "watch", conflicts_with = "output_stdout")]
+    json_lines: bool,
+
+    /// In watch mode, fetch the next cycle's input while the current one is
+    /// proven, starting that cycle as soon as its submission is done
+    #[arg(long, default_value = "false", requires = "watch")]
+    pipeline: bool,
+
+    /// Minimum safety score change (scaled by 1e4, 1000 = 0.10%) before watch mode re-submits
+    #[arg(long, default_value = "1000")]
+    submit_threshold: u64,
+
+    /// Abort unless the compiled guest's image ID matches this 0x-prefixed hex value
+    #[arg(long)]
+    expected_image_id: Option<String>,
+
+    /// Abort unless the compiled guest's image ID matches the hex ID committed
+    /// in this file, e.g. when the guest source changed without updating it
+    #[arg(long, value_name = "FILE")]
+    verify_image_id_file: Option<String>,
+
+    /// Before submitting, confirm the oracle verifies this protocol's proofs against this build's image ID
+    /// Oracles without a protocolImageIds getter are checked with a simulated submission instead
+    #[arg(long, default_value = "false")]
+    check_onchain_image_id: bool,
+
+    /// Check the proof against the oracle with an eth_call before (or instead of) submitting
+    /// Without --submit, only the simulation runs; with it, a failed simulation aborts the submission
+    #[arg(long, default_value = "false")]
+    simulate_submission: bool,
+
+    /// Wait for the pending tx in <output-dir>/last_submission.json instead of sending a new one
+    /// Skips fetching and proving; the saved journal is used
+    #[arg(long, default_value = "false")]
+    resume_submission: bool,
+
     /// Private key for on-chain submission
+    /// Discouraged: prefer --keystore or the PRIVATE_KEY env var, which take precedence
     #[arg(long)]
     private_key: Option<String>,
 
-    /// DeRiskOracle contract address
+    /// Encrypted JSON keystore for on-chain submission
+    /// Password is read from KEYSTORE_PASSWORD or prompted for
+    #[arg(long)]
+    keystore: Option<String>,
+
+    /// DeRiskOracle contract address (ORACLE_ADDRESS env var takes precedence)
     #[arg(long)]
     oracle_address: Option<String>,
 }
@@ -57,204 +444,1365 @@ struct Args {
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize logging
+    // Logs go to stderr so stdout can carry the JSON result in pipe mode
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::filter::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
         .init();
 
     // Load environment variables from .env file
     dotenv::dotenv().ok();
 
     // Parse CLI arguments
-    let args = Args::parse();
+    let mut args = Args::parse();
 
-    println!("╔════════════════════════════════════════╗");
-    println!("║   DeRisk Protocol - ZK Oracle Host    ║");
-    println!("╚════════════════════════════════════════╝\n");
+    if args.output_stdout {
+        check_pipe_mode(&args)?;
+        console::enable_pipe_mode();
+    }
+    // A what-if score must never reach the oracle, even simulated
+    PriceScenario::parse(&args.price_overrides, &args.stress)?
+        .check_not_submitted(args.submit || args.simulate_submission)?;
+    // stdout carries only the JSON lines
+    if args.json_lines {
+        console::enable_pipe_mode();
+    }
+    if args.no_redact {
+        redact::reveal_secrets();
+    }
+
+    status!("╔════════════════════════════════════════╗");
+    status!("║   DeRisk Protocol - ZK Oracle Host    ║");
+    status!("╚════════════════════════════════════════╝\n");
 
     // Determine network addresses
-    let aave_addresses = match args.network.as_str() {
-        "mainnet" => AaveAddresses::mainnet(),
-        "sepolia" => AaveAddresses::sepolia(),
-        _ => {
-            eprintln!("Error: Unknown network '{}'. Use 'mainnet' or 'sepolia'", args.network);
+    let aave_addresses = match network_config::resolve_network(&args.network, args.network_config.as_deref(), args.aave_version)
+        .and_then(|addresses| with_fallback_oracle(&args, addresses))
+    {
+        Ok(addresses) => addresses,
+        Err(e) => {
+            eprintln!("Error: {}", e);
             std::process::exit(1);
         }
     };
 
-    println!("Network: {}", args.network);
-    println!("RPC URL: {}", args.rpc_url);
-    println!("Mode: {}\n", args.mode);
+    status!("Network: {}", args.network);
+    // Fail on a bad rate or bounds now, not after the fetch
+    let currency = display_currency(&args)?;
+    derisk_type::check_score_bounds(args.score_floor, args.score_ceiling)
+        .map_err(|e| eyre::eyre!("Invalid --score-floor/--score-ceiling: {}", e))?;
+    if !currency.is_usd() {
+        status!("Display currency: {} at {} per USD (proof stays in USD)",
+            currency.code, args.fx_rate.as_deref().unwrap_or_default());
+    }
+    if args.aave_version != AaveVersion::V3 {
+        status!("Aave version: {}", args.aave_version);
+    }
+    if let Some(path) = &args.network_config {
+        status!("Network config: {} (chain {})", path, aave_addresses.chain_id);
+        status!("Multicall3: {}", aave_addresses.multicall3);
+    }
+    if let Some(oracle) = aave_addresses.fallback_oracle {
+        status!("Fallback oracle: {}", oracle);
+    }
+    status!("RPC URL: {}", redact::url(&args.rpc_url));
+    if let (SourceKind::Subgraph, Some(url)) = (args.source, &args.subgraph_url) {
+        status!("Source: subgraph {}", redact::url(url));
+    }
+    status!("Mode: {}", args.mode);
+    if let Some(kind) = args.prover {
+        // Fail on a missing feature or credential now, not after the fetch
+        prover::select_prover(Some(kind))?;
+        status!("Prover: {}", kind);
+    }
+
+    // A templated --output-dir expands into a directory of this run's own
+    if output_dir::is_template(&args.output_dir) {
+        let context = output_dir::RunContext {
+            network: args.network.clone(),
+            block: args.block,
+            timestamp: match args.timestamp {
+                Some(timestamp) => timestamp,
+                None => std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs(),
+            },
+            protocol: format!("aave-{}", args.aave_version),
+        };
+        args.output_dir = output_dir::create_run_dir(&args.output_dir, &context)?;
+        status!("Output dir: {}", args.output_dir);
+    }
+    status!("Image ID: {}\n", image_id::image_id_hex(&AAVE_ID));
+
+    if let Some(expected) = &args.expected_image_id {
+        image_id::check_expected_image_id(expected, &AAVE_ID)?;
+        status!("✓ Guest image ID matches --expected-image-id\n");
+    }
+    if let Some(path) = &args.verify_image_id_file {
+        image_id::check_image_id_file(path, &AAVE_ID)?;
+        status!("✓ Guest image ID matches {}\n", path);
+    }
+
+    if args.mode == "migrate" {
+        return migrate_input_file(&args);
+    }
+
+    if args.mode == "schema" {
+        return export_json_schemas(&args);
+    }
+
+    if args.mode == "diff" {
+        return diff_outputs(&args);
+    }
+
+    if args.mode == "audit" {
+        return audit_journal(&args);
+    }
+
+    if args.mode == "compare-receipts" {
+        return compare_receipt_files(&args);
+    }
+
+    if args.mode == "verify-raw" {
+        return verify_raw_proof(&args);
+    }
+
+    if args.mode == "bench" {
+        return run_reserve_bench(&args);
+    }
+
+    if args.mode == "generate" {
+        return generate_input_file(&args);
+    }
+
+    if args.mode == "status" {
+        return report_status(&args, &aave_addresses).await;
+    }
+
+    if args.resume_submission && !args.watch {
+        return resume_submission(&args, &aave_addresses).await;
+    }
+
+    // Ctrl-C stops at the next safe point instead of discarding all work
+    let shutdown = ShutdownSignal::install();
+
+    if let Some(spec) = &args.blocks {
+        // Every point is pinned to its own block and stamped with its time
+        if args.block.is_some() || args.timestamp.is_some() || args.watch {
+            eyre::bail!("--block, --timestamp and --watch can't be used with --blocks");
+        }
+        if args.from_events {
+            return run_backtest(&args, &aave_addresses, spec, &shutdown).await;
+        }
+        return run_series(&args, &aave_addresses, spec, &shutdown).await;
+    }
+    if args.from_events {
+        eyre::bail!("--from-events needs --blocks to say which blocks to score");
+    }
+
+    if args.watch {
+        // Each cycle must see fresh state; a fixed block or time would repeat one snapshot
+        if args.block.is_some() || args.timestamp.is_some() || args.nonce.is_some() {
+            eyre::bail!("--block, --timestamp and --nonce can't be used with --watch");
+        }
+        if args.resume_fetch {
+            eyre::bail!("--resume-fetch can't be used with --watch; every cycle fetches from scratch");
+        }
+        return run_watch_mode(&args, &aave_addresses, &shutdown).await;
+    }
+
+    if args.mode == "portfolio" {
+        if args.source != SourceKind::Rpc {
+            eyre::bail!("portfolio mode only supports --source rpc");
+        }
+        return run_portfolio(&args, &shutdown).await;
+    }
 
     // ========================================================================
     // STEP 1: Fetch Aave Data (or load from file)
     // ========================================================================
-    let aave_input = if args.mode == "prove-only" {
-        // Load from file
-        let input_file = args.input_file.expect("--input-file required for prove-only mode");
-        println!(" Loading data from file: {}", input_file);
-        let json = std::fs::read_to_string(input_file)?;
-        serde_json::from_str(&json)?
-    } else {
-        // Fetch from blockchain
-        println!("═══════════════════════════════════════");
-        println!("  STEP 1: Fetching Aave Reserve Data");
-        println!("═══════════════════════════════════════\n");
-
-        let fetcher = AaveFetcher::new(aave_addresses.clone(), args.rpc_url.clone());
-        let input = fetcher.fetch_reserves().await?;
-
-        // Save to file for future prove-only runs
-        let output_path = format!("{}/aave_input.json", args.output_dir);
-        std::fs::create_dir_all(&args.output_dir)?;
-        std::fs::write(&output_path, serde_json::to_string_pretty(&input)?)?;
-        println!("\n💾 Saved input data to: {}", output_path);
-
-        if args.mode == "fetch-only" {
-            println!("\n✓ Fetch complete. Exiting (fetch-only mode).");
-            return Ok(());
+    shutdown.enter(Phase::Fetch);
+    let mut session = match load_or_fetch_input(&args, &aave_addresses, &shutdown).await? {
+        Some(session) => session,
+        None => return Ok(()),
+    };
+    apply_input_overrides(&args, session.input_mut())?;
+    let aave_input = session.input();
+
+    status!("\n📊 Input Summary:");
+    status!("  - Protocol: {}", aave_input.protocol_name);
+    status!("  - Schema: v{}", aave_input.schema_version);
+    status!("  - Reserves: {}", aave_input.reserves.len());
+    status!("  - Timestamp: {}", aave_input.timestamp);
+    status!("  - Min Reserve Value: {}", UsdAmount(aave_input.min_reserve_usd));
+    status!("  - Excluded Reserves: {}", aave_input.excluded.len());
+    if aave_input.collateral_only {
+        status!("  - Assets: collateral-enabled reserves only");
+    }
+    if aave_input.score_floor.is_some() || aave_input.score_ceiling.is_some() {
+        let bound = |score: Option<u64>| score.map_or("none".to_string(), |score| format!("{:.4}%", score as f64 / 1e4));
+        status!("  - Score Bounds: {} to {}", bound(aave_input.score_floor), bound(aave_input.score_ceiling));
+    }
+    if aave_input.backstop_usd > 0 {
+        status!("  - Backstop: {}", UsdAmount(aave_input.backstop_usd));
+    }
+    if !aave_input.weights.is_empty() {
+        status!("  - Custom Risk Weights: {} assets", aave_input.weights.len());
+    }
+    if let Some(secs) = aave_input.stale_after_secs {
+        status!("  - Stale After: {}s without an on-chain update", secs);
+    }
+    if let Some(secs) = aave_input.projection_horizon_secs {
+        status!("  - Projection Horizon: {}s at current rates", secs);
+    }
+    if !aave_input.price_overrides.is_empty() {
+        status!("  ⚠ Hypothetical: {} reserve prices overridden, not the oracle's", aave_input.price_overrides.len());
+    }
+    if aave_input.failed_reserves > 0 {
+        status!("  ⚠ {} reserves failed to fetch and are missing from the input", aave_input.failed_reserves);
+    }
+    if let Some(base) = aave_input.base_currency.denomination() {
+        status!("  ⚠ Prices are in base currency {} with no --base-price-usd; totals won't be USD", base);
+    }
+
+    let meta = RunMeta { currency, protocol: Some(aave_input.protocol_name.clone()) };
+    if args.mode == "execute" {
+        let execution = session.execute()?;
+        status!("\n⚡ Executed guest in {} cycles ({} segments), no proof generated",
+            execution.cycles, execution.segments);
+        status!();
+        status!("{}", report::render_summary(&execution.output, &meta));
+        if args.output_stdout {
+            println!("{}", report::render_json(&execution.output, &meta)?);
         }
+        return Ok(());
+    }
 
-        input
+    if args.mode == "compose" {
+        return run_compose(&session, &args, &meta);
+    }
+
+    // ========================================================================
+    // STEP 2: Execute Guest Program in zkVM
+    // ========================================================================
+    shutdown.enter(Phase::Prove);
+    let proof = match prove_input(&session, &args, &shutdown, args.assume_yes)? {
+        Some(proof) => proof,
+        None => return Ok(()),
     };
 
-    println!("\n📊 Input Summary:");
-    println!("  - Protocol: {}", aave_input.protocol_name);
-    println!("  - Reserves: {}", aave_input.reserves.len());
-    println!("  - Timestamp: {}", aave_input.timestamp);
+    // ========================================================================
+    // STEP 3: Extract Proof and Journal
+    // ========================================================================
+    shutdown.enter(Phase::Finalize);
+    let (output, journal_bytes, seal_bytes) = save_proof_artifacts(&session, &proof, &args)?;
+
+    if args.output_stdout {
+        println!("{}", report::render_json(&output, &meta)?);
+        return Ok(());
+    }
 
     // ========================================================================
-    // STEP 2: Execute Guest Program in zkVM
+    // STEP 4: Submit to On-Chain Oracle (Optional)
     // ========================================================================
-    println!("\n═══════════════════════════════════════");
-    println!("  STEP 2: Executing zkVM Guest Program");
-    println!("═══════════════════════════════════════\n");
-
-    println!("🔧 Building ExecutorEnv with input data...");
-    let env = ExecutorEnv::builder()
-        .write(&aave_input)
-        .map_err(|e| eyre::eyre!("Failed to write input: {}", e))?
-        .build()
-        .map_err(|e| eyre::eyre!("Failed to build env: {}", e))?;
-
-    println!("✓ ExecutorEnv ready");
-    println!("\n🚀 Starting zkVM execution with Groth16...");
-    println!("⏳ This will take 5-10 minutes for Groth16 proving (grab a coffee ☕)...\n");
-
-    let prover = default_prover();
-    
+    // A saved input can carry price overrides the flags check never saw
+    if args.submit || args.simulate_submission {
+        submission::check_submittable(&output, args.submit_any_status)?;
+    }
+    if args.simulate_submission && !args.submit {
+        status!("\n═══════════════════════════════════════");
+        status!("  STEP 4: Simulating On-Chain Submission");
+        status!("═══════════════════════════════════════\n");
+
+        let submitter = oracle_submitter(&args, &aave_addresses)?;
+        simulate_submission(&submitter, &journal_bytes, &seal_bytes).await?;
+        status!("\n💡 To submit to on-chain oracle, run with --submit flag");
+        return Ok(());
+    }
+
+    if args.submit && shutdown.is_requested() {
+        status!("\n⚠ Interrupted: skipping on-chain submission");
+        status!("  The saved journal and seal can be submitted later");
+        return Ok(());
+    }
+
+    if args.submit {
+        status!("\n═══════════════════════════════════════");
+        status!("  STEP 4: Submitting to On-Chain Oracle");
+        status!("═══════════════════════════════════════\n");
+
+        let submitter = oracle_submitter(&args, &aave_addresses)?;
+
+        if args.simulate_submission {
+            simulate_submission(&submitter, &journal_bytes, &seal_bytes).await?;
+        }
+
+        confirm::confirm(&format!("Submit the proof to the oracle on {}", args.network), args.assume_yes)?;
+        let tx_hash = session.submit(&submitter, &proof, &args.output_dir, args.resume_submission).await?;
+        
+        status!("\n✓ Proof submitted successfully!");
+        status!("  - Transaction: {}", tx_hash);
+    } else {
+        status!("\n💡 To submit to on-chain oracle, run with --submit flag");
+    }
+
+    status!("\n╔════════════════════════════════════════╗");
+    status!("║        ✓ All Steps Complete!          ║");
+    status!("╚════════════════════════════════════════╝\n");
+
+    Ok(())
+}
+
+/// Prove the input in batches and compose them (--mode compose)
+fn run_compose(session: &ProvingSession, args: &Args, meta: &RunMeta) -> Result<()> {
+    let input = session.input();
+    status!("\n═══════════════════════════════════════");
+    status!("  Proving {} Reserves in Batches of {}", input.reserves.len(), args.compose_batch_size);
+    status!("═══════════════════════════════════════\n");
+
+    let proof = composition::prove_composed(input, args.compose_batch_size, args.prover)?;
+    let output = composition::verify_composed(&proof.receipt)?;
+    // The same score a single run over the input commits
+    if output != derisk_type::compute_output(input).with_batch_image_id(output.batch_image_id) {
+        eyre::bail!("Composed proof commits a different output than the input scores");
+    }
+    status!("\n✓ Composed {} batches ({} cycles in total)", proof.batches, proof.total_cycles);
+    status!();
+    status!("{}", report::render_summary(&output, meta));
+
+    std::fs::create_dir_all(&args.output_dir)?;
+    let (receipt_bytes, receipt_format) = receipt_format::serialize_receipt(&proof.receipt)?;
+    let receipt_path = format!("{}/composed_receipt.bin", args.output_dir);
+    let journal_path = format!("{}/composed_journal.bin", args.output_dir);
+    std::fs::write(&receipt_path, &receipt_bytes)?;
+    std::fs::write(&journal_path, &proof.receipt.journal.bytes)?;
+    status!("\n💾 Saved composed proof:");
+    status!("  - Receipt: {} ({})", receipt_path, receipt_format);
+    status!("  - Journal: {}", journal_path);
+    status!("  ⚠ Proven by the compose guest, which the on-chain verifier doesn't accept yet");
+
+    if args.output_stdout {
+        println!("{}", report::render_json(&output, meta)?);
+    }
+    Ok(())
+}
+
+/// Re-score on a schedule until interrupted (--watch)
+/// Each cycle fetches and executes; proving and submission only happen with
+/// --submit and when the score moved by at least --submit-threshold
+async fn run_watch_mode(
+    args: &Args,
+    aave_addresses: &AaveAddresses,
+    shutdown: &ShutdownSignal,
+) -> Result<()> {
+    let submitter = if args.submit {
+        // Asked once up front: every qualifying cycle proves and submits unattended
+        confirm::confirm(&format!("Prove and submit to the oracle on {} every qualifying cycle", args.network),
+            args.assume_yes)?;
+        Some(oracle_submitter(args, aave_addresses)?)
+    } else {
+        None
+    };
+
+    let source = match args.source {
+        SourceKind::Rpc => {
+            let mut fetcher = AaveFetcher::new(aave_addresses.clone(), args.rpc_url.clone())
+                .with_rpc_options(rpc_options(args))
+                .with_shutdown(shutdown.clone())
+                .with_per_reserve_timeout(Duration::from_secs(args.per_reserve_timeout))
+                .with_max_reserves(args.max_reserves)
+                .with_reserve_filter(reserve_filter(args)?)
+                .with_fallback_prices(fallback_prices(args)?)
+                .with_version(args.aave_version)
+                .with_strict(strict_fetch(args))
+                .with_decimals_cache(decimals_cache_path(args), args.refresh_decimals);
+            if args.use_data_provider {
+                fetcher = fetcher.with_data_provider();
+            }
+            if let Some(module) = safety_module(args)? {
+                fetcher = fetcher.with_safety_module(module);
+            }
+            Source::Rpc(fetcher)
+        }
+        SourceKind::Subgraph => Source::Subgraph(subgraph_fetcher(args, aave_addresses, None)?),
+    };
+
+    let config = WatchConfig {
+        interval: Duration::from_secs(args.interval),
+        submit_threshold: args.submit_threshold,
+        submit: submitter.is_some(),
+        max_backoff: Duration::from_secs(args.interval.saturating_mul(8)),
+        max_iterations: None,
+        segment_limit_po2: args.segment_limit_po2,
+        json_lines: args.json_lines,
+        pipeline: args.pipeline,
+    };
+
+    // Read once up front so a bad file fails fast rather than every cycle
+    let volatility_table = args.volatility_file.as_deref().map(volatility::load_volatility).transpose()?;
+    let weights = args.weights_file.as_deref().map(risk_weights::load_weights).transpose()?;
+    let siloed_haircut = siloed_haircut(args)?;
+    let scenario = PriceScenario::parse(&args.price_overrides, &args.stress)?;
+    let min_reserve_usd = args.min_reserve_usd.map(|usd| scale_whole_usd("--min-reserve-usd", usd)).transpose()?;
+    let backstop_usd = args.backstop_usd.map(|usd| scale_whole_usd("--backstop-usd", usd)).transpose()?;
+
+    let source = &source;
+    let submitter = &submitter;
+    let volatility_table = &volatility_table;
+    let weights = &weights;
+    let scenario = &scenario;
+    watch::run_watch(
+        &config,
+        shutdown,
+        move || async move {
+            let mut input = source.fetch().await?;
+            if let Some(min_usd) = min_reserve_usd {
+                input.min_reserve_usd = min_usd;
+            }
+            if let Some(price) = args.base_price_usd {
+                input.base_currency.price_usd = Some(price);
+            }
+            input.collateral_only = args.collateral_only;
+            input.score_floor = args.score_floor;
+            input.score_ceiling = args.score_ceiling;
+            if let Some(backstop_usd) = backstop_usd {
+                input.backstop_usd = backstop_usd;
+            }
+            input.stale_after_secs = args.stale_after;
+            input.nonce = input.block_number;
+            if let Some(table) = volatility_table {
+                volatility::apply_volatility(&mut input, table);
+            }
+            if let Some(weights) = weights {
+                input.weights = weights.clone();
+            }
+            input.siloed_haircut_bps = siloed_haircut.unwrap_or(0);
+            input.projection_horizon_secs = args.projection_horizon;
+            scenario.apply(&mut input);
+            Ok(input)
+        },
+        move |input, _output| async move {
+            // Only called with --submit, which sets the submitter up
+            let Some(submitter) = submitter else {
+                eyre::bail!("no oracle to submit to");
+            };
+            // Proving blocks, so it runs off the runtime and a pipelined
+            // fetch can make progress alongside it
+            let (owned_args, owned_shutdown) = (args.clone(), shutdown.clone());
+            let (session, proof) = tokio::task::spawn_blocking(move || -> Result<_> {
+                let session = proving_session(&owned_args, input);
+                let proof = prove_input(&session, &owned_args, &owned_shutdown, true)?;
+                Ok((session, proof))
+            })
+            .await??;
+            let Some(proof) = proof else {
+                eyre::bail!("proving interrupted");
+            };
+            let (_, journal_bytes, seal_bytes) = save_proof_artifacts(&session, &proof, args)?;
+            if args.simulate_submission {
+                simulate_submission(submitter, &journal_bytes, &seal_bytes).await?;
+            }
+            let tx_hash = session.submit(submitter, &proof, &args.output_dir, args.resume_submission).await?;
+            status!("  - Transaction: {}", tx_hash);
+            Ok(tx_hash.to_string())
+        },
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Score several protocols and write a combined report (portfolio mode)
+/// Protocols are fetched and executed concurrently; nothing is proven
+async fn run_portfolio(args: &Args, shutdown: &ShutdownSignal) -> Result<()> {
+    if args.protocols.is_empty() {
+        eyre::bail!("--protocol <NETWORK[=RPC_URL]> required (at least once) for portfolio mode");
+    }
+    let targets = args
+        .protocols
+        .iter()
+        .map(|spec| portfolio::ProtocolTarget::parse(spec, &args.rpc_url))
+        .collect::<Result<Vec<_>>>()?;
+
+    status!("═══════════════════════════════════════");
+    status!("  Scoring {} Protocols", targets.len());
+    status!("═══════════════════════════════════════\n");
+
+    let report = portfolio::score_protocols(
+        targets,
+        Duration::from_secs(args.per_reserve_timeout),
+        rpc_options(args),
+        args.segment_limit_po2,
+        shutdown,
+    )
+    .await;
+    report.print();
+
+    let report_path = format!("{}/portfolio_report.json", args.output_dir);
+    std::fs::create_dir_all(&args.output_dir)?;
+    std::fs::write(&report_path, serde_json::to_string_pretty(&report)?)?;
+    status!("\n💾 Saved portfolio report to: {}", report_path);
+
+    if report.protocols.is_empty() {
+        eyre::bail!("No protocol could be scored");
+    }
+    Ok(())
+}
+
+/// A whole-USD flag value scaled by 1e8, or an error naming the flag if it overflows
+fn scale_whole_usd(flag: &str, usd: u128) -> Result<u128> {
+    usd.checked_mul(USD_SCALE)
+        .ok_or_else(|| eyre::eyre!("{} {} is too large (max {})", flag, usd, u128::MAX / USD_SCALE))
+}
+
+/// Command-line settings that override what was fetched or loaded
+fn apply_input_overrides(args: &Args, aave_input: &mut AaveInput) -> Result<()> {
+    if let Some(min_usd) = args.min_reserve_usd {
+        aave_input.min_reserve_usd = scale_whole_usd("--min-reserve-usd", min_usd)?;
+    }
+    if let Some(price) = args.base_price_usd {
+        aave_input.base_currency.price_usd = Some(price);
+    }
+    if args.collateral_only {
+        aave_input.collateral_only = true;
+    }
+    if args.score_floor.is_some() {
+        aave_input.score_floor = args.score_floor;
+    }
+    if args.score_ceiling.is_some() {
+        aave_input.score_ceiling = args.score_ceiling;
+    }
+    if let Some(backstop_usd) = args.backstop_usd {
+        aave_input.backstop_usd = scale_whole_usd("--backstop-usd", backstop_usd)?;
+    }
+    if args.stale_after.is_some() {
+        aave_input.stale_after_secs = args.stale_after;
+    }
+    if args.nonce.is_some() {
+        aave_input.nonce = args.nonce;
+    } else if aave_input.nonce.is_none() {
+        aave_input.nonce = aave_input.block_number;
+    }
+    if let Some(timestamp) = args.timestamp {
+        aave_input.timestamp = timestamp;
+    }
+    if let Some(path) = &args.volatility_file {
+        let table = volatility::load_volatility(path)?;
+        let applied = volatility::apply_volatility(aave_input, &table);
+        status!("\n📈 Applied volatility to {} of {} reserves", applied, aave_input.reserves.len());
+    }
+    if let Some(path) = &args.weights_file {
+        aave_input.weights = risk_weights::load_weights(path)?;
+    }
+    if let Some(bps) = siloed_haircut(args)? {
+        aave_input.siloed_haircut_bps = bps;
+    }
+    if args.projection_horizon.is_some() {
+        aave_input.projection_horizon_secs = args.projection_horizon;
+    }
+    let scenario = PriceScenario::parse(&args.price_overrides, &args.stress)?;
+    if !scenario.is_empty() {
+        let repriced = scenario.apply(aave_input);
+        status!("\n🧪 Scenario repriced {} of {} reserves", repriced, aave_input.reserves.len());
+        for address in scenario.unmatched(aave_input) {
+            status!("  ⚠ {} is not a reserve in the input", address);
+        }
+    }
+    Ok(())
+}
+
+/// Score the protocol at each --blocks block, executing without proofs
+async fn run_series(
+    args: &Args,
+    aave_addresses: &AaveAddresses,
+    spec: &str,
+    shutdown: &ShutdownSignal,
+) -> Result<()> {
+    let blocks = series::parse_blocks(spec)?;
+
+    status!("═══════════════════════════════════════");
+    status!("  Scoring {} Blocks", blocks.len());
+    status!("═══════════════════════════════════════");
+
+    let filter = reserve_filter(args)?;
+    let prices = fallback_prices(args)?;
+    let module = safety_module(args)?;
+    let series = series::collect_series(&blocks, shutdown, |block| {
+        let source = match args.source {
+            SourceKind::Rpc => Ok(Source::Rpc(block_fetcher(
+                args,
+                aave_addresses,
+                block,
+                filter.clone(),
+                prices.clone(),
+                module,
+                shutdown,
+            ))),
+            SourceKind::Subgraph => subgraph_fetcher(args, aave_addresses, Some(block)).map(Source::Subgraph),
+        };
+        async move {
+            let mut input = source?.fetch().await?;
+            // A fetch cut short by Ctrl-C is partial - never score it
+            if shutdown.is_requested() {
+                eyre::bail!("interrupted");
+            }
+            apply_input_overrides(args, &mut input)?;
+            let segment_limit_po2 = args.segment_limit_po2;
+            let execution =
+                tokio::task::spawn_blocking(move || guest::execute_guest(&input, segment_limit_po2)).await??;
+            Ok::<_, eyre::Report>(execution.output)
+        }
+    })
+    .await;
+
+    let currency = display_currency(args)?;
+    status!("\n📈 Score Series:");
+    for point in &series.points {
+        status!("  - Block {}: {:.4}% ({} assets, {} liabilities)",
+            point.block,
+            point.safety_score as f64 / 1e4,
+            currency.format(point.total_assets_usd),
+            currency.format(point.total_liabilities_usd));
+    }
+    for (block, _) in &series.failed {
+        status!("  - Block {}: failed", block);
+    }
+
+    std::fs::create_dir_all(&args.output_dir)?;
+    let csv_path = format!("{}/score_series.csv", args.output_dir);
+    let json_path = format!("{}/score_series.json", args.output_dir);
+    std::fs::write(&csv_path, series.to_csv())?;
+    std::fs::write(&json_path, serde_json::to_string_pretty(&series)?)?;
+    status!("\n💾 Saved score series to: {} and {}", csv_path, json_path);
+
+    if series.points.is_empty() {
+        eyre::bail!("No block could be scored");
+    }
+    Ok(())
+}
+
+/// An RPC fetcher pinned to `block`, configured from the command line
+fn block_fetcher(
+    args: &Args,
+    aave_addresses: &AaveAddresses,
+    block: u64,
+    filter: ReserveFilter,
+    prices: PriceTable,
+    module: Option<alloy::primitives::Address>,
+    shutdown: &ShutdownSignal,
+) -> AaveFetcher {
+    let mut fetcher = AaveFetcher::new(aave_addresses.clone(), args.rpc_url.clone())
+        .with_rpc_options(rpc_options(args))
+        .with_shutdown(shutdown.clone())
+        .with_per_reserve_timeout(Duration::from_secs(args.per_reserve_timeout))
+        .with_max_reserves(args.max_reserves)
+        .with_reserve_filter(filter)
+        .with_fallback_prices(prices)
+        .with_version(args.aave_version)
+        .with_block(block)
+        .with_strict(strict_fetch(args))
+        .with_decimals_cache(decimals_cache_path(args), args.refresh_decimals);
+    if args.use_data_provider {
+        fetcher = fetcher.with_data_provider();
+    }
+    if let Some(module) = module {
+        fetcher = fetcher.with_safety_module(module);
+    }
+    fetcher
+}
+
+/// Score the --blocks points from one fetch and the pool's events since
+async fn run_backtest(
+    args: &Args,
+    aave_addresses: &AaveAddresses,
+    spec: &str,
+    shutdown: &ShutdownSignal,
+) -> Result<()> {
+    if args.source != SourceKind::Rpc {
+        eyre::bail!("--from-events only supports --source rpc");
+    }
+    let blocks = series::parse_blocks(spec)?;
+    let (first, last) = match (blocks.first(), blocks.last()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => eyre::bail!("--blocks names no blocks"),
+    };
+
+    status!("═══════════════════════════════════════");
+    status!("  Backtesting {} Blocks From Events", blocks.len());
+    status!("═══════════════════════════════════════");
+
+    status!("\n📡 Fetching the starting state at block {}...", first);
+    let fetcher = block_fetcher(
+        args,
+        aave_addresses,
+        first,
+        reserve_filter(args)?,
+        fallback_prices(args)?,
+        safety_module(args)?,
+        shutdown,
+    );
+    let mut snapshot = fetcher.fetch().await?;
+    // A fetch cut short by Ctrl-C is partial - never replay from it
+    if shutdown.is_requested() {
+        eyre::bail!("Interrupted fetching block {}", first);
+    }
+    apply_input_overrides(args, &mut snapshot)?;
+
+    // The snapshot already reflects the first block's own events
+    status!("\n📜 Reading pool events for blocks {}..={}...", first.saturating_add(1), last);
+    let provider = rpc::build_provider(&args.rpc_url, &rpc_options(args))?;
+    let events = event_backtest::fetch_events(
+        &provider,
+        aave_addresses.pool,
+        first.saturating_add(1),
+        last,
+        args.log_batch_blocks,
+        shutdown,
+    )
+    .await?;
+    status!("✓ Decoded {} reserve changes", events.len());
+
+    let series = event_backtest::replay_series(snapshot, &events, &blocks);
+
+    let currency = display_currency(args)?;
+    status!("\n📈 Reconstructed Score Series:");
+    for point in &series.points {
+        status!("  - Block {}: {:.4}% ({} assets, {} liabilities)",
+            point.block,
+            point.safety_score as f64 / 1e4,
+            currency.format(point.total_assets_usd),
+            currency.format(point.total_liabilities_usd));
+    }
+
+    std::fs::create_dir_all(&args.output_dir)?;
+    let csv_path = format!("{}/{}", args.output_dir, event_backtest::BACKTEST_CSV_FILE);
+    std::fs::write(&csv_path, series.to_csv())?;
+    status!("\n💾 Saved reconstructed series to: {}", csv_path);
+    Ok(())
+}
+
+/// Finish a submission that was broadcast by an earlier, interrupted run
+/// Uses the journal and seal saved in the output directory, so nothing is re-proven
+async fn resume_submission(args: &Args, aave_addresses: &AaveAddresses) -> Result<()> {
+    status!("═══════════════════════════════════════");
+    status!("  Resuming On-Chain Submission");
+    status!("═══════════════════════════════════════\n");
+
+    let journal_path = format!("{}/proof_journal.bin", args.output_dir);
+    let journal_bytes = std::fs::read(&journal_path)
+        .map_err(|e| eyre::eyre!("Failed to read {}: {}", journal_path, e))?;
+    let seal_bytes = seal::load_seal(&args.output_dir)?;
+
+    let submitter = oracle_submitter(args, aave_addresses)?;
+
+    confirm::confirm(&format!("Submit the saved proof to the oracle on {}", args.network), args.assume_yes)?;
+    let tx_hash = submission::submit_once(&submitter, &journal_bytes, &seal_bytes, &args.output_dir, true).await?;
+    status!("\n✓ Proof submitted successfully!");
+    status!("  - Transaction: {}", tx_hash);
+    Ok(())
+}
+
+/// Report the last saved run and the oracle's current score (status mode)
+async fn report_status(args: &Args, aave_addresses: &AaveAddresses) -> Result<()> {
+    let oracle_address = signer::load_oracle_address(args.oracle_address.as_deref())?;
+    let oracle = OracleReader::new(args.rpc_url.clone(), oracle_address, aave_addresses.pool)
+        .with_rpc_options(rpc_options(args));
+    status!(" Oracle contract: {}", oracle_address);
+    status!(" Output dir: {}\n", args.output_dir);
+
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+    health::check_health(&oracle, &args.output_dir, now).await?.print();
+    Ok(())
+}
+
+/// Dry-run the submission with an eth_call (--simulate-submission)
+async fn simulate_submission(submitter: &OracleSubmitter, journal_bytes: &[u8], seal_bytes: &[u8]) -> Result<()> {
+    status!("🧪 Simulating updateScore with eth_call...");
+    submitter.simulate(journal_bytes, seal_bytes).await?;
+    status!("✓ Simulation passed: the oracle would accept this proof");
+    Ok(())
+}
+
+/// Read an input file, or stdin when the path is `-`
+fn read_input_file(path: &str) -> Result<String> {
+    if path == "-" {
+        let mut json = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut json)?;
+        return Ok(json);
+    }
+    std::fs::read_to_string(path).map_err(|e| eyre::eyre!("Failed to read {}: {}", path, e))
+}
+
+/// Decode an input file (- for stdin) as it is read, so a large file is never
+/// held in memory as text alongside the decoded input
+/// A file that fails to decode is probed for its schema version, so one from
+/// an older schema is still reported as unsupported rather than malformed
+fn stream_input_file(path: &str) -> Result<AaveInput> {
+    if path == "-" {
+        return Ok(schema::load_input_from_reader(std::io::stdin().lock())?);
+    }
+    let open = || {
+        std::fs::File::open(path)
+            .map(std::io::BufReader::new)
+            .map_err(|e| eyre::eyre!("Failed to read {}: {}", path, e))
+    };
+    match schema::load_input_from_reader(open()?) {
+        Err(SchemaError::Parse(e)) => match schema::input_schema_version_from_reader(open()?) {
+            Ok(found) if found != schema::INPUT_SCHEMA_VERSION => {
+                Err(SchemaError::Unsupported { found, expected: schema::INPUT_SCHEMA_VERSION }.into())
+            }
+            _ => Err(SchemaError::Parse(e).into()),
+        },
+        loaded => Ok(loaded?),
+    }
+}
+
+/// Reject --output-stdout where something other than the result would need stdout
+fn check_pipe_mode(args: &Args) -> Result<()> {
+    if args.mode != "execute" && args.mode != "prove-only" {
+        eyre::bail!("--output-stdout only works with --mode execute or --mode prove-only");
+    }
+    if args.input_file.is_none() {
+        eyre::bail!("--output-stdout needs --input-file (use - to read stdin)");
+    }
+    if args.submit || args.watch {
+        eyre::bail!("--output-stdout can't be combined with --submit or --watch");
+    }
+    Ok(())
+}
+
+/// RPC timeouts from --connect-timeout / --rpc-timeout, and --verbose-rpc
+fn rpc_options(args: &Args) -> RpcOptions {
+    RpcOptions {
+        connect_timeout: Duration::from_secs(args.connect_timeout),
+        request_timeout: Duration::from_secs(args.rpc_timeout),
+        verbose: args.verbose_rpc,
+        ..RpcOptions::default()
+    }
+}
+
+/// Currency for printed totals, from --display-currency and --fx-rate
+fn display_currency(args: &Args) -> Result<DisplayCurrency> {
+    match (&args.display_currency, &args.fx_rate) {
+        (Some(code), Some(rate)) => DisplayCurrency::new(code, rate),
+        (None, None) => Ok(DisplayCurrency::default()),
+        _ => Err(eyre::eyre!("--display-currency and --fx-rate must be given together")),
+    }
+}
+
+/// Submitter for the configured signer and oracle, guarded against the wrong
+/// chain and, with --check-onchain-image-id, the wrong guest image
+fn oracle_submitter(args: &Args, aave_addresses: &AaveAddresses) -> Result<OracleSubmitter> {
+    let signer = signer::load_signer(args.keystore.as_deref(), args.private_key.as_deref())?;
+    let oracle_address = signer::load_oracle_address(args.oracle_address.as_deref())?;
+    let mut submitter = OracleSubmitter::new(args.rpc_url.clone(), signer, oracle_address, aave_addresses.pool)
+        .with_rpc_options(rpc_options(args))
+        .with_expected_chain_id(aave_addresses.chain_id);
+    if args.check_onchain_image_id {
+        submitter = submitter.with_expected_image_id(AAVE_ID);
+    }
+    Ok(submitter)
+}
+
+/// Build the reserve filter from --include-reserve / --exclude-reserve
+fn reserve_filter(args: &Args) -> Result<ReserveFilter> {
+    let parse = |addresses: &[String]| -> Result<Vec<alloy::primitives::Address>> {
+        addresses
+            .iter()
+            .map(|a| a.parse().map_err(|e| eyre::eyre!("Invalid reserve address '{}': {}", a, e)))
+            .collect()
+    };
+    Ok(ReserveFilter {
+        include: parse(&args.include_reserves)?,
+        exclude: parse(&args.exclude_reserves)?,
+    })
+}
+
+/// Apply --fallback-oracle over the network's own fallback_oracle
+fn with_fallback_oracle(args: &Args, mut addresses: AaveAddresses) -> Result<AaveAddresses> {
+    if let Some(oracle) = &args.fallback_oracle {
+        let oracle = oracle
+            .parse()
+            .map_err(|e| eyre::eyre!("Invalid --fallback-oracle '{}': {}", oracle, e))?;
+        addresses.fallback_oracle = Some(oracle);
+    }
+    Ok(addresses)
+}
+
+/// The safety module from --safety-module, if given
+fn safety_module(args: &Args) -> Result<Option<alloy::primitives::Address>> {
+    args.safety_module
+        .as_ref()
+        .map(|module| module.parse().map_err(|e| eyre::eyre!("Invalid --safety-module '{}': {}", module, e)))
+        .transpose()
+}
+
+/// The haircut from --siloed-haircut, if given
+fn siloed_haircut(args: &Args) -> Result<Option<u16>> {
+    match args.siloed_haircut {
+        Some(bps) if bps > 10_000 => eyre::bail!("--siloed-haircut is in bps and can't exceed 10000 (100%)"),
+        bps => Ok(bps),
+    }
+}
+
+/// The fetcher for --source subgraph, pinned to `block` when given
+/// RPC-only options are refused rather than silently ignored, since
+/// leaving them out would change the input
+fn subgraph_fetcher(args: &Args, aave_addresses: &AaveAddresses, block: Option<u64>) -> Result<SubgraphFetcher> {
+    let Some(url) = &args.subgraph_url else {
+        eyre::bail!("--source subgraph needs --subgraph-url");
+    };
+    // V2 subgraphs price in ETH, and the subgraph doesn't say which base
+    // currency an oracle quotes in
+    if args.aave_version != AaveVersion::V3 || aave_addresses.base_currency_unit != USD_SCALE {
+        eyre::bail!("--source subgraph only supports Aave V3 priced in USD; use --source rpc");
+    }
+    if args.use_data_provider || args.safety_module.is_some() || args.fallback_prices.is_some() || args.resume_fetch {
+        eyre::bail!("--use-data-provider, --safety-module, --fallback-prices and --resume-fetch need --source rpc");
+    }
+
+    let mut fetcher = SubgraphFetcher::new(url.clone())
+        .with_rpc_options(rpc_options(args))
+        .with_reserve_filter(reserve_filter(args)?);
+    if let Some(block) = block {
+        fetcher = fetcher.with_block(block);
+    }
+    if let Some(timestamp) = args.timestamp {
+        fetcher = fetcher.with_timestamp(timestamp);
+    }
+    Ok(fetcher)
+}
+
+/// Whether a reserve that can't be fetched aborts the run
+/// A score submitted on-chain must cover the whole pool unless asked otherwise
+fn strict_fetch(args: &Args) -> bool {
+    args.strict || (args.submit && !args.allow_partial)
+}
+
+/// Where the fetcher keeps known token decimals between runs
+fn decimals_cache_path(args: &Args) -> String {
+    format!("{}/{}", args.output_dir, decimals_cache::DECIMALS_CACHE_FILE)
+}
+
+/// Static prices from --fallback-prices, empty without it
+fn fallback_prices(args: &Args) -> Result<PriceTable> {
+    let Some(path) = &args.fallback_prices else {
+        return Ok(PriceTable::new());
+    };
+    let prices = price_fallback::load_fallback_prices(path)?;
+    status!("Loaded {} fallback prices from {}", prices.len(), path);
+    Ok(prices)
+}
+
+/// Upgrade an older saved input file to the current schema (migrate mode)
+fn migrate_input_file(args: &Args) -> Result<()> {
+    let input_file = args.input_file.as_ref().expect("--input-file required for migrate mode");
+    status!(" Migrating input file: {}", input_file);
+    let json = read_input_file(input_file)?;
+    let input = derisk_type::migrate_input(&json)?;
+
+    let output_path = format!("{}/aave_input.json", args.output_dir);
+    std::fs::create_dir_all(&args.output_dir)?;
+    saved_json::write_json(&output_path, &input, args.json_compact)?;
+    status!("\n💾 Saved migrated input (schema v{}) to: {}", input.schema_version, output_path);
+    Ok(())
+}
+
+/// Write the input and output formats as JSON Schema (schema mode)
+/// For validating inputs produced by other tools before prove-only mode
+fn export_json_schemas(args: &Args) -> Result<()> {
+    let dir = format!("{}/schemas", args.output_dir);
+    std::fs::create_dir_all(&dir)?;
+    for (name, schema) in derisk_type::json_schemas() {
+        let path = format!("{}/{}.schema.json", dir, name);
+        saved_json::write_json(&path, &schema, args.json_compact)?;
+        status!("💾 Saved {} schema to: {}", name, path);
+    }
+    Ok(())
+}
+
+/// Compare two saved outputs and report what changed (diff mode)
+fn diff_outputs(args: &Args) -> Result<()> {
+    let [old_path, new_path] = args.diff_files.as_slice() else {
+        eyre::bail!("--diff-files <OLD> <NEW> required for diff mode");
+    };
+    status!(" Comparing {} → {}\n", old_path, new_path);
+
+    let old = journal_diff::load_output(old_path)?;
+    let new = journal_diff::load_output(new_path)?;
+    OutputDiff::between(&old, &new).print(&old, &new);
+    Ok(())
+}
+
+/// Recompute an input's output and compare it with a published journal (audit mode)
+fn audit_journal(args: &Args) -> Result<()> {
+    let (Some(input_path), Some(journal_path)) = (&args.input_file, &args.journal) else {
+        eyre::bail!("--input-file and --journal required for audit mode");
+    };
+    status!(" Auditing {} against {}\n", journal_path, input_path);
+
+    let input = stream_input_file(input_path)?;
+    let committed = journal_diff::load_output(journal_path)?;
+    let report = audit::audit(&input, &committed)?;
+    report.print();
+    if !report.passed() {
+        eyre::bail!("Audit failed");
+    }
+    Ok(())
+}
+
+/// Verify a Groth16 proof from its journal, seal and image ID (verify-raw mode)
+fn verify_raw_proof(args: &Args) -> Result<()> {
+    let (Some(journal_path), Some(seal_path)) = (&args.journal, &args.seal) else {
+        eyre::bail!("--journal and --seal required for verify-raw mode");
+    };
+    let guest_id = match &args.image_id {
+        Some(hex_id) => image_id::parse_image_id(hex_id)?,
+        None => AAVE_ID,
+    };
+    status!(" Verifying {} with {} against image ID {}\n", journal_path, seal_path, image_id::image_id_hex(&guest_id));
+
+    let journal = artifact_encoding::read_artifact(journal_path)?;
+    let seal_bytes = artifact_encoding::read_artifact(seal_path)?;
+    seal::verify_groth16(&journal, &seal_bytes, guest_id)?;
+    status!("✅ The seal proves this journal was committed by image ID {}\n", image_id::image_id_hex(&guest_id));
+
+    // A journal from another guest (or an older output format) still
+    // verifies, it just can't be shown as a score
+    match risc0_zkvm::Journal::new(journal).decode::<SafetyScoreOutput>() {
+        Ok(output) => status!("{}", report::render_summary(&output, &RunMeta { currency: display_currency(args)?, protocol: None })),
+        Err(e) => status!("⚠ Journal doesn't decode as a safety score output: {}", e),
+    }
+    Ok(())
+}
+
+/// Measure guest cost against reserve count on synthetic inputs (bench mode)
+fn run_reserve_bench(args: &Args) -> Result<()> {
+    let counts = bench::parse_counts(&args.bench_reserves)?;
+    let prover = if args.bench_prove { Some(prover::select_prover(args.prover)?) } else { None };
+    status!(" Benchmarking {} input sizes ({})", counts.len(),
+        if prover.is_some() { "execute and prove" } else { "execute only" });
+
+    let rows = bench::run_bench(&counts, args.segment_limit_po2, prover.as_deref())?;
+    bench::print_table(&rows);
+
+    std::fs::create_dir_all(&args.output_dir)?;
+    let csv_path = format!("{}/{}", args.output_dir, bench::BENCH_FILE);
+    std::fs::write(&csv_path, bench::to_csv(&rows))?;
+    status!("\n💾 Saved benchmark to: {}", csv_path);
+    Ok(())
+}
+
+/// Write a synthetic input for load testing (generate mode)
+fn generate_input_file(args: &Args) -> Result<()> {
+    let spec = synthetic::GenerateSpec {
+        reserves: args.reserves,
+        seed: args.seed,
+        insolvent_share: args.insolvent_share,
+    };
+    status!(" Generating {} reserves from seed {} ({}% insolvent)", spec.reserves, spec.seed, spec.insolvent_share);
+    let input = synthetic::generate_input(&spec)?;
+
+    let output_path = format!("{}/aave_input.json", args.output_dir);
+    std::fs::create_dir_all(&args.output_dir)?;
+    saved_json::write_json(&output_path, &input, args.json_compact)?;
+    status!("\n💾 Saved synthetic input to: {}", output_path);
+    Ok(())
+}
+
+/// Verify a STARK and a Groth16 receipt and check they commit the same output
+fn compare_receipt_files(args: &Args) -> Result<()> {
+    let (stark_path, groth16_path) = match args.receipt_files.as_slice() {
+        [stark, groth16] => (stark.clone(), groth16.clone()),
+        _ => (
+            format!("{}/stark_receipt.bin", args.output_dir),
+            format!("{}/proof_receipt.bin", args.output_dir),
+        ),
+    };
+    status!(" Comparing {} with {}\n", stark_path, groth16_path);
+
+    let output = receipt_compare::verify_and_compare(
+        &compression::load_receipt(&stark_path)?,
+        &compression::load_receipt(&groth16_path)?,
+        AAVE_ID,
+    )?;
+
+    status!("✅ Both receipts verify and commit the same output\n");
+    status!("{}", report::render_summary(&output, &RunMeta { currency: display_currency(args)?, protocol: None }));
+    Ok(())
+}
+
+/// Load the input from file (prove-only) or fetch it from the chain, and
+/// start a proving session over it
+/// Returns None when the run should stop here (fetch-only mode or interrupted)
+async fn load_or_fetch_input(
+    args: &Args,
+    aave_addresses: &AaveAddresses,
+    shutdown: &ShutdownSignal,
+) -> Result<Option<ProvingSession>> {
+    // execute mode takes a file when given one and fetches otherwise
+    if args.mode == "prove-only" || (args.mode == "execute" && args.input_file.is_some()) {
+        // Load from file
+        let input_file = args.input_file.as_ref().expect("--input-file required for prove-only mode");
+        status!(" Loading data from file: {}", input_file);
+        return match stream_input_file(input_file) {
+            Ok(input) => Ok(Some(proving_session(args, input))),
+            Err(e) if matches!(e.downcast_ref::<SchemaError>(), Some(SchemaError::Unsupported { .. })) => {
+                eprintln!("Error: {}", e);
+                eprintln!("Hint: upgrade the file with:");
+                eprintln!("  --mode migrate --input-file {} --output-dir <dir>", input_file);
+                eprintln!("or re-fetch it with --mode fetch-only");
+                std::process::exit(1);
+            }
+            Err(e) => Err(e.into()),
+        };
+    }
+
+    // Fetch from blockchain
+    status!("═══════════════════════════════════════");
+    status!("  STEP 1: Fetching Aave Reserve Data");
+    status!("═══════════════════════════════════════\n");
+
+    let source = match args.source {
+        SourceKind::Rpc => {
+            let mut fetcher = AaveFetcher::new(aave_addresses.clone(), args.rpc_url.clone())
+                .with_rpc_options(rpc_options(args))
+                .with_shutdown(shutdown.clone())
+                .with_per_reserve_timeout(Duration::from_secs(args.per_reserve_timeout))
+                .with_max_reserves(args.max_reserves)
+                .with_reserve_filter(reserve_filter(args)?)
+                .with_fallback_prices(fallback_prices(args)?)
+                .with_version(args.aave_version)
+                .with_strict(strict_fetch(args))
+                .with_decimals_cache(decimals_cache_path(args), args.refresh_decimals);
+            if let Some(block) = args.block {
+                fetcher = fetcher.with_block(block);
+            }
+            if args.use_data_provider {
+                fetcher = fetcher.with_data_provider();
+            }
+            if let Some(timestamp) = args.timestamp {
+                fetcher = fetcher.with_timestamp(timestamp);
+            }
+            if let Some(module) = safety_module(args)? {
+                fetcher = fetcher.with_safety_module(module);
+            }
+            std::fs::create_dir_all(&args.output_dir)?;
+            let checkpoint_path = format!("{}/{}", args.output_dir, checkpoint::CHECKPOINT_FILE);
+            Source::Rpc(fetcher.with_checkpoint(checkpoint_path, args.resume_fetch))
+        }
+        SourceKind::Subgraph => Source::Subgraph(subgraph_fetcher(args, aave_addresses, args.block)?),
+    };
+    let session = ProvingSession::fetch(&source)
+        .await?
+        .with_segment_limit_po2(args.segment_limit_po2)
+        .with_prover(args.prover);
+    let input = session.input();
+
+    // Save to file for future prove-only runs
+    // Done even when interrupted so the RPC work isn't wasted
+    let output_path = format!("{}/aave_input.json", args.output_dir);
+    std::fs::create_dir_all(&args.output_dir)?;
+    saved_json::write_json(&output_path, input, args.json_compact)?;
+    status!("\n💾 Saved input data to: {}", output_path);
+
+    if shutdown.is_requested() {
+        status!("\n⚠ Fetch interrupted. Saved {} reserves fetched so far.", input.reserves.len());
+        status!("  Finish the fetch with: --resume-fetch");
+        status!("  Or prove what was fetched with: --mode prove-only --input-file {}", output_path);
+        return Ok(None);
+    }
+
+    if args.mode == "fetch-only" {
+        status!("\n✓ Fetch complete. Exiting (fetch-only mode).");
+        return Ok(None);
+    }
+
+    Ok(Some(session))
+}
+
+/// A proving session over `input`, with the segment size and prover from the flags
+fn proving_session(args: &Args, input: AaveInput) -> ProvingSession {
+    ProvingSession::new(input)
+        .with_segment_limit_po2(args.segment_limit_po2)
+        .with_prover(args.prover)
+        .with_submit_any_status(args.submit_any_status)
+}
+
+/// Run the guest and wrap the STARK proof into a Groth16 receipt
+/// The guest is executed first to measure cycles and enforce --max-cycles.
+/// Proving can't be interrupted; if a shutdown was requested during the
+/// STARK phase the intermediate receipt is saved and None is returned.
+/// Unless `confirmed`, asks before proving starts (see --assume-yes)
+fn prove_input(
+    session: &ProvingSession,
+    args: &Args,
+    shutdown: &ShutdownSignal,
+    confirmed: bool,
+) -> Result<Option<Groth16Proof>> {
+    let output_dir = args.output_dir.as_str();
+
+    status!("\n═══════════════════════════════════════");
+    status!("  STEP 2: Executing zkVM Guest Program");
+    status!("═══════════════════════════════════════\n");
+
+    // Execute without proving to learn the cycle count up front
+    status!("🔍 Executing guest to measure cycles...");
+    let execution = session.execute()?;
+    status!("✓ Execution complete ({} segments)\n", execution.segments);
+
+    ProvingEstimate::new(execution.cycles, args.cycles_per_second).print();
+    estimate::check_cycle_budget(execution.cycles, args.max_cycles)?;
+
+    if shutdown.is_requested() {
+        status!("\n⚠ Interrupted before proving started, nothing to save");
+        return Ok(None);
+    }
+    confirm::confirm("Start Groth16 proving", confirmed)?;
+    status!();
+
+    if let Some(po2) = args.segment_limit_po2 {
+        status!("  Segment limit: 2^{} cycles", po2);
+    }
+    status!("\n🚀 Starting zkVM execution with Groth16...");
+    status!("⏳ This will take a while for Groth16 proving (grab a coffee ☕)...\n");
+
     // Step 1: Generate STARK proof first
-    println!("📝 Step 1/2: Generating STARK proof...");
-    let prove_info = prover
-        .prove(env, AAVE_ELF)
-        .map_err(|e| eyre::eyre!("Failed to prove: {}", e))?;
-
-    println!("✓ STARK proof complete!");
-    println!("  - Cycles: {}", prove_info.stats.total_cycles);
-    println!("  - Segments: {}", prove_info.stats.segments);
+    status!("📝 Step 1/2: Generating STARK proof...");
+    let stark = session.prove_stark()?;
+
+    status!("✓ STARK proof complete! ({:.1}s)", stark.timer.stark().as_secs_f64());
+    status!("  - Cycles: {}", stark.total_cycles);
+    status!("  - Segments: {}", stark.segments);
+
+    // Keep the STARK receipt so the work done so far isn't lost, and so
+    // compare-receipts can check it against the Groth16 receipt later
+    std::fs::create_dir_all(output_dir)?;
+    let (stark_bytes, _) = receipt_format::serialize_receipt(&stark.receipt)?;
+    let stark_path = compression::write_artifact(
+        &format!("{}/stark_receipt.bin", output_dir),
+        &stark_bytes,
+        args.compress_artifacts,
+    )?;
+
+    if shutdown.is_requested() {
+        status!("\n⚠ Interrupted: skipping Groth16 conversion");
+        status!("  Saved STARK receipt to: {}", stark_path);
+        return Ok(None);
+    }
     
     // Step 2: Convert to Groth16
-    println!("\n📝 Step 2/2: Converting to Groth16 (this is the slow part)...");
-    let stark_receipt = prove_info.receipt;
+    status!("\n📝 Step 2/2: Converting to Groth16 (this is the slow part)...");
     
-    let groth16_prover = Groth16Prover::new();
-    let receipt = groth16_prover
-        .prove(&stark_receipt)
-        .map_err(|e| eyre::eyre!("Failed to convert to Groth16: {}", e))?;
+    // The session checks the wrapped receipt commits exactly what the STARK proved
+    let proof = session.prove_groth16(stark)?;
 
-    println!("✅ Groth16 conversion complete!");
+    status!("✅ Groth16 conversion complete! ({:.1}s)", proof.stats.timer.groth16().as_secs_f64());
+    status!("✓ Groth16 journal matches the STARK journal");
 
-    // ========================================================================
-    // STEP 3: Extract Proof and Journal
-    // ========================================================================
-    println!("\n═══════════════════════════════════════");
-    println!("  STEP 3: Extracting Proof & Journal");
-    println!("═══════════════════════════════════════\n");
+    Ok(Some(proof))
+}
+
+/// Verify and decode the proof, report the result and write all proof artifacts
+/// Also writes the proof manifest and, with --bench-output, a benchmark row
+/// Returns the output with the raw journal bytes and the encoded seal for submission
+fn save_proof_artifacts(
+    session: &ProvingSession,
+    proof: &Groth16Proof,
+    args: &Args,
+) -> Result<(SafetyScoreOutput, Vec<u8>, Vec<u8>)> {
+    let output_dir = args.output_dir.as_str();
 
-    // Decode the journal to get the SafetyScoreOutput
-    let output: SafetyScoreOutput = receipt.journal.decode()?;
+    status!("\n═══════════════════════════════════════");
+    status!("  STEP 3: Extracting Proof & Journal");
+    status!("═══════════════════════════════════════\n");
 
-    println!("📊 Safety Score Result:");
-    println!("  - Safety Score: {:.4}%", output.to_percentage());
-    println!("  - Total Assets: ${:.2}", output.total_assets_usd as f64 / 1e8);
-    println!("  - Total Liabilities: ${:.2}", output.total_liabilities_usd as f64 / 1e8);
-    println!("  - Buffer: ${:.2}", 
-        (output.total_assets_usd - output.total_liabilities_usd) as f64 / 1e8);
+    // Decode the journal to get the SafetyScoreOutput, checking the on-chain
+    // verifier will read the same values
+    let output = session.verify(proof)?;
+    status!("{}", report::render_summary(&output, &RunMeta { currency: display_currency(args)?, protocol: None }));
 
     // Extract the Groth16 seal and journal
-    let journal_bytes = receipt.journal.bytes.clone();
-    
-    // Extract the Groth16 seal from the receipt's inner structure
-    // Groth16 seals are MUCH smaller than STARK seals (~300-400 bytes vs ~250KB!)
-    let seal_bytes = bincode::serialize(&receipt.inner)
-        .map_err(|e| eyre::eyre!("Failed to serialize Groth16 seal: {}", e))?;
+    let journal_bytes = proof.journal().to_vec();
+    let journal_digest = journal_abi::journal_digest(&journal_bytes);
     
-    // Also save the full receipt for reference
-    let receipt_bytes = bincode::serialize(&receipt)?;
-
-    println!("\n🔐 Groth16 Proof Artifacts:");
-    println!("  - Proof type: Groth16 ✨");
-    println!("  - Journal size: {} bytes", journal_bytes.len());
-    println!("  - Groth16 Seal size: {} bytes ({:.2} KB)", seal_bytes.len(), seal_bytes.len() as f64 / 1024.0);
-    println!("  - Receipt size: {} bytes", receipt_bytes.len());
-    println!("  - Reduction: {}x smaller than STARK!", 250_000 / seal_bytes.len().max(1));
-    println!("  - Image ID: {:?}", AAVE_ID);
+    // The seal the on-chain verifier checks: selector + Groth16 proof
+    let seal_bytes = proof.seal()?;
     
-    // Sanity check - Groth16 seals should be small
-    if seal_bytes.len() > 10_000 {
-        println!("\n⚠️  Warning: Seal larger than expected for Groth16 ({} bytes)", seal_bytes.len());
-        println!("    Expected: 200-1000 bytes. Got: {}", seal_bytes.len());
-    } else {
-        println!("\n✅ Seal size looks good for Groth16!");
-    }
+    // Also save the full receipt for reference; a serialization failure
+    // falls back to JSON rather than losing the proof
+    let (receipt_bytes, receipt_format) = receipt_format::serialize_receipt(&proof.receipt)?;
 
+    status!("\n🔐 Groth16 Proof Artifacts:");
+    status!("  - Proof type: Groth16 ✨");
+    status!("  - Journal size: {} bytes", journal_bytes.len());
+    status!("  - Journal digest (sha256): 0x{}", hex::encode(journal_digest));
+    status!("  - Groth16 Seal size: {} bytes (selector + a/b/c)", seal_bytes.len());
+    status!("  - Receipt size: {} bytes ({})", receipt_bytes.len(), receipt_format);
+    status!("  - Image ID: {}", image_id::image_id_hex(&AAVE_ID));
+    
     // Save artifacts
-    let journal_path = format!("{}/proof_journal.bin", args.output_dir);
-    let seal_path = format!("{}/proof_seal.bin", args.output_dir);
-    let receipt_path = format!("{}/proof_receipt.bin", args.output_dir);
-    let output_path = format!("{}/safety_score_output.json", args.output_dir);
+    let journal_path = format!("{}/proof_journal.bin", output_dir);
+    let seal_path = format!("{}/{}", output_dir, seal::SEAL_FILE);
+    let output_path = format!("{}/{}", output_dir, health::OUTPUT_FILE);
+    // Same values as ABI-encoded (uint64, uint256, uint256, uint64), for consumers without risc0
+    let abi_path = format!("{}/safety_score_abi.hex", output_dir);
 
+    std::fs::create_dir_all(output_dir)?;
     std::fs::write(&journal_path, &journal_bytes)?;
     std::fs::write(&seal_path, &seal_bytes)?;
-    std::fs::write(&receipt_path, &receipt_bytes)?;
-    std::fs::write(&output_path, serde_json::to_string_pretty(&output)?)?;
-
-    println!("\n💾 Saved proof artifacts:");
-    println!("  - Journal: {}", journal_path);
-    println!("  - Seal: {}", seal_path);
-    println!("  - Receipt: {}", receipt_path);
-    println!("  - Output: {}", output_path);
+    let receipt_path = compression::write_artifact(
+        &format!("{}/proof_receipt.bin", output_dir),
+        &receipt_bytes,
+        args.compress_artifacts,
+    )?;
+    saved_json::write_json(&output_path, &output, args.json_compact)?;
+    std::fs::write(&abi_path, format!("0x{}", hex::encode(derisk_type::journal_abi::encode_abi(&output))))?;
+    let mut encoded_paths = Vec::new();
+    if args.artifact_encoding != ArtifactEncoding::Binary {
+        for (path, bytes) in [(&journal_path, &journal_bytes), (&seal_path, &seal_bytes)] {
+            encoded_paths.push(artifact_encoding::write_encoded(path, bytes, args.artifact_encoding)?);
+        }
+    }
 
-    // ========================================================================
-    // STEP 4: Submit to On-Chain Oracle (Optional)
-    // ========================================================================
-    if args.submit {
-        println!("\n═══════════════════════════════════════");
-        println!("  STEP 4: Submitting to On-Chain Oracle");
-        println!("═══════════════════════════════════════\n");
-
-        let private_key = args.private_key
-            .expect("--private-key or PRIVATE_KEY env var required for submission");
-        let oracle_address = args.oracle_address
-            .expect("--oracle-address or ORACLE_ADDRESS env var required for submission");
-
-        let submitter = OracleSubmitter::new(
-            args.rpc_url,
-            private_key,
-            oracle_address.parse()?,
-            aave_addresses.pool,
-        );
-
-        let tx_hash = submitter.submit_proof(journal_bytes, vec![]).await?;
-        
-        println!("\n✓ Proof submitted successfully!");
-        println!("  - Transaction: {}", tx_hash);
-    } else {
-        println!("\n💡 To submit to on-chain oracle, run with --submit flag");
+    status!("\n💾 Saved proof artifacts:");
+    status!("  - Journal: {}", journal_path);
+    status!("  - Seal: {}", seal_path);
+    status!("  - Receipt: {}", receipt_path);
+    status!("  - Output: {}", output_path);
+    status!("  - ABI-encoded output: {}", abi_path);
+    for path in &encoded_paths {
+        status!("  - {} copy: {}", args.artifact_encoding, path);
     }
+    if args.artifact_encoding != ArtifactEncoding::Binary {
+        for (name, bytes) in [("Journal", &journal_bytes), ("Seal", &seal_bytes)] {
+            if bytes.len() <= artifact_encoding::PRINT_LIMIT_BYTES {
+                let text = args.artifact_encoding.encode(bytes);
+                status!("\n📋 {} ({}):\n{}", name, args.artifact_encoding, String::from_utf8_lossy(&text));
+            }
+        }
+    }
+
+    let telemetry = ProofTelemetry::new(
+        &proof.stats,
+        image_id::image_id_hex(&AAVE_ID),
+        seal_bytes.len(),
+        journal_bytes.len(),
+    )?;
+    let manifest = ProofManifest {
+        artifacts: [&journal_path, &seal_path, &receipt_path, &output_path, &abi_path]
+            .into_iter()
+            .chain(&encoded_paths)
+            .filter_map(|path| std::path::Path::new(path).file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect(),
+        receipt_format,
+        telemetry,
+    };
+    status!("  - Manifest: {}", manifest.save(output_dir)?);
 
-    println!("\n╔════════════════════════════════════════╗");
-    println!("║        ✓ All Steps Complete!          ║");
-    println!("╚════════════════════════════════════════╝\n");
+    if let Some(bench_path) = &args.bench_output {
+        telemetry::append_bench_row(bench_path, &manifest.telemetry)?;
+        status!("  - Benchmark row appended to: {}", bench_path);
+    }
 
-    Ok(())
+    Ok((output, journal_bytes, seal_bytes))
 }
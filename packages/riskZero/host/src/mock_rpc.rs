@@ -0,0 +1,69 @@
+// Mock JSON-RPC Endpoint
+// A local HTTP server for tests that need a provider to talk to something,
+// without a node or network access.
+
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Body of one HTTP request, or None if the connection closed first
+async fn read_body(stream: &mut TcpStream) -> Option<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut buf).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        request.extend_from_slice(&buf[..n]);
+
+        let text = String::from_utf8_lossy(&request);
+        let Some(end) = text.find("\r\n\r\n") else { continue };
+        let length = text[..end]
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .map_or(0, |(_, value)| value.trim().parse().unwrap());
+        if request.len() >= end + 4 + length {
+            return Some(request[end + 4..end + 4 + length].to_vec());
+        }
+    }
+}
+
+/// JSON-RPC endpoint answering every request with `result`
+/// Returns its URL and the methods called on it
+pub async fn mock_rpc(result: serde_json::Value) -> (String, Arc<Mutex<Vec<String>>>) {
+    mock_rpc_with(move |_| serde_json::json!({ "result": result })).await
+}
+
+/// JSON-RPC endpoint answering each request with what `respond` returns for
+/// it: an object holding either a `result` or an `error`
+/// Returns its URL and the methods called on it
+pub async fn mock_rpc_with(
+    respond: impl Fn(&serde_json::Value) -> serde_json::Value + Send + 'static,
+) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let methods = Arc::new(Mutex::new(Vec::new()));
+
+    let called = methods.clone();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let Some(body) = read_body(&mut stream).await else { continue };
+            let call: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            called.lock().unwrap().push(call["method"].as_str().unwrap().to_string());
+
+            let mut response = respond(&call);
+            response["jsonrpc"] = "2.0".into();
+            response["id"] = call["id"].clone();
+            let response = response.to_string();
+            let reply = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            );
+            stream.write_all(reply.as_bytes()).await.unwrap();
+        }
+    });
+    (url, methods)
+}
@@ -0,0 +1,198 @@
+// Network Config
+// mainnet and sepolia are built in, plus Aave V2 on mainnet (--aave-version
+// v2). Any other Aave deployment, or a fork with its own chain id, is
+// described in a TOML file and selected with
+// `--network custom --network-config <path>`:
+//
+//   chain_id = 31337                # checked against the RPC endpoint
+//   pool = "0x..."                  # Pool (proxy)
+//   price_oracle = "0x..."          # AaveOracle
+//   data_provider = "0x..."         # optional, for --use-data-provider
+//   fallback_oracle = "0x..."       # optional, priced from when price_oracle reverts
+//   gho = "0x..."                   # optional, GHO if the pool lists it (debt read from its facilitator bucket)
+//   base_currency_unit = 100000000  # checked against the oracle
+//   multicall3 = "0xcA11bde05977b3631167028862bE2a173976CA11"
+//
+// Every field except data_provider, fallback_oracle and gho is required; unknown keys are rejected so
+// a typo can't silently leave a field unset.
+
+use crate::aave_fetcher::{AaveAddresses, AaveVersion};
+use alloy::primitives::Address;
+use eyre::{Result, eyre};
+use serde::Deserialize;
+
+/// Network name that reads its addresses from --network-config
+pub const CUSTOM_NETWORK: &str = "custom";
+
+/// Contents of a network config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkConfig {
+    pub chain_id: u64,
+    pub pool: Address,
+    pub price_oracle: Address,
+    pub data_provider: Option<Address>,
+    /// The oracle's BASE_CURRENCY_UNIT, e.g. 100000000 for USD with 8 decimals
+    pub base_currency_unit: u64,
+    pub multicall3: Address,
+    /// Same base currency and unit as price_oracle
+    pub fallback_oracle: Option<Address>,
+    /// GHO token, when the pool lists it as a reserve
+    pub gho: Option<Address>,
+}
+
+impl NetworkConfig {
+    /// Parse and validate a config; `source` names it in errors
+    pub fn parse(contents: &str, source: &str) -> Result<Self> {
+        let config: Self = toml::from_str(contents)
+            .map_err(|e| eyre!("Invalid network config {}: {}", source, e))?;
+        config
+            .validate()
+            .map_err(|e| eyre!("Invalid network config {}: {}", source, e))?;
+        Ok(config)
+    }
+
+    /// Read, parse and validate the config at `path`
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| eyre!("Failed to read network config {}: {}", path, e))?;
+        Self::parse(&contents, path)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.chain_id == 0 {
+            return Err(eyre!("chain_id must not be 0"));
+        }
+        let addresses = [
+            ("pool", Some(self.pool)),
+            ("price_oracle", Some(self.price_oracle)),
+            ("data_provider", self.data_provider),
+            ("multicall3", Some(self.multicall3)),
+            ("fallback_oracle", self.fallback_oracle),
+            ("gho", self.gho),
+        ];
+        for (field, address) in addresses {
+            if address == Some(Address::ZERO) {
+                return Err(eyre!("{} must not be the zero address", field));
+            }
+        }
+        if !is_power_of_ten(self.base_currency_unit) {
+            return Err(eyre!(
+                "base_currency_unit must be a power of ten (100000000 for USD with 8 decimals), got {}",
+                self.base_currency_unit
+            ));
+        }
+        Ok(())
+    }
+
+    /// Addresses for the fetcher and submitter
+    pub fn addresses(&self) -> AaveAddresses {
+        AaveAddresses {
+            pool: self.pool,
+            price_oracle: self.price_oracle,
+            data_provider: self.data_provider,
+            chain_id: self.chain_id,
+            base_currency_unit: self.base_currency_unit as u128,
+            multicall3: self.multicall3,
+            fallback_oracle: self.fallback_oracle,
+            gho: self.gho,
+        }
+    }
+}
+
+fn is_power_of_ten(value: u64) -> bool {
+    // 10^19 is the largest power of ten in a u64
+    (0..=19).any(|exp| 10u64.pow(exp) == value)
+}
+
+/// Addresses for --network, loading --network-config for the custom network
+/// A custom network's file describes whichever version it runs
+pub fn resolve_network(network: &str, config_path: Option<&str>, version: AaveVersion) -> Result<AaveAddresses> {
+    match (network, config_path) {
+        (CUSTOM_NETWORK, Some(path)) => Ok(NetworkConfig::load(path)?.addresses()),
+        (CUSTOM_NETWORK, None) => Err(eyre!("--network custom needs --network-config <path>")),
+        (_, Some(_)) => Err(eyre!("--network-config only applies to --network custom")),
+        (_, None) if version == AaveVersion::V2 => AaveAddresses::for_network_version(network, version)
+            .ok_or_else(|| eyre!("Aave V2 is only built in for mainnet; use --network custom for '{}'", network)),
+        (_, None) => AaveAddresses::for_network(network)
+            .ok_or_else(|| eyre!("Unknown network '{}'. Use 'mainnet', 'sepolia' or 'custom'", network)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        chain_id = 31337
+        pool = "0x1111111111111111111111111111111111111111"
+        price_oracle = "0x2222222222222222222222222222222222222222"
+        data_provider = "0x3333333333333333333333333333333333333333"
+        base_currency_unit = 100000000
+        multicall3 = "0xcA11bde05977b3631167028862bE2a173976CA11"
+    "#;
+
+    fn without(field: &str) -> String {
+        CONFIG.lines()
+            .filter(|line| !line.trim_start().starts_with(field))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_valid_config_builds_addresses() {
+        let addresses = NetworkConfig::parse(CONFIG, "fork.toml").unwrap().addresses();
+
+        assert_eq!(
+            addresses,
+            AaveAddresses {
+                pool: "0x1111111111111111111111111111111111111111".parse().unwrap(),
+                price_oracle: "0x2222222222222222222222222222222222222222".parse().unwrap(),
+                data_provider: Some("0x3333333333333333333333333333333333333333".parse().unwrap()),
+                chain_id: 31337,
+                base_currency_unit: 100_000_000,
+                multicall3: "0xcA11bde05977b3631167028862bE2a173976CA11".parse().unwrap(),
+                fallback_oracle: None,
+                gho: None,
+            }
+        );
+
+        // The data provider, fallback oracle and GHO are the only optional fields
+        let config = NetworkConfig::parse(&without("data_provider"), "fork.toml").unwrap();
+        assert_eq!(config.data_provider, None);
+        let with_fallback = format!("{}\nfallback_oracle = \"0x4444444444444444444444444444444444444444\"", CONFIG);
+        let addresses = NetworkConfig::parse(&with_fallback, "fork.toml").unwrap().addresses();
+        assert_eq!(addresses.fallback_oracle, Some("0x4444444444444444444444444444444444444444".parse().unwrap()));
+        let with_gho = format!("{}\ngho = \"0x5555555555555555555555555555555555555555\"", CONFIG);
+        let addresses = NetworkConfig::parse(&with_gho, "fork.toml").unwrap().addresses();
+        assert_eq!(addresses.gho, Some("0x5555555555555555555555555555555555555555".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_invalid_config_names_the_problem() {
+        let err = NetworkConfig::parse(&without("pool"), "fork.toml").unwrap_err().to_string();
+        assert!(err.contains("fork.toml") && err.contains("missing field `pool`"), "{}", err);
+
+        let typo = CONFIG.replace("multicall3", "multicall");
+        let err = NetworkConfig::parse(&typo, "fork.toml").unwrap_err().to_string();
+        assert!(err.contains("unknown field `multicall`"), "{}", err);
+
+        let zero = CONFIG.replace("0x2222222222222222222222222222222222222222", &Address::ZERO.to_string());
+        let err = NetworkConfig::parse(&zero, "fork.toml").unwrap_err().to_string();
+        assert!(err.contains("price_oracle must not be the zero address"), "{}", err);
+
+        let unit = CONFIG.replace("100000000", "12345");
+        let err = NetworkConfig::parse(&unit, "fork.toml").unwrap_err().to_string();
+        assert!(err.contains("base_currency_unit must be a power of ten"), "{}", err);
+    }
+
+    #[test]
+    fn test_resolve_network() {
+        assert_eq!(resolve_network("mainnet", None, AaveVersion::V3).unwrap(), AaveAddresses::mainnet());
+        assert!(resolve_network("custom", None, AaveVersion::V3).unwrap_err().to_string().contains("--network-config"));
+        assert!(resolve_network("mainnet", Some("fork.toml"), AaveVersion::V3).is_err());
+        assert!(resolve_network("arbitrum", None, AaveVersion::V3).unwrap_err().to_string().contains("custom"));
+        assert_eq!(resolve_network("mainnet", None, AaveVersion::V2).unwrap(), AaveAddresses::mainnet_v2());
+        assert!(resolve_network("sepolia", None, AaveVersion::V2).is_err());
+    }
+}
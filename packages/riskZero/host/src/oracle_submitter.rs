@@ -2,14 +2,21 @@
 // Handles submission of ZK proofs to the DeRiskOracle smart contract
 
 use alloy::{
-    providers::ProviderBuilder,
-    primitives::{Address, Bytes, TxHash},
+    providers::{Provider, ProviderBuilder},
+    primitives::{Address, Bytes, TxHash, U256},
     sol,
-    transports::http::reqwest::Url,
+    sol_types::decode_revert_reason,
     signers::local::PrivateKeySigner,
     network::EthereumWallet,
 };
-use eyre::Result;
+use eyre::{Result, eyre};
+use std::time::Duration;
+
+use crate::health::OracleView;
+use crate::image_id::{image_id_from_bytes, image_id_hex};
+use crate::redact;
+use crate::rpc::{build_client, build_provider, RpcOptions};
+use crate::submission::{SubmissionBackend, TxStatus};
 
 // Define DeRiskOracle contract interface
 sol! {
@@ -22,100 +29,491 @@ sol! {
         ) external;
         
         function safetyScores(address protocol) external view returns (uint256);
+
+        function protocolImageIds(address protocol) external view returns (bytes32);
+
+        function protocolMetrics(address protocol) external view returns (
+            uint128 totalAssets,
+            uint128 totalLiabilities,
+            uint64 lastUpdateTime,
+            uint64 zkVerificationCount
+        );
         
         event ScoreUpdated(address indexed protocol, uint256 newScore);
     }
 }
 
+/// How often to poll for a receipt while waiting for confirmation
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(4);
+
+/// An on-chain score too large for the u64 the journal commits scores as
+/// A correctly configured oracle never stores one, so this points at a
+/// misconfigured or foreign contract rather than a real score
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreOverflow {
+    pub score: U256,
+}
+
+impl std::fmt::Display for ScoreOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "on-chain score {} does not fit in a u64; is this the right oracle contract?", self.score)
+    }
+}
+
+impl std::error::Error for ScoreOverflow {}
+
+/// The RPC endpoint is on a different chain than the network being submitted for
+/// Submitting anyway would send e.g. a mainnet proof to a testnet oracle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainIdMismatch {
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl std::fmt::Display for ChainIdMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RPC endpoint is on chain {}, but the proof is for chain {}; check --rpc-url and --network",
+            self.actual, self.expected
+        )
+    }
+}
+
+impl std::error::Error for ChainIdMismatch {}
+
+/// The oracle verifies the protocol's proofs against a different guest image
+/// Its verifier would reject the proof, so sending it would only waste gas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageIdMismatch {
+    pub expected: [u32; 8],
+    pub onchain: [u32; 8],
+}
+
+impl std::fmt::Display for ImageIdMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "oracle expects image ID {} for this protocol, but the proof is for {}; update it with setImageId or rebuild the guest",
+            image_id_hex(&self.onchain),
+            image_id_hex(&self.expected)
+        )
+    }
+}
+
+impl std::error::Error for ImageIdMismatch {}
+
+/// Narrow an on-chain score to a u64, failing instead of truncating
+///
+/// Byte order: the ABI returns a uint256 as one big-endian 32-byte word, and
+/// alloy decodes it into a `U256` that stores little-endian limbs internally.
+/// The conversion works on the numeric value, so neither order leaks through;
+/// only raw bytes need care (`U256::from_be_bytes` for an ABI word)
+fn score_to_u64(score: U256) -> Result<u64, ScoreOverflow> {
+    u64::try_from(score).map_err(|_| ScoreOverflow { score })
+}
+
 /// Handles submission of proofs to the on-chain oracle
+/// Use `submission::submit_once` to submit, so a broadcast tx is never repeated
 pub struct OracleSubmitter {
     rpc_url: String,
-    private_key: String,
+    rpc_options: RpcOptions,
+    signer: PrivateKeySigner,
     oracle_address: Address,
     protocol_address: Address,
+    expected_chain_id: Option<u64>,
+    expected_image_id: Option<[u32; 8]>,
 }
 
 impl OracleSubmitter {
+    /// The signer is constructed by the caller so it can come from a keystore,
+    /// an env var or a flag (see `signer::load_signer`)
     pub fn new(
         rpc_url: String,
-        private_key: String,
+        signer: PrivateKeySigner,
         oracle_address: Address,
         protocol_address: Address,
     ) -> Self {
         Self {
             rpc_url,
-            private_key,
+            rpc_options: RpcOptions::default(),
+            signer,
+            oracle_address,
+            protocol_address,
+            expected_chain_id: None,
+            expected_image_id: None,
+        }
+    }
+
+    /// Connect to the RPC endpoint with these timeouts and pool size
+    pub fn with_rpc_options(mut self, options: RpcOptions) -> Self {
+        self.rpc_options = options;
+        self
+    }
+
+    /// Refuse to simulate or send unless the RPC endpoint is on this chain
+    pub fn with_expected_chain_id(mut self, chain_id: u64) -> Self {
+        self.expected_chain_id = Some(chain_id);
+        self
+    }
+
+    /// Refuse to send unless the oracle verifies the protocol's proofs against this image ID
+    pub fn with_expected_image_id(mut self, image_id: [u32; 8]) -> Self {
+        self.expected_image_id = Some(image_id);
+        self
+    }
+
+    /// Fail with `ChainIdMismatch` if the endpoint isn't on the expected chain
+    async fn check_chain_id(&self) -> Result<()> {
+        let Some(expected) = self.expected_chain_id else {
+            return Ok(());
+        };
+        let provider = build_provider(&self.rpc_url, &self.rpc_options)?;
+        let actual = provider.get_chain_id().await?;
+        if actual != expected {
+            return Err(ChainIdMismatch { expected, actual }.into());
+        }
+        Ok(())
+    }
+
+    /// The image ID the oracle verifies the protocol's proofs against
+    /// All zero if none is set; None if the oracle has no `protocolImageIds` getter
+    pub async fn onchain_image_id(&self) -> Result<Option<[u32; 8]>> {
+        let provider = build_provider(&self.rpc_url, &self.rpc_options)?;
+        let oracle = IDeRiskOracle::new(self.oracle_address, &provider);
+
+        match oracle.protocolImageIds(self.protocol_address).call().await {
+            Ok(id) => Ok(Some(image_id_from_bytes(id._0.0))),
+            // A missing function returns nothing, or reverts in a fallback
+            Err(alloy::contract::Error::ZeroData(..)) => Ok(None),
+            Err(e) if revert_data(&e).is_some() => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Fail with `ImageIdMismatch` unless the oracle expects the image ID set
+    /// with `with_expected_image_id`. An oracle without the getter is checked
+    /// by simulating the submission instead
+    async fn check_image_id(&self, journal: &[u8], seal: &[u8]) -> Result<()> {
+        let Some(expected) = self.expected_image_id else {
+            return Ok(());
+        };
+        match self.onchain_image_id().await? {
+            Some(onchain) if onchain == expected => Ok(()),
+            Some(onchain) => Err(ImageIdMismatch { expected, onchain }.into()),
+            None => {
+                status!("  Oracle has no image ID getter, simulating the submission instead");
+                self.simulate(journal, seal).await
+            }
+        }
+    }
+
+    /// Read the current safety score from the oracle
+    /// Fails with `ScoreOverflow` if the stored value doesn't fit in a u64
+    pub async fn get_current_score(&self) -> Result<u64> {
+        Ok(score_to_u64(self.get_current_score_u256().await?)?)
+    }
+
+    /// Read the current safety score as the full uint256 the oracle stores
+    pub async fn get_current_score_u256(&self) -> Result<U256> {
+        let provider = build_provider(&self.rpc_url, &self.rpc_options)?;
+
+        let oracle = IDeRiskOracle::new(self.oracle_address, &provider);
+        
+        Ok(oracle.safetyScores(self.protocol_address).call().await?._0)
+    }
+
+    /// Run updateScore as an eth_call: the verifier checks the seal and journal
+    /// exactly as it would on-chain, but nothing is sent and no gas is spent
+    /// Errors with the decoded revert reason if the oracle would reject the proof
+    pub async fn simulate(&self, journal: &[u8], seal: &[u8]) -> Result<()> {
+        self.check_chain_id().await?;
+        let provider = build_provider(&self.rpc_url, &self.rpc_options)?;
+
+        let oracle = IDeRiskOracle::new(self.oracle_address, &provider);
+
+        let result = oracle
+            .updateScore(
+                self.protocol_address,
+                Bytes::copy_from_slice(journal),
+                Bytes::copy_from_slice(seal),
+            )
+            // Same sender as the real submission, in case the oracle checks it
+            .from(self.signer.address())
+            .call()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => match revert_data(&e) {
+                Some(data) => Err(eyre!("Oracle would reject the proof: {}", describe_revert(&data))),
+                None => Err(eyre!("Simulation failed: {}", e)),
+            },
+        }
+    }
+}
+
+/// Revert data from a failed call, if the node returned any
+fn revert_data(e: &alloy::contract::Error) -> Option<Bytes> {
+    match e {
+        alloy::contract::Error::TransportError(transport) => {
+            transport.as_error_resp().and_then(|payload| payload.as_revert_data())
+        }
+        _ => None,
+    }
+}
+
+/// Human-readable revert reason from raw revert data
+/// Handles `Error(string)` and `Panic(uint256)`; custom errors are shown by selector
+fn describe_revert(data: &[u8]) -> String {
+    if let Some(reason) = decode_revert_reason(data) {
+        return reason;
+    }
+    match data.get(..4) {
+        Some(selector) => format!("custom error 0x{}", hex::encode(selector)),
+        None => "reverted without a reason".to_string(),
+    }
+}
+
+/// Look up a transaction by hash
+async fn lookup_tx(rpc_url: &str, options: &RpcOptions, tx_hash: TxHash) -> Result<TxStatus> {
+    let provider = build_provider(rpc_url, options)?;
+
+    if let Some(receipt) = provider.get_transaction_receipt(tx_hash).await? {
+        return Ok(TxStatus::Confirmed { block: receipt.block_number.unwrap_or_default() });
+    }
+    match provider.get_transaction_by_hash(tx_hash).await? {
+        Some(_) => Ok(TxStatus::Pending),
+        None => Ok(TxStatus::Unknown),
+    }
+}
+
+/// Read-only view of the oracle, for checks that need no signer (status mode)
+pub struct OracleReader {
+    rpc_url: String,
+    rpc_options: RpcOptions,
+    oracle_address: Address,
+    protocol_address: Address,
+}
+
+impl OracleReader {
+    pub fn new(rpc_url: String, oracle_address: Address, protocol_address: Address) -> Self {
+        Self {
+            rpc_url,
+            rpc_options: RpcOptions::default(),
             oracle_address,
             protocol_address,
         }
     }
 
-    /// Submit a proof to the DeRiskOracle contract
-    pub async fn submit_proof(
-        &self,
-        journal: Vec<u8>,
-        seal: Vec<u8>,
-    ) -> Result<TxHash> {
-        println!(" Connecting to RPC: {}", self.rpc_url);
-        println!(" Oracle contract: {}", self.oracle_address);
-        println!(" Protocol address: {}", self.protocol_address);
+    /// Connect to the RPC endpoint with these timeouts and pool size
+    pub fn with_rpc_options(mut self, options: RpcOptions) -> Self {
+        self.rpc_options = options;
+        self
+    }
+}
+
+impl OracleView for OracleReader {
+    async fn current_score(&self) -> Result<u64> {
+        let provider = build_provider(&self.rpc_url, &self.rpc_options)?;
+        let oracle = IDeRiskOracle::new(self.oracle_address, &provider);
+        Ok(score_to_u64(oracle.safetyScores(self.protocol_address).call().await?._0)?)
+    }
+
+    async fn last_update_time(&self) -> Result<u64> {
+        let provider = build_provider(&self.rpc_url, &self.rpc_options)?;
+        let oracle = IDeRiskOracle::new(self.oracle_address, &provider);
+        Ok(oracle.protocolMetrics(self.protocol_address).call().await?.lastUpdateTime)
+    }
+
+    async fn tx_status(&self, tx_hash: TxHash) -> Result<TxStatus> {
+        lookup_tx(&self.rpc_url, &self.rpc_options, tx_hash).await
+    }
+}
+
+impl SubmissionBackend for OracleSubmitter {
+    /// Broadcast updateScore and return without waiting for the receipt
+    async fn send(&self, journal: &[u8], seal: &[u8]) -> Result<TxHash> {
+        status!(" Connecting to RPC: {}", redact::url(&self.rpc_url));
+        status!(" Oracle contract: {}", self.oracle_address);
+        status!(" Protocol address: {}", self.protocol_address);
+        status!(" Signer: {}", self.signer.address());
+
+        // Before anything is signed: a wrong endpoint must not get the tx
+        self.check_chain_id().await?;
+        self.check_image_id(journal, seal).await?;
 
-        // Create signer from private key
-        let signer: PrivateKeySigner = self.private_key.parse()?;
-        let wallet = EthereumWallet::from(signer);
+        let wallet = EthereumWallet::from(self.signer.clone());
 
         // Create provider with wallet
-        let url = Url::parse(&self.rpc_url)?;
         let provider = ProviderBuilder::new()
             .with_recommended_fillers()
             .wallet(wallet)
-            .on_http(url);
+            .on_client(build_client(&self.rpc_url, &self.rpc_options)?);
 
         // Create contract instance
         let oracle = IDeRiskOracle::new(self.oracle_address, &provider);
 
-        println!("\n📤 Preparing transaction...");
-        println!("  - Journal size: {} bytes", journal.len());
-        println!("  - Seal size: {} bytes", seal.len());
+        status!("\n📤 Preparing transaction...");
+        status!("  - Journal size: {} bytes", journal.len());
+        status!("  - Seal size: {} bytes", seal.len());
 
         // Call updateScore
         let tx = oracle
             .updateScore(
                 self.protocol_address,
-                Bytes::from(journal),
-                Bytes::from(seal),
+                Bytes::copy_from_slice(journal),
+                Bytes::copy_from_slice(seal),
             )
             .send()
             .await?;
 
-        println!("⏳ Transaction sent, waiting for confirmation...");
-        
-        let receipt = tx.get_receipt().await?;
-        let tx_hash = receipt.transaction_hash;
-
-        println!("✓ Transaction confirmed!");
-        println!("  - Block: {}", receipt.block_number.unwrap_or_default());
-        println!("  - Gas used: {}", receipt.gas_used);
-
-        Ok(tx_hash)
+        Ok(*tx.tx_hash())
     }
 
-    /// Read the current safety score from the oracle
-    pub async fn get_current_score(&self) -> Result<u64> {
-        let url = Url::parse(&self.rpc_url)?;
-        let provider = ProviderBuilder::new().on_http(url);
+    async fn status(&self, tx_hash: TxHash) -> Result<TxStatus> {
+        lookup_tx(&self.rpc_url, &self.rpc_options, tx_hash).await
+    }
 
-        let oracle = IDeRiskOracle::new(self.oracle_address, &provider);
-        
-        let score = oracle.safetyScores(self.protocol_address).call().await?._0;
-        
-        Ok(score.try_into()?)
+    async fn wait_for_confirmation(&self, tx_hash: TxHash) -> Result<u64> {
+        loop {
+            match self.status(tx_hash).await? {
+                TxStatus::Confirmed { block } => return Ok(block),
+                TxStatus::Pending => tokio::time::sleep(RECEIPT_POLL_INTERVAL).await,
+                TxStatus::Unknown => {
+                    return Err(eyre!("Transaction {} was dropped before confirmation", tx_hash));
+                }
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock_rpc::mock_rpc;
+
+    #[test]
+    fn test_describe_revert() {
+        use alloy::sol_types::{Revert, SolError};
+
+        let data = Revert::from("Proof verification failed: Unknown error").abi_encode();
+        assert!(describe_revert(&data).contains("Proof verification failed: Unknown error"));
+
+        assert_eq!(describe_revert(&[0xde, 0xad, 0xbe, 0xef, 0x00]), "custom error 0xdeadbeef");
+        assert_eq!(describe_revert(&[]), "reverted without a reason");
+    }
+
+    #[test]
+    fn test_score_in_range() {
+        assert_eq!(score_to_u64(U256::from(985_000u64)), Ok(985_000));
+        assert_eq!(score_to_u64(U256::from(u64::MAX)), Ok(u64::MAX));
+
+        // An ABI word is big-endian: the value sits in the last 8 bytes
+        let mut word = [0u8; 32];
+        word[24..].copy_from_slice(&985_000u64.to_be_bytes());
+        assert_eq!(score_to_u64(U256::from_be_bytes(word)), Ok(985_000));
+    }
+
+    #[test]
+    fn test_score_overflow_is_an_error() {
+        let just_over = U256::from(u64::MAX) + U256::from(1u64);
+        assert_eq!(score_to_u64(just_over), Err(ScoreOverflow { score: just_over }));
+        assert!(score_to_u64(U256::MAX).is_err());
+
+        // Truncating would have read this as 0
+        let high_bits_only = U256::from(1u64) << 64;
+        assert!(score_to_u64(high_bits_only).is_err());
+    }
+
+    fn submitter(rpc_url: String) -> OracleSubmitter {
+        OracleSubmitter::new(
+            rpc_url,
+            "0x0000000000000000000000000000000000000000000000000000000000000001".parse().unwrap(),
+            "0x0000000000000000000000000000000000000001".parse().unwrap(),
+            "0x0000000000000000000000000000000000000002".parse().unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_wrong_chain_submission_refused() {
+        // A Sepolia endpoint, given a mainnet proof
+        let (url, methods) = mock_rpc(serde_json::json!("0xaa36a7")).await;
+        let submitter = submitter(url).with_expected_chain_id(1);
+
+        let err = submitter.send(&[0; 4], &[0; 4]).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ChainIdMismatch>(),
+            Some(&ChainIdMismatch { expected: 1, actual: 11_155_111 })
+        );
+        assert!(submitter.simulate(&[0; 4], &[0; 4]).await.is_err());
+
+        // Nothing besides the chain id was asked for, so nothing was signed or sent
+        assert!(methods.lock().unwrap().iter().all(|method| method == "eth_chainId"));
+    }
+
+    #[tokio::test]
+    async fn test_matching_chain_passes_check() {
+        let (url, _) = mock_rpc(serde_json::json!("0x1")).await;
+        assert!(submitter(url.clone()).with_expected_chain_id(1).check_chain_id().await.is_ok());
+        assert!(submitter(url).check_chain_id().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_image_id_mismatch_aborts_submission() {
+        let onchain = [1u32, 2, 3, 4, 5, 6, 7, 8];
+        let (url, methods) = mock_rpc(serde_json::json!(image_id_hex(&onchain))).await;
+        let submitter = submitter(url).with_expected_image_id([9; 8]);
+
+        let err = submitter.send(&[0; 4], &[0; 4]).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ImageIdMismatch>(),
+            Some(&ImageIdMismatch { expected: [9; 8], onchain })
+        );
+        // Only the getter was called; nothing was signed or sent
+        assert_eq!(*methods.lock().unwrap(), vec!["eth_call".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_image_id_check() {
+        let id = [1u32, 2, 3, 4, 5, 6, 7, 8];
+        let (url, methods) = mock_rpc(serde_json::json!(image_id_hex(&id))).await;
+        let matching = submitter(url).with_expected_image_id(id);
+        assert_eq!(matching.onchain_image_id().await.unwrap(), Some(id));
+        assert!(matching.check_image_id(&[0; 4], &[0; 4]).await.is_ok());
+        assert_eq!(methods.lock().unwrap().len(), 2);
+
+        // No getter: falls back to simulating updateScore, which succeeds here
+        let (url, methods) = mock_rpc(serde_json::json!("0x")).await;
+        let without_getter = submitter(url).with_expected_image_id(id);
+        assert_eq!(without_getter.onchain_image_id().await.unwrap(), None);
+        methods.lock().unwrap().clear();
+        assert!(without_getter.check_image_id(&[0; 4], &[0; 4]).await.is_ok());
+        assert_eq!(*methods.lock().unwrap(), vec!["eth_call".to_string(), "eth_call".to_string()]);
+    }
+
+    #[tokio::test]
+    #[ignore] // Run with: cargo test -- --ignored --nocapture
+    async fn test_simulate_submission() {
+        // Requires a deployed oracle and a saved proof, e.g.
+        // ETH_RPC_URL=... ORACLE_ADDRESS=0x... PROOF_DIR=./sepolia_output \
+        //   cargo test test_simulate_submission -- --ignored --nocapture
+        let proof_dir = std::env::var("PROOF_DIR").unwrap_or_else(|_| "./output".to_string());
+        let journal = std::fs::read(format!("{}/proof_journal.bin", proof_dir)).unwrap();
+        let seal = crate::seal::load_seal(&proof_dir).unwrap();
+
+        let submitter = OracleSubmitter::new(
+            std::env::var("ETH_RPC_URL").unwrap(),
+            "0x0000000000000000000000000000000000000000000000000000000000000001".parse().unwrap(),
+            std::env::var("ORACLE_ADDRESS").unwrap().parse().unwrap(),
+            "0x0000000000000000000000000000000000000000".parse().unwrap(),
+        );
+
+        match submitter.simulate(&journal, &seal).await {
+            Ok(()) => status!("Oracle would accept the proof"),
+            Err(e) => status!("{}", e),
+        }
+    }
 
     #[tokio::test]
     #[ignore] // Run with: cargo test -- --ignored --nocapture
@@ -123,7 +521,7 @@ mod tests {
         // This test requires a deployed oracle contract
         let submitter = OracleSubmitter::new(
             std::env::var("ETH_RPC_URL").unwrap(),
-            "0x0000000000000000000000000000000000000000000000000000000000000001".to_string(),
+            "0x0000000000000000000000000000000000000000000000000000000000000001".parse().unwrap(),
             "0x0000000000000000000000000000000000000000".parse().unwrap(),
             "0x0000000000000000000000000000000000000000".parse().unwrap(),
         );
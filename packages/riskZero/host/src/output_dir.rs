@@ -0,0 +1,125 @@
+// Output Directory Templates
+// A plain --output-dir is reused by every run, so each run overwrites the
+// last one's artifacts. A template such as ./runs/{network}/{block}-{timestamp}
+// expands into a directory of its own per run instead.
+
+use eyre::{Result, eyre};
+
+/// Placeholders an output directory template may use
+pub const PLACEHOLDERS: [&str; 4] = ["{network}", "{block}", "{timestamp}", "{protocol}"];
+
+/// Values the placeholders expand to for one run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunContext {
+    pub network: String,
+    /// Pinned block, or None for "latest"
+    pub block: Option<u64>,
+    /// Snapshot timestamp, or the run's start time when none is pinned
+    pub timestamp: u64,
+    pub protocol: String,
+}
+
+/// Whether `template` contains any placeholder
+pub fn is_template(template: &str) -> bool {
+    PLACEHOLDERS.iter().any(|placeholder| template.contains(placeholder))
+}
+
+/// Substitute the run's values into `template`
+/// Braces that aren't a known placeholder are rejected rather than kept,
+/// so a typo like {netwrk} doesn't silently become part of the path
+pub fn expand_template(template: &str, context: &RunContext) -> Result<String> {
+    let block = context.block.map_or_else(|| "latest".to_string(), |block| block.to_string());
+    let expanded = template
+        .replace("{network}", &context.network)
+        .replace("{block}", &block)
+        .replace("{timestamp}", &context.timestamp.to_string())
+        .replace("{protocol}", &context.protocol);
+
+    if let Some(start) = expanded.find('{') {
+        let end = expanded[start..].find('}').map_or(expanded.len(), |end| start + end + 1);
+        return Err(eyre!(
+            "Unknown placeholder {} in --output-dir (supported: {})",
+            &expanded[start..end],
+            PLACEHOLDERS.join(", ")
+        ));
+    }
+    Ok(expanded)
+}
+
+/// Expand `template` into a fresh directory for this run and create it
+/// A plain path is returned as is, keeping the shared directory behavior.
+/// When the expanded directory already exists (e.g. two runs in the same
+/// second), a -1, -2, ... suffix is added so the runs don't collide
+pub fn create_run_dir(template: &str, context: &RunContext) -> Result<String> {
+    if !is_template(template) {
+        return Ok(template.to_string());
+    }
+
+    let base = expand_template(template, context)?;
+    let mut dir = base.clone();
+    let mut attempt = 0;
+    loop {
+        // create_dir fails on an existing directory, so claiming a name is
+        // atomic even with concurrent runs
+        if let Some(parent) = std::path::Path::new(&dir).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                attempt += 1;
+                dir = format!("{}-{}", base, attempt);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> RunContext {
+        RunContext {
+            network: "mainnet".to_string(),
+            block: Some(21_000_000),
+            timestamp: 1_730_000_000,
+            protocol: "aave-v3".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_expand_all_placeholders() {
+        let expanded = expand_template("./runs/{protocol}/{network}/{block}-{timestamp}", &context()).unwrap();
+        assert_eq!(expanded, "./runs/aave-v3/mainnet/21000000-1730000000");
+
+        let latest = RunContext { block: None, ..context() };
+        assert_eq!(expand_template("out/{block}", &latest).unwrap(), "out/latest");
+    }
+
+    #[test]
+    fn test_plain_path_is_untouched() {
+        assert!(!is_template("./output"));
+        assert_eq!(create_run_dir("./output", &context()).unwrap(), "./output");
+    }
+
+    #[test]
+    fn test_unknown_placeholder_rejected() {
+        let err = expand_template("./runs/{netwrk}", &context()).unwrap_err();
+        assert!(err.to_string().contains("{netwrk}"));
+    }
+
+    #[test]
+    fn test_repeated_runs_do_not_collide() {
+        let root = std::env::temp_dir().join("derisk_output_dir_test");
+        std::fs::remove_dir_all(&root).ok();
+        let template = format!("{}/{{network}}/{{timestamp}}", root.display());
+
+        let first = create_run_dir(&template, &context()).unwrap();
+        let second = create_run_dir(&template, &context()).unwrap();
+        std::fs::remove_dir_all(&root).ok();
+
+        assert_eq!(first, format!("{}/mainnet/1730000000", root.display()));
+        assert_eq!(second, format!("{}-1", first));
+    }
+}
@@ -0,0 +1,241 @@
+// Portfolio Aggregation
+// Scores several protocols side by side and combines them into one solvency
+// view. Each protocol is fetched and executed independently (no proof); the
+// combined score is the asset-weighted average of the per-protocol scores.
+
+use derisk_type::{AaveInput, SafetyScoreOutput, UsdAmount, SCORE_SCALE};
+use eyre::{Result, eyre};
+use serde::Serialize;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+use crate::aave_fetcher::{AaveAddresses, AaveFetcher};
+use crate::guest::execute_guest;
+use crate::rpc::RpcOptions;
+use crate::shutdown::ShutdownSignal;
+
+/// One protocol to score, parsed from `--protocol <network>[=<rpc_url>]`
+#[derive(Debug, Clone)]
+pub struct ProtocolTarget {
+    pub name: String,
+    pub addresses: AaveAddresses,
+    pub rpc_url: String,
+}
+
+impl ProtocolTarget {
+    /// Parse a target spec, falling back to `default_rpc_url` when none is given
+    pub fn parse(spec: &str, default_rpc_url: &str) -> Result<Self> {
+        let (name, rpc_url) = match spec.split_once('=') {
+            Some((name, rpc_url)) => (name, rpc_url),
+            None => (spec, default_rpc_url),
+        };
+        let addresses = AaveAddresses::for_network(name)
+            .ok_or_else(|| eyre!("Unknown protocol network '{}'. Use 'mainnet' or 'sepolia'", name))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            addresses,
+            rpc_url: rpc_url.to_string(),
+        })
+    }
+}
+
+/// A single protocol's scored output
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtocolScore {
+    pub name: String,
+    pub output: SafetyScoreOutput,
+}
+
+/// Combined report written to portfolio_report.json
+#[derive(Debug, Clone, Serialize)]
+pub struct PortfolioReport {
+    pub protocols: Vec<ProtocolScore>,
+    /// Protocols that could not be fetched or executed
+    pub failed: Vec<String>,
+    /// Asset-weighted average of the protocol scores (scaled by 1e4)
+    pub safety_score: u64,
+    /// Sum of protocol assets (USD, scaled by 1e8)
+    pub total_assets_usd: u128,
+    /// Sum of protocol liabilities (USD, scaled by 1e8)
+    pub total_liabilities_usd: u128,
+    /// Whether any protocol's totals, or the sums here, were clamped at u128::MAX
+    pub saturated: bool,
+}
+
+/// Combine per-protocol outputs into a portfolio score
+/// All outputs share the same scales (score 1e4, USD 1e8), so assets and
+/// liabilities add directly and the score is weighted by each protocol's assets
+pub fn aggregate(protocols: Vec<ProtocolScore>, failed: Vec<String>) -> PortfolioReport {
+    let mut saturated = false;
+    let mut total_assets_usd: u128 = 0;
+    let mut total_liabilities_usd: u128 = 0;
+    let mut weighted_score: u128 = 0;
+
+    for protocol in &protocols {
+        let output = &protocol.output;
+        saturated |= output.saturated;
+        total_assets_usd = derisk_type::saturating_sum(total_assets_usd, output.total_assets_usd, &mut saturated);
+        total_liabilities_usd = derisk_type::saturating_sum(total_liabilities_usd, output.total_liabilities_usd, &mut saturated);
+
+        let weighted = output.total_assets_usd.checked_mul(output.safety_score as u128).unwrap_or_else(|| {
+            saturated = true;
+            u128::MAX
+        });
+        weighted_score = derisk_type::saturating_sum(weighted_score, weighted, &mut saturated);
+    }
+
+    let safety_score = if total_assets_usd == 0 {
+        0
+    } else {
+        (weighted_score / total_assets_usd).min(SCORE_SCALE as u128) as u64
+    };
+
+    PortfolioReport {
+        protocols,
+        failed,
+        safety_score,
+        total_assets_usd,
+        total_liabilities_usd,
+        saturated,
+    }
+}
+
+/// Fetch and execute every target concurrently
+/// Guest execution is CPU-bound, so it runs on the blocking pool rather than
+/// stalling the other protocols' RPC fetches
+pub async fn score_protocols(
+    targets: Vec<ProtocolTarget>,
+    per_reserve_timeout: Duration,
+    rpc_options: RpcOptions,
+    segment_limit_po2: Option<u32>,
+    shutdown: &ShutdownSignal,
+) -> PortfolioReport {
+    let mut tasks = JoinSet::new();
+    for (index, target) in targets.into_iter().enumerate() {
+        let shutdown = shutdown.clone();
+        tasks.spawn(async move {
+            let result = score_protocol(&target, per_reserve_timeout, rpc_options, segment_limit_po2, shutdown).await;
+            (index, target.name, result)
+        });
+    }
+
+    let mut results = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok(result) => results.push(result),
+            Err(e) => status!("⚠ Protocol task panicked: {}", e),
+        }
+    }
+    // Report in the order the protocols were given, not completion order
+    results.sort_by_key(|(index, _, _)| *index);
+
+    let mut protocols = Vec::new();
+    let mut failed = Vec::new();
+    for (_, name, result) in results {
+        match result {
+            Ok(output) => protocols.push(ProtocolScore { name, output }),
+            Err(e) => {
+                status!("⚠ {} failed: {}", name, e);
+                failed.push(name);
+            }
+        }
+    }
+
+    aggregate(protocols, failed)
+}
+
+/// Fetch one protocol and execute the guest on it
+async fn score_protocol(
+    target: &ProtocolTarget,
+    per_reserve_timeout: Duration,
+    rpc_options: RpcOptions,
+    segment_limit_po2: Option<u32>,
+    shutdown: ShutdownSignal,
+) -> Result<SafetyScoreOutput> {
+    let fetcher = AaveFetcher::new(target.addresses.clone(), target.rpc_url.clone())
+        .with_rpc_options(rpc_options)
+        .with_shutdown(shutdown.clone())
+        .with_per_reserve_timeout(per_reserve_timeout);
+    let input: AaveInput = fetcher.fetch_reserves().await?.input;
+
+    // A fetch cut short by Ctrl-C is partial - never score it
+    if shutdown.is_requested() {
+        return Err(eyre!("interrupted"));
+    }
+
+    let execution = tokio::task::spawn_blocking(move || execute_guest(&input, segment_limit_po2)).await??;
+    Ok(execution.output)
+}
+
+impl PortfolioReport {
+    /// Print the report in the host's summary style
+    pub fn print(&self) {
+        status!("\n📊 Portfolio Summary:");
+        for protocol in &self.protocols {
+            status!("  - {}: {:.4}% ({} assets, {} liabilities)",
+                protocol.name,
+                protocol.output.to_percentage(),
+                UsdAmount(protocol.output.total_assets_usd),
+                UsdAmount(protocol.output.total_liabilities_usd));
+        }
+        for name in &self.failed {
+            status!("  - {}: failed", name);
+        }
+        status!("\n  Portfolio Score: {:.4}%", self.safety_score as f64 / 1e4);
+        status!("  Total Assets: {}", UsdAmount(self.total_assets_usd));
+        status!("  Total Liabilities: {}", UsdAmount(self.total_liabilities_usd));
+        if self.saturated {
+            status!("  ⚠ Totals overflowed u128 and were clamped (values are lower bounds)");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn protocol(name: &str, score: u64, assets: u128, liabilities: u128) -> ProtocolScore {
+        ProtocolScore {
+            name: name.to_string(),
+            output: SafetyScoreOutput::new(score, assets, liabilities, 1234567890),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_weights_by_assets() {
+        let report = aggregate(
+            vec![
+                // $1,000 assets at 60%, $3,000 assets at 90%
+                protocol("mainnet", 600_000, 1_000 * 100_000_000, 400 * 100_000_000),
+                protocol("sepolia", 900_000, 3_000 * 100_000_000, 300 * 100_000_000),
+            ],
+            vec![],
+        );
+
+        // (60% * 1000 + 90% * 3000) / 4000 = 82.5%
+        assert_eq!(report.safety_score, 825_000);
+        assert_eq!(report.total_assets_usd, 4_000 * 100_000_000);
+        assert_eq!(report.total_liabilities_usd, 700 * 100_000_000);
+        assert!(!report.saturated);
+    }
+
+    #[test]
+    fn test_aggregate_empty_portfolio() {
+        let report = aggregate(vec![], vec!["mainnet".to_string()]);
+        assert_eq!(report.safety_score, 0);
+        assert_eq!(report.failed, vec!["mainnet".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_target() {
+        let target = ProtocolTarget::parse("sepolia=https://rpc.sepolia.org", "https://default").unwrap();
+        assert_eq!(target.name, "sepolia");
+        assert_eq!(target.rpc_url, "https://rpc.sepolia.org");
+
+        let target = ProtocolTarget::parse("mainnet", "https://default").unwrap();
+        assert_eq!(target.rpc_url, "https://default");
+
+        assert!(ProtocolTarget::parse("polygon", "https://default").is_err());
+    }
+}
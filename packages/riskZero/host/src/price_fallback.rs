@@ -0,0 +1,143 @@
+// Price Fallback
+// A reverting getAssetPrice (e.g. while a feed is being migrated) would skip
+// the whole reserve and can drop a large position from the score. Before
+// giving up, the fetcher asks the network's fallback_oracle, if configured,
+// then a static price file given with --fallback-prices, mapping token
+// address → price in the oracle's base currency units, e.g.
+// { "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48": 100000000 }
+// Only when every source fails is the reserve skipped.
+
+use alloy::primitives::{Address, U256};
+use eyre::{Result, eyre};
+use std::collections::HashMap;
+use std::future::Future;
+
+/// Static prices per token address, in the oracle's base currency units
+pub type PriceTable = HashMap<Address, u128>;
+
+/// Load a fallback price file
+pub fn load_fallback_prices(path: &str) -> Result<PriceTable> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| eyre!("Failed to read fallback prices {}: {}", path, e))?;
+    parse_fallback_prices(&json).map_err(|e| eyre!("Invalid fallback prices {}: {}", path, e))
+}
+
+fn parse_fallback_prices(json: &str) -> Result<PriceTable> {
+    let table: HashMap<String, u128> = serde_json::from_str(json)?;
+    table
+        .into_iter()
+        .map(|(address, price)| {
+            let parsed = address.parse::<Address>().map_err(|_| eyre!("'{}' is not an address", address))?;
+            if price == 0 {
+                return Err(eyre!("price for {} must not be zero", address));
+            }
+            Ok((parsed, price))
+        })
+        .collect()
+}
+
+/// Price from the first source that answers: the primary oracle, the
+/// fallback oracle, then the static table. `query` asks one oracle for the
+/// asset's price; the primary oracle's error is returned if all fail
+pub async fn price_with_fallback<F, Fut>(
+    asset: Address,
+    primary: Address,
+    fallback_oracle: Option<Address>,
+    static_prices: &PriceTable,
+    mut query: F,
+) -> Result<U256>
+where
+    F: FnMut(Address) -> Fut,
+    Fut: Future<Output = Result<U256>>,
+{
+    let primary_error = match query(primary).await {
+        Ok(price) => return Ok(price),
+        Err(e) => e,
+    };
+
+    if let Some(oracle) = fallback_oracle {
+        match query(oracle).await {
+            Ok(price) => {
+                status!("  ⚠ Primary oracle failed ({}), priced by fallback oracle {}", primary_error, oracle);
+                return Ok(price);
+            }
+            Err(e) => status!("  ⚠ Fallback oracle {} failed too: {}", oracle, e),
+        }
+    }
+
+    if let Some(&price) = static_prices.get(&asset) {
+        status!("  ⚠ Primary oracle failed ({}), using the static fallback price", primary_error);
+        return Ok(U256::from(price));
+    }
+    Err(primary_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+    #[tokio::test]
+    async fn test_reverting_primary_falls_back_to_price_map() {
+        let usdc: Address = USDC.parse().unwrap();
+        let primary = Address::with_last_byte(1);
+        let prices = parse_fallback_prices(&format!(r#"{{ "{}": 100000000 }}"#, USDC)).unwrap();
+        let asked = RefCell::new(Vec::new());
+
+        let price = price_with_fallback(usdc, primary, None, &prices, |oracle| {
+            asked.borrow_mut().push(oracle);
+            async { Err::<U256, _>(eyre!("execution reverted")) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(price, U256::from(100_000_000u64));
+        assert_eq!(*asked.borrow(), vec![primary]);
+    }
+
+    #[tokio::test]
+    async fn test_sources_are_tried_in_order() {
+        let usdc: Address = USDC.parse().unwrap();
+        let (primary, fallback) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let prices = PriceTable::from([(usdc, 99_000_000)]);
+
+        // A healthy primary is the only oracle asked
+        let asked = RefCell::new(Vec::new());
+        let price = price_with_fallback(usdc, primary, Some(fallback), &prices, |oracle| {
+            asked.borrow_mut().push(oracle);
+            async { Ok(U256::from(100_000_000u64)) }
+        })
+        .await;
+        assert_eq!(price.unwrap(), U256::from(100_000_000u64));
+        assert_eq!(*asked.borrow(), vec![primary]);
+
+        // The fallback oracle wins over the static table
+        let price = price_with_fallback(usdc, primary, Some(fallback), &prices, |oracle| async move {
+            if oracle == primary {
+                Err(eyre!("execution reverted"))
+            } else {
+                Ok(U256::from(100_010_000u64))
+            }
+        })
+        .await;
+        assert_eq!(price.unwrap(), U256::from(100_010_000u64));
+
+        // Nothing answers for an asset missing from the table: the primary's error
+        let other = Address::with_last_byte(9);
+        let err = price_with_fallback(other, primary, Some(fallback), &prices, |oracle| async move {
+            Err::<U256, _>(eyre!("oracle {} reverted", oracle))
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains(&primary.to_string()), "{}", err);
+    }
+
+    #[test]
+    fn test_bad_price_files_are_rejected() {
+        assert!(parse_fallback_prices(r#"{ "usdc": 100000000 }"#).is_err());
+        assert!(parse_fallback_prices(&format!(r#"{{ "{}": 0 }}"#, USDC)).is_err());
+        assert!(parse_fallback_prices(&format!(r#"{{ "{}": -1 }}"#, USDC)).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+// Prover Selection
+// default_prover() picks an implementation from RISC0_PROVER and the enabled
+// features, which makes it hard to tell what a run actually used. --prover
+// names it explicitly:
+//   cpu    - local prover on the CPU; always available
+//   cuda   - local prover on an NVIDIA GPU; build with `--features cuda`
+//   bonsai - remote Bonsai proving; build with `--features bonsai` and set
+//            BONSAI_API_KEY and BONSAI_API_URL
+// Without --prover, default_prover() still decides.
+
+use eyre::{Result, eyre};
+use risc0_zkvm::{default_prover, LocalProver, Prover};
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// Env vars Bonsai reads its credentials from
+pub const BONSAI_ENV_VARS: [&str; 2] = ["BONSAI_API_KEY", "BONSAI_API_URL"];
+
+/// Prover named by --prover
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProverKind {
+    Cpu,
+    Cuda,
+    Bonsai,
+}
+
+impl FromStr for ProverKind {
+    type Err = eyre::Report;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "cpu" => Ok(Self::Cpu),
+            "cuda" => Ok(Self::Cuda),
+            "bonsai" => Ok(Self::Bonsai),
+            _ => Err(eyre!("Unknown prover '{}' (expected cpu, cuda or bonsai)", name)),
+        }
+    }
+}
+
+impl std::fmt::Display for ProverKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Cpu => "cpu",
+            Self::Cuda => "cuda",
+            Self::Bonsai => "bonsai",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Build the prover for `kind`, or the default one when none is named
+/// Fails when the selection needs a feature this binary was built without,
+/// or Bonsai credentials that aren't set
+pub fn select_prover(kind: Option<ProverKind>) -> Result<Rc<dyn Prover>> {
+    match kind {
+        None => Ok(default_prover()),
+        Some(ProverKind::Cpu) => {
+            if cfg!(feature = "cuda") {
+                // The local prover picks the GPU whenever it is compiled in
+                return Err(eyre!("--prover cpu needs a build without the cuda feature"));
+            }
+            Ok(Rc::new(LocalProver::new("cpu")))
+        }
+        Some(ProverKind::Cuda) => {
+            if !cfg!(feature = "cuda") {
+                return Err(eyre!("--prover cuda needs the host built with `--features cuda`"));
+            }
+            Ok(Rc::new(LocalProver::new("cuda")))
+        }
+        Some(ProverKind::Bonsai) => bonsai_prover(),
+    }
+}
+
+#[cfg(feature = "bonsai")]
+fn bonsai_prover() -> Result<Rc<dyn Prover>> {
+    check_bonsai_credentials(|name| std::env::var(name).ok())?;
+    Ok(Rc::new(risc0_zkvm::BonsaiProver::new("bonsai")))
+}
+
+#[cfg(not(feature = "bonsai"))]
+fn bonsai_prover() -> Result<Rc<dyn Prover>> {
+    Err(eyre!("--prover bonsai needs the host built with `--features bonsai`"))
+}
+
+/// Fail with the names of any unset or empty Bonsai credential vars
+#[cfg_attr(not(feature = "bonsai"), allow(dead_code))]
+fn check_bonsai_credentials(var: impl Fn(&str) -> Option<String>) -> Result<()> {
+    let missing: Vec<&str> = BONSAI_ENV_VARS
+        .iter()
+        .copied()
+        .filter(|name| var(name).is_none_or(|value| value.is_empty()))
+        .collect();
+    if !missing.is_empty() {
+        return Err(eyre!("--prover bonsai needs {} set", missing.join(" and ")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prover_names() {
+        assert_eq!("cpu".parse::<ProverKind>().unwrap(), ProverKind::Cpu);
+        assert_eq!("CUDA".parse::<ProverKind>().unwrap(), ProverKind::Cuda);
+        assert_eq!("bonsai".parse::<ProverKind>().unwrap(), ProverKind::Bonsai);
+        assert_eq!(ProverKind::Bonsai.to_string(), "bonsai");
+    }
+
+    #[test]
+    fn test_unknown_prover_rejected() {
+        let err = "metal".parse::<ProverKind>().unwrap_err();
+        assert!(err.to_string().contains("metal"));
+    }
+
+    #[test]
+    fn test_missing_bonsai_credentials_named() {
+        let err = check_bonsai_credentials(|name| (name == "BONSAI_API_URL").then(|| "https://api".to_string()))
+            .unwrap_err();
+        assert!(err.to_string().contains("BONSAI_API_KEY"));
+        assert!(!err.to_string().contains("BONSAI_API_URL"));
+
+        assert!(check_bonsai_credentials(|_| Some("set".to_string())).is_ok());
+    }
+}
@@ -0,0 +1,115 @@
+// Receipt Comparison
+// Wrapping the STARK receipt in Groth16 must not change what it commits to.
+// Both receipts are verified on their own and their journals compared, byte
+// for byte and as decoded outputs, so a wrapping bug can't slip a different
+// result on-chain.
+
+use derisk_type::SafetyScoreOutput;
+use eyre::{Result, eyre};
+use risc0_zkvm::{sha::Digest, Journal, Receipt};
+
+/// Verify both receipts against `image_id` and check they commit the same output
+/// Returns the (shared) decoded output
+pub fn verify_and_compare(
+    stark: &Receipt,
+    groth16: &Receipt,
+    image_id: impl Into<Digest>,
+) -> Result<SafetyScoreOutput> {
+    let image_id = image_id.into();
+
+    stark
+        .verify(image_id)
+        .map_err(|e| eyre!("STARK receipt failed verification: {}", e))?;
+    groth16
+        .inner
+        .groth16()
+        .map_err(|_| eyre!("Expected a Groth16 receipt, got a different receipt kind"))?;
+    groth16
+        .verify(image_id)
+        .map_err(|e| eyre!("Groth16 receipt failed verification: {}", e))?;
+
+    compare_journals(&stark.journal, &groth16.journal)
+}
+
+/// Check two journals commit the same output, failing loudly if not
+pub fn compare_journals(stark: &Journal, groth16: &Journal) -> Result<SafetyScoreOutput> {
+    let stark_output: SafetyScoreOutput = stark
+        .decode()
+        .map_err(|e| eyre!("Failed to decode STARK journal: {}", e))?;
+    let groth16_output: SafetyScoreOutput = groth16
+        .decode()
+        .map_err(|e| eyre!("Failed to decode Groth16 journal: {}", e))?;
+
+    if stark_output != groth16_output {
+        return Err(eyre!(
+            "Groth16 conversion changed the committed output\n  STARK:   {}\n  Groth16: {}",
+            serde_json::to_string(&stark_output)?,
+            serde_json::to_string(&groth16_output)?
+        ));
+    }
+    // Equal outputs can still come from different bytes (e.g. trailing data
+    // the decoder ignores); the verifier hashes the bytes, so they must match too
+    if stark.bytes != groth16.bytes {
+        return Err(eyre!(
+            "Groth16 journal bytes differ from the STARK journal ({} vs {} bytes) despite decoding equal",
+            groth16.bytes.len(),
+            stark.bytes.len()
+        ));
+    }
+
+    Ok(stark_output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal(output: &SafetyScoreOutput) -> Journal {
+        let words = risc0_zkvm::serde::to_vec(output).unwrap();
+        Journal::new(words.iter().flat_map(|word| word.to_le_bytes()).collect())
+    }
+
+    #[test]
+    fn test_identical_journals_pass() {
+        let output = SafetyScoreOutput::new(464_285, 1_000, 536, 1234567890).with_skipped_reserves(2);
+        let compared = compare_journals(&journal(&output), &journal(&output)).unwrap();
+        assert_eq!(compared, output);
+    }
+
+    #[test]
+    fn test_altered_value_fails() {
+        let stark = SafetyScoreOutput::new(464_285, 1_000, 536, 1234567890);
+        let groth16 = SafetyScoreOutput::new(464_285, 1_000, 535, 1234567890);
+
+        let err = compare_journals(&journal(&stark), &journal(&groth16)).unwrap_err();
+        assert!(err.to_string().contains("changed the committed output"));
+    }
+
+    #[test]
+    fn test_trailing_bytes_fail() {
+        let output = SafetyScoreOutput::new(464_285, 1_000, 536, 1234567890);
+        let mut padded = journal(&output);
+        padded.bytes.extend_from_slice(&[0; 4]);
+
+        assert!(compare_journals(&journal(&output), &padded).is_err());
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored --nocapture
+    fn test_compare_real_receipts() {
+        // Requires both receipts from a full run, e.g.
+        // PROOF_DIR=./output cargo test test_compare_real_receipts -- --ignored --nocapture
+        let proof_dir = std::env::var("PROOF_DIR").unwrap_or_else(|_| "./output".to_string());
+        let load = |name: &str| -> Receipt {
+            crate::compression::load_receipt(&format!("{}/{}", proof_dir, name)).unwrap()
+        };
+
+        let output = verify_and_compare(
+            &load("stark_receipt.bin"),
+            &load("proof_receipt.bin"),
+            methods::AAVE_ID,
+        )
+        .unwrap();
+        status!("Receipts agree: score {}", output.safety_score);
+    }
+}
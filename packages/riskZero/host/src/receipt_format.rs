@@ -0,0 +1,116 @@
+// Receipt Serialization
+// Receipts are saved with bincode, whose encoding of risc0's types can break
+// when risc0 is upgraded: serializing can fail, or write bytes that no longer
+// decode, and either would abort the run after the expensive proof had
+// already succeeded. bincode output is checked to decode back, and if it
+// doesn't the receipt is saved as JSON instead, with a warning. The format is
+// recorded in the proof manifest, and loading accepts either.
+
+use eyre::{Result, eyre};
+use risc0_zkvm::Receipt;
+use serde::{Deserialize, Serialize};
+
+/// How a saved receipt is encoded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReceiptFormat {
+    /// bincode, compact and what every earlier run wrote
+    #[default]
+    Bincode,
+    /// serde_json, several times larger but independent of bincode's layout
+    Json,
+}
+
+impl std::fmt::Display for ReceiptFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Bincode => "bincode",
+            Self::Json => "json",
+        })
+    }
+}
+
+impl ReceiptFormat {
+    /// The format saved bytes are in: a JSON receipt is an object, while
+    /// bincode starts with the receipt kind's index (a small integer)
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.first() == Some(&b'{') {
+            Self::Json
+        } else {
+            Self::Bincode
+        }
+    }
+
+    /// Encode `receipt` in this format
+    /// bincode output is decoded again, so bytes that wouldn't load back fail here
+    pub fn serialize(self, receipt: &Receipt) -> Result<Vec<u8>> {
+        match self {
+            Self::Bincode => {
+                let bytes = bincode::serialize(receipt)?;
+                bincode::deserialize::<Receipt>(&bytes)
+                    .map_err(|e| eyre!("bincode receipt doesn't decode back: {}", e))?;
+                Ok(bytes)
+            }
+            Self::Json => Ok(serde_json::to_vec(receipt)?),
+        }
+    }
+
+    /// Decode a receipt saved in this format
+    pub fn deserialize(self, bytes: &[u8]) -> Result<Receipt> {
+        match self {
+            Self::Bincode => Ok(bincode::deserialize(bytes)?),
+            Self::Json => Ok(serde_json::from_slice(bytes)?),
+        }
+    }
+}
+
+/// Serialize a receipt for saving, with bincode or else JSON
+/// Only fails if neither works; the format used is returned for the manifest
+pub fn serialize_receipt(receipt: &Receipt) -> Result<(Vec<u8>, ReceiptFormat)> {
+    match ReceiptFormat::Bincode.serialize(receipt) {
+        Ok(bytes) => Ok((bytes, ReceiptFormat::Bincode)),
+        Err(e) => {
+            status!("⚠ Couldn't save the receipt with bincode ({}); saving it as JSON instead", e);
+            let bytes = ReceiptFormat::Json
+                .serialize(receipt)
+                .map_err(|json_err| eyre!("Failed to serialize the receipt: bincode: {}; JSON: {}", e, json_err))?;
+            Ok((bytes, ReceiptFormat::Json))
+        }
+    }
+}
+
+/// Load a receipt saved by `serialize_receipt`, in whichever format it is
+pub fn deserialize_receipt(bytes: &[u8]) -> Result<Receipt> {
+    ReceiptFormat::detect(bytes).deserialize(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use risc0_zkvm::{sha::Digest, Groth16Receipt, InnerReceipt, ReceiptClaim};
+
+    fn receipt() -> Receipt {
+        let claim = ReceiptClaim::ok(Digest::ZERO, vec![7u8; 64]);
+        let inner = Groth16Receipt::new(vec![3u8; 256], claim.into(), Digest::ZERO);
+        Receipt::new(InnerReceipt::Groth16(inner), vec![7u8; 64])
+    }
+
+    #[test]
+    fn test_receipt_round_trips_in_either_format() {
+        let receipt = receipt();
+
+        // A healthy receipt is saved with bincode
+        let (bytes, format) = serialize_receipt(&receipt).unwrap();
+        assert_eq!(format, ReceiptFormat::Bincode);
+
+        for format in [ReceiptFormat::Bincode, ReceiptFormat::Json] {
+            let bytes = format.serialize(&receipt).unwrap();
+            assert_eq!(ReceiptFormat::detect(&bytes), format);
+            // Read back without being told the format, to the same receipt
+            let loaded = deserialize_receipt(&bytes).unwrap();
+            assert_eq!(loaded.journal.bytes, receipt.journal.bytes);
+            assert_eq!(format.serialize(&loaded).unwrap(), bytes);
+        }
+        assert_eq!(bytes, ReceiptFormat::Bincode.serialize(&receipt).unwrap());
+    }
+}
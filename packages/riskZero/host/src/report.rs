@@ -0,0 +1,215 @@
+// Result Report
+// How a computed output is presented: the text summary printed after
+// executing, proving or comparing receipts, and the JSON --output-stdout
+// prints. Rendering returns strings instead of printing so main only decides
+// where they go, and the formatting can be tested.
+
+use derisk_type::{share_bps, RoundingMode, SafetyScoreOutput, ScoreStatus};
+use eyre::Result;
+
+use crate::display_currency::{DisplayCurrency, DisplayedOutput};
+
+/// What a report needs besides the output itself
+#[derive(Debug, Clone, Default)]
+pub struct RunMeta {
+    /// Currency totals are shown in (USD unless --display-currency)
+    pub currency: DisplayCurrency,
+    /// Protocol the output was computed for, when known
+    pub protocol: Option<String>,
+}
+
+/// The human-readable result summary, one line per value
+pub fn render_summary(output: &SafetyScoreOutput, meta: &RunMeta) -> String {
+    let mut lines = Vec::new();
+    match &meta.protocol {
+        Some(protocol) => lines.push(format!("📊 Safety Score Result ({}):", protocol)),
+        None => lines.push("📊 Safety Score Result:".to_string()),
+    }
+    lines.push(format!("  - Safety Score: {:.4}%", output.to_percentage()));
+    lines.push(format!("  - Status: {}", status_label(output.status)));
+    if let Some(projected) = output.projected_score {
+        lines.push(format!("  - Projected Score: {:.4}% (after the input's horizon at current rates)", projected as f64 / 1e4));
+    }
+    if output.hypothetical {
+        lines.push("  ⚠ Hypothetical: computed over overridden prices, not the oracle's".to_string());
+    }
+    if let Some(base) = &output.denomination {
+        lines.push(format!("  ⚠ Values below are in base currency {} (scaled 1e8), not USD", base));
+    }
+    // The FX rate is per USD, so it can't convert another base currency
+    let usd = DisplayCurrency::default();
+    let currency = if output.denomination.is_some() { &usd } else { &meta.currency };
+    let percent = |bps: u32| format!("{}.{:02}%", bps / 100, bps % 100);
+    let asset_share = |value: u128| percent(share_bps(value, output.total_assets_usd, RoundingMode::Floor));
+    let liability_share = |value: u128| percent(share_bps(value, output.total_liabilities_usd, RoundingMode::Floor));
+    if output.collateral_only {
+        lines.push(format!("  - Total Assets: {} (collateral-enabled reserves only)", currency.format(output.total_assets_usd)));
+    } else {
+        lines.push(format!("  - Total Assets: {}", currency.format(output.total_assets_usd)));
+    }
+    lines.push(format!("  - Total Liabilities: {}", currency.format(output.total_liabilities_usd)));
+    lines.push(format!("    (stable {}, variable {})",
+        currency.format(output.total_stable_liabilities_usd), currency.format(output.total_variable_liabilities_usd)));
+    lines.push(format!("  - Buffer: {}",
+        currency.format(output.total_assets_usd.saturating_sub(output.total_liabilities_usd))));
+    if output.backstop_usd > 0 {
+        lines.push(format!("  - Backstop: {} (counted as assets in the score)", currency.format(output.backstop_usd)));
+    }
+    lines.push(match output.weighted_health_factor {
+        Some(u64::MAX) => "  - Weighted Health Factor: ∞ (no debt)".to_string(),
+        Some(hf) => format!("  - Weighted Health Factor: {:.4}", hf as f64 / 1e4),
+        None => "  - Weighted Health Factor: unavailable".to_string(),
+    });
+    lines.push(format!("  - Isolation Mode Debt: {}", currency.format(output.isolation_mode_debt_usd)));
+    lines.push(format!("  - Skipped Dust Reserves: {}", output.skipped_reserves));
+    lines.push(format!("  - Excluded Reserves: {}", output.excluded_reserves));
+    if output.stale_reserve_count > 0 {
+        lines.push(format!("  ⚠ {} reserves haven't been updated on-chain recently (stale indices)", output.stale_reserve_count));
+    }
+    if output.siloed_reserve_count > 0 {
+        lines.push(format!("  - Siloed Borrowing Reserves: {}", output.siloed_reserve_count));
+    }
+    if output.capped_reserves > 0 {
+        lines.push(format!("  ⚠ {} reserves are at or near their supply/borrow cap", output.capped_reserves));
+    }
+    if output.reserves_with_missing_price > 0 {
+        lines.push(format!("  ⚠ {} reserves had no price and are not in the score (score is incomplete)",
+            output.reserves_with_missing_price));
+    }
+    if output.reserves_included < output.reserves_total {
+        lines.push(format!("  ⚠ Data completeness: {} ({} of {} reserves in the score)",
+            percent(output.data_completeness_bps), output.reserves_included, output.reserves_total));
+    }
+    if !output.reserve_breakdown.is_empty() {
+        lines.push("  - Largest Reserves:".to_string());
+        for reserve in &output.reserve_breakdown {
+            lines.push(format!("      {} {} assets ({}), {} liabilities ({})",
+                reserve.token_address,
+                currency.format(reserve.assets_usd), asset_share(reserve.assets_usd),
+                currency.format(reserve.liabilities_usd), liability_share(reserve.liabilities_usd)));
+        }
+        if output.truncated {
+            lines.push(format!("      {} others: {} assets ({}), {} liabilities ({})",
+                output.omitted_count,
+                currency.format(output.other_reserves.assets_usd), asset_share(output.other_reserves.assets_usd),
+                currency.format(output.other_reserves.liabilities_usd), liability_share(output.other_reserves.liabilities_usd)));
+        }
+    }
+    if output.asset_concentration_bps > 0 {
+        lines.push(format!("  - Asset Concentration (HHI): {} bps", output.asset_concentration_bps));
+    }
+    if output.volatility_adjusted {
+        lines.push("  - Score is volatility-weighted".to_string());
+    }
+    if output.risk_weighted {
+        lines.push("  - Score uses custom risk weights".to_string());
+    }
+    if let Some(nonce) = output.nonce {
+        lines.push(format!("  - Nonce: {}", nonce));
+    }
+    if !output.guest_version.is_empty() {
+        lines.push(format!("  - Guest Version: {}", output.guest_version));
+    }
+    if output.clamped {
+        lines.push("  ⚠ Score was clamped into the input's governance bounds; the totals show the computed score".to_string());
+    }
+    if output.saturated {
+        lines.push("  ⚠ Totals overflowed u128 and were clamped (values are lower bounds)".to_string());
+    }
+    lines.join("\n")
+}
+
+/// How a score status reads in the summary
+fn status_label(status: ScoreStatus) -> &'static str {
+    match status {
+        ScoreStatus::Ok => "ok",
+        ScoreStatus::Insolvent => "insolvent (liabilities cover all assets)",
+        ScoreStatus::NoData => "no data (nothing to score; the score says nothing about solvency)",
+        ScoreStatus::Incomplete => "incomplete (some reserves are missing from the score)",
+    }
+}
+
+/// The output as --output-stdout prints it, with display totals when a
+/// display currency is set
+pub fn render_json(output: &SafetyScoreOutput, meta: &RunMeta) -> Result<String> {
+    let displayed = DisplayedOutput { output, display: meta.currency.totals(output) };
+    Ok(serde_json::to_string_pretty(&displayed)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derisk_type::{bound_breakdown, ReserveValue, USD_SCALE};
+
+    /// $1,000 of assets against $400 of debt over two reserves
+    fn fixed_output() -> SafetyScoreOutput {
+        let mut saturated = false;
+        let breakdown = bound_breakdown(
+            vec![
+                ReserveValue { token_address: "0xUSDC".to_string(), assets_usd: 600 * USD_SCALE, liabilities_usd: 300 * USD_SCALE },
+                ReserveValue { token_address: "0xWETH".to_string(), assets_usd: 400 * USD_SCALE, liabilities_usd: 100 * USD_SCALE },
+            ],
+            1,
+            &mut saturated,
+        );
+        SafetyScoreOutput::new(600_000, 1_000 * USD_SCALE, 400 * USD_SCALE, 1_700_000_000)
+            .with_weighted_health_factor(Some(20_000))
+            .with_skipped_reserves(2)
+            .with_breakdown(breakdown)
+            .with_debt_mix(100 * USD_SCALE, 300 * USD_SCALE)
+            .with_asset_concentration_bps(5_200)
+    }
+
+    #[test]
+    fn test_summary_snapshot() {
+        let meta = RunMeta { protocol: Some("Aave V3".to_string()), ..RunMeta::default() };
+        let expected = "\
+📊 Safety Score Result (Aave V3):
+  - Safety Score: 60.0000%
+  - Status: ok
+  - Total Assets: $1,000.00
+  - Total Liabilities: $400.00
+    (stable $100.00, variable $300.00)
+  - Buffer: $600.00
+  - Weighted Health Factor: 2.0000
+  - Isolation Mode Debt: $0.00
+  - Skipped Dust Reserves: 2
+  - Excluded Reserves: 0
+  - Largest Reserves:
+      0xUSDC $600.00 assets (60.00%), $300.00 liabilities (75.00%)
+      1 others: $400.00 assets (40.00%), $100.00 liabilities (25.00%)
+  - Asset Concentration (HHI): 5200 bps";
+        assert_eq!(render_summary(&fixed_output(), &meta), expected);
+    }
+
+    #[test]
+    fn test_summary_in_display_currency() {
+        let meta = RunMeta { currency: DisplayCurrency::new("EUR", "0.5").unwrap(), protocol: None };
+        let summary = render_summary(&fixed_output(), &meta);
+        assert!(summary.starts_with("📊 Safety Score Result:\n"));
+        assert!(summary.contains("  - Buffer: €300.00 ($600.00)"), "{}", summary);
+    }
+
+    #[test]
+    fn test_summary_shows_why_a_score_is_zero() {
+        let empty = SafetyScoreOutput::new(0, 0, 0, 1_700_000_000).with_status(ScoreStatus::NoData);
+        let summary = render_summary(&empty, &RunMeta::default());
+        assert!(summary.contains("  - Status: no data"), "{}", summary);
+
+        let underwater = SafetyScoreOutput::new(0, 1_000 * USD_SCALE, 1_200 * USD_SCALE, 1_700_000_000)
+            .with_status(ScoreStatus::Insolvent);
+        let summary = render_summary(&underwater, &RunMeta::default());
+        assert!(summary.contains("  - Status: insolvent"), "{}", summary);
+    }
+
+    #[test]
+    fn test_json_matches_output_in_usd() {
+        let output = fixed_output();
+        let json: serde_json::Value = serde_json::from_str(&render_json(&output, &RunMeta::default()).unwrap()).unwrap();
+        assert_eq!(json, serde_json::to_value(&output).unwrap());
+
+        let meta = RunMeta { currency: DisplayCurrency::new("EUR", "0.5").unwrap(), protocol: None };
+        let json: serde_json::Value = serde_json::from_str(&render_json(&output, &meta).unwrap()).unwrap();
+        assert_eq!(json["display"]["buffer"], 300 * USD_SCALE as u64);
+    }
+}
@@ -0,0 +1,79 @@
+// Reserve Sources
+// Where an input's reserve data comes from (--source). RPC reads every value
+// from the contracts at a block of our choosing; a subgraph answers in one
+// query, but is only as fresh as its indexing. Both produce the same
+// AaveInput, so nothing after the fetch depends on which one was used.
+
+use derisk_type::AaveInput;
+use eyre::{Result, eyre};
+use std::str::FromStr;
+
+use crate::aave_fetcher::AaveFetcher;
+use crate::subgraph_fetcher::SubgraphFetcher;
+
+/// Anything that can produce a snapshot of a protocol's reserves
+#[allow(async_fn_in_trait)]
+pub trait ReserveSource {
+    /// Fetch the reserves and package them as a guest input
+    async fn fetch(&self) -> Result<AaveInput>;
+}
+
+impl ReserveSource for AaveFetcher {
+    /// Reserves that failed are counted in the input's `failed_reserves`
+    async fn fetch(&self) -> Result<AaveInput> {
+        Ok(self.fetch_reserves().await?.input)
+    }
+}
+
+impl ReserveSource for SubgraphFetcher {
+    async fn fetch(&self) -> Result<AaveInput> {
+        self.fetch_reserves().await
+    }
+}
+
+/// Data source selected with --source
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SourceKind {
+    #[default]
+    Rpc,
+    Subgraph,
+}
+
+impl FromStr for SourceKind {
+    type Err = eyre::Report;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "rpc" => Ok(Self::Rpc),
+            "subgraph" => Ok(Self::Subgraph),
+            _ => Err(eyre!("Unknown source '{}' (expected rpc or subgraph)", name)),
+        }
+    }
+}
+
+impl std::fmt::Display for SourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Rpc => "rpc",
+            Self::Subgraph => "subgraph",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The source a run was configured with
+/// A trait with an async fn can't be a trait object, so the choice made at
+/// runtime is an enum instead
+pub enum Source {
+    Rpc(AaveFetcher),
+    Subgraph(SubgraphFetcher),
+}
+
+impl ReserveSource for Source {
+    async fn fetch(&self) -> Result<AaveInput> {
+        match self {
+            Source::Rpc(fetcher) => fetcher.fetch().await,
+            Source::Subgraph(fetcher) => fetcher.fetch().await,
+        }
+    }
+}
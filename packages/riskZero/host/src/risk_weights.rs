@@ -0,0 +1,41 @@
+// Custom Risk Weights
+// Risk teams often weight assets differently from Aave's on-chain parameters,
+// e.g. a haircut on a risky long-tail asset. They supply a JSON file mapping
+// token address → weight in basis points, e.g.
+// { "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48": 5000 }
+// A listed reserve's assets count at that share in the score; the rest in full.
+
+use derisk_type::check_weights;
+use eyre::{Result, eyre};
+use std::collections::BTreeMap;
+
+/// Load a weights file, normalizing addresses to lowercase
+pub fn load_weights(path: &str) -> Result<BTreeMap<String, u16>> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| eyre!("Failed to read weights file {}: {}", path, e))?;
+    parse_weights(&json).map_err(|e| eyre!("Invalid weights file {}: {}", path, e))
+}
+
+fn parse_weights(json: &str) -> Result<BTreeMap<String, u16>> {
+    let table: BTreeMap<String, u16> = serde_json::from_str(json)?;
+    let weights = table
+        .into_iter()
+        .map(|(address, bps)| (address.to_lowercase(), bps))
+        .collect();
+    check_weights(&weights).map_err(|e| eyre!("{}", e))?;
+    Ok(weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_normalizes_and_checks_weights() {
+        let weights = parse_weights(r#"{ "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48": 5000 }"#).unwrap();
+        assert_eq!(weights.get("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"), Some(&5_000));
+
+        assert!(parse_weights(r#"{ "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48": 12000 }"#).is_err());
+        assert!(parse_weights(r#"{ "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48": -1 }"#).is_err());
+    }
+}
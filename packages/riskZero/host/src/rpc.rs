@@ -0,0 +1,234 @@
+// RPC Provider
+// alloy's default HTTP provider has no timeouts, so a dead or blackholed
+// endpoint hangs the fetch or submission forever. Every provider is built
+// here instead, on a reqwest client with connect and request timeouts and a
+// connection pool that keeps connections open across reserve fetches.
+// --verbose-rpc layers a logger over the transport that prints every call
+// and its raw result, for tokens whose responses don't decode.
+
+use alloy::{
+    providers::{ProviderBuilder, RootProvider},
+    rpc::{
+        client::{ClientBuilder, RpcClient},
+        json_rpc::{RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest},
+    },
+    transports::{
+        http::{reqwest::{Client, Url}, Http},
+        utils::guess_local_url,
+        BoxTransport, TransportError, TransportFut,
+    },
+};
+use eyre::Result;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::{Layer, Service};
+
+/// Default time allowed to open a connection to the RPC endpoint
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default time allowed for a single RPC request, connection included
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Idle connections kept open per host
+/// The fetcher has at most a few calls in flight, so this covers it with
+/// room to spare; more would only hold sockets open for nothing
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+/// How providers connect to the RPC endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RpcOptions {
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+    pub pool_size: usize,
+    /// Log every request and response (--verbose-rpc)
+    pub verbose: bool,
+}
+
+impl Default for RpcOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            pool_size: DEFAULT_POOL_SIZE,
+            verbose: false,
+        }
+    }
+}
+
+/// HTTP client with the timeouts and pool size from `opts`
+/// Also used for endpoints that aren't JSON-RPC, e.g. a subgraph
+pub fn build_http_client(opts: &RpcOptions) -> Result<Client> {
+    Ok(Client::builder()
+        .connect_timeout(opts.connect_timeout)
+        .timeout(opts.request_timeout)
+        .pool_max_idle_per_host(opts.pool_size)
+        .build()?)
+}
+
+/// RPC client for `url` using `opts`
+/// For providers that need layers on top, e.g. a wallet (`ProviderBuilder::on_client`)
+/// Boxed so verbose and quiet clients share a type
+pub fn build_client(url: &str, opts: &RpcOptions) -> Result<RpcClient<BoxTransport>> {
+    let parsed = Url::parse(url)?;
+    let transport = Http::with_client(build_http_client(opts)?, parsed);
+    let is_local = guess_local_url(url);
+
+    Ok(if opts.verbose {
+        ClientBuilder::default().layer(VerboseRpcLayer).transport(transport, is_local).boxed()
+    } else {
+        RpcClient::new(transport, is_local).boxed()
+    })
+}
+
+/// Read-only provider for `url` using `opts`
+pub fn build_provider(url: &str, opts: &RpcOptions) -> Result<RootProvider<BoxTransport>> {
+    Ok(ProviderBuilder::new().on_client(build_client(url, opts)?))
+}
+
+/// Transport layer that logs each call and its result (--verbose-rpc)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VerboseRpcLayer;
+
+impl<S> Layer<S> for VerboseRpcLayer {
+    type Service = VerboseRpc<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        VerboseRpc { inner }
+    }
+}
+
+/// A transport wrapped by `VerboseRpcLayer`
+#[derive(Debug, Clone)]
+pub struct VerboseRpc<S> {
+    inner: S,
+}
+
+impl<S> Service<RequestPacket> for VerboseRpc<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError, Future = TransportFut<'static>>,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, packet: RequestPacket) -> Self::Future {
+        let requests = match &packet {
+            RequestPacket::Single(request) => std::slice::from_ref(request),
+            RequestPacket::Batch(requests) => requests.as_slice(),
+        };
+        requests.iter().for_each(|request| log(describe_request(request)));
+
+        let response = self.inner.call(packet);
+        Box::pin(async move {
+            let response = response.await;
+            match &response {
+                Ok(ResponsePacket::Single(single)) => log(describe_response(single)),
+                Ok(ResponsePacket::Batch(batch)) => batch.iter().for_each(|single| log(describe_response(single))),
+                Err(e) => log(format!("[rpc] ✗ transport error: {}", e)),
+            }
+            response
+        })
+    }
+}
+
+/// Method, target and calldata of a request; other methods show their params
+fn describe_request(request: &SerializedRequest) -> String {
+    let params: serde_json::Value = request
+        .params()
+        .and_then(|raw| serde_json::from_str(raw.get()).ok())
+        .unwrap_or_default();
+    let call = &params[0];
+    let calldata = call["input"].as_str().or_else(|| call["data"].as_str());
+    match (call["to"].as_str(), calldata) {
+        (Some(to), Some(calldata)) => format!(
+            "[rpc #{}] → {} to {} at {} calldata {}",
+            request.id(), request.method(), to, params[1].as_str().unwrap_or("latest"), calldata
+        ),
+        _ if params.is_null() => format!("[rpc #{}] → {}", request.id(), request.method()),
+        _ => format!("[rpc #{}] → {} {}", request.id(), request.method(), params),
+    }
+}
+
+/// Raw result of a response, or its error code, message and revert data
+fn describe_response(response: &Response) -> String {
+    match &response.payload {
+        ResponsePayload::Success(result) => format!("[rpc #{}] ← {}", response.id, result.get().trim_matches('"')),
+        ResponsePayload::Failure(error) => {
+            let data = error.data.as_ref().map(|data| format!(" data {}", data.get().trim_matches('"')));
+            format!("[rpc #{}] ✗ error {}: {}{}", response.id, error.code, error.message, data.unwrap_or_default())
+        }
+    }
+}
+
+/// Lines logged during tests, so they can check what the layer saw
+#[cfg(test)]
+static LOGGED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+/// Verbose output goes to stderr, so it never mixes into --output-stdout JSON
+fn log(line: String) {
+    #[cfg(test)]
+    LOGGED.lock().unwrap().push(line.clone());
+    eprintln!("{}", line);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_rpc::mock_rpc;
+    use alloy::primitives::{Address, Bytes};
+    use alloy::providers::Provider;
+    use alloy::rpc::types::TransactionRequest;
+    use std::time::Instant;
+
+    #[test]
+    fn test_invalid_url_rejected() {
+        assert!(build_provider("not a url", &RpcOptions::default()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_endpoint_fails_fast() {
+        // 10.255.255.1 is non-routable: packets are dropped rather than
+        // refused, which is exactly the case that used to hang
+        let opts = RpcOptions {
+            connect_timeout: Duration::from_millis(200),
+            request_timeout: Duration::from_millis(500),
+            ..RpcOptions::default()
+        };
+        let provider = build_provider("http://10.255.255.1:8545", &opts).unwrap();
+
+        let started = Instant::now();
+        // Outer bound only so a regression fails the test instead of hanging it
+        let result = tokio::time::timeout(Duration::from_secs(10), provider.get_block_number())
+            .await
+            .expect("request was not bounded by the configured timeouts");
+
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(2), "took {:?}", started.elapsed());
+    }
+
+    #[tokio::test]
+    async fn test_verbose_rpc_logs_calls_without_changing_them() {
+        // decimals() on a token answering 6
+        let six = format!("0x{:064x}", 6);
+        let (url, _) = mock_rpc(serde_json::json!(six)).await;
+        let token: Address = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".parse().unwrap();
+        let call = TransactionRequest::default().to(token).input(Bytes::from_static(&[0x31, 0x3c, 0xe5, 0x67]).into());
+
+        let quiet = build_provider(&url, &RpcOptions::default()).unwrap().call(&call).await.unwrap();
+        assert!(LOGGED.lock().unwrap().is_empty(), "logged without --verbose-rpc");
+
+        let opts = RpcOptions { verbose: true, ..RpcOptions::default() };
+        let verbose = build_provider(&url, &opts).unwrap().call(&call).await.unwrap();
+        assert_eq!(verbose, quiet);
+
+        let logged = LOGGED.lock().unwrap().join("\n").to_lowercase();
+        assert!(logged.contains("eth_call"), "{}", logged);
+        assert!(logged.contains("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"), "{}", logged);
+        assert!(logged.contains("calldata 0x313ce567"), "{}", logged);
+        assert!(logged.contains(&six), "{}", logged);
+    }
+}
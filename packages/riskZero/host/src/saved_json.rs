@@ -0,0 +1,62 @@
+// Saved JSON
+// aave_input.json and safety_score_output.json are pretty-printed by default
+// so they can be read and diffed by hand. For a large protocol most of the
+// input file is indentation, so --json-compact writes both on one line
+// instead; either form loads back to the same values.
+
+use eyre::Result;
+use serde::Serialize;
+
+/// Serialize `value`, on one line when `compact` is set
+pub fn to_json<T: Serialize>(value: &T, compact: bool) -> Result<String> {
+    Ok(if compact {
+        serde_json::to_string(value)?
+    } else {
+        serde_json::to_string_pretty(value)?
+    })
+}
+
+/// Write `value` to `path` as JSON, on one line when `compact` is set
+pub fn write_json<T: Serialize>(path: &str, value: &T, compact: bool) -> Result<()> {
+    std::fs::write(path, to_json(value, compact)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derisk_type::{AaveInput, AaveReserveData, SafetyScoreOutput};
+
+    fn input() -> AaveInput {
+        AaveInput::new(
+            vec![
+                AaveReserveData::builder("0xUSDC").decimals(6).total_variable_debt(400_000).build(),
+                AaveReserveData::builder("0xWETH").liquidation_threshold(8_250).build(),
+            ],
+            "Compact Test".to_string(),
+            1_700_000_000,
+        )
+    }
+
+    #[test]
+    fn test_compact_json_is_smaller_and_loads_identically() {
+        let input = input();
+        let pretty = to_json(&input, false).unwrap();
+        let compact = to_json(&input, true).unwrap();
+        assert!(compact.len() < pretty.len(), "{} >= {}", compact.len(), pretty.len());
+        assert_eq!(compact.lines().count(), 1);
+
+        let from_compact: AaveInput = serde_json::from_str(&compact).unwrap();
+        let from_pretty: AaveInput = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(to_json(&from_compact, false).unwrap(), pretty);
+        assert_eq!(to_json(&from_pretty, true).unwrap(), compact);
+
+        let output = SafetyScoreOutput::new(600_000, 1_000, 400, 1_700_000_000);
+        let path = std::env::temp_dir().join("derisk_compact_output.json");
+        let path = path.to_str().unwrap();
+        write_json(path, &output, true).unwrap();
+        let loaded: SafetyScoreOutput = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(loaded, output);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+// Price Scenarios
+// Risk teams want to ask "what's the score if ETH drops 40%?" without waiting
+// for it to happen on-chain. --price-override sets a reserve's price outright
+// and --stress scales prices by a percentage, before the input is proved.
+// Every price replaced is recorded in the input's price_overrides; the guest
+// checks the reserves carry those prices and commits the output as
+// hypothetical. The oracle contracts revert on a hypothetical journal, and
+// the host refuses to submit or simulate one before it gets that far.
+
+use alloy::primitives::Address;
+use derisk_type::AaveInput;
+use eyre::{Result, eyre};
+use std::collections::BTreeMap;
+
+/// Price changes to apply to an input, from --price-override and --stress
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PriceScenario {
+    /// Token address (lowercase) → price in the oracle's base unit
+    prices: BTreeMap<String, u128>,
+    /// Token address (lowercase) → price change in bps (-4_000 = -40%)
+    stress: BTreeMap<String, i64>,
+    /// Price change in bps for every reserve not named otherwise
+    stress_all: Option<i64>,
+}
+
+impl PriceScenario {
+    /// A scenario from `ADDRESS=PRICE` overrides and `[ADDRESS=]PCT` stresses
+    pub fn parse(price_overrides: &[String], stress: &[String]) -> Result<Self> {
+        let mut scenario = Self::default();
+        for entry in price_overrides {
+            let (address, price) = entry
+                .split_once('=')
+                .ok_or_else(|| eyre!("--price-override takes ADDRESS=PRICE, got '{}'", entry))?;
+            let price: u128 = price
+                .trim()
+                .parse()
+                .map_err(|_| eyre!("--price-override price '{}' is not a whole number in the oracle's unit", price))?;
+            scenario.prices.insert(token_key(address)?, price);
+        }
+        for entry in stress {
+            match entry.split_once('=') {
+                Some((address, pct)) => {
+                    scenario.stress.insert(token_key(address)?, stress_bps(pct)?);
+                }
+                None => scenario.stress_all = Some(stress_bps(entry)?),
+            }
+        }
+        Ok(scenario)
+    }
+
+    /// Whether the scenario changes nothing
+    pub fn is_empty(&self) -> bool {
+        self.prices.is_empty() && self.stress.is_empty() && self.stress_all.is_none()
+    }
+
+    /// Refuse a scenario on a run that sends its score to the oracle
+    /// Checked with the arguments, before anything is fetched or proved
+    pub fn check_not_submitted(&self, submitting: bool) -> Result<()> {
+        if submitting && !self.is_empty() {
+            return Err(eyre!("--price-override and --stress make the score hypothetical, which can't be submitted"));
+        }
+        Ok(())
+    }
+
+    /// Reprice the input's reserves, recording each new price in
+    /// `price_overrides`. Explicit prices win over stresses; reserves with no
+    /// price aren't stressed, since 0 marks a missing price
+    /// Returns how many reserves were repriced
+    pub fn apply(&self, input: &mut AaveInput) -> usize {
+        let mut repriced = 0;
+        for reserve in &mut input.reserves {
+            let key = reserve.token_address.to_ascii_lowercase();
+            let price = match (self.prices.get(&key), self.stress.get(&key).or(self.stress_all.as_ref())) {
+                (Some(&price), _) => price,
+                (None, Some(&bps)) if reserve.price_usd > 0 => stressed_price(reserve.price_usd, bps),
+                _ => continue,
+            };
+            reserve.price_usd = price;
+            input.price_overrides.insert(key, price);
+            repriced += 1;
+        }
+        repriced
+    }
+
+    /// Addresses the scenario names that no reserve in `input` matches
+    pub fn unmatched<'a>(&'a self, input: &AaveInput) -> Vec<&'a str> {
+        self.prices
+            .keys()
+            .chain(self.stress.keys())
+            .filter(|address| !input.reserves.iter().any(|r| r.token_address.eq_ignore_ascii_case(address)))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// A token address as reserves key it: lowercase 0x hex
+fn token_key(address: &str) -> Result<String> {
+    let address: Address = address
+        .trim()
+        .parse()
+        .map_err(|_| eyre!("'{}' is not a token address", address))?;
+    Ok(format!("{:?}", address))
+}
+
+/// A percentage change (e.g. "-40" or "12.5") in bps
+/// Falls of 100% or more are refused: they'd price the reserve at 0, which
+/// reads as a missing price rather than a worthless asset
+fn stress_bps(pct: &str) -> Result<i64> {
+    let pct: f64 = pct
+        .trim()
+        .parse()
+        .map_err(|_| eyre!("--stress takes a percentage like -40, got '{}'", pct))?;
+    if !pct.is_finite() || pct <= -100.0 {
+        return Err(eyre!("--stress {}% is out of range; it must be above -100%", pct));
+    }
+    Ok((pct * 100.0).round() as i64)
+}
+
+/// `price` changed by `bps`, rounded down
+fn stressed_price(price: u128, bps: i64) -> u128 {
+    // stress_bps keeps bps above -10_000, so the factor is positive
+    let factor = (10_000 + bps) as u128;
+    price
+        .checked_mul(factor)
+        .map_or_else(|| (price / 10_000).saturating_mul(factor), |scaled| scaled / 10_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derisk_type::{check_price_overrides, compute_output, scaled, AaveReserveData};
+
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    /// $1,000 of USDC with $600 borrowed, and 1 WETH at $2,000
+    fn input() -> AaveInput {
+        let usdc = AaveReserveData::builder(USDC)
+            .decimals(6)
+            .total_atoken(1_000_000_000)
+            .total_variable_debt(600_000_000)
+            .build();
+        let weth = AaveReserveData::builder(WETH).price_usd(2_000 * 100_000_000).build();
+        AaveInput::new(vec![usdc, weth], "Aave V3".to_string(), 1_700_000_000)
+    }
+
+    #[test]
+    fn test_eth_down_40_percent_lowers_score() {
+        let spot = compute_output(&input());
+        assert_eq!(spot.safety_score, scaled(80.0));
+        assert!(!spot.hypothetical);
+
+        // WETH at $1,200: $1,600 buffer on $2,200, 72.73%
+        let mut stressed = input();
+        let scenario = PriceScenario::parse(&[], &[format!("{}=-40", WETH)]).unwrap();
+        assert_eq!(scenario.apply(&mut stressed), 1);
+        assert_eq!(stressed.price_overrides, BTreeMap::from([(WETH.to_string(), 1_200 * 100_000_000)]));
+        assert_eq!(check_price_overrides(&stressed), Ok(()));
+
+        let output = compute_output(&stressed);
+        assert_eq!(output.safety_score, 727_272);
+        assert!(output.hypothetical);
+    }
+
+    #[test]
+    fn test_explicit_price_wins_over_stress() {
+        let scenario = PriceScenario::parse(&[format!("{}=95000000", USDC)], &["-10".to_string()]).unwrap();
+        let mut input = input();
+        assert_eq!(scenario.apply(&mut input), 2);
+        assert_eq!(input.reserves[0].price_usd, 95_000_000);
+        assert_eq!(input.reserves[1].price_usd, 1_800 * 100_000_000);
+        assert!(scenario.unmatched(&input).is_empty());
+
+        // The guest refuses an input that scores other prices than it records
+        input.reserves[0].price_usd = 100_000_000;
+        assert!(check_price_overrides(&input).is_err());
+
+        assert!(PriceScenario::parse(&[], &["-100".to_string()]).is_err());
+        assert!(PriceScenario::parse(&["0xnotanaddress=1".to_string()], &[]).is_err());
+        assert!(PriceScenario::parse(&[format!("{}=1.5", USDC)], &[]).is_err());
+    }
+
+    #[test]
+    fn test_scenario_refused_when_submitting() {
+        let scenario = PriceScenario::parse(&[], &["-40".to_string()]).unwrap();
+        assert!(scenario.check_not_submitted(true).is_err());
+        assert!(scenario.check_not_submitted(false).is_ok());
+
+        // Without a scenario the score is the market's and can be submitted
+        assert!(PriceScenario::default().check_not_submitted(true).is_ok());
+    }
+}
@@ -0,0 +1,170 @@
+// Groth16 Seal
+// The on-chain RiscZeroVerifierRouter takes the seal as a 4-byte selector
+// naming the verifier version, followed by the 256-byte Groth16 proof (the
+// a, b and c points). This extracts exactly that from a Groth16 receipt;
+// the serialized receipt is neither of those and no verifier accepts it.
+// Going the other way, a receipt is rebuilt from a journal, a seal and an
+// image ID, so a proof received without its receipt can still be verified.
+
+use eyre::{Result, eyre};
+use risc0_zkvm::{
+    sha::Digestible, Groth16Receipt, Groth16ReceiptVerifierParameters, InnerReceipt, Receipt, ReceiptClaim,
+};
+
+/// Groth16 proof size: a (G1, 64 bytes), b (G2, 128 bytes), c (G1, 64 bytes)
+pub const GROTH16_PROOF_LEN: usize = 256;
+
+/// Verifier selector prepended to the proof, taken from the verifier parameters digest
+pub const SELECTOR_LEN: usize = 4;
+
+/// Length of a seal as submitted on-chain
+pub const SEAL_LEN: usize = SELECTOR_LEN + GROTH16_PROOF_LEN;
+
+/// Seal file in the output directory
+pub const SEAL_FILE: &str = "proof_seal.bin";
+
+/// Encode a Groth16 receipt's seal for the on-chain verifier
+/// Fails for any other receipt kind, e.g. a STARK receipt from a run that
+/// skipped the Groth16 conversion
+pub fn encode_seal(receipt: &Receipt) -> Result<Vec<u8>> {
+    let groth16 = receipt
+        .inner
+        .groth16()
+        .map_err(|_| eyre!("Only a Groth16 receipt can be submitted on-chain, got a different receipt kind"))?;
+    if groth16.seal.len() != GROTH16_PROOF_LEN {
+        return Err(eyre!(
+            "Groth16 proof is {} bytes, expected {}",
+            groth16.seal.len(),
+            GROTH16_PROOF_LEN
+        ));
+    }
+
+    let mut seal = Vec::with_capacity(SEAL_LEN);
+    seal.extend_from_slice(&groth16.verifier_parameters.as_bytes()[..SELECTOR_LEN]);
+    seal.extend_from_slice(&groth16.seal);
+    Ok(seal)
+}
+
+/// Read the seal saved by an earlier run, checking it has the submitted shape
+/// Seals saved before this format (a serialized receipt) are rejected
+pub fn load_seal(output_dir: &str) -> Result<Vec<u8>> {
+    let path = format!("{}/{}", output_dir, SEAL_FILE);
+    let seal = std::fs::read(&path).map_err(|e| eyre!("Failed to read {}: {}", path, e))?;
+    if seal.len() != SEAL_LEN {
+        return Err(eyre!(
+            "{} is {} bytes, not a {}-byte Groth16 seal; re-prove to regenerate it",
+            path,
+            seal.len(),
+            SEAL_LEN
+        ));
+    }
+    Ok(seal)
+}
+
+/// Verify a Groth16 proof of `journal` by the guest `image_id`, given only
+/// the seal: the on-chain form (selector + proof) or the bare 256-byte proof
+/// A selector must name the verifier parameters this build verifies with,
+/// since a proof for other parameters can't verify here
+pub fn verify_groth16(journal: &[u8], seal: &[u8], image_id: [u32; 8]) -> Result<()> {
+    let parameters = Groth16ReceiptVerifierParameters::default().digest();
+    let proof = match seal.len() {
+        GROTH16_PROOF_LEN => seal,
+        SEAL_LEN => {
+            let (selector, proof) = seal.split_at(SELECTOR_LEN);
+            if selector != &parameters.as_bytes()[..SELECTOR_LEN] {
+                return Err(eyre!(
+                    "Seal selector 0x{} is not this build's Groth16 verifier (0x{})",
+                    hex::encode(selector),
+                    hex::encode(&parameters.as_bytes()[..SELECTOR_LEN])
+                ));
+            }
+            proof
+        }
+        len => {
+            return Err(eyre!(
+                "Seal is {} bytes, expected a {}-byte seal or a {}-byte Groth16 proof",
+                len, SEAL_LEN, GROTH16_PROOF_LEN
+            ))
+        }
+    };
+
+    let claim = ReceiptClaim::ok(image_id, journal.to_vec());
+    let inner = Groth16Receipt::new(proof.to_vec(), claim.into(), parameters);
+    Receipt::new(InnerReceipt::Groth16(inner), journal.to_vec())
+        .verify(image_id)
+        .map_err(|e| eyre!("Groth16 proof does not verify: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use risc0_zkvm::{sha::Digest, FakeReceipt};
+
+    fn claim() -> ReceiptClaim {
+        ReceiptClaim::ok(Digest::ZERO, Vec::<u8>::new())
+    }
+
+    fn groth16_receipt(proof: Vec<u8>, verifier_parameters: Digest) -> Receipt {
+        let inner = Groth16Receipt::new(proof, claim().into(), verifier_parameters);
+        Receipt::new(InnerReceipt::Groth16(inner), Vec::new())
+    }
+
+    #[test]
+    fn test_seal_is_selector_and_proof() {
+        let proof: Vec<u8> = (0..GROTH16_PROOF_LEN).map(|i| i as u8).collect();
+        let parameters = Digest::from_bytes([0xab; 32]);
+
+        let seal = encode_seal(&groth16_receipt(proof.clone(), parameters)).unwrap();
+
+        // Hundreds of bytes, not the ~250KB of a serialized STARK receipt
+        assert_eq!(seal.len(), SEAL_LEN);
+        assert_eq!(seal[..SELECTOR_LEN], [0xab; SELECTOR_LEN]);
+        assert_eq!(seal[SELECTOR_LEN..], proof[..]);
+    }
+
+    #[test]
+    fn test_wrong_proof_length_rejected() {
+        let receipt = groth16_receipt(vec![0; GROTH16_PROOF_LEN + 1], Digest::ZERO);
+        assert!(encode_seal(&receipt).is_err());
+    }
+
+    #[test]
+    fn test_non_groth16_receipt_rejected() {
+        let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim())), Vec::new());
+        assert!(encode_seal(&receipt).is_err());
+    }
+
+    #[test]
+    fn test_raw_verification_checks_seal_shape() {
+        let message = |seal: &[u8]| verify_groth16(&[], seal, [0; 8]).unwrap_err().to_string();
+
+        // Neither a seal nor a bare proof, e.g. a serialized receipt
+        assert!(message(&[0; SEAL_LEN + 1]).contains("261 bytes"));
+        assert!(message(&[]).contains("0 bytes"));
+
+        // The right length, but selecting a verifier other than this build's
+        let selector = Groth16ReceiptVerifierParameters::default().digest().as_bytes()[0] ^ 0xff;
+        let mut seal = vec![0; SEAL_LEN];
+        seal[0] = selector;
+        assert!(message(&seal).contains("not this build's Groth16 verifier"));
+    }
+
+    /// Verifies the journal and seal of a real Groth16 run, saved in
+    /// DERISK_PROOF_DIR (default ./output) by a full or prove-only run
+    #[test]
+    #[ignore = "needs proof_journal.bin and proof_seal.bin from a Groth16 run"]
+    fn test_saved_proof_verifies_from_raw_parts() {
+        let dir = std::env::var("DERISK_PROOF_DIR").unwrap_or_else(|_| "./output".to_string());
+        let journal = std::fs::read(format!("{}/proof_journal.bin", dir)).unwrap();
+        let seal = load_seal(&dir).unwrap();
+
+        verify_groth16(&journal, &seal, methods::AAVE_ID).unwrap();
+        verify_groth16(&journal, &seal[SELECTOR_LEN..], methods::AAVE_ID).unwrap();
+
+        // The same proof says nothing about another journal or guest
+        let mut tampered = journal.clone();
+        tampered[0] ^= 1;
+        assert!(verify_groth16(&tampered, &seal, methods::AAVE_ID).is_err());
+        assert!(verify_groth16(&journal, &seal, [0; 8]).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+// Score Series
+// Scores the protocol at several blocks for trend analysis. Each block is
+// fetched pinned (as with --block) and executed without proving; the points
+// are written as score_series.csv and score_series.json, oldest block first.
+//
+// Blocks are given as a list (`--blocks 19000000,19100000`) or as an
+// inclusive range with a step (`--blocks 19000000:19100000:50000`).
+
+use derisk_type::SafetyScoreOutput;
+use eyre::{Result, eyre};
+use serde::Serialize;
+use std::future::Future;
+
+use crate::shutdown::ShutdownSignal;
+
+/// Most blocks one invocation will score; each one is a full fetch
+pub const MAX_SERIES_BLOCKS: usize = 1_000;
+
+/// CSV columns, in SeriesPoint field order
+pub const CSV_HEADER: &str = "block,timestamp,safety_score,total_assets_usd,total_liabilities_usd";
+
+/// The score at one block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SeriesPoint {
+    pub block: u64,
+    pub timestamp: u64,
+    /// Scaled by 1e4
+    pub safety_score: u64,
+    /// USD, scaled by 1e8
+    pub total_assets_usd: u128,
+    /// USD, scaled by 1e8
+    pub total_liabilities_usd: u128,
+}
+
+impl SeriesPoint {
+    pub fn new(block: u64, output: &SafetyScoreOutput) -> Self {
+        Self {
+            block,
+            timestamp: output.timestamp,
+            safety_score: output.safety_score,
+            total_assets_usd: output.total_assets_usd,
+            total_liabilities_usd: output.total_liabilities_usd,
+        }
+    }
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            self.block, self.timestamp, self.safety_score, self.total_assets_usd, self.total_liabilities_usd
+        )
+    }
+}
+
+/// Points in block order, plus the blocks that couldn't be scored
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScoreSeries {
+    pub points: Vec<SeriesPoint>,
+    /// Blocks whose fetch or execution failed, with the reason
+    pub failed: Vec<(u64, String)>,
+}
+
+impl ScoreSeries {
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        for point in &self.points {
+            csv.push('\n');
+            csv.push_str(&point.csv_row());
+        }
+        csv.push('\n');
+        csv
+    }
+}
+
+/// Parse a `--blocks` spec into ascending, distinct block numbers
+pub fn parse_blocks(spec: &str) -> Result<Vec<u64>> {
+    let parse = |value: &str| {
+        value
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| eyre!("Invalid block number '{}' in --blocks", value.trim()))
+    };
+
+    let mut blocks = match spec.split(':').collect::<Vec<_>>()[..] {
+        [single] => single.split(',').map(parse).collect::<Result<Vec<_>>>()?,
+        [start, end, step] => {
+            let (start, end, step) = (parse(start)?, parse(end)?, parse(step)?);
+            if step == 0 || start > end {
+                return Err(eyre!("--blocks range needs start <= end and a non-zero step, got {}", spec));
+            }
+            let count = (end - start) / step + 1;
+            if count > MAX_SERIES_BLOCKS as u64 {
+                return Err(eyre!("--blocks {} covers {} blocks, more than {}", spec, count, MAX_SERIES_BLOCKS));
+            }
+            (0..count).map(|i| start + i * step).collect()
+        }
+        _ => return Err(eyre!("--blocks takes b1,b2,... or start:end:step, got {}", spec)),
+    };
+
+    blocks.sort_unstable();
+    blocks.dedup();
+    if blocks.len() > MAX_SERIES_BLOCKS {
+        return Err(eyre!("--blocks lists {} blocks, more than {}", blocks.len(), MAX_SERIES_BLOCKS));
+    }
+    Ok(blocks)
+}
+
+/// Score each block in turn with `score_block`
+/// Blocks are scored one at a time, oldest first, to keep the RPC load of a
+/// long series at that of a single fetch. A failed block is recorded and
+/// skipped; an interrupt stops before the next block
+pub async fn collect_series<F, Fut>(blocks: &[u64], shutdown: &ShutdownSignal, mut score_block: F) -> ScoreSeries
+where
+    F: FnMut(u64) -> Fut,
+    Fut: Future<Output = Result<SafetyScoreOutput>>,
+{
+    let mut series = ScoreSeries::default();
+    for (index, &block) in blocks.iter().enumerate() {
+        if shutdown.is_requested() {
+            status!("⚠ Interrupted: {} of {} blocks not scored", blocks.len() - index, blocks.len());
+            break;
+        }
+
+        status!("\n🧱 Block {} ({}/{})", block, index + 1, blocks.len());
+        match score_block(block).await {
+            Ok(output) => {
+                status!("✓ Block {}: {:.4}%", block, output.to_percentage());
+                series.points.push(SeriesPoint::new(block, &output));
+            }
+            Err(e) => {
+                status!("⚠ Block {} failed: {}", block, e);
+                series.failed.push((block, e.to_string()));
+            }
+        }
+    }
+    series
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_block_list_and_range() {
+        assert_eq!(parse_blocks("300, 100,200,100").unwrap(), vec![100, 200, 300]);
+        assert_eq!(parse_blocks("100:200:50").unwrap(), vec![100, 150, 200]);
+        assert_eq!(parse_blocks("100:220:50").unwrap(), vec![100, 150, 200]);
+        assert_eq!(parse_blocks("7").unwrap(), vec![7]);
+
+        assert!(parse_blocks("100:200:0").is_err());
+        assert!(parse_blocks("200:100:10").is_err());
+        assert!(parse_blocks("100:200").is_err());
+        assert!(parse_blocks("latest").is_err());
+        assert!(parse_blocks("0:10000:1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_series_over_mock_blocks() {
+        let blocks = parse_blocks("102,100,101").unwrap();
+        let mut scored = Vec::new();
+
+        let series = collect_series(&blocks, &ShutdownSignal::default(), |block| {
+            scored.push(block);
+            async move {
+                if block == 101 {
+                    return Err(eyre!("archive node pruned block {}", block));
+                }
+                // The score improves by a point per block
+                let score = 900_000 + (block - 100) * 10_000;
+                Ok(SafetyScoreOutput::new(score, 1_000, 100, 1_700_000_000 + block * 12))
+            }
+        })
+        .await;
+
+        assert_eq!(scored, vec![100, 101, 102]);
+        assert_eq!(
+            series.points,
+            vec![
+                SeriesPoint {
+                    block: 100,
+                    timestamp: 1_700_001_200,
+                    safety_score: 900_000,
+                    total_assets_usd: 1_000,
+                    total_liabilities_usd: 100,
+                },
+                SeriesPoint {
+                    block: 102,
+                    timestamp: 1_700_001_224,
+                    safety_score: 920_000,
+                    total_assets_usd: 1_000,
+                    total_liabilities_usd: 100,
+                },
+            ]
+        );
+        assert_eq!(series.failed.len(), 1);
+        assert_eq!(series.failed[0].0, 101);
+
+        assert_eq!(
+            series.to_csv(),
+            format!("{}\n100,1700001200,900000,1000,100\n102,1700001224,920000,1000,100\n", CSV_HEADER)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_stops_between_blocks() {
+        let shutdown = ShutdownSignal::default();
+        let series = collect_series(&[1, 2, 3], &shutdown, |block| {
+            if block == 2 {
+                shutdown.request();
+            }
+            async move { Ok(SafetyScoreOutput::new(0, 0, 0, block)) }
+        })
+        .await;
+
+        // Block 2 was already running when the interrupt arrived
+        assert_eq!(series.points.iter().map(|p| p.block).collect::<Vec<_>>(), vec![1, 2]);
+    }
+}
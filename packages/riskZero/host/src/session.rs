@@ -0,0 +1,168 @@
+// Proving Session
+// The pipeline the CLI runs - fetch, execute, prove, wrap in Groth16, verify,
+// submit - as steps another program (a web service, a bot) can drive itself.
+// Each step returns what it produced; printing, prompting and saving
+// artifacts are left to the caller, so the CLI is one driver among others.
+
+use alloy::primitives::TxHash;
+use derisk_type::{compute_output, AaveInput, SafetyScoreOutput};
+use eyre::{Result, eyre};
+use methods::{AAVE_ELF, AAVE_ID};
+use risc0_groth16::Prover as Groth16Prover;
+use risc0_zkvm::Receipt;
+
+use crate::guest::{self, Execution};
+use crate::journal_abi;
+use crate::prover::{self, ProverKind};
+use crate::receipt_compare;
+use crate::reserve_source::ReserveSource;
+use crate::seal;
+use crate::submission::{self, SubmissionBackend};
+use crate::telemetry::{PhaseTimer, ProvingStats};
+
+/// A STARK proof of the guest on the session's input
+pub struct StarkProof {
+    pub receipt: Receipt,
+    pub total_cycles: u64,
+    pub segments: usize,
+    /// Started with the proof; the Groth16 step keeps timing on it
+    pub timer: PhaseTimer,
+}
+
+/// A STARK proof wrapped into the Groth16 receipt the on-chain verifier takes
+pub struct Groth16Proof {
+    pub receipt: Receipt,
+    pub stats: ProvingStats,
+}
+
+impl Groth16Proof {
+    /// Journal bytes exactly as committed
+    pub fn journal(&self) -> &[u8] {
+        &self.receipt.journal.bytes
+    }
+
+    /// Seal the on-chain verifier checks: selector + Groth16 proof
+    pub fn seal(&self) -> Result<Vec<u8>> {
+        seal::encode_seal(&self.receipt)
+    }
+}
+
+/// One input taken through the proving pipeline
+pub struct ProvingSession {
+    input: AaveInput,
+    segment_limit_po2: Option<u32>,
+    prover: Option<ProverKind>,
+    submit_any_status: bool,
+}
+
+impl ProvingSession {
+    /// A session over an input that was already fetched or loaded
+    pub fn new(input: AaveInput) -> Self {
+        Self { input, segment_limit_po2: None, prover: None, submit_any_status: false }
+    }
+
+    /// Fetch a snapshot from `source` and start a session over it
+    pub async fn fetch(source: &impl ReserveSource) -> Result<Self> {
+        Ok(Self::new(source.fetch().await?))
+    }
+
+    /// Split execution into segments of at most 2^po2 cycles (see guest.rs)
+    pub fn with_segment_limit_po2(mut self, po2: Option<u32>) -> Self {
+        self.segment_limit_po2 = po2;
+        self
+    }
+
+    /// Prove with this backend instead of risc0's default
+    pub fn with_prover(mut self, kind: Option<ProverKind>) -> Self {
+        self.prover = kind;
+        self
+    }
+
+    /// Submit NoData and Incomplete scores too (see `submission::check_submittable`)
+    pub fn with_submit_any_status(mut self, submit_any_status: bool) -> Self {
+        self.submit_any_status = submit_any_status;
+        self
+    }
+
+    /// The input being proved
+    pub fn input(&self) -> &AaveInput {
+        &self.input
+    }
+
+    /// The input, to adjust before it is executed or proved
+    pub fn input_mut(&mut self) -> &mut AaveInput {
+        &mut self.input
+    }
+
+    /// Run the guest without proving, for the score and cycle count
+    pub fn execute(&self) -> Result<Execution> {
+        guest::execute_guest(&self.input, self.segment_limit_po2)
+    }
+
+    /// Prove the guest on the input
+    pub fn prove_stark(&self) -> Result<StarkProof> {
+        let env = guest::build_env(&self.input, self.segment_limit_po2)?;
+        let prover = prover::select_prover(self.prover)?;
+
+        let mut timer = PhaseTimer::start();
+        let prove_info = prover
+            .prove(env, AAVE_ELF)
+            .map_err(|e| eyre!("Failed to prove: {}", e))?;
+        timer.stark_done();
+
+        Ok(StarkProof {
+            receipt: prove_info.receipt,
+            total_cycles: prove_info.stats.total_cycles,
+            segments: prove_info.stats.segments,
+            timer,
+        })
+    }
+
+    /// Wrap a STARK proof into a Groth16 receipt
+    /// The wrapped receipt is checked to commit exactly what the STARK proved
+    pub fn prove_groth16(&self, stark: StarkProof) -> Result<Groth16Proof> {
+        let StarkProof { receipt: stark_receipt, total_cycles, segments, mut timer } = stark;
+
+        let receipt = Groth16Prover::new()
+            .prove(&stark_receipt)
+            .map_err(|e| eyre!("Failed to convert to Groth16: {}", e))?;
+        timer.groth16_done();
+
+        receipt_compare::verify_and_compare(&stark_receipt, &receipt, AAVE_ID)?;
+
+        let stats = ProvingStats { reserve_count: self.input.reserves.len(), total_cycles, segments, timer };
+        Ok(Groth16Proof { receipt, stats })
+    }
+
+    /// Verify a proof against the guest and decode what it committed
+    /// Fails unless it commits this input's score, and the on-chain verifier
+    /// would read the same values from its journal
+    pub fn verify(&self, proof: &Groth16Proof) -> Result<SafetyScoreOutput> {
+        proof
+            .receipt
+            .verify(AAVE_ID)
+            .map_err(|e| eyre!("Proof failed verification: {}", e))?;
+        let output: SafetyScoreOutput = proof.receipt.journal.decode()?;
+        if output != compute_output(&self.input) {
+            return Err(eyre!("Proof commits a different output than this session's input scores"));
+        }
+        journal_abi::check_verifier_compat(proof.journal(), &output)?;
+        Ok(output)
+    }
+
+    /// Submit a proof through `backend`, recording it in `output_dir`
+    /// A journal already submitted from there isn't sent twice; see
+    /// `submission::submit_once` for what `resume` does. Fails without
+    /// sending anything if `submission::check_submittable` refuses the score
+    pub async fn submit(
+        &self,
+        backend: &impl SubmissionBackend,
+        proof: &Groth16Proof,
+        output_dir: &str,
+        resume: bool,
+    ) -> Result<TxHash> {
+        let output: SafetyScoreOutput = proof.receipt.journal.decode()?;
+        submission::check_submittable(&output, self.submit_any_status)?;
+        submission::submit_once(backend, proof.journal(), &proof.seal()?, output_dir, resume).await
+    }
+}
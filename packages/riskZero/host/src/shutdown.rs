@@ -0,0 +1,138 @@
+// Graceful Shutdown
+// Tracks Ctrl-C so long-running steps can stop at a safe point and keep
+// whatever artifacts they already produced
+
+use std::sync::{
+    atomic::{AtomicBool, AtomicU8, Ordering},
+    Arc,
+};
+use tokio::sync::Notify;
+
+/// The step the host is currently running
+/// Determines how an interrupt is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Phase {
+    /// Fetching reserve data over RPC - safe to stop between reserves
+    Fetch = 0,
+    /// Generating a proof - cannot be stopped mid-way
+    Prove = 1,
+    /// Saving artifacts or submitting on-chain
+    Finalize = 2,
+}
+
+impl Phase {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Phase::Fetch,
+            1 => Phase::Prove,
+            _ => Phase::Finalize,
+        }
+    }
+}
+
+/// Shared shutdown flag, cheap to clone into any step that can stop early
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownSignal {
+    requested: Arc<AtomicBool>,
+    phase: Arc<AtomicU8>,
+    notify: Arc<Notify>,
+}
+
+impl ShutdownSignal {
+    /// Create a signal and install the Ctrl-C handler
+    /// The first interrupt requests a graceful stop; a second one exits immediately
+    pub fn install() -> Self {
+        let signal = Self::default();
+        let handler = signal.clone();
+
+        tokio::spawn(async move {
+            loop {
+                if tokio::signal::ctrl_c().await.is_err() {
+                    return;
+                }
+
+                if handler.requested.swap(true, Ordering::SeqCst) {
+                    eprintln!("\n⚠ Second interrupt received, exiting immediately");
+                    std::process::exit(130);
+                }
+                handler.notify.notify_waiters();
+
+                match handler.phase() {
+                    Phase::Fetch => {
+                        eprintln!("\n⚠ Interrupt received, stopping after the current reserve...");
+                        eprintln!("  Reserves fetched so far will be saved");
+                    }
+                    Phase::Prove => {
+                        eprintln!("\n⚠ Interrupt received, but proving cannot be interrupted safely");
+                        eprintln!("  Finishing the current phase, then saving and exiting");
+                        eprintln!("  Press Ctrl-C again to abort without saving");
+                    }
+                    Phase::Finalize => {
+                        eprintln!("\n⚠ Interrupt received, finishing the current step...");
+                    }
+                }
+            }
+        });
+
+        signal
+    }
+
+    /// Whether an interrupt has been received
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once a shutdown has been requested
+    /// Used to cut sleeps short, e.g. between watch-mode cycles
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Record which step is running so the handler can react accordingly
+    pub fn enter(&self, phase: Phase) {
+        self.phase.store(phase as u8, Ordering::SeqCst);
+    }
+
+    /// The step currently running
+    pub fn phase(&self) -> Phase {
+        Phase::from_u8(self.phase.load(Ordering::SeqCst))
+    }
+
+    /// Request a shutdown without a signal (used by tests)
+    #[cfg(test)]
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phase_tracking() {
+        let signal = ShutdownSignal::default();
+        assert_eq!(signal.phase(), Phase::Fetch);
+
+        signal.enter(Phase::Prove);
+        assert_eq!(signal.clone().phase(), Phase::Prove);
+    }
+
+    #[test]
+    fn test_request_is_shared_between_clones() {
+        let signal = ShutdownSignal::default();
+        let step = signal.clone();
+        assert!(!step.is_requested());
+
+        signal.request();
+        assert!(step.is_requested());
+    }
+}
@@ -0,0 +1,92 @@
+// Signer Loading
+// Resolves the submission signer and oracle address without requiring the
+// private key on the command line, where it leaks into shell history and
+// process listings
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use eyre::{Result, eyre};
+
+/// Environment variable holding the raw private key
+pub const PRIVATE_KEY_ENV: &str = "PRIVATE_KEY";
+
+/// Environment variable holding the DeRiskOracle contract address
+pub const ORACLE_ADDRESS_ENV: &str = "ORACLE_ADDRESS";
+
+/// Environment variable holding the keystore password (skips the prompt)
+pub const KEYSTORE_PASSWORD_ENV: &str = "KEYSTORE_PASSWORD";
+
+/// Load the signer, in order of preference:
+/// 1. An encrypted JSON keystore (`--keystore`), password from env or prompt
+/// 2. The `PRIVATE_KEY` environment variable (also loaded from .env)
+/// 3. The `--private-key` flag (discouraged)
+pub fn load_signer(keystore: Option<&str>, private_key_flag: Option<&str>) -> Result<PrivateKeySigner> {
+    if let Some(path) = keystore {
+        let password = match std::env::var(KEYSTORE_PASSWORD_ENV) {
+            Ok(password) => password,
+            Err(_) => rpassword::prompt_password(format!("Password for keystore {}: ", path))?,
+        };
+        return decrypt_keystore(path, &password);
+    }
+
+    if let Ok(key) = std::env::var(PRIVATE_KEY_ENV) {
+        return Ok(key.trim().parse()?);
+    }
+
+    if let Some(key) = private_key_flag {
+        status!("⚠ Warning: --private-key is visible in shell history and process listings");
+        status!("  Prefer --keystore <path> or the {} env var", PRIVATE_KEY_ENV);
+        return Ok(key.parse()?);
+    }
+
+    Err(eyre!(
+        "No signer configured: use --keystore <path>, the {} env var, or --private-key",
+        PRIVATE_KEY_ENV
+    ))
+}
+
+/// Decrypt an encrypted JSON keystore file into a signer
+pub fn decrypt_keystore(path: &str, password: &str) -> Result<PrivateKeySigner> {
+    PrivateKeySigner::decrypt_keystore(path, password)
+        .map_err(|e| eyre!("Failed to decrypt keystore {}: {}", path, e))
+}
+
+/// Resolve the oracle address, preferring the `ORACLE_ADDRESS` env var over the flag
+pub fn load_oracle_address(flag: Option<&str>) -> Result<Address> {
+    let address = std::env::var(ORACLE_ADDRESS_ENV)
+        .ok()
+        .or_else(|| flag.map(str::to_string))
+        .ok_or_else(|| eyre!(
+            "No oracle address configured: set {} or pass --oracle-address",
+            ORACLE_ADDRESS_ENV
+        ))?;
+    Ok(address.trim().parse()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystore_decrypts_to_expected_address() {
+        let dir = std::env::temp_dir().join("derisk_keystore_test");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let key = PrivateKeySigner::random();
+        let mut rng = rand::thread_rng();
+        let (_, name) = PrivateKeySigner::encrypt_keystore(
+            &dir,
+            &mut rng,
+            key.to_bytes(),
+            "correct horse",
+            Some("derisk-test.json"),
+        )
+        .unwrap();
+
+        let path = dir.join(name);
+        let signer = decrypt_keystore(path.to_str().unwrap(), "correct horse").unwrap();
+        assert_eq!(signer.address(), key.address());
+
+        // Wrong password must not produce a signer
+        assert!(decrypt_keystore(path.to_str().unwrap(), "wrong").is_err());
+    }
+}
@@ -0,0 +1,366 @@
+// Subgraph Fetcher
+// Reads the same reserve data as AaveFetcher from an Aave V3 subgraph in a
+// single GraphQL query, instead of several RPC calls per reserve. A subgraph
+// only serves what it has indexed, which can trail the chain head by a few
+// blocks, so the input records the block the subgraph answered at.
+// Field names follow the Aave V3 protocol subgraph's schema.
+
+use alloy::primitives::Address;
+use derisk_type::{AaveInput, AaveReserveData, EModeCategory};
+use eyre::{Result, eyre};
+use serde::{Deserialize, Deserializer};
+use std::str::FromStr;
+
+use crate::aave_fetcher::{build_input, snapshot_timestamp, AaveVersion, ReserveFilter};
+use crate::redact;
+use crate::rpc::{build_http_client, RpcOptions};
+
+/// Most reserves one query returns: The Graph caps `first` at 1000
+/// Far above any Aave deployment; hitting it means the answer may be cut short
+const MAX_RESERVES: usize = 1000;
+
+/// `$block` pins the query to a block; null reads the latest indexed one
+/// Stable debt is the principal, without interest accrued since the last
+/// update; Aave V3 has had no new stable borrows since it was deprecated
+const RESERVES_QUERY: &str = r#"query Reserves($block: Block_height) {
+  _meta(block: $block) {
+    block { number timestamp }
+  }
+  reserves(first: 1000, block: $block) {
+    underlyingAsset
+    decimals
+    reserveLiquidationThreshold
+    totalATokenSupply
+    totalPrincipalStableDebt
+    totalCurrentVariableDebt
+    supplyCap
+    borrowCap
+    eMode { id liquidationThreshold label }
+    lastUpdateTimestamp
+    siloedBorrowing
+    liquidityRate
+    variableBorrowRate
+    price { priceInEth }
+  }
+}"#;
+
+#[derive(Deserialize)]
+struct GraphQlResponse {
+    data: Option<ReservesData>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ReservesData {
+    #[serde(rename = "_meta")]
+    meta: Meta,
+    reserves: Vec<SubgraphReserve>,
+}
+
+#[derive(Deserialize)]
+struct Meta {
+    block: MetaBlock,
+}
+
+#[derive(Deserialize)]
+struct MetaBlock {
+    number: u64,
+    /// Not reported by older graph-node versions
+    #[serde(default)]
+    timestamp: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubgraphReserve {
+    underlying_asset: String,
+    #[serde(deserialize_with = "number")]
+    decimals: u8,
+    #[serde(deserialize_with = "number")]
+    reserve_liquidation_threshold: u16,
+    #[serde(rename = "totalATokenSupply", deserialize_with = "number")]
+    total_atoken_supply: u128,
+    #[serde(deserialize_with = "number")]
+    total_principal_stable_debt: u128,
+    #[serde(deserialize_with = "number")]
+    total_current_variable_debt: u128,
+    #[serde(deserialize_with = "number")]
+    supply_cap: u64,
+    #[serde(deserialize_with = "number")]
+    borrow_cap: u64,
+    e_mode: Option<SubgraphEMode>,
+    #[serde(deserialize_with = "number")]
+    last_update_timestamp: u64,
+    #[serde(default)]
+    siloed_borrowing: bool,
+    #[serde(default, deserialize_with = "number")]
+    liquidity_rate: u128,
+    #[serde(default, deserialize_with = "number")]
+    variable_borrow_rate: u128,
+    price: SubgraphPrice,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubgraphEMode {
+    #[serde(deserialize_with = "number")]
+    id: u8,
+    #[serde(deserialize_with = "number")]
+    liquidation_threshold: u16,
+    label: String,
+}
+
+/// Despite the name, V3 subgraphs hold the oracle's base currency price here
+/// (USD scaled by 1e8 on mainnet), exactly what the RPC fetch reads
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubgraphPrice {
+    #[serde(deserialize_with = "number")]
+    price_in_eth: u128,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Number {
+    Text(String),
+    Int(u64),
+}
+
+/// GraphQL sends BigInt and ID as strings but Int as a number; accept either
+fn number<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: TryFrom<u128>,
+{
+    let value = match Number::deserialize(deserializer)? {
+        Number::Text(text) => text.parse::<u128>().map_err(serde::de::Error::custom)?,
+        Number::Int(int) => u128::from(int),
+    };
+    T::try_from(value).map_err(|_| serde::de::Error::custom(format!("{} out of range", value)))
+}
+
+/// Fetches an input from an Aave V3 subgraph
+pub struct SubgraphFetcher {
+    url: String,
+    rpc_options: RpcOptions,
+    filter: ReserveFilter,
+    block: Option<u64>,
+    timestamp: Option<u64>,
+}
+
+impl SubgraphFetcher {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            rpc_options: RpcOptions::default(),
+            filter: ReserveFilter::default(),
+            block: None,
+            timestamp: None,
+        }
+    }
+
+    /// Query with these timeouts (the subgraph is one HTTP request)
+    pub fn with_rpc_options(mut self, options: RpcOptions) -> Self {
+        self.rpc_options = options;
+        self
+    }
+
+    /// Only score the reserves the filter allows
+    pub fn with_reserve_filter(mut self, filter: ReserveFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Query the subgraph as of this block instead of its latest one
+    /// Fails if the subgraph hasn't indexed that far yet
+    pub fn with_block(mut self, block: u64) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Stamp the input with this timestamp instead of the indexed block's
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Query every reserve and prepare the input for the zkVM
+    pub async fn fetch_reserves(&self) -> Result<AaveInput> {
+        status!(" Querying subgraph: {}", redact::url(&self.url));
+        if let Some(block) = self.block {
+            status!(" Pinned to block: {}", block);
+        }
+
+        let request = serde_json::json!({
+            "query": RESERVES_QUERY,
+            "variables": { "block": self.block.map(|number| serde_json::json!({ "number": number })) },
+        });
+        // reqwest errors name the URL, which would print the API key
+        let body = build_http_client(&self.rpc_options)?
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_vec(&request)?)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| eyre!("Subgraph request failed: {}", e.without_url()))?
+            .bytes()
+            .await
+            .map_err(|e| eyre!("Subgraph response failed: {}", e.without_url()))?;
+
+        let input = self.input_from_response(&body)?;
+        status!("✓ Subgraph indexed to block {}", input.block_number.unwrap_or_default());
+        status!("✓ Fetched {} reserves", input.reserves.len());
+        if !input.excluded.is_empty() {
+            status!("✓ Excluded {} reserves by filter", input.excluded.len());
+        }
+        Ok(input)
+    }
+
+    fn input_from_response(&self, body: &[u8]) -> Result<AaveInput> {
+        let response: GraphQlResponse = serde_json::from_slice(body)?;
+        if let Some(error) = response.errors.first() {
+            return Err(eyre!("Subgraph query failed: {}", error.message));
+        }
+        let data = response.data.ok_or_else(|| eyre!("Subgraph returned no data"))?;
+        if data.reserves.len() >= MAX_RESERVES {
+            return Err(eyre!("Subgraph returned {} reserves, the most one query can; some may be missing", MAX_RESERVES));
+        }
+
+        let addresses = data.reserves
+            .iter()
+            .map(|reserve| {
+                Address::from_str(&reserve.underlying_asset)
+                    .map_err(|e| eyre!("Invalid reserve address '{}': {}", reserve.underlying_asset, e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let (scored, excluded) = self.filter.apply(&addresses);
+
+        let mut reserves = Vec::new();
+        let mut emode_categories: Vec<EModeCategory> = Vec::new();
+        for (reserve, address) in data.reserves.into_iter().zip(addresses) {
+            if !scored.contains(&address) {
+                continue;
+            }
+            if let Some(category) = &reserve.e_mode {
+                if !emode_categories.iter().any(|known| known.id == category.id) {
+                    emode_categories.push(EModeCategory {
+                        id: category.id,
+                        liquidation_threshold: category.liquidation_threshold,
+                        label: category.label.clone(),
+                    });
+                }
+            }
+            reserves.push(reserve_from_subgraph(address, reserve));
+        }
+        if reserves.is_empty() {
+            return Err(eyre!("Subgraph returned no reserves to score"));
+        }
+        emode_categories.sort_by_key(|category| category.id);
+
+        let block = data.meta.block;
+        let timestamp = snapshot_timestamp(self.timestamp, block.timestamp)?;
+        Ok(build_input(AaveVersion::V3, reserves, &excluded, timestamp)
+            .with_emode_categories(emode_categories)
+            .with_block_number(Some(block.number)))
+    }
+}
+
+/// Map a subgraph reserve onto the same fields the RPC fetch fills in
+/// The subgraph doesn't index isolation-mode debt, so it's left at 0
+fn reserve_from_subgraph(asset: Address, reserve: SubgraphReserve) -> AaveReserveData {
+    AaveReserveData {
+        token_address: format!("{:?}", asset),
+        total_atoken: reserve.total_atoken_supply,
+        total_stable_debt: reserve.total_principal_stable_debt,
+        total_variable_debt: reserve.total_current_variable_debt,
+        price_usd: reserve.price.price_in_eth,
+        decimals: reserve.decimals,
+        liquidation_threshold: Some(reserve.reserve_liquidation_threshold),
+        volatility_bps: None,
+        isolation_mode_debt: 0,
+        emode_category: reserve.e_mode.map(|category| category.id).unwrap_or_default(),
+        supply_cap: reserve.supply_cap,
+        borrow_cap: reserve.borrow_cap,
+        last_update_timestamp: reserve.last_update_timestamp,
+        siloed_borrowing: reserve.siloed_borrowing,
+        liquidity_rate: reserve.liquidity_rate,
+        variable_borrow_rate: reserve.variable_borrow_rate,
+        saturated: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const DAI: &str = "0x6b175474e89094c44da98b954eedeac495271d0f";
+
+    /// A response recorded from an Aave V3 subgraph, trimmed to three reserves
+    fn recorded_response() -> Vec<u8> {
+        std::fs::read(format!("{}/tests/fixtures/subgraph_reserves.json", env!("CARGO_MANIFEST_DIR"))).unwrap()
+    }
+
+    fn fetcher() -> SubgraphFetcher {
+        SubgraphFetcher::new("https://gateway.thegraph.com/api/key/subgraphs/id/aave-v3".to_string())
+    }
+
+    #[test]
+    fn test_recorded_response_becomes_input() {
+        let input = fetcher().input_from_response(&recorded_response()).unwrap();
+
+        // Stamped with the block the subgraph had indexed, not the clock
+        assert_eq!(input.block_number, Some(18_499_995));
+        assert_eq!(input.timestamp, 1_699_000_000);
+        assert_eq!(input.protocol_name, "Aave V3");
+
+        let addresses: Vec<&str> = input.reserves.iter().map(|r| r.token_address.as_str()).collect();
+        assert_eq!(addresses, vec![DAI, USDC, WETH]);
+
+        let weth = &input.reserves[2];
+        assert_eq!(weth.total_atoken, 1_250_000 * 10u128.pow(18));
+        assert_eq!(weth.total_variable_debt, 900_000 * 10u128.pow(18));
+        assert_eq!(weth.price_usd, 180_000_000_000);
+        assert_eq!(weth.liquidation_threshold, Some(8_300));
+        assert_eq!(weth.emode_category, 1);
+        assert_eq!((weth.supply_cap, weth.borrow_cap), (1_800_000, 1_400_000));
+        assert_eq!(weth.last_update_timestamp, 1_698_999_850);
+        assert_eq!(weth.liquidity_rate, 18_500_000_000_000_000_000_000_000);
+        assert_eq!(weth.variable_borrow_rate, 27_400_000_000_000_000_000_000_000);
+        assert_eq!(input.reserves[1].total_stable_debt, 1_000_000_000_000);
+        assert_eq!(input.emode_categories, vec![EModeCategory {
+            id: 1,
+            liquidation_threshold: 9_500,
+            label: "ETH correlated".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn test_filter_and_timestamp_override_apply() {
+        let filter = ReserveFilter { include: Vec::new(), exclude: vec![DAI.parse().unwrap()] };
+        let input = fetcher()
+            .with_reserve_filter(filter)
+            .with_timestamp(1_700_000_000)
+            .input_from_response(&recorded_response())
+            .unwrap();
+
+        assert_eq!(input.reserves.len(), 2);
+        assert_eq!(input.excluded, vec![DAI.to_string()]);
+        assert_eq!(input.timestamp, 1_700_000_000);
+    }
+
+    #[test]
+    fn test_query_errors_are_reported() {
+        let body = br#"{"errors": [{"message": "subgraph has only indexed up to block number 18499995"}]}"#;
+        let err = fetcher().input_from_response(body).unwrap_err();
+        assert!(err.to_string().contains("only indexed up to block"), "{}", err);
+    }
+}
@@ -0,0 +1,305 @@
+// Idempotent Submission
+// A transaction can land on-chain even if the host dies before its receipt
+// comes back. The tx hash is persisted as soon as it's broadcast, and later
+// runs check that hash before sending anything new, so a proof is never
+// submitted twice by accident.
+
+use alloy::primitives::TxHash;
+use derisk_type::{SafetyScoreOutput, ScoreStatus};
+use eyre::{Result, eyre};
+use serde::{Deserialize, Serialize};
+
+use crate::journal_abi::journal_digest;
+
+/// File in the output directory holding the last broadcast submission
+pub const SUBMISSION_FILE: &str = "last_submission.json";
+
+/// On-chain state of a previously broadcast transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxStatus {
+    /// Mined in the given block
+    Confirmed { block: u64 },
+    /// Known to the node but not mined yet
+    Pending,
+    /// Not known to the node (dropped or never propagated)
+    Unknown,
+}
+
+/// The chain operations a submission needs
+/// Implemented by `OracleSubmitter`; tests use an in-memory mock
+#[allow(async_fn_in_trait)]
+pub trait SubmissionBackend {
+    /// Broadcast the proof and return as soon as the tx hash is known
+    async fn send(&self, journal: &[u8], seal: &[u8]) -> Result<TxHash>;
+    /// Look up a transaction by hash
+    async fn status(&self, tx_hash: TxHash) -> Result<TxStatus>;
+    /// Wait until the transaction is mined, returning its block number
+    async fn wait_for_confirmation(&self, tx_hash: TxHash) -> Result<u64>;
+}
+
+/// What was last broadcast, saved to `SUBMISSION_FILE`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmissionRecord {
+    pub tx_hash: TxHash,
+    /// sha256 of the submitted journal (hex), ties the tx to a specific proof
+    pub journal_digest: String,
+    /// Block the tx was mined in; None until confirmed
+    pub confirmed_block: Option<u64>,
+}
+
+impl SubmissionRecord {
+    fn path(output_dir: &str) -> String {
+        format!("{}/{}", output_dir, SUBMISSION_FILE)
+    }
+
+    /// Load the record from the output directory, if there is one
+    pub fn load(output_dir: &str) -> Result<Option<Self>> {
+        match std::fs::read_to_string(Self::path(output_dir)) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the record to the output directory
+    pub fn save(&self, output_dir: &str) -> Result<()> {
+        std::fs::create_dir_all(output_dir)?;
+        std::fs::write(Self::path(output_dir), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Refuse an output the oracle mustn't publish
+/// A hypothetical score was computed on prices the market never quoted (see
+/// scenario.rs). A NoData or Incomplete score doesn't measure the whole
+/// protocol, so it is only sent with `any_status`
+pub fn check_submittable(output: &SafetyScoreOutput, any_status: bool) -> Result<()> {
+    if output.hypothetical {
+        return Err(eyre!("Refusing to submit a hypothetical score: it was computed with overridden prices"));
+    }
+    match output.status {
+        ScoreStatus::Ok | ScoreStatus::Insolvent => Ok(()),
+        status if any_status => {
+            status!("⚠ Submitting a {:?} score (--submit-any-status)", status);
+            Ok(())
+        }
+        status => Err(eyre!(
+            "Refusing to submit a {:?} score; pass --submit-any-status to send it anyway",
+            status
+        )),
+    }
+}
+
+/// Submit a proof unless this exact journal was already submitted
+/// A confirmed earlier submission is returned as-is. A pending one is only
+/// waited on with `resume`; otherwise this errors rather than risk a duplicate.
+/// A previous tx the node no longer knows about is re-sent.
+pub async fn submit_once<B: SubmissionBackend>(
+    backend: &B,
+    journal: &[u8],
+    seal: &[u8],
+    output_dir: &str,
+    resume: bool,
+) -> Result<TxHash> {
+    let digest = hex::encode(journal_digest(journal));
+
+    if let Some(mut record) = SubmissionRecord::load(output_dir)? {
+        if record.journal_digest == digest {
+            match backend.status(record.tx_hash).await? {
+                TxStatus::Confirmed { block } => {
+                    status!("✓ This journal was already submitted in tx {} (block {})", record.tx_hash, block);
+                    if record.confirmed_block.is_none() {
+                        record.confirmed_block = Some(block);
+                        record.save(output_dir)?;
+                    }
+                    return Ok(record.tx_hash);
+                }
+                TxStatus::Pending if resume => {
+                    status!("⏳ Resuming pending submission {}...", record.tx_hash);
+                    return confirm(backend, record, output_dir).await;
+                }
+                TxStatus::Pending => {
+                    return Err(eyre!(
+                        "A submission of this journal is still pending (tx {}). \
+                         Re-run with --resume-submission to wait for it instead of sending again",
+                        record.tx_hash
+                    ));
+                }
+                TxStatus::Unknown => {
+                    status!("⚠ Previous submission {} was dropped, sending again", record.tx_hash);
+                }
+            }
+        }
+    } else if resume {
+        status!("⚠ No previous submission found in {}, sending a new one", output_dir);
+    }
+
+    let tx_hash = backend.send(journal, seal).await?;
+
+    // Persist before waiting so a crash from here on can be resumed
+    let record = SubmissionRecord {
+        tx_hash,
+        journal_digest: digest,
+        confirmed_block: None,
+    };
+    record.save(output_dir)?;
+    status!("⏳ Transaction {} sent, waiting for confirmation...", tx_hash);
+
+    confirm(backend, record, output_dir).await
+}
+
+/// Wait for a recorded submission to be mined and mark it confirmed
+async fn confirm<B: SubmissionBackend>(
+    backend: &B,
+    mut record: SubmissionRecord,
+    output_dir: &str,
+) -> Result<TxHash> {
+    let block = backend.wait_for_confirmation(record.tx_hash).await?;
+    record.confirmed_block = Some(block);
+    record.save(output_dir)?;
+
+    status!("✓ Transaction confirmed!");
+    status!("  - Block: {}", block);
+    Ok(record.tx_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derisk_type::{compute_output, AaveInput, AaveReserveData};
+    use std::cell::{Cell, RefCell};
+
+    /// In-memory chain: sent txs stay pending until `mine` is called
+    #[derive(Default)]
+    struct MockBackend {
+        sent: RefCell<Vec<TxHash>>,
+        mined: RefCell<Vec<TxHash>>,
+        /// Simulates the process dying while waiting for the receipt
+        crash_while_waiting: Cell<bool>,
+    }
+
+    impl MockBackend {
+        fn mine(&self) {
+            self.mined.borrow_mut().extend(self.sent.borrow().iter().copied());
+        }
+    }
+
+    impl SubmissionBackend for MockBackend {
+        async fn send(&self, _journal: &[u8], _seal: &[u8]) -> Result<TxHash> {
+            let mut sent = self.sent.borrow_mut();
+            let tx_hash = TxHash::with_last_byte(sent.len() as u8 + 1);
+            sent.push(tx_hash);
+            Ok(tx_hash)
+        }
+
+        async fn status(&self, tx_hash: TxHash) -> Result<TxStatus> {
+            if self.mined.borrow().contains(&tx_hash) {
+                Ok(TxStatus::Confirmed { block: 100 })
+            } else if self.sent.borrow().contains(&tx_hash) {
+                Ok(TxStatus::Pending)
+            } else {
+                Ok(TxStatus::Unknown)
+            }
+        }
+
+        async fn wait_for_confirmation(&self, tx_hash: TxHash) -> Result<u64> {
+            if self.crash_while_waiting.get() {
+                return Err(eyre!("process killed"));
+            }
+            self.mined.borrow_mut().push(tx_hash);
+            Ok(100)
+        }
+    }
+
+    /// $1,000 of USDC with $600 borrowed
+    fn output() -> SafetyScoreOutput {
+        let usdc = AaveReserveData::builder("0xUSDC")
+            .decimals(6)
+            .total_atoken(1_000_000_000)
+            .total_variable_debt(600_000_000)
+            .build();
+        compute_output(&AaveInput::new(vec![usdc], "Test".to_string(), 1_700_000_000))
+    }
+
+    #[test]
+    fn test_hypothetical_score_is_not_submittable() {
+        assert!(check_submittable(&output(), false).is_ok());
+        assert!(check_submittable(&output().with_hypothetical(true), false).is_err());
+        assert!(check_submittable(&output().with_hypothetical(true), true).is_err());
+    }
+
+    #[test]
+    fn test_only_ok_and_insolvent_are_submitted_by_default() {
+        assert!(check_submittable(&output().with_status(ScoreStatus::Insolvent), false).is_ok());
+        for status in [ScoreStatus::NoData, ScoreStatus::Incomplete] {
+            assert!(check_submittable(&output().with_status(status), false).is_err());
+            assert!(check_submittable(&output().with_status(status), true).is_ok());
+        }
+    }
+
+    fn temp_output_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("derisk_submission_{}", name));
+        std::fs::remove_dir_all(&dir).ok();
+        dir.to_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_then_resumed_submission() {
+        let output_dir = temp_output_dir("resume");
+        let backend = MockBackend::default();
+        let journal = b"journal".to_vec();
+
+        // First run: the tx is broadcast, then the host dies before the receipt
+        backend.crash_while_waiting.set(true);
+        assert!(submit_once(&backend, &journal, &[], &output_dir, false).await.is_err());
+        let record = SubmissionRecord::load(&output_dir).unwrap().expect("hash persisted after send");
+        assert_eq!(record.confirmed_block, None);
+        assert_eq!(backend.sent.borrow().len(), 1);
+
+        // Second run without --resume-submission refuses to send a duplicate
+        backend.crash_while_waiting.set(false);
+        let err = submit_once(&backend, &journal, &[], &output_dir, false).await.unwrap_err();
+        assert!(err.to_string().contains("--resume-submission"));
+        assert_eq!(backend.sent.borrow().len(), 1);
+
+        // Resuming reconnects to the same tx
+        let tx_hash = submit_once(&backend, &journal, &[], &output_dir, true).await.unwrap();
+        assert_eq!(tx_hash, record.tx_hash);
+        assert_eq!(backend.sent.borrow().len(), 1);
+        assert_eq!(SubmissionRecord::load(&output_dir).unwrap().unwrap().confirmed_block, Some(100));
+
+        // Once confirmed, running again is a no-op
+        let again = submit_once(&backend, &journal, &[], &output_dir, false).await.unwrap();
+        assert_eq!(again, record.tx_hash);
+        assert_eq!(backend.sent.borrow().len(), 1);
+
+        std::fs::remove_dir_all(&output_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_new_journal_is_sent_and_dropped_tx_is_resent() {
+        let output_dir = temp_output_dir("resend");
+        let backend = MockBackend::default();
+
+        let first = submit_once(&backend, b"old", &[], &output_dir, false).await.unwrap();
+        backend.mine();
+
+        // A different journal is a new proof and gets its own tx
+        let second = submit_once(&backend, b"new", &[], &output_dir, false).await.unwrap();
+        assert_ne!(first, second);
+        assert_eq!(backend.sent.borrow().len(), 2);
+
+        // A recorded tx the node has forgotten is sent again
+        SubmissionRecord {
+            tx_hash: TxHash::with_last_byte(0xff),
+            journal_digest: hex::encode(journal_digest(b"new")),
+            confirmed_block: None,
+        }
+        .save(&output_dir)
+        .unwrap();
+        submit_once(&backend, b"new", &[], &output_dir, false).await.unwrap();
+        assert_eq!(backend.sent.borrow().len(), 3);
+
+        std::fs::remove_dir_all(&output_dir).ok();
+    }
+}
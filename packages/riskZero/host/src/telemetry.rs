@@ -0,0 +1,221 @@
+// Proving Telemetry
+// Records what each proof cost (cycles, wall-clock per phase, seal size) next
+// to the artifacts, and optionally appends it to a CSV so proving cost can be
+// charted against reserve count across versions.
+
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::receipt_format::ReceiptFormat;
+
+/// File in the output directory describing the artifacts of the last proof
+pub const MANIFEST_FILE: &str = "proof_manifest.json";
+
+/// Column order of the --bench-output CSV
+const CSV_HEADER: &str = "recorded_at,version,image_id,reserve_count,total_cycles,segments,stark_ms,groth16_ms,total_ms,seal_bytes,journal_bytes";
+
+/// Wall-clock marks around the proving phases
+/// Instants are monotonic, so a phase can never report a negative duration
+#[derive(Debug, Clone, Copy)]
+pub struct PhaseTimer {
+    started: Instant,
+    stark_done: Option<Instant>,
+    groth16_done: Option<Instant>,
+}
+
+impl PhaseTimer {
+    /// Start timing, just before the STARK proof begins
+    pub fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            stark_done: None,
+            groth16_done: None,
+        }
+    }
+
+    /// Mark the end of the STARK phase
+    pub fn stark_done(&mut self) {
+        self.stark_done = Some(Instant::now());
+    }
+
+    /// Mark the end of the Groth16 phase
+    pub fn groth16_done(&mut self) {
+        self.groth16_done = Some(Instant::now());
+    }
+
+    /// Time spent generating the STARK proof
+    pub fn stark(&self) -> Duration {
+        self.stark_done.map_or(Duration::ZERO, |done| done - self.started)
+    }
+
+    /// Time spent wrapping the STARK proof into Groth16
+    pub fn groth16(&self) -> Duration {
+        match (self.stark_done, self.groth16_done) {
+            (Some(stark), Some(groth16)) => groth16 - stark,
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// Cost of one proof, as measured while proving
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofTelemetry {
+    /// Unix time the proof finished
+    pub recorded_at: u64,
+    /// Host version that produced the proof
+    pub version: String,
+    /// Guest image ID (hex)
+    pub image_id: String,
+    pub reserve_count: usize,
+    pub total_cycles: u64,
+    pub segments: usize,
+    pub stark_ms: u64,
+    pub groth16_ms: u64,
+    /// STARK + Groth16
+    pub total_ms: u64,
+    pub seal_bytes: usize,
+    pub journal_bytes: usize,
+}
+
+/// Proving measurements taken before the artifacts exist
+#[derive(Debug, Clone, Copy)]
+pub struct ProvingStats {
+    pub reserve_count: usize,
+    pub total_cycles: u64,
+    pub segments: usize,
+    pub timer: PhaseTimer,
+}
+
+impl ProofTelemetry {
+    /// Combine proving measurements with the sizes of the finished artifacts
+    pub fn new(stats: &ProvingStats, image_id: String, seal_bytes: usize, journal_bytes: usize) -> Result<Self> {
+        let stark_ms = stats.timer.stark().as_millis() as u64;
+        let groth16_ms = stats.timer.groth16().as_millis() as u64;
+        Ok(Self {
+            recorded_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            image_id,
+            reserve_count: stats.reserve_count,
+            total_cycles: stats.total_cycles,
+            segments: stats.segments,
+            stark_ms,
+            groth16_ms,
+            total_ms: stark_ms + groth16_ms,
+            seal_bytes,
+            journal_bytes,
+        })
+    }
+
+    /// One CSV row in `CSV_HEADER` order
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            self.recorded_at, self.version, self.image_id, self.reserve_count, self.total_cycles,
+            self.segments, self.stark_ms, self.groth16_ms, self.total_ms, self.seal_bytes, self.journal_bytes
+        )
+    }
+}
+
+/// Artifacts of the last proof and what producing them cost
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofManifest {
+    /// Artifact file names, relative to the output directory
+    pub artifacts: Vec<String>,
+    /// How the receipt artifact is serialized; manifests from before this
+    /// was recorded always mean bincode
+    #[serde(default)]
+    pub receipt_format: ReceiptFormat,
+    pub telemetry: ProofTelemetry,
+}
+
+impl ProofManifest {
+    /// Write the manifest to `MANIFEST_FILE` in the output directory
+    pub fn save(&self, output_dir: &str) -> Result<String> {
+        let path = format!("{}/{}", output_dir, MANIFEST_FILE);
+        std::fs::create_dir_all(output_dir)?;
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+
+    /// Load the manifest from the output directory, if a proof was saved there
+    pub fn load(output_dir: &str) -> Result<Option<Self>> {
+        match std::fs::read_to_string(format!("{}/{}", output_dir, MANIFEST_FILE)) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Append a row to the benchmark CSV, writing the header if the file is new
+pub fn append_bench_row(path: &str, telemetry: &ProofTelemetry) -> Result<()> {
+    let is_new = std::fs::metadata(path).map(|m| m.len() == 0).unwrap_or(true);
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    if is_new {
+        writeln!(file, "{}", CSV_HEADER)?;
+    }
+    writeln!(file, "{}", telemetry.csv_row())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timed_stats() -> ProvingStats {
+        let mut timer = PhaseTimer::start();
+        std::thread::sleep(Duration::from_millis(5));
+        timer.stark_done();
+        std::thread::sleep(Duration::from_millis(5));
+        timer.groth16_done();
+
+        ProvingStats {
+            reserve_count: 12,
+            total_cycles: 1_048_576,
+            segments: 1,
+            timer,
+        }
+    }
+
+    #[test]
+    fn test_timings_populated_and_monotonic() {
+        let telemetry = ProofTelemetry::new(&timed_stats(), "0xabc".to_string(), 256, 120).unwrap();
+
+        assert!(telemetry.stark_ms >= 5);
+        assert!(telemetry.groth16_ms >= 5);
+        assert_eq!(telemetry.total_ms, telemetry.stark_ms + telemetry.groth16_ms);
+        assert!(telemetry.recorded_at > 0);
+        assert_eq!(telemetry.reserve_count, 12);
+        assert_eq!(telemetry.seal_bytes, 256);
+    }
+
+    #[test]
+    fn test_unfinished_phases_report_zero() {
+        let timer = PhaseTimer::start();
+        assert_eq!(timer.stark(), Duration::ZERO);
+        assert_eq!(timer.groth16(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_bench_csv_appends_rows_under_one_header() {
+        let path = std::env::temp_dir().join("derisk_bench_test.csv");
+        std::fs::remove_file(&path).ok();
+        let path = path.to_str().unwrap();
+
+        let first = ProofTelemetry::new(&timed_stats(), "0xabc".to_string(), 256, 120).unwrap();
+        let second = ProofTelemetry::new(&timed_stats(), "0xabc".to_string(), 260, 120).unwrap();
+        append_bench_row(path, &first).unwrap();
+        append_bench_row(path, &second).unwrap();
+
+        let csv = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).ok();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines, vec![CSV_HEADER, first.csv_row().as_str(), second.csv_row().as_str()]);
+        assert_eq!(lines[1].split(',').count(), CSV_HEADER.split(',').count());
+    }
+}
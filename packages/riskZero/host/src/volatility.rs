@@ -0,0 +1,84 @@
+// Reserve Volatility
+// Volatility isn't available from the Aave contracts, so risk teams supply it
+// as a JSON file mapping token address → volatility in basis points, e.g.
+// { "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48": 500 }
+
+use derisk_type::AaveInput;
+use eyre::{Result, eyre};
+use std::collections::HashMap;
+
+/// Volatility per token address (lowercase), in basis points
+pub type VolatilityTable = HashMap<String, u16>;
+
+/// Load a volatility file, normalizing addresses to lowercase
+pub fn load_volatility(path: &str) -> Result<VolatilityTable> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| eyre!("Failed to read volatility file {}: {}", path, e))?;
+    let table: HashMap<String, u16> = serde_json::from_str(&json)
+        .map_err(|e| eyre!("Failed to parse volatility file {}: {}", path, e))?;
+
+    Ok(table
+        .into_iter()
+        .map(|(address, bps)| (address.to_lowercase(), bps))
+        .collect())
+}
+
+/// Attach volatility to every reserve listed in the table
+/// Reserves not in the table are left as they are
+/// Returns the number of reserves that were updated
+pub fn apply_volatility(input: &mut AaveInput, table: &VolatilityTable) -> usize {
+    let mut applied = 0;
+    for reserve in &mut input.reserves {
+        if let Some(&bps) = table.get(&reserve.token_address.to_lowercase()) {
+            reserve.volatility_bps = Some(bps);
+            applied += 1;
+        }
+    }
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derisk_type::AaveReserveData;
+
+    fn reserve(token_address: &str) -> AaveReserveData {
+        AaveReserveData {
+            token_address: token_address.to_string(),
+            total_atoken: 1_000_000_000,
+            total_stable_debt: 0,
+            total_variable_debt: 0,
+            price_usd: 100_000_000,
+            decimals: 6,
+            liquidation_threshold: None,
+            volatility_bps: None,
+            isolation_mode_debt: 0,
+            emode_category: 0,
+            supply_cap: 0,
+            borrow_cap: 0,
+            last_update_timestamp: 0,
+            siloed_borrowing: false,
+            liquidity_rate: 0,
+            variable_borrow_rate: 0,
+            saturated: false,
+        }
+    }
+
+    #[test]
+    fn test_apply_matches_addresses_case_insensitively() {
+        let mut input = AaveInput::new(
+            vec![reserve("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"), reserve("0xDAI")],
+            "Volatility Test".to_string(),
+            1234567890,
+        );
+
+        let path = std::env::temp_dir().join("derisk_volatility_test.json");
+        std::fs::write(&path, r#"{ "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48": 500 }"#).unwrap();
+        let table = load_volatility(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(apply_volatility(&mut input, &table), 1);
+        assert_eq!(input.reserves[0].volatility_bps, Some(500));
+        assert_eq!(input.reserves[1].volatility_bps, None);
+    }
+}
@@ -0,0 +1,542 @@
+// Watch Mode
+// Long-running loop for keepers: fetch → execute → (optionally prove + submit)
+// on a fixed interval. A failed cycle is logged and retried with backoff
+// rather than taking the daemon down.
+//
+// With --json-lines each cycle's result is also written to stdout as one JSON
+// object per line, for log pipelines; everything else goes to stderr.
+//
+// With --pipeline the next cycle's fetch runs while the current cycle proves
+// and submits, and that cycle starts as soon as the submission is done rather
+// than after --interval. At most one fetched input is held ahead, and it is
+// dropped unused on shutdown.
+
+use derisk_type::{AaveInput, SafetyScoreOutput, UsdAmount};
+use eyre::Result;
+use serde::Serialize;
+use std::{future::Future, io::Write, time::Duration};
+
+use crate::guest::execute_guest;
+use crate::shutdown::{Phase, ShutdownSignal};
+
+/// Settings for the watch loop
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    /// Time between cycles
+    pub interval: Duration,
+    /// Minimum score change (scaled by 1e4) since the last submission before re-submitting
+    pub submit_threshold: u64,
+    /// Whether qualifying cycles should prove and submit at all
+    pub submit: bool,
+    /// Upper bound on the delay after repeated failures
+    pub max_backoff: Duration,
+    /// Stop after this many cycles (None runs until interrupted)
+    pub max_iterations: Option<u64>,
+    /// Segment size limit passed to the executor (see `guest::build_env`)
+    pub segment_limit_po2: Option<u32>,
+    /// Write a `CycleLine` per cycle
+    pub json_lines: bool,
+    /// Fetch the next input while proving the current one
+    pub pipeline: bool,
+}
+
+/// What happened in a single cycle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CycleOutcome {
+    /// Score computed and submitted on-chain
+    Submitted { score: u64, tx_hash: String },
+    /// Score computed but the change was too small (or submission disabled)
+    Skipped { score: u64 },
+    /// Fetching, executing or submitting failed
+    Failed,
+}
+
+/// One cycle's result as --json-lines writes it
+/// Values are left out (null) when the cycle failed before computing them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CycleLine {
+    /// Unix time the cycle finished
+    pub timestamp: u64,
+    /// Block the input was read at, when pinned or known
+    pub block: Option<u64>,
+    /// Safety score, scaled by 1e4
+    pub score: Option<u64>,
+    /// Totals in USD, scaled by 1e8
+    pub assets: Option<u128>,
+    pub liabilities: Option<u128>,
+    pub submitted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Counters returned when the loop stops
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WatchStats {
+    pub cycles: u64,
+    pub submissions: u64,
+    pub failures: u64,
+}
+
+/// Whether a new score differs enough from the last submitted one to submit
+pub fn should_submit(last_submitted: Option<u64>, score: u64, threshold: u64) -> bool {
+    match last_submitted {
+        None => true,
+        Some(last) => last.abs_diff(score) >= threshold,
+    }
+}
+
+/// Delay before the next cycle: the interval, doubled per consecutive failure
+pub fn backoff_delay(interval: Duration, consecutive_failures: u32, max_backoff: Duration) -> Duration {
+    if consecutive_failures == 0 {
+        return interval;
+    }
+    let factor = 1u32.checked_shl(consecutive_failures.min(16)).unwrap_or(u32::MAX);
+    interval.saturating_mul(factor).min(max_backoff.max(interval))
+}
+
+/// Run the watch loop until interrupted or `max_iterations` is reached
+/// `fetch` produces a fresh input each cycle; `submit` proves and submits it,
+/// returning the transaction hash
+pub async fn run_watch<F, FFut, S, SFut>(
+    config: &WatchConfig,
+    shutdown: &ShutdownSignal,
+    fetch: F,
+    submit: S,
+) -> WatchStats
+where
+    F: FnMut() -> FFut,
+    FFut: Future<Output = Result<AaveInput>>,
+    S: FnMut(AaveInput, SafetyScoreOutput) -> SFut,
+    SFut: Future<Output = Result<String>>,
+{
+    run_watch_to(config, shutdown, fetch, submit, &mut std::io::stdout()).await
+}
+
+/// `run_watch`, writing JSON lines (when enabled) to `out`
+pub async fn run_watch_to<F, FFut, S, SFut>(
+    config: &WatchConfig,
+    shutdown: &ShutdownSignal,
+    mut fetch: F,
+    mut submit: S,
+    out: &mut impl Write,
+) -> WatchStats
+where
+    F: FnMut() -> FFut,
+    FFut: Future<Output = Result<AaveInput>>,
+    S: FnMut(AaveInput, SafetyScoreOutput) -> SFut,
+    SFut: Future<Output = Result<String>>,
+{
+    let mut stats = WatchStats::default();
+    let mut last_submitted: Option<u64> = None;
+    let mut consecutive_failures: u32 = 0;
+    // Fetched during the last cycle's proof, for the next cycle (--pipeline)
+    let mut prefetched: Option<Result<AaveInput>> = None;
+
+    status!("👀 Watch mode: every {}s, submit threshold {:.4}%",
+        config.interval.as_secs(), config.submit_threshold as f64 / 1e4);
+
+    loop {
+        if shutdown.is_requested() {
+            break;
+        }
+        if config.max_iterations.is_some_and(|max| stats.cycles >= max) {
+            break;
+        }
+
+        stats.cycles += 1;
+        let cycle = stats.cycles;
+        shutdown.enter(Phase::Fetch);
+
+        let mut line = CycleLine::default();
+        let outcome = run_cycle(config, shutdown, last_submitted, &mut fetch, &mut submit, &mut line, &mut prefetched).await;
+        match &outcome {
+            Ok(CycleOutcome::Submitted { score, .. }) => {
+                last_submitted = Some(*score);
+                stats.submissions += 1;
+                consecutive_failures = 0;
+                status!("[cycle {}] ✓ score {:.4}% submitted", cycle, *score as f64 / 1e4);
+            }
+            Ok(CycleOutcome::Skipped { score }) => {
+                consecutive_failures = 0;
+                status!("[cycle {}] ✓ score {:.4}% (no submission)", cycle, *score as f64 / 1e4);
+            }
+            Ok(CycleOutcome::Failed) | Err(_) => {
+                stats.failures += 1;
+                consecutive_failures += 1;
+                if let Err(e) = &outcome {
+                    status!("[cycle {}] ⚠ failed: {}", cycle, e);
+                }
+            }
+        }
+
+        if config.json_lines {
+            line.timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs());
+            line.error = match &outcome {
+                Ok(CycleOutcome::Failed) => Some("interrupted".to_string()),
+                Err(e) => Some(e.to_string()),
+                Ok(_) => None,
+            };
+            // A log line that can't be written mustn't stop the daemon
+            if let Err(e) = write_json_line(out, &line) {
+                status!("[cycle {}] ⚠ failed to write JSON line: {}", cycle, e);
+            }
+        }
+
+        if config.max_iterations.is_some_and(|max| stats.cycles >= max) {
+            break;
+        }
+
+        // The next input is already here, fetched while this cycle proved.
+        // After a failure, or a failed prefetch, it's dropped and the cycle
+        // waits as usual, so a failing submit can't retry in a tight loop
+        if consecutive_failures == 0 && matches!(prefetched, Some(Ok(_))) {
+            continue;
+        }
+        prefetched = None;
+
+        let delay = backoff_delay(config.interval, consecutive_failures, config.max_backoff);
+        if consecutive_failures > 0 {
+            status!("[cycle {}] backing off for {}s ({} consecutive failures)",
+                cycle, delay.as_secs(), consecutive_failures);
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.cancelled() => break,
+        }
+    }
+
+    status!("\n👋 Watch mode stopped: {} cycles, {} submissions, {} failures",
+        stats.cycles, stats.submissions, stats.failures);
+    stats
+}
+
+/// Write `line` as a single line of JSON and flush it
+fn write_json_line(out: &mut impl Write, line: &CycleLine) -> Result<()> {
+    serde_json::to_writer(&mut *out, line)?;
+    out.write_all(b"\n")?;
+    out.flush()?;
+    Ok(())
+}
+
+/// One fetch → execute → maybe-submit cycle
+/// `line` is filled in as the cycle gets further. The input is taken from
+/// `prefetched` when the last cycle left one there, and with --pipeline the
+/// next one is fetched into it while submitting
+async fn run_cycle<F, FFut, S, SFut>(
+    config: &WatchConfig,
+    shutdown: &ShutdownSignal,
+    last_submitted: Option<u64>,
+    fetch: &mut F,
+    submit: &mut S,
+    line: &mut CycleLine,
+    prefetched: &mut Option<Result<AaveInput>>,
+) -> Result<CycleOutcome>
+where
+    F: FnMut() -> FFut,
+    FFut: Future<Output = Result<AaveInput>>,
+    S: FnMut(AaveInput, SafetyScoreOutput) -> SFut,
+    SFut: Future<Output = Result<String>>,
+{
+    let input = match prefetched.take() {
+        Some(input) => input?,
+        None => fetch().await?,
+    };
+    line.block = input.block_number;
+
+    // A fetch cut short by Ctrl-C is partial - never score it
+    if shutdown.is_requested() {
+        return Ok(CycleOutcome::Failed);
+    }
+
+    let execution = execute_guest(&input, config.segment_limit_po2)?;
+    let output = execution.output;
+    status!("  Reserves: {}, Assets: {}, Liabilities: {}, Cycles: {}",
+        input.reserves.len(),
+        UsdAmount(output.total_assets_usd),
+        UsdAmount(output.total_liabilities_usd),
+        execution.cycles);
+    if input.failed_reserves > 0 {
+        status!("  ⚠ {} reserves failed to fetch and are not in this cycle's score", input.failed_reserves);
+    }
+
+    let score = output.safety_score;
+    line.score = Some(score);
+    line.assets = Some(output.total_assets_usd);
+    line.liabilities = Some(output.total_liabilities_usd);
+    if !config.submit || !should_submit(last_submitted, score, config.submit_threshold) {
+        return Ok(CycleOutcome::Skipped { score });
+    }
+
+    shutdown.enter(Phase::Prove);
+    let tx_hash = if config.pipeline {
+        let (submitted, next) = tokio::join!(submit(input, output), fetch());
+        *prefetched = Some(next);
+        submitted?
+    } else {
+        submit(input, output).await?
+    };
+    line.submitted = true;
+    line.tx_hash = Some(tx_hash.clone());
+    Ok(CycleOutcome::Submitted { score, tx_hash })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derisk_type::AaveReserveData;
+    use std::cell::Cell;
+
+    fn mock_input(total_variable_debt: u128) -> AaveInput {
+        AaveInput::new(
+            vec![AaveReserveData::builder("0xUSDC")
+                .decimals(6)
+                .total_atoken(1_000_000_000) // 1,000 USDC
+                .total_variable_debt(total_variable_debt)
+                .price_usd(100_000_000) // $1.00
+                .build()],
+            "Watch Test".to_string(),
+            1234567890,
+        )
+    }
+
+    #[test]
+    fn test_should_submit() {
+        assert!(should_submit(None, 500_000, 1_000));
+        assert!(!should_submit(Some(500_000), 500_999, 1_000));
+        assert!(should_submit(Some(500_000), 501_000, 1_000));
+        assert!(should_submit(Some(500_000), 499_000, 1_000));
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let interval = Duration::from_secs(10);
+        let max = Duration::from_secs(60);
+        assert_eq!(backoff_delay(interval, 0, max), interval);
+        assert_eq!(backoff_delay(interval, 1, max), Duration::from_secs(20));
+        assert_eq!(backoff_delay(interval, 2, max), Duration::from_secs(40));
+        assert_eq!(backoff_delay(interval, 3, max), max);
+        assert_eq!(backoff_delay(interval, 100, max), max);
+    }
+
+    #[tokio::test]
+    async fn test_execute_only_iterations() {
+        let config = WatchConfig {
+            interval: Duration::from_millis(1),
+            submit_threshold: 1_000,
+            submit: true,
+            max_backoff: Duration::from_millis(5),
+            max_iterations: Some(4),
+            segment_limit_po2: None,
+            json_lines: false,
+            pipeline: false,
+        };
+        let shutdown = ShutdownSignal::default();
+
+        // Cycle 1: 40% debt, cycle 2: RPC failure, cycle 3: unchanged, cycle 4: 50% debt
+        let debts = [Some(400_000_000), None, Some(400_000_000), Some(500_000_000)];
+        let call = Cell::new(0);
+        let submitted = std::cell::RefCell::new(Vec::new());
+
+        let stats = run_watch(
+            &config,
+            &shutdown,
+            || {
+                let debt = debts[call.get()];
+                call.set(call.get() + 1);
+                async move {
+                    debt.map(mock_input).ok_or_else(|| eyre::eyre!("mock RPC failure"))
+                }
+            },
+            |_, output| {
+                submitted.borrow_mut().push(output.safety_score);
+                async { Ok("0xabc".to_string()) }
+            },
+        )
+        .await;
+
+        assert_eq!(stats, WatchStats { cycles: 4, submissions: 2, failures: 1 });
+        // The unchanged score in cycle 3 is below the threshold and skipped
+        assert_eq!(*submitted.borrow(), vec![600_000, 500_000]);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_matches_sequential() {
+        async fn run(pipeline: bool) -> (WatchStats, Vec<u64>, usize) {
+            let config = WatchConfig {
+                interval: Duration::from_millis(1),
+                submit_threshold: 1_000,
+                submit: true,
+                max_backoff: Duration::from_millis(5),
+                max_iterations: Some(4),
+                segment_limit_po2: None,
+                json_lines: false,
+                pipeline,
+            };
+            let shutdown = ShutdownSignal::default();
+
+            // Submitted, unchanged, submitted, submitted
+            let debts = [400_000_000, 400_000_000, 500_000_000, 400_000_000];
+            let call = Cell::new(0);
+            let submitted = std::cell::RefCell::new(Vec::new());
+
+            let stats = run_watch(
+                &config,
+                &shutdown,
+                || {
+                    let debt = debts.get(call.get()).copied();
+                    call.set(call.get() + 1);
+                    async move {
+                        debt.map(mock_input).ok_or_else(|| eyre::eyre!("mock RPC failure"))
+                    }
+                },
+                |_, output| {
+                    submitted.borrow_mut().push(output.safety_score);
+                    async {
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                        Ok("0xabc".to_string())
+                    }
+                },
+            )
+            .await;
+            (stats, submitted.into_inner(), call.get())
+        }
+
+        let (sequential, sequential_submitted, sequential_fetches) = run(false).await;
+        let (pipelined, pipelined_submitted, pipelined_fetches) = run(true).await;
+        assert_eq!(pipelined, sequential);
+        assert_eq!(pipelined_submitted, sequential_submitted);
+        assert_eq!(sequential_submitted, vec![600_000, 500_000, 600_000]);
+        assert_eq!(sequential_fetches, 4);
+        // The last submission fetched ahead for a cycle that never ran
+        assert_eq!(pipelined_fetches, 5);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_backs_off_after_a_failed_submit() {
+        let config = WatchConfig {
+            interval: Duration::from_millis(10),
+            submit_threshold: 1_000,
+            submit: true,
+            max_backoff: Duration::from_secs(1),
+            max_iterations: Some(3),
+            segment_limit_po2: None,
+            json_lines: false,
+            pipeline: true,
+        };
+        let shutdown = ShutdownSignal::default();
+        let fetches = Cell::new(0);
+
+        let started = std::time::Instant::now();
+        let stats = run_watch(
+            &config,
+            &shutdown,
+            || {
+                fetches.set(fetches.get() + 1);
+                async { Ok(mock_input(400_000_000)) }
+            },
+            |_, _| async { Err(eyre::eyre!("mock submission failure")) },
+        )
+        .await;
+
+        assert_eq!(stats, WatchStats { cycles: 3, submissions: 0, failures: 3 });
+        // Each failed cycle's prefetch is dropped and fetched again after
+        // backing off 20ms, then 40ms
+        assert_eq!(fetches.get(), 6);
+        assert!(started.elapsed() >= Duration::from_millis(60), "{:?}", started.elapsed());
+    }
+
+    #[tokio::test]
+    async fn test_json_line_per_cycle() {
+        let config = WatchConfig {
+            interval: Duration::from_millis(1),
+            submit_threshold: 1_000,
+            submit: true,
+            max_backoff: Duration::from_millis(5),
+            max_iterations: Some(3),
+            segment_limit_po2: None,
+            json_lines: true,
+            pipeline: false,
+        };
+        let shutdown = ShutdownSignal::default();
+
+        // Cycle 1 is submitted, cycle 2 fails to fetch, cycle 3 is unchanged
+        let debts = [Some(400_000_000), None, Some(400_000_000)];
+        let call = Cell::new(0);
+        let mut out = Vec::new();
+
+        run_watch_to(
+            &config,
+            &shutdown,
+            || {
+                let debt = debts[call.get()];
+                call.set(call.get() + 1);
+                async move {
+                    debt.map(|debt| mock_input(debt).with_block_number(Some(18_500_000)))
+                        .ok_or_else(|| eyre::eyre!("mock RPC failure"))
+                }
+            },
+            |_, _| async { Ok("0xabc".to_string()) },
+            &mut out,
+        )
+        .await;
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line["timestamp"].as_u64().unwrap() > 0));
+
+        assert_eq!(lines[0]["block"], 18_500_000);
+        assert_eq!(lines[0]["score"], 600_000);
+        assert_eq!(lines[0]["liabilities"], 400 * 100_000_000u64);
+        assert_eq!(lines[0]["submitted"], true);
+        assert_eq!(lines[0]["tx_hash"], "0xabc");
+        assert!(lines[0].get("error").is_none());
+
+        assert_eq!(lines[1]["error"], "mock RPC failure");
+        assert!(lines[1]["score"].is_null() && lines[1].get("tx_hash").is_none());
+
+        assert_eq!(lines[2]["score"], 600_000);
+        assert_eq!(lines[2]["submitted"], false);
+    }
+
+    #[tokio::test]
+    async fn test_stops_on_shutdown() {
+        let config = WatchConfig {
+            interval: Duration::from_secs(3600),
+            submit_threshold: 0,
+            submit: false,
+            max_backoff: Duration::from_secs(3600),
+            max_iterations: None,
+            segment_limit_po2: None,
+            json_lines: false,
+            pipeline: false,
+        };
+        let shutdown = ShutdownSignal::default();
+        let stopper = shutdown.clone();
+
+        // Request shutdown while the loop is sleeping between cycles
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stopper.request();
+        });
+
+        let stats = run_watch(
+            &config,
+            &shutdown,
+            || async { Ok(mock_input(0)) },
+            |_, _| async { Ok(String::new()) },
+        )
+        .await;
+
+        assert_eq!(stats.cycles, 1);
+        assert_eq!(stats.submissions, 0);
+    }
+}
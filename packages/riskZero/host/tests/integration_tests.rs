@@ -1,8 +1,14 @@
 // Integration tests for the DeRisk Oracle system
 
-use derisk_type::{AaveInput, AaveReserveData, SafetyScoreOutput};
-use methods::{AAVE_ELF, AAVE_ID};
-use risc0_zkvm::{default_prover, ExecutorEnv};
+use derisk_type::{
+    check_output_invariants, compute_output, compute_safety_score, normalize_amount_rounded, scaled, AaveInput, AaveReserveData,
+    EModeCategory, RoundingMode, SafetyScoreOutput, ScoringStrategy, MAX_BREAKDOWN_RESERVES, SCORE_SCALE,
+};
+use host::composition;
+use host::reserve_source::ReserveSource;
+use host::session::ProvingSession;
+use methods::{AAVE_BATCH_ID, AAVE_ELF, AAVE_ID};
+use risc0_zkvm::{default_executor, default_prover, ExecutorEnv};
 
 /// Test the guest program with mock data
 #[test]
@@ -35,7 +41,7 @@ fn test_guest_with_mock_data() {
 
     // Basic sanity checks
     assert!(output.safety_score > 0, "Safety score should be positive");
-    assert!(output.safety_score <= 1_000_000, "Safety score should be <= 100%");
+    assert!(output.safety_score <= SCORE_SCALE, "Safety score should be <= 100%");
     assert!(output.total_assets_usd >= output.total_liabilities_usd, 
         "Assets should be >= liabilities for a healthy protocol");
 }
@@ -43,11 +49,7 @@ fn test_guest_with_mock_data() {
 /// Test with edge case: empty reserves
 #[test]
 fn test_empty_reserves() {
-    let input = AaveInput {
-        reserves: vec![],
-        protocol_name: "Empty Test".to_string(),
-        timestamp: 1234567890,
-    };
+    let input = AaveInput::new(vec![], "Empty Test".to_string(), 1234567890);
 
     let env = ExecutorEnv::builder()
         .write(&input)
@@ -73,21 +75,16 @@ fn test_empty_reserves() {
 #[test]
 fn test_insolvent_protocol() {
     let reserves = vec![
-        AaveReserveData {
-            token_address: "0xUSDC".to_string(),
-            total_atoken: 1_000_000_000_000,      // $1,000 supplied
-            total_stable_debt: 800_000_000_000,   // $800 borrowed stable
-            total_variable_debt: 400_000_000_000, // $400 borrowed variable
-            price_usd: 100_000_000,                // $1.00
-            decimals: 6,
-        },
+        AaveReserveData::builder("0xUSDC")
+            .total_atoken(1_000_000_000_000)      // $1,000 supplied
+            .total_stable_debt(800_000_000_000)   // $800 borrowed stable
+            .total_variable_debt(400_000_000_000) // $400 borrowed variable
+            .price_usd(100_000_000)               // $1.00
+            .decimals(6)
+            .build(),
     ];
 
-    let input = AaveInput {
-        reserves,
-        protocol_name: "Insolvent Test".to_string(),
-        timestamp: 1234567890,
-    };
+    let input = AaveInput::new(reserves, "Insolvent Test".to_string(), 1234567890);
 
     let env = ExecutorEnv::builder()
         .write(&input)
@@ -115,39 +112,31 @@ fn test_insolvent_protocol() {
 fn test_multiple_reserves_different_decimals() {
     let reserves = vec![
         // USDC (6 decimals)
-        AaveReserveData {
-            token_address: "0xUSDC".to_string(),
-            total_atoken: 1_000_000_000_000,      // 1,000,000 USDC
-            total_stable_debt: 500_000_000_000,   // 500,000 USDC
-            total_variable_debt: 200_000_000_000, // 200,000 USDC
-            price_usd: 100_000_000,                // $1.00
-            decimals: 6,
-        },
+        AaveReserveData::builder("0xUSDC")
+            .total_atoken(1_000_000_000_000)      // 1,000,000 USDC
+            .total_stable_debt(500_000_000_000)   // 500,000 USDC
+            .total_variable_debt(200_000_000_000) // 200,000 USDC
+            .price_usd(100_000_000)               // $1.00
+            .decimals(6)
+            .build(),
         // WETH (18 decimals)
-        AaveReserveData {
-            token_address: "0xWETH".to_string(),
-            total_atoken: 1_000_000_000_000_000_000, // 1 WETH
-            total_stable_debt: 500_000_000_000_000_000, // 0.5 WETH
-            total_variable_debt: 0,
-            price_usd: 200_000_000_000,            // $2000.00
-            decimals: 18,
-        },
+        AaveReserveData::builder("0xWETH")
+            .total_atoken(1_000_000_000_000_000_000)    // 1 WETH
+            .total_stable_debt(500_000_000_000_000_000) // 0.5 WETH
+            .price_usd(200_000_000_000)                 // $2000.00
+            .decimals(18)
+            .build(),
         // DAI (18 decimals)
-        AaveReserveData {
-            token_address: "0xDAI".to_string(),
-            total_atoken: 500_000_000_000_000_000_000, // 500 DAI
-            total_stable_debt: 100_000_000_000_000_000_000, // 100 DAI
-            total_variable_debt: 50_000_000_000_000_000_000, // 50 DAI
-            price_usd: 100_000_000,                // $1.00
-            decimals: 18,
-        },
+        AaveReserveData::builder("0xDAI")
+            .total_atoken(500_000_000_000_000_000_000)       // 500 DAI
+            .total_stable_debt(100_000_000_000_000_000_000)  // 100 DAI
+            .total_variable_debt(50_000_000_000_000_000_000) // 50 DAI
+            .price_usd(100_000_000)                          // $1.00
+            .decimals(18)
+            .build(),
     ];
 
-    let input = AaveInput {
-        reserves,
-        protocol_name: "Multi-Reserve Test".to_string(),
-        timestamp: 1234567890,
-    };
+    let input = AaveInput::new(reserves, "Multi-Reserve Test".to_string(), 1234567890);
 
     let env = ExecutorEnv::builder()
         .write(&input)
@@ -181,43 +170,381 @@ fn test_multiple_reserves_different_decimals() {
     assert!(output.total_assets_usd > output.total_liabilities_usd);
 }
 
+/// Test the weighted health factor against a hand-computed value
+#[test]
+fn test_weighted_health_factor() {
+    let reserves = vec![
+        // USDC: $1,000 supplied at 80% threshold, $500 borrowed
+        AaveReserveData::builder("0xUSDC")
+            .total_atoken(1_000_000_000)      // 1,000 USDC
+            .total_variable_debt(500_000_000) // 500 USDC
+            .price_usd(100_000_000)           // $1.00
+            .decimals(6)
+            .liquidation_threshold(8_000)
+            .build(),
+        // WETH: $2,000 supplied at 82.5% threshold, no debt
+        AaveReserveData::builder("0xWETH")
+            .total_atoken(1_000_000_000_000_000_000) // 1 WETH
+            .price_usd(200_000_000_000)              // $2000.00
+            .decimals(18)
+            .liquidation_threshold(8_250)
+            .build(),
+    ];
+
+    let input = AaveInput::new(reserves, "Health Factor Test".to_string(), 1234567890);
+    let output = prove_and_decode(&input);
+
+    // Expected calculation:
+    // Weighted collateral = $1,000 * 0.80 + $2,000 * 0.825 = $2,450
+    // Debt = $500
+    // HF = 2,450 / 500 = 4.9 -> 49,000 (scaled 1e4)
+    assert_eq!(output.weighted_health_factor, Some(49_000));
+}
+
+/// Test that a reserve in an eMode category uses the category's threshold
+#[test]
+fn test_emode_category_threshold() {
+    let reserves = vec![
+        // USDC in the stablecoin category: $1,000 supplied, $500 borrowed
+        AaveReserveData::builder("0xUSDC")
+            .total_atoken(1_000_000_000)
+            .total_variable_debt(500_000_000)
+            .price_usd(100_000_000)
+            .decimals(6)
+            .liquidation_threshold(8_000)
+            .emode_category(1)
+            .build(),
+        // WETH outside any category: $2,000 supplied at 82.5%
+        AaveReserveData::builder("0xWETH")
+            .total_atoken(1_000_000_000_000_000_000)
+            .price_usd(200_000_000_000)
+            .decimals(18)
+            .liquidation_threshold(8_250)
+            .build(),
+    ];
+
+    let input = AaveInput::new(reserves, "eMode Test".to_string(), 1234567890)
+        .with_emode_categories(vec![EModeCategory {
+            id: 1,
+            liquidation_threshold: 9_300,
+            label: "Stablecoins".to_string(),
+        }]);
+    let output = prove_and_decode(&input);
+
+    // Weighted collateral = $1,000 * 0.93 + $2,000 * 0.825 = $2,580
+    // HF = 2,580 / 500 = 5.16 -> 51,600 (vs 49,000 without eMode)
+    assert_eq!(output.weighted_health_factor, Some(51_600));
+}
+
+/// Test the health factor is omitted when thresholds are missing
+#[test]
+fn test_weighted_health_factor_requires_thresholds() {
+    let output = prove_and_decode(&create_mock_aave_input());
+    assert_eq!(output.weighted_health_factor, None);
+}
+
+/// Test that reserves below the minimum value are excluded inside the guest
+#[test]
+fn test_dust_reserves_excluded() {
+    let reserves = vec![
+        // USDC: $1,000 supplied, $400 borrowed
+        AaveReserveData::builder("0xUSDC")
+            .total_atoken(1_000_000_000)      // 1,000 USDC
+            .total_variable_debt(400_000_000) // 400 USDC
+            .price_usd(100_000_000)           // $1.00
+            .decimals(6)
+            .build(),
+        // DUST: $5 supplied, $5 borrowed - would drag the score down if counted
+        AaveReserveData::builder("0xDUST")
+            .total_atoken(5_000_000_000_000_000_000)        // 5 DUST
+            .total_variable_debt(5_000_000_000_000_000_000)
+            .price_usd(100_000_000)                         // $1.00
+            .decimals(18)
+            .build(),
+    ];
+
+    let input = AaveInput::new(reserves, "Dust Test".to_string(), 1234567890)
+        .with_min_reserve_usd(10 * 100_000_000); // $10 minimum
+    let output = prove_and_decode(&input);
+
+    assert_eq!(output.skipped_reserves, 1);
+    assert_eq!(output.total_assets_usd, 1_000 * 100_000_000);
+    assert_eq!(output.total_liabilities_usd, 400 * 100_000_000);
+    assert_eq!(output.safety_score, scaled(60.0));
+
+    // Without the filter the dust reserve is counted
+    let input = AaveInput { min_reserve_usd: 0, ..input };
+    let output = prove_and_decode(&input);
+    assert_eq!(output.skipped_reserves, 0);
+    assert_eq!(output.total_assets_usd, 1_005 * 100_000_000);
+}
+
+/// Test that debt summing past u128::MAX is clamped and flagged instead of aborting
+#[test]
+fn test_debt_overflow_saturates() {
+    // 38 decimals keeps normalization in range even for u128::MAX amounts
+    // (liabilities are rounded up by the guest)
+    let reserve = |total_stable_debt: u128, total_variable_debt: u128| AaveReserveData::builder("0xHUGE")
+        .total_atoken(10u128.pow(38))             // 1 HUGE
+        .total_stable_debt(total_stable_debt)
+        .total_variable_debt(total_variable_debt)
+        .price_usd(100_000_000)                   // $1.00
+        .decimals(38)
+        .build();
+
+    // Exactly at the boundary: no overflow
+    let input = AaveInput::new(vec![reserve(u128::MAX - 1, 1)], "Boundary Test".to_string(), 1234567890);
+    let output = prove_and_decode(&input);
+    assert!(!output.saturated);
+    assert_eq!(output.total_liabilities_usd, normalize_amount_rounded(u128::MAX, 38, 100_000_000, RoundingMode::Ceil));
+
+    // One past the boundary: clamped and flagged
+    let input = AaveInput::new(vec![reserve(u128::MAX, 1)], "Overflow Test".to_string(), 1234567890);
+    let output = prove_and_decode(&input);
+    assert!(output.saturated);
+    assert_eq!(output.total_liabilities_usd, normalize_amount_rounded(u128::MAX, 38, 100_000_000, RoundingMode::Ceil));
+    assert_eq!(output.safety_score, 0); // Liabilities far exceed assets
+}
+
+/// Test that volatility discounts collateral and lowers the score
+#[test]
+fn test_volatility_lowers_score() {
+    let with_volatility = |volatility_bps: Option<u16>| {
+        let mut input = create_mock_aave_input();
+        // WETH is the volatile collateral
+        input.reserves[1].volatility_bps = volatility_bps;
+        input
+    };
+
+    let baseline = prove_and_decode(&with_volatility(None));
+    let calm = prove_and_decode(&with_volatility(Some(1_000)));   // 10%
+    let volatile = prove_and_decode(&with_volatility(Some(6_000))); // 60%
+
+    // No volatility supplied: unchanged behavior
+    assert!(!baseline.volatility_adjusted);
+    assert_eq!(baseline.safety_score, scaled(46.4285)); // $130M buffer / $280M assets
+
+    assert!(calm.volatility_adjusted);
+    assert_eq!(calm.safety_score, scaled(44.4444));     // $120M / $270M
+    assert!(calm.safety_score < baseline.safety_score);
+    assert!(volatile.safety_score < calm.safety_score);
+
+    // Committed totals stay at market value
+    assert_eq!(volatile.total_assets_usd, baseline.total_assets_usd);
+    assert_eq!(volatile.total_liabilities_usd, baseline.total_liabilities_usd);
+}
+
+/// Test isolation-mode debt is converted from Aave's 1e2 scale and floors liabilities
+#[test]
+fn test_isolation_mode_debt_scaling() {
+    let mut input = create_mock_aave_input();
+    // $12,345.67 borrowed against WETH in isolation mode, in Aave's 2-decimal units
+    input.reserves[1].isolation_mode_debt = 1_234_567;
+
+    let output = prove_and_decode(&input);
+    assert_eq!(output.isolation_mode_debt_usd, 1_234_567 * 1_000_000);
+
+    // Already covered by the debt tokens, so liabilities are unchanged
+    let baseline = prove_and_decode(&create_mock_aave_input());
+    assert_eq!(output.total_liabilities_usd, baseline.total_liabilities_usd);
+    assert_eq!(output.safety_score, baseline.safety_score);
+
+    // With no priced debt at all, the isolated debt becomes the liability floor
+    for reserve in &mut input.reserves {
+        reserve.total_stable_debt = 0;
+        reserve.total_variable_debt = 0;
+    }
+    let output = prove_and_decode(&input);
+    assert_eq!(output.total_liabilities_usd, 1_234_567 * 1_000_000);
+}
+
+/// Test that the guest rounds assets down and liabilities up
+#[test]
+fn test_conservative_rounding() {
+    // 2.6e-8 USD supplied and borrowed: not a whole unit of the 1e8 scale
+    let reserve = AaveReserveData::builder("0xWETH")
+        .total_atoken(26_000_000_000)
+        .total_variable_debt(26_000_000_000)
+        .price_usd(100_000_000)              // $1.00
+        .decimals(18)
+        .build();
+
+    let input = AaveInput::new(vec![reserve], "Rounding Test".to_string(), 1234567890);
+    let output = prove_and_decode(&input);
+    assert_eq!(output.total_assets_usd, 2);
+    assert_eq!(output.total_liabilities_usd, 3);
+    assert_eq!(output.safety_score, 0); // Equal values never round into solvency
+}
+
+/// Differential test: the guest must agree with the reference implementation
+#[test]
+fn test_guest_matches_reference_implementation() {
+    let mut input = create_mock_aave_input().with_min_reserve_usd(100_000_000);
+    input.reserves[0].liquidation_threshold = Some(7_800);
+    input.reserves[1].volatility_bps = Some(3_000);
+
+    let guest = prove_and_decode(&input);
+    let reference = SafetyScoreOutput {
+        timestamp: input.timestamp,
+        ..compute_safety_score(&input.reserves, &ScoringStrategy::for_input(&input))
+    };
+
+    // Compare every field through the serialized form
+    assert_eq!(
+        serde_json::to_string(&guest).unwrap(),
+        serde_json::to_string(&reference).unwrap()
+    );
+}
+
+/// Test that reserves beyond the breakdown cap are summed into "other"
+#[test]
+fn test_breakdown_truncates_to_largest_reserves() {
+    let mut input = create_mock_aave_input();
+    // Ten $1,000 reserves with $100 of debt each, all smaller than the mock's
+    for i in 0..10 {
+        input.reserves.push(AaveReserveData::builder(format!("0xSMALL{}", i))
+            .total_atoken(1_000_000_000)      // 1,000 tokens
+            .total_variable_debt(100_000_000) // 100 tokens
+            .price_usd(100_000_000)           // $1.00
+            .decimals(6)
+            .build());
+    }
+
+    let output = prove_and_decode(&input);
+    let omitted = input.reserves.len() - MAX_BREAKDOWN_RESERVES;
+
+    assert!(output.truncated);
+    assert_eq!(output.reserve_breakdown.len(), MAX_BREAKDOWN_RESERVES);
+    assert_eq!(output.omitted_count as usize, omitted);
+
+    // WETH ($100M) is the largest reserve
+    assert_eq!(output.reserve_breakdown[0].token_address, "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+    // Only small reserves are omitted, so "other" is `omitted` x $1,000 / $100
+    assert_eq!(output.other_reserves.assets_usd, omitted as u128 * 1_000 * 100_000_000);
+    assert_eq!(output.other_reserves.liabilities_usd, omitted as u128 * 100 * 100_000_000);
+}
+
+/// Test that a zero-priced reserve is reported instead of silently dropped
+#[test]
+fn test_zero_price_reserve_reported() {
+    let mut input = create_mock_aave_input();
+    // Heavily borrowed reserve whose price feed is down
+    input.reserves.push(AaveReserveData::builder("0xFEEDDOWN")
+        .total_atoken(10_000_000_000_000)       // 10M tokens
+        .total_variable_debt(9_500_000_000_000) // 9.5M tokens
+        .price_usd(0)
+        .decimals(6)
+        .build());
+
+    let output = prove_and_decode(&input);
+
+    assert_eq!(output.reserves_with_missing_price, 1);
+    // Scored as if the reserve weren't there
+    assert_eq!(output.safety_score, scaled(46.4285));
+}
+
+/// Test piping an input through execute mode: JSON in on stdin, JSON out on stdout
+#[test]
+fn test_execute_mode_pipe() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let input_json = serde_json::to_string(&create_mock_aave_input()).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_host"))
+        .args(["--mode", "execute", "--input-file", "-", "--output-stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start host");
+    child.stdin.take().unwrap().write_all(input_json.as_bytes()).unwrap();
+    let result = child.wait_with_output().unwrap();
+
+    assert!(result.status.success(), "host failed: {}", String::from_utf8_lossy(&result.stderr));
+
+    // stdout holds nothing but the output JSON; banners went to stderr
+    let output: SafetyScoreOutput = serde_json::from_slice(&result.stdout)
+        .expect("stdout should be exactly one SafetyScoreOutput");
+    assert_eq!(output.safety_score, scaled(46.4285));
+    assert_eq!(output.timestamp, 1234567890);
+    assert!(String::from_utf8_lossy(&result.stderr).contains("DeRisk Protocol"));
+}
+
+/// A reserve source answering with a fixed input instead of calling a chain
+struct MockSource(AaveInput);
+
+impl ReserveSource for MockSource {
+    async fn fetch(&self) -> eyre::Result<AaveInput> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Test driving the pipeline as a library, without the CLI: fetch from a
+/// mock source, then execute
+#[tokio::test]
+async fn test_session_executes_mock_input() {
+    let mut session = ProvingSession::fetch(&MockSource(create_mock_aave_input())).await.unwrap();
+    let execution = session.execute().unwrap();
+    assert_eq!(execution.output, compute_output(session.input()));
+    assert_eq!(execution.output.safety_score, scaled(46.4285));
+    assert!(execution.cycles > 0);
+
+    // What the session executes follows its input
+    session.input_mut().reserves[1].volatility_bps = Some(3_000);
+    let weighted = session.execute().unwrap();
+    assert!(weighted.output.volatility_adjusted);
+    assert!(weighted.output.safety_score < execution.output.safety_score);
+}
+
+/// Helper to run the guest on an input and decode its journal
+fn prove_and_decode(input: &AaveInput) -> SafetyScoreOutput {
+    let env = ExecutorEnv::builder()
+        .write(input)
+        .expect("Failed to write input")
+        .build()
+        .expect("Failed to build env");
+
+    let prover = default_prover();
+    let prove_info = prover
+        .prove(env, AAVE_ELF)
+        .expect("Failed to prove");
+
+    prove_info.receipt.journal
+        .decode()
+        .expect("Failed to decode output")
+}
+
 /// Helper function to create mock Aave input data
 fn create_mock_aave_input() -> AaveInput {
     let reserves = vec![
         // USDC reserve
-        AaveReserveData {
-            token_address: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
-            total_atoken: 100_000_000_000_000,    // 100M USDC (6 decimals)
-            total_stable_debt: 20_000_000_000_000, // 20M USDC
-            total_variable_debt: 30_000_000_000_000, // 30M USDC
-            price_usd: 100_000_000,                 // $1.00 (scaled by 1e8)
-            decimals: 6,
-        },
+        AaveReserveData::builder("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48")
+            .total_atoken(100_000_000_000_000)       // 100M USDC (6 decimals)
+            .total_stable_debt(20_000_000_000_000)   // 20M USDC
+            .total_variable_debt(30_000_000_000_000) // 30M USDC
+            .price_usd(100_000_000)                  // $1.00 (scaled by 1e8)
+            .decimals(6)
+            .build(),
         // WETH reserve
-        AaveReserveData {
-            token_address: "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".to_string(),
-            total_atoken: 50_000_000_000_000_000_000_000, // 50,000 WETH (18 decimals)
-            total_stable_debt: 10_000_000_000_000_000_000_000, // 10,000 WETH
-            total_variable_debt: 15_000_000_000_000_000_000_000, // 15,000 WETH
-            price_usd: 200_000_000_000,             // $2000.00 (scaled by 1e8)
-            decimals: 18,
-        },
+        AaveReserveData::builder("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2")
+            .total_atoken(50_000_000_000_000_000_000_000)        // 50,000 WETH (18 decimals)
+            .total_stable_debt(10_000_000_000_000_000_000_000)   // 10,000 WETH
+            .total_variable_debt(15_000_000_000_000_000_000_000) // 15,000 WETH
+            .price_usd(200_000_000_000)                          // $2000.00 (scaled by 1e8)
+            .decimals(18)
+            .build(),
         // DAI reserve
-        AaveReserveData {
-            token_address: "0x6B175474E89094C44Da98b954EedeAC495271d0F".to_string(),
-            total_atoken: 80_000_000_000_000_000_000_000_000, // 80M DAI (18 decimals)
-            total_stable_debt: 30_000_000_000_000_000_000_000_000, // 30M DAI
-            total_variable_debt: 20_000_000_000_000_000_000_000_000, // 20M DAI
-            price_usd: 100_000_000,                 // $1.00 (scaled by 1e8)
-            decimals: 18,
-        },
+        AaveReserveData::builder("0x6B175474E89094C44Da98b954EedeAC495271d0F")
+            .total_atoken(80_000_000_000_000_000_000_000_000)        // 80M DAI (18 decimals)
+            .total_stable_debt(30_000_000_000_000_000_000_000_000)   // 30M DAI
+            .total_variable_debt(20_000_000_000_000_000_000_000_000) // 20M DAI
+            .price_usd(100_000_000)                                  // $1.00 (scaled by 1e8)
+            .decimals(18)
+            .build(),
     ];
 
-    AaveInput {
-        reserves,
-        protocol_name: "Aave V3 Mock".to_string(),
-        timestamp: 1234567890,
-    }
+    AaveInput::new(reserves, "Aave V3 Mock".to_string(), 1234567890)
 }
 
 /// Test Image ID is correctly generated
@@ -243,4 +570,195 @@ fn test_serialization() {
     assert_eq!(input.reserves.len(), deserialized.reserves.len());
     assert_eq!(input.protocol_name, deserialized.protocol_name);
     assert_eq!(input.timestamp, deserialized.timestamp);
+    assert_eq!(input.schema_version, deserialized.schema_version);
+}
+
+// Golden vectors
+// The guest decodes AaveInput and the host decodes SafetyScoreOutput with the
+// zkVM serializer, which is positional: reordering or retyping a field changes
+// the bytes without any compile error. These committed vectors pin the layout.
+// If a change is intentional, bump INPUT_SCHEMA_VERSION and regenerate them.
+
+const GOLDEN_INPUT_JSON: &str = include_str!("golden/aave_input.json");
+const GOLDEN_INPUT_HEX: &str = include_str!("golden/aave_input.hex");
+const GOLDEN_JOURNAL_HEX: &str = include_str!("golden/journal.hex");
+
+fn golden_bytes(hex_str: &str) -> Vec<u8> {
+    hex::decode(hex_str.trim().trim_start_matches("0x")).expect("Golden vector is not hex")
+}
+
+/// Encode with the zkVM serializer, as the guest reads and commits values
+fn zkvm_bytes<T: serde::Serialize>(value: &T) -> Vec<u8> {
+    let words = risc0_zkvm::serde::to_vec(value).expect("Failed to serialize");
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+fn zkvm_words(bytes: &[u8]) -> Vec<u32> {
+    bytes.chunks_exact(4).map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap())).collect()
+}
+
+fn golden_input() -> AaveInput {
+    serde_json::from_str(GOLDEN_INPUT_JSON).expect("Failed to parse golden input")
+}
+
+/// Test the input's zkVM layout hasn't moved
+#[test]
+fn test_golden_input_layout() {
+    let golden = golden_bytes(GOLDEN_INPUT_HEX);
+    assert_eq!(zkvm_bytes(&golden_input()), golden);
+
+    // Decoding and re-encoding must round-trip byte for byte
+    let decoded: AaveInput = risc0_zkvm::serde::from_slice(&zkvm_words(&golden)).unwrap();
+    assert_eq!(zkvm_bytes(&decoded), golden);
+}
+
+/// Test the journal for the golden input, computed on the host, hasn't moved
+#[test]
+fn test_golden_journal_layout() {
+    let input = golden_input();
+    let reference = SafetyScoreOutput {
+        timestamp: input.timestamp,
+        ..compute_safety_score(&input.reserves, &ScoringStrategy::for_input(&input))
+    }
+    .with_excluded_reserves(input.excluded.len() as u32);
+
+    let golden = golden_bytes(GOLDEN_JOURNAL_HEX);
+    assert_eq!(zkvm_bytes(&reference), golden);
+
+    let decoded: SafetyScoreOutput = risc0_zkvm::serde::from_slice(&zkvm_words(&golden)).unwrap();
+    assert_eq!(decoded, reference);
+    assert_eq!(decoded.safety_score, scaled(41.1764));
+    assert_eq!(decoded.capped_reserves, 1);
+    assert_eq!(
+        decoded.total_stable_liabilities_usd + decoded.total_variable_liabilities_usd,
+        decoded.total_liabilities_usd
+    );
+}
+
+/// Test the guest commits exactly the golden journal (execute only, no proof)
+#[test]
+fn test_guest_commits_golden_journal() {
+    let env = ExecutorEnv::builder()
+        .write(&golden_input())
+        .expect("Failed to write input")
+        .build()
+        .expect("Failed to build env");
+
+    let session = default_executor()
+        .execute(env, AAVE_ELF)
+        .expect("Failed to execute");
+
+    assert_eq!(session.journal.bytes, golden_bytes(GOLDEN_JOURNAL_HEX));
+}
+
+/// Test the guest's pre-commit invariants hold for valid data and would
+/// catch an inconsistent output (execute only, no proof)
+#[test]
+fn test_guest_output_invariants() {
+    let mut input = create_mock_aave_input();
+    input.reserves[1].volatility_bps = Some(3_000);
+
+    let env = ExecutorEnv::builder()
+        .write(&input)
+        .expect("Failed to write input")
+        .build()
+        .expect("Failed to build env");
+
+    // The guest panics instead of committing when an invariant fails, so a
+    // journal at all means they held
+    let session = default_executor()
+        .execute(env, AAVE_ELF)
+        .expect("Failed to execute");
+    let output: SafetyScoreOutput = session.journal.decode().expect("Failed to decode output");
+    assert!(output.volatility_adjusted);
+    assert_eq!(check_output_invariants(&output), Ok(()));
+
+    // A score that doesn't follow from the committed totals is refused
+    let unweighted = SafetyScoreOutput { volatility_adjusted: false, ..output.clone() };
+    assert!(check_output_invariants(&unweighted).is_err());
+    let inflated = SafetyScoreOutput { safety_score: output.safety_score + SCORE_SCALE, ..output };
+    assert!(check_output_invariants(&inflated).is_err());
+}
+
+/// Test the guest splits the liabilities into stable and variable rate debt
+/// that add up to the combined total (execute only, no proof)
+#[test]
+fn test_guest_reports_debt_mix() {
+    let env = ExecutorEnv::builder()
+        .write(&create_mock_aave_input())
+        .expect("Failed to write input")
+        .build()
+        .expect("Failed to build env");
+
+    let session = default_executor()
+        .execute(env, AAVE_ELF)
+        .expect("Failed to execute");
+    let output: SafetyScoreOutput = session.journal.decode().expect("Failed to decode output");
+
+    // Stable: $20M USDC + $20M WETH + $30M DAI; variable: $30M + $30M + $20M
+    assert_eq!(output.total_stable_liabilities_usd, 70_000_000 * 100_000_000);
+    assert_eq!(output.total_variable_liabilities_usd, 80_000_000 * 100_000_000);
+    assert_eq!(
+        output.total_stable_liabilities_usd + output.total_variable_liabilities_usd,
+        output.total_liabilities_usd
+    );
+}
+
+/// Test the guest clamps the score into the input's governance bounds and
+/// marks it, and refuses bounds that cross (execute only, no proof)
+#[test]
+fn test_guest_clamps_score_into_bounds() {
+    let execute = |input: &AaveInput| {
+        let env = ExecutorEnv::builder().write(input).expect("Failed to write input").build().expect("Failed to build env");
+        default_executor().execute(env, AAVE_ELF)
+    };
+
+    // The mock scores about 46%: a 40% ceiling caps it, a 50% floor raises it
+    for (floor, ceiling, expected) in [(None, Some(scaled(40.0)), scaled(40.0)), (Some(scaled(50.0)), None, scaled(50.0))] {
+        let input = create_mock_aave_input().with_score_bounds(floor, ceiling);
+        let output: SafetyScoreOutput = execute(&input).expect("Failed to execute").journal.decode().unwrap();
+        assert_eq!(output.safety_score, expected);
+        assert!(output.clamped);
+        assert_eq!(output.total_liabilities_usd, 150_000_000 * 100_000_000);
+    }
+
+    let crossed = create_mock_aave_input().with_score_bounds(Some(scaled(60.0)), Some(scaled(40.0)));
+    assert!(execute(&crossed).is_err());
+}
+
+/// Test proofs of the same state with different nonces commit different
+/// journals, so a replayed update can be told apart (execute only, no proof)
+#[test]
+fn test_nonce_distinguishes_identical_states() {
+    let journal = |nonce: u64| {
+        let input = create_mock_aave_input().with_nonce(Some(nonce));
+        let env = ExecutorEnv::builder().write(&input).expect("Failed to write input").build().expect("Failed to build env");
+        default_executor().execute(env, AAVE_ELF).expect("Failed to execute").journal
+    };
+
+    let first = journal(18_500_000);
+    let second = journal(18_500_001);
+    assert_ne!(first.bytes, second.bytes);
+
+    let first: SafetyScoreOutput = first.decode().unwrap();
+    let second: SafetyScoreOutput = second.decode().unwrap();
+    assert_eq!((first.nonce, second.nonce), (Some(18_500_000), Some(18_500_001)));
+    assert_eq!(first.safety_score, second.safety_score);
+}
+
+/// Test proving the reserves in two batches and composing them commits the
+/// score a single run does
+#[test]
+fn test_two_batch_composition_matches_single_run() {
+    let input = create_mock_aave_input();
+    assert_eq!(input.reserves.len(), 3);
+    let single = prove_and_decode(&input);
+
+    let proof = composition::prove_composed(&input, 2, None).expect("Failed to prove composition");
+    assert_eq!(proof.batches, 2);
+    let composed = composition::verify_composed(&proof.receipt).expect("Composed proof failed to verify");
+
+    assert_eq!(composed.safety_score, single.safety_score);
+    assert_eq!(composed.batch_image_id, Some(AAVE_BATCH_ID));
+    assert_eq!(composed, single.with_batch_image_id(Some(AAVE_BATCH_ID)));
 }
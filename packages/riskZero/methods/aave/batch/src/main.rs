@@ -0,0 +1,42 @@
+// Aave Safety Score - Batch Guest Program
+// Values one batch of an input's reserves and commits their summed totals,
+// for the compose guest to merge into a single score (see
+// derisk_type::compose). Each batch is proven on its own, so a pool too large
+// to prove in one run can be proven a batch at a time
+
+use risc0_zkvm::guest::env;
+use derisk_type::{compose::compute_batch, AaveInput, INPUT_SCHEMA_VERSION};
+
+fn main() {
+    // The input the batch was cut from, holding only the batch's reserves
+    let input: AaveInput = env::read();
+
+    assert_eq!(
+        input.schema_version, INPUT_SCHEMA_VERSION,
+        "Unsupported input schema version"
+    );
+
+    // As in the main guest, reserves the host filtered out must be absent
+    for reserve in &input.reserves {
+        assert!(
+            !input.excluded.iter().any(|excluded| excluded.eq_ignore_ascii_case(&reserve.token_address)),
+            "Excluded reserve present in input"
+        );
+    }
+
+    eprintln!("=== Aave Safety Score Batch ===");
+    eprintln!("Protocol: {}", input.protocol_name);
+    eprintln!("Reserves in batch: {}", input.reserves.len());
+
+    // Bounds and weights apply to the whole input; the compose guest checks them
+    let journal = match compute_batch(input) {
+        Ok(journal) => journal,
+        Err(problem) => panic!("Invalid price overrides: {}", problem),
+    };
+
+    eprintln!("Scored reserves: {}", journal.totals.values.len());
+    eprintln!("Assets (USD, 1e8): {}", journal.totals.total_assets_usd);
+    eprintln!("Liabilities (USD, 1e8): {}", journal.totals.total_liabilities_usd);
+
+    env::commit(&journal);
+}
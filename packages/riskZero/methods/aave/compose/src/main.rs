@@ -0,0 +1,60 @@
+// Aave Safety Score - Compose Guest Program
+// Merges the totals batch proofs committed into the score a single run over
+// all their reserves would commit (see derisk_type::compose). Each batch's
+// receipt is verified as an assumption, which the prover resolves, so the
+// final receipt stands for every batch
+
+use risc0_zkvm::guest::env;
+use derisk_type::{
+    check_output_invariants, check_score_bounds, check_weights,
+    compose::{compose_output, ComposeInput},
+    INPUT_SCHEMA_VERSION, MAX_JOURNAL_BYTES,
+};
+
+fn main() {
+    let input: ComposeInput = env::read();
+    let header = &input.header;
+
+    assert_eq!(
+        header.schema_version, INPUT_SCHEMA_VERSION,
+        "Unsupported input schema version"
+    );
+    if let Err(problem) = check_score_bounds(header.score_floor, header.score_ceiling) {
+        panic!("Invalid score bounds: {}", problem);
+    }
+    if let Err(problem) = check_weights(&header.weights) {
+        panic!("Invalid risk weights: {}", problem);
+    }
+
+    eprintln!("=== Aave Safety Score Composition ===");
+    eprintln!("Protocol: {}", header.protocol_name);
+    eprintln!("Batches: {}", input.batches.len());
+
+    // Each batch must have been proven by the batch guest the output names:
+    // its journal is exactly what that guest committed
+    for batch in &input.batches {
+        let words = risc0_zkvm::serde::to_vec(batch).expect("Failed to encode batch journal");
+        let journal: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        env::verify(input.batch_image_id, &journal).expect("Batch proof failed to verify");
+    }
+
+    let output = match compose_output(input) {
+        Ok(output) => output,
+        Err(problem) => panic!("Batches don't compose: {}", problem),
+    };
+
+    eprintln!("Total Assets (USD, 1e8): {}", output.total_assets_usd);
+    eprintln!("Total Liabilities (USD, 1e8): {}", output.total_liabilities_usd);
+    eprintln!("Safety Score (scaled 1e4): {}", output.safety_score);
+
+    if let Err(violation) = check_output_invariants(&output) {
+        panic!("Output invariant violated: {}", violation);
+    }
+
+    let journal = risc0_zkvm::serde::to_vec(&output).expect("Failed to encode output");
+    assert!(
+        journal.len() * 4 <= MAX_JOURNAL_BYTES,
+        "Journal exceeds MAX_JOURNAL_BYTES"
+    );
+    env::commit_slice(&journal);
+}
@@ -3,7 +3,10 @@
 // The computation is proven cryptographically, making it trustless
 
 use risc0_zkvm::guest::env;
-use derisk_type::{AaveInput, SafetyScoreOutput, normalize_amount};
+use derisk_type::{
+    check_output_invariants, check_price_overrides, check_score_bounds, check_weights, compute_output,
+    AaveInput, INPUT_SCHEMA_VERSION, MAX_JOURNAL_BYTES,
+};
 
 fn main() {
     // ========================================================================
@@ -13,109 +16,164 @@ fn main() {
     // We deserialize it here inside the zkVM
     let input: AaveInput = env::read();
 
+    // Refuse inputs built for a different schema - the fields may not mean
+    // what this program thinks they mean
+    assert_eq!(
+        input.schema_version, INPUT_SCHEMA_VERSION,
+        "Unsupported input schema version"
+    );
+
+    // Reserves the host filtered out must really be absent, otherwise the
+    // committed exclusion count would misstate what was scored
+    for reserve in &input.reserves {
+        assert!(
+            !input.excluded.iter().any(|excluded| excluded.eq_ignore_ascii_case(&reserve.token_address)),
+            "Excluded reserve present in input"
+        );
+    }
+
+    // Bounds that cross or exceed 100% would commit a score no one approved
+    if let Err(problem) = check_score_bounds(input.score_floor, input.score_ceiling) {
+        panic!("Invalid score bounds: {}", problem);
+    }
+
+    // A weight above 100% would inflate a reserve past its market value
+    if let Err(problem) = check_weights(&input.weights) {
+        panic!("Invalid risk weights: {}", problem);
+    }
+
+    // A what-if output must be for the prices it says were overridden
+    if let Err(problem) = check_price_overrides(&input) {
+        panic!("Invalid price overrides: {}", problem);
+    }
+
     // Log basic info (visible in zkVM execution logs)
     eprintln!("=== Aave Safety Score Calculation ===");
     eprintln!("Protocol: {}", input.protocol_name);
+    eprintln!("Schema version: {}", input.schema_version);
     eprintln!("Number of reserves: {}", input.reserves.len());
     eprintln!("Timestamp: {}", input.timestamp);
+    if let Some(block) = input.block_number {
+        eprintln!("Block: {}", block);
+    }
+    if let Some(nonce) = input.nonce {
+        eprintln!("Nonce: {}", nonce);
+    }
+    eprintln!("Min reserve value (USD, 1e8): {}", input.min_reserve_usd);
+    eprintln!("Excluded reserves: {}", input.excluded.len());
+    eprintln!("Base currency: {} (unit {})", input.base_currency.address, input.base_currency.unit);
+    if input.collateral_only {
+        eprintln!("Assets: collateral-enabled reserves only");
+    }
+    if input.score_floor.is_some() || input.score_ceiling.is_some() {
+        eprintln!("Score bounds (scaled 1e4): {:?} to {:?}", input.score_floor, input.score_ceiling);
+    }
+    if input.backstop_usd > 0 {
+        eprintln!("Backstop (USD, 1e8): {}", input.backstop_usd);
+    }
+    if !input.weights.is_empty() {
+        eprintln!("Custom risk weights: {} assets", input.weights.len());
+    }
+    if let Some(secs) = input.stale_after_secs {
+        eprintln!("Stale after: {}s", secs);
+    }
+    if input.siloed_haircut_bps > 0 {
+        eprintln!("Siloed borrowing haircut: {} bps", input.siloed_haircut_bps);
+    }
+    if let Some(secs) = input.projection_horizon_secs {
+        eprintln!("Projection horizon: {}s", secs);
+    }
+    if !input.price_overrides.is_empty() {
+        eprintln!("⚠ Hypothetical: {} reserve prices overridden", input.price_overrides.len());
+    }
 
     // ========================================================================
-    // STEP 2: Calculate total assets and liabilities in USD
+    // STEP 2: Calculate the safety score
     // ========================================================================
-    let mut total_assets_usd: u128 = 0;
-    let mut total_liabilities_usd: u128 = 0;
-
-    // Loop through each reserve (USDC, WETH, DAI, etc.)
-    for (index, reserve) in input.reserves.iter().enumerate() {
-        eprintln!("\n--- Reserve #{}: {} ---", index + 1, reserve.token_address);
-        
-        // Calculate asset value (total supplied by users)
-        // Assets = aToken balance (what users have deposited)
-        let asset_value_usd = normalize_amount(
-            reserve.total_atoken,
-            reserve.decimals,
-            reserve.price_usd,
-        );
-        
-        // Calculate liability value (total borrowed by users)
-        // Liabilities = stable debt + variable debt
-        let total_debt = reserve.total_stable_debt + reserve.total_variable_debt;
-        let liability_value_usd = normalize_amount(
-            total_debt,
-            reserve.decimals,
-            reserve.price_usd,
-        );
-
-        eprintln!("  Total aToken: {}", reserve.total_atoken);
-        eprintln!("  Total Stable Debt: {}", reserve.total_stable_debt);
-        eprintln!("  Total Variable Debt: {}", reserve.total_variable_debt);
-        eprintln!("  Price (USD, 1e8): {}", reserve.price_usd);
-        eprintln!("  Asset Value (USD, 1e8): {}", asset_value_usd);
-        eprintln!("  Liability Value (USD, 1e8): {}", liability_value_usd);
-
-        // Accumulate totals
-        total_assets_usd += asset_value_usd;
-        total_liabilities_usd += liability_value_usd;
-    }
+    // The math lives in derisk_type::score so the host can run the exact same
+    // code in tests without the zkVM. Dust filtering happens there too (not on
+    // the host) so it's part of the proof
+    let output = compute_output(&input);
 
     eprintln!("\n=== Totals ===");
-    eprintln!("Total Assets (USD, 1e8): {}", total_assets_usd);
-    eprintln!("Total Liabilities (USD, 1e8): {}", total_liabilities_usd);
-
-    // ========================================================================
-    // STEP 3: Calculate the safety score
-    // ========================================================================
-    // Safety Score = (Buffer / Total Assets) * 100
-    // Where Buffer = Total Assets - Total Liabilities
-    //
-    // This represents what percentage of assets are "safe" (not owed to borrowers)
-    //
-    // Examples:
-    // - Score = 100% → No debt, fully safe
-    // - Score = 95% → Protocol has 5% buffer
-    // - Score = 0% → Protocol is insolvent (liabilities >= assets)
-
-    let safety_score = if total_assets_usd == 0 {
-        // Edge case: no assets = unsafe
-        0u64
-    } else if total_liabilities_usd >= total_assets_usd {
-        // Insolvent: liabilities exceed assets
-        0u64
-    } else {
-        // Normal case: calculate buffer percentage
-        let buffer = total_assets_usd - total_liabilities_usd;
-        
-        // Scale to 1e4 for precision (e.g., 98.5% = 985000)
-        // Formula: (buffer * 1e4 * 100) / total_assets
-        // The 100 converts to percentage, 1e4 gives us 2 decimal places
-        let score = (buffer * 1_000_000) / total_assets_usd;
-        
-        // Cap at 100% (1_000_000 in our scale)
-        if score > 1_000_000 {
-            1_000_000u64
-        } else {
-            score as u64
-        }
-    };
+    if let Some(base) = &output.denomination {
+        eprintln!("⚠ Totals are in base currency {}, not USD", base);
+    }
+    eprintln!("Total Assets (USD, 1e8): {}", output.total_assets_usd);
+    eprintln!("Total Liabilities (USD, 1e8): {}", output.total_liabilities_usd);
+    eprintln!("  Stable / Variable: {} / {}",
+        output.total_stable_liabilities_usd, output.total_variable_liabilities_usd);
+    eprintln!("Isolation Mode Debt (USD, 1e8): {}", output.isolation_mode_debt_usd);
+    eprintln!("Skipped Reserves: {}", output.skipped_reserves);
+    eprintln!("Reserves Near Cap: {}", output.capped_reserves);
+    if output.reserves_with_missing_price > 0 {
+        eprintln!("⚠ {} reserves have no price and were left out of the score",
+            output.reserves_with_missing_price);
+    }
+    if output.reserves_included < output.reserves_total {
+        eprintln!("⚠ Data completeness: {} of {} reserves ({} bps)",
+            output.reserves_included, output.reserves_total, output.data_completeness_bps);
+    }
+    if output.truncated {
+        eprintln!("Breakdown lists {} reserves, {} summed into \"other\"",
+            output.reserve_breakdown.len(), output.omitted_count);
+    }
+    if output.volatility_adjusted {
+        eprintln!("Score is volatility-weighted");
+    }
+    if output.risk_weighted {
+        eprintln!("Score uses custom risk weights");
+    }
+    if output.stale_reserve_count > 0 {
+        eprintln!("⚠ {} reserves have stale on-chain data", output.stale_reserve_count);
+    }
+    if output.saturated {
+        eprintln!("⚠ Totals overflowed u128 and were clamped");
+    }
+    if output.clamped {
+        eprintln!("Score clamped into the input's governance bounds");
+    }
 
     eprintln!("\n=== Final Safety Score ===");
-    eprintln!("Safety Score (scaled 1e4): {}", safety_score);
-    eprintln!("Safety Score (percentage): {:.2}%", safety_score as f64 / 10_000.0);
+    eprintln!("Guest version: {}", output.guest_version);
+    eprintln!("Safety Score (scaled 1e4): {}", output.safety_score);
+    eprintln!("Status: {:?}", output.status);
+    // Integer formatting: to_percentage is host-only, the guest stays off f64
+    eprintln!(
+        "Safety Score (percentage): {}.{:02}%",
+        output.safety_score / 10_000,
+        output.safety_score % 10_000 / 100
+    );
+    if let Some(projected) = output.projected_score {
+        eprintln!("Projected Safety Score (scaled 1e4): {}", projected);
+    }
+    match output.weighted_health_factor {
+        Some(hf) => eprintln!("Weighted Health Factor (scaled 1e4): {}", hf),
+        None => eprintln!("Weighted Health Factor: unavailable (missing thresholds)"),
+    }
 
     // ========================================================================
-    // STEP 4: Commit the result to the public journal
+    // STEP 3: Commit the result to the public journal
     // ========================================================================
     // This is the ONLY data that becomes public and goes on-chain
     // The zkVM will generate a proof that this output was computed correctly
-    let output = SafetyScoreOutput::new(
-        safety_score,
-        total_assets_usd,
-        total_liabilities_usd,
-        input.timestamp,
+
+    // A logic bug must fail the proof, not put an absurd score on-chain
+    if let Err(violation) = check_output_invariants(&output) {
+        panic!("Output invariant violated: {}", violation);
+    }
+
+    // The journal is calldata on-chain, so its size is capped. The breakdown
+    // is already bounded; this catches the output format itself growing
+    let journal = risc0_zkvm::serde::to_vec(&output).expect("Failed to encode output");
+    assert!(
+        journal.len() * 4 <= MAX_JOURNAL_BYTES,
+        "Journal exceeds MAX_JOURNAL_BYTES"
     );
 
     // Commit to journal - this is what the on-chain verifier will see
-    env::commit(&output);
+    // (the same words env::commit would write for `output`)
+    env::commit_slice(&journal);
 
     eprintln!("\n✓ Safety score calculation complete!");
     eprintln!("✓ Output committed to journal");
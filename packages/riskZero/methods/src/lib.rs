@@ -6,5 +6,8 @@ include!(concat!(env!("OUT_DIR"), "/methods.rs"));
 // After building, these will be available as:
 // - AAVE_GUEST_ELF: The compiled RISC-V binary
 // - AAVE_GUEST_ID: The unique Image ID (cryptographic hash of the program)
+// The batch and compose guests, which prove a large reserve set in parts and
+// merge them (see derisk_type::compose), keep their generated names:
+// AAVE_BATCH_ELF / AAVE_BATCH_ID and AAVE_COMPOSE_ELF / AAVE_COMPOSE_ID
 pub use AAVE_GUEST_ELF as AAVE_ELF;
 pub use AAVE_GUEST_ID as AAVE_ID;